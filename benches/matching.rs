@@ -0,0 +1,50 @@
+//! Compares `match_path` with the allocation-free `match_into`.
+//!
+//! Run with `cargo bench --bench matching`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use traject::Pattern;
+
+const ITERATIONS: u32 = 200_000;
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    // warm up
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<40} {:>8.1} ns/iter",
+        name,
+        elapsed.as_nanos() as f64 / f64::from(ITERATIONS)
+    );
+}
+
+fn main() {
+    let pattern = Pattern::new("departments/{department_id}/employees/{employee_id}").unwrap();
+    let path = "departments/10/employees/20";
+
+    bench("match_path", || {
+        black_box(pattern.match_path(black_box(path)));
+    });
+    let mut variables = Vec::new();
+    bench("match_into", || {
+        black_box(pattern.match_into(black_box(path), &mut variables));
+    });
+
+    let literal = Pattern::new("api/v1/status/health").unwrap();
+    let path = "api/v1/status/health";
+    bench("match_path, literal steps", || {
+        black_box(literal.match_path(black_box(path)));
+    });
+    let mut variables = Vec::new();
+    bench("match_into, literal steps", || {
+        black_box(literal.match_into(black_box(path), &mut variables));
+    });
+}
@@ -0,0 +1,193 @@
+//! Match, expand and lint the routes in a JSON routes file.
+//!
+//! ```text
+//! traject [--routes FILE] match PATH
+//! traject [--routes FILE] expand NAME [VARIABLE=VALUE]...
+//! traject [--routes FILE] lint
+//! ```
+//!
+//! The routes file defaults to `routes.json`.
+
+use std::collections::HashMap;
+use std::{env, fs, process};
+
+use traject::{RouteConfig, Router};
+
+const USAGE: &str = "usage: traject [--routes FILE] match PATH
+       traject [--routes FILE] expand NAME [VARIABLE=VALUE]...
+       traject [--routes FILE] lint";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Error {
+    /// The command line is wrong; exits with 2.
+    Usage(String),
+    /// The command failed; exits with 1.
+    Failed(String),
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => print!("{}", output),
+        Err(Error::Usage(message)) => {
+            eprintln!("traject: {}\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(Error::Failed(message)) => {
+            eprint!("{}", message);
+            process::exit(1);
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<String, Error> {
+    let (file, command) = match args {
+        [option, file, command @ ..] if option == "--routes" => (file.as_str(), command),
+        [option] if option == "--routes" => {
+            return Err(Error::Usage("--routes needs a file".to_string()))
+        }
+        command => ("routes.json", command),
+    };
+    let s = fs::read_to_string(file)
+        .map_err(|error| Error::Failed(format!("{}: {}\n", file, error)))?;
+    let router =
+        Router::from_json(&s).map_err(|error| Error::Failed(format!("{}: {}\n", file, error)))?;
+    execute(&router, command)
+}
+
+fn execute(router: &Router<RouteConfig>, command: &[String]) -> Result<String, Error> {
+    match command {
+        [command, path] if command == "match" => match router.resolve(path) {
+            Some((route, m)) => {
+                let mut output = format!("{} ({})\n", route.name, route.handler);
+                for (name, value) in m.iter() {
+                    output.push_str(&format!("{} = {}\n", name, value));
+                }
+                Ok(output)
+            }
+            None => Err(Error::Failed(format!("no route matches `{}`\n", path))),
+        },
+        [command, name, values @ ..] if command == "expand" => {
+            let values = values
+                .iter()
+                .map(|value| match value.find('=') {
+                    Some(i) => Ok((&value[..i], &value[i + 1..])),
+                    None => Err(Error::Usage(format!(
+                        "expected VARIABLE=VALUE, not `{}`",
+                        value
+                    ))),
+                })
+                .collect::<Result<HashMap<_, _>, _>>()?;
+            router
+                .path_for(name, &values)
+                .map(|path| format!("{}\n", path))
+                .map_err(|error| Error::Failed(format!("{}\n", error)))
+        }
+        [command] if command == "lint" => {
+            let conflicts = router.check();
+            if conflicts.is_empty() {
+                return Ok(String::new());
+            }
+            Err(Error::Failed(
+                conflicts
+                    .iter()
+                    .map(|conflict| format!("{}\n", conflict))
+                    .collect(),
+            ))
+        }
+        [command, ..] if ["match", "expand", "lint"].contains(&command.as_str()) => Err(
+            Error::Usage(format!("wrong number of arguments for `{}`", command)),
+        ),
+        [command, ..] => Err(Error::Usage(format!("unknown command `{}`", command))),
+        [] => Err(Error::Usage("no command".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTES: &str = r#"{
+        "employee_detail": {
+            "pattern": "departments/{d}/employees/{e}",
+            "handler": "employees.detail"
+        },
+        "employee_by_id": {
+            "pattern": "departments/{d}/employees/{e:int}",
+            "handler": "employees.by_id"
+        },
+        "employee_other": {
+            "pattern": "departments/{x}/employees/{y}",
+            "handler": "employees.other"
+        }
+    }"#;
+
+    fn execute(command: &str) -> Result<String, Error> {
+        let router = Router::from_json(ROUTES).unwrap();
+        let command: Vec<String> = command.split_whitespace().map(String::from).collect();
+        super::execute(&router, &command)
+    }
+
+    #[test]
+    fn test_match() {
+        assert_eq!(
+            execute("match departments/a/employees/2"),
+            Ok("employee_by_id (employees.by_id)\nd = a\ne = 2\n".to_string())
+        );
+        assert_eq!(
+            execute("match departments"),
+            Err(Error::Failed(
+                "no route matches `departments`\n".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            execute("expand employee_detail d=a e=b"),
+            Ok("departments/a/employees/b\n".to_string())
+        );
+        assert_eq!(
+            execute("expand employee_detail d=a"),
+            Err(Error::Failed("missing variable `e`\n".to_string()))
+        );
+        assert_eq!(
+            execute("expand employee_detail d"),
+            Err(Error::Usage("expected VARIABLE=VALUE, not `d`".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        assert_eq!(
+            execute("lint"),
+            Err(Error::Failed(
+                "`departments/{x}/employees/{y}` duplicates `departments/{d}/employees/{e}`\n"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_usage() {
+        assert_eq!(
+            execute("match"),
+            Err(Error::Usage(
+                "wrong number of arguments for `match`".to_string()
+            ))
+        );
+        assert_eq!(
+            execute("frobnicate"),
+            Err(Error::Usage("unknown command `frobnicate`".to_string()))
+        );
+        assert_eq!(execute(""), Err(Error::Usage("no command".to_string())));
+    }
+
+    #[test]
+    fn test_run_missing_file() {
+        let args = ["--routes".to_string(), "/nonexistent.json".to_string()];
+        assert!(matches!(run(&args), Err(Error::Failed(_))));
+        assert!(matches!(run(&args[..1]), Err(Error::Usage(_))));
+    }
+}
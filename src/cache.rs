@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::converter::{self, ConverterRegistry};
+use crate::error::ParseError;
+use crate::pattern::{Case, Pattern};
+
+/// What a pattern is parsed from: its text, the identity of the converters
+/// and the options that change how it matches.
+type Key = (String, u64, char, bool, Case);
+
+/// Patterns shared by everything that parses the same pattern the same
+/// way, while they are in use.
+///
+/// Give the same cache to several routers with `Router::with_pattern_cache`,
+/// and a pattern that they all register, with the same converters and
+/// options, is parsed once and kept in memory once. The cache doesn't keep
+/// patterns alive: an entry goes when the last route with the pattern does.
+/// Clones of a cache share its patterns.
+#[derive(Debug, Clone, Default)]
+pub struct PatternCache {
+    patterns: Arc<Mutex<HashMap<Key, Weak<Pattern>>>>,
+}
+
+impl PatternCache {
+    pub fn new() -> PatternCache {
+        PatternCache::default()
+    }
+
+    /// The shared pattern for `s`, parsed with the built-in converters.
+    pub fn get(&self, s: &str) -> Result<Arc<Pattern>, ParseError> {
+        self.get_with(s, converter::builtin(), '/', false, Case::default())
+    }
+
+    /// The number of patterns in use.
+    pub fn len(&self) -> usize {
+        self.lock()
+            .values()
+            .filter(|pattern| pattern.strong_count() > 0)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn get_with(
+        &self,
+        s: &str,
+        converters: &ConverterRegistry,
+        separator: char,
+        percent_encoding: bool,
+        case: Case,
+    ) -> Result<Arc<Pattern>, ParseError> {
+        let key = (
+            s.to_string(),
+            converters.id(),
+            separator,
+            percent_encoding,
+            case,
+        );
+        if let Some(pattern) = self.lock().get(&key).and_then(Weak::upgrade) {
+            return Ok(pattern);
+        }
+        let pattern = Arc::new(
+            Pattern::parse(s, converters, separator)?
+                .with_percent_encoding(percent_encoding)
+                .with_case(case),
+        );
+        let mut patterns = self.lock();
+        if let Some(pattern) = patterns.get(&key).and_then(Weak::upgrade) {
+            return Ok(pattern);
+        }
+        patterns.retain(|_, pattern| pattern.strong_count() > 0);
+        patterns.insert(key, Arc::downgrade(&pattern));
+        Ok(pattern)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Key, Weak<Pattern>>> {
+        self.patterns.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::router::Router;
+
+    #[test]
+    fn test_get() {
+        let cache = PatternCache::new();
+        let a = cache.get("users/{id:int}").unwrap();
+        let b = cache.get("users/{id:int}").unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        let c = cache.get("users/{id}").unwrap();
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(cache.len(), 2);
+        drop((a, b));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            cache.get("users/{id").unwrap_err(),
+            ParseError::UnbalancedBrace { offset: 6 }
+        );
+    }
+
+    #[test]
+    fn test_with_pattern_cache() {
+        let cache = PatternCache::new();
+        let router = |percent_encoding| {
+            let mut router = Router::new()
+                .with_percent_encoding(percent_encoding)
+                .with_pattern_cache(cache.clone());
+            router.register("users/{id:int}", 1).unwrap();
+            router.register("posts/{id}", 2).unwrap();
+            router
+        };
+        let (a, b) = (router(false), router(false));
+        assert_eq!(cache.len(), 2);
+        let shared = |a: &Router<i32>, b: &Router<i32>| {
+            a.iter()
+                .zip(b.iter())
+                .all(|(a, b)| std::ptr::eq(a.pattern, b.pattern))
+        };
+        assert!(shared(&a, &b));
+        let c = router(true);
+        assert_eq!(cache.len(), 4);
+        assert!(!shared(&a, &c));
+        assert_eq!(
+            *c.resolve("posts/a%20b").unwrap().1.get("id").unwrap(),
+            *"a b"
+        );
+
+        let mut converters = ConverterRegistry::new();
+        converters.register("int", crate::converter::SlugConverter);
+        let mut d = Router::new()
+            .with_converters(converters)
+            .with_pattern_cache(cache.clone());
+        d.register("users/{id:int}", 1).unwrap();
+        assert!(d.resolve("users/abc").is_some());
+        assert!(a.resolve("users/abc").is_none());
+        drop((a, b, c, d));
+        assert!(cache.is_empty());
+    }
+}
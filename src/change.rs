@@ -0,0 +1,26 @@
+use std::fmt;
+
+/// A difference between two routers found by `Router::diff`.
+///
+/// Routes are identified by their name if they have one, and otherwise by
+/// their pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteChange {
+    /// A route that is only in the new router.
+    Added { route: String },
+    /// A route that is only in the old router.
+    Removed { route: String },
+    /// A route that is in both routers, with a different pattern, priority
+    /// or value.
+    Changed { route: String },
+}
+
+impl fmt::Display for RouteChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RouteChange::Added { route } => write!(f, "`{}` was added", route),
+            RouteChange::Removed { route } => write!(f, "`{}` was removed", route),
+            RouteChange::Changed { route } => write!(f, "`{}` was changed", route),
+        }
+    }
+}
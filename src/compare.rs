@@ -0,0 +1,82 @@
+use crate::router::{Resolution, Router};
+
+/// A path that two routers compared by `Router::compare` resolve
+/// differently.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Divergence<'a, T> {
+    pub path: &'a str,
+    /// How this router resolves the path.
+    pub left: Resolution<'a, T>,
+    /// How the other router resolves the path.
+    pub right: Resolution<'a, T>,
+}
+
+impl<T> Router<T> {
+    /// Look up each path in this router and in another one, for instance
+    /// the routes before and after a migration, and return the paths they
+    /// don't agree on: a different value, different variables, or a
+    /// different kind of resolution.
+    ///
+    /// Divergences come in the order of the paths.
+    pub fn compare<'a, I>(&'a self, other: &'a Router<T>, paths: I) -> Vec<Divergence<'a, T>>
+    where
+        I: IntoIterator<Item = &'a str>,
+        T: PartialEq,
+    {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let (left, right) = (self.lookup(path), other.lookup(path));
+                if left == right {
+                    None
+                } else {
+                    Some(Divergence { path, left, right })
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::matching::Match;
+    use crate::router::TieBreak;
+
+    #[test]
+    fn test_compare() {
+        let mut old = Router::new();
+        old.register("users/{id}", 1).unwrap();
+        old.register("users/new", 2).unwrap();
+        old.register("posts/{slug}", 3).unwrap();
+        old.register("about", 4).unwrap();
+        let mut new = Router::new().with_tie_break(TieBreak::FirstRegistered);
+        new.register("users/{id}", 1).unwrap();
+        new.register("users/new", 2).unwrap();
+        new.register("posts/{title}", 3).unwrap();
+
+        let paths = ["users/1", "users/new", "posts/hello", "about", "missing"];
+        assert_eq!(
+            old.compare(&new, paths.iter().copied()),
+            vec![
+                Divergence {
+                    path: "users/new",
+                    left: Resolution::Found(&2, Match::default()),
+                    right: Resolution::Found(&1, vec![("id", "new")].into_iter().collect()),
+                },
+                Divergence {
+                    path: "posts/hello",
+                    left: Resolution::Found(&3, vec![("slug", "hello")].into_iter().collect()),
+                    right: Resolution::Found(&3, vec![("title", "hello")].into_iter().collect()),
+                },
+                Divergence {
+                    path: "about",
+                    left: Resolution::Found(&4, Match::default()),
+                    right: Resolution::NotFound,
+                },
+            ]
+        );
+        assert!(old.compare(&old, paths.iter().copied()).is_empty());
+    }
+}
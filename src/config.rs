@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+
+use crate::error::ConfigError;
+use crate::json::{self, Spanned, SyntaxError, Value};
+use crate::router::Router;
+
+/// A route as declared in a routes file.
+///
+/// This is the value of each route in a router loaded with
+/// `Router::from_json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteConfig {
+    pub name: String,
+    /// The identifier of the handler, for the application to look up.
+    pub handler: String,
+    /// Kept in key order, so it is listed the same way on every run.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl RouteConfig {
+    /// The newest version of the routes file format that `Router::from_json`
+    /// loads.
+    pub const VERSION: i32 = 2;
+}
+
+impl Router<RouteConfig> {
+    /// Load a router from a JSON routes file.
+    ///
+    /// The file has the version of its format and maps route names to
+    /// routes, which are registered in the order they appear in:
+    ///
+    /// ```json
+    /// {
+    ///   "version": 2,
+    ///   "routes": {
+    ///     "employee_detail": {
+    ///       "pattern": "departments/{d}/employees/{e}",
+    ///       "handler": "employees.detail",
+    ///       "priority": 1,
+    ///       "metadata": {"auth": "required"}
+    ///     }
+    ///   }
+    /// }
+    /// ```
+    ///
+    /// `pattern` and `handler` are required; `priority` defaults to 0.
+    /// Files of version 1 are just the object with the routes, and are
+    /// still loaded. A version newer than `RouteConfig::VERSION` is an
+    /// error, as the file may mean things this version doesn't know about.
+    /// Errors report the line in the file. Nesting arrays and objects more
+    /// than 128 deep is an error. Routes files can only be JSON; there is
+    /// no TOML or YAML loader.
+    pub fn from_json(s: &str) -> Result<Router<RouteConfig>, ConfigError> {
+        let mut router = Router::new();
+        for (name, route) in routes(json::parse(s)?)? {
+            let line = route.line;
+            let mut pattern = None;
+            let mut handler = None;
+            let mut priority = 0;
+            let mut metadata = BTreeMap::new();
+            for (key, value) in object(route)? {
+                match key.as_str() {
+                    "pattern" => pattern = Some((value.line, string(value)?)),
+                    "handler" => handler = Some(string(value)?),
+                    "priority" => priority = integer(value)?,
+                    "metadata" => {
+                        for (key, value) in object(value)? {
+                            metadata.insert(key, string(value)?);
+                        }
+                    }
+                    _ => return Err(invalid(format!("unknown key `{}`", key), value.line)),
+                }
+            }
+            let missing = |key| invalid(format!("route `{}` has no {}", name, key), line);
+            let (pattern_line, pattern) = pattern.ok_or_else(|| missing("pattern"))?;
+            let handler = handler.ok_or_else(|| missing("handler"))?;
+            let value = RouteConfig {
+                name: name.clone(),
+                handler,
+                metadata,
+            };
+            router
+                .register_named_with_priority(&name, &pattern, value, priority)
+                .map_err(|error| ConfigError::Pattern {
+                    error,
+                    line: pattern_line,
+                })?;
+        }
+        Ok(router)
+    }
+}
+
+impl From<SyntaxError> for ConfigError {
+    fn from(error: SyntaxError) -> ConfigError {
+        match error {
+            SyntaxError::Invalid { message, line } => invalid(message, line),
+            SyntaxError::TooDeep { line } => ConfigError::TooDeep {
+                limit: json::MAX_DEPTH,
+                line,
+            },
+        }
+    }
+}
+
+fn invalid(message: String, line: usize) -> ConfigError {
+    ConfigError::Invalid { message, line }
+}
+
+/// The routes in a routes file, whatever its version.
+fn routes(spanned: Spanned) -> Result<Vec<(String, Spanned)>, ConfigError> {
+    let line = spanned.line;
+    let members = object(spanned)?;
+    // in version 1, `version` can only be the name of a route
+    let versioned = members
+        .iter()
+        .any(|(key, value)| key == "version" && matches!(value.value, Value::Number(_)));
+    if !versioned {
+        return Ok(members);
+    }
+    let mut version = None;
+    let mut routes = None;
+    for (key, value) in members {
+        match key.as_str() {
+            "version" => version = Some((value.line, integer(value)?)),
+            "routes" => routes = Some(object(value)?),
+            _ => return Err(invalid(format!("unknown key `{}`", key), value.line)),
+        }
+    }
+    let (version_line, version) = version.expect("a version");
+    if !(1..=RouteConfig::VERSION).contains(&version) {
+        return Err(ConfigError::UnsupportedVersion {
+            version,
+            line: version_line,
+        });
+    }
+    routes.ok_or_else(|| invalid("the file has no routes".to_string(), line))
+}
+
+fn object(spanned: Spanned) -> Result<Vec<(String, Spanned)>, ConfigError> {
+    match spanned.value {
+        Value::Object(members) => {
+            for (i, (key, value)) in members.iter().enumerate() {
+                if members[..i].iter().any(|(earlier, _)| earlier == key) {
+                    return Err(invalid(format!("duplicate key `{}`", key), value.line));
+                }
+            }
+            Ok(members)
+        }
+        _ => Err(invalid("expected an object".to_string(), spanned.line)),
+    }
+}
+
+fn string(spanned: Spanned) -> Result<String, ConfigError> {
+    match spanned.value {
+        Value::String(s) => Ok(s),
+        _ => Err(invalid("expected a string".to_string(), spanned.line)),
+    }
+}
+
+fn integer(spanned: Spanned) -> Result<i32, ConfigError> {
+    match spanned.value {
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => Ok(n as i32),
+        _ => Err(invalid("expected an integer".to_string(), spanned.line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ParseError;
+
+    const ROUTES: &str = r#"{
+        "employee_detail": {
+            "pattern": "departments/{d}/employees/{e}",
+            "handler": "employees.detail",
+            "metadata": {"auth": "required"}
+        },
+        "employee_default": {
+            "pattern": "departments/{d}/employees/{e:int}",
+            "handler": "employees.by_id",
+            "priority": -1
+        }
+    }"#;
+
+    #[test]
+    fn test_from_json() {
+        let router = Router::from_json(ROUTES).unwrap();
+        let (route, m) = router.resolve("departments/1/employees/2").unwrap();
+        assert_eq!(route.name, "employee_detail");
+        assert_eq!(route.handler, "employees.detail");
+        assert_eq!(route.metadata["auth"], "required");
+        assert_eq!(m.get("e"), Some("2"));
+        let values = [("d", "1"), ("e", "2")].iter().cloned().collect();
+        assert_eq!(
+            router.path_for("employee_default", &values).unwrap(),
+            "departments/1/employees/2"
+        );
+    }
+
+    #[test]
+    fn test_from_json_versions() {
+        let versioned = format!("{{\"version\": 2, \"routes\": {}}}", ROUTES);
+        let router = Router::from_json(&versioned).unwrap();
+        let (route, _) = router.resolve("departments/1/employees/2").unwrap();
+        assert_eq!(route.name, "employee_detail");
+        let named_version = r#"{"version": {"pattern": "v", "handler": "h"}}"#;
+        let router = Router::from_json(named_version).unwrap();
+        assert_eq!(router.resolve("v").unwrap().0.name, "version");
+
+        let error = |s| Router::from_json(s).unwrap_err().to_string();
+        assert_eq!(
+            error("{\"routes\": {},\n\"version\": 3}"),
+            "line 2: unsupported version 3, the newest supported is 2"
+        );
+        assert_eq!(error("{\"version\": 2}"), "line 1: the file has no routes");
+        assert_eq!(
+            error("{\"version\": 2, \"routes\": {}, \"other\": 1}"),
+            "line 1: unknown key `other`"
+        );
+    }
+
+    #[test]
+    fn test_from_json_invalid_pattern() {
+        let s = "{\n\"a\": {\n\"handler\": \"h\",\n\"pattern\": \"a/{b\"}}";
+        assert_eq!(
+            Router::from_json(s).unwrap_err(),
+            ConfigError::Pattern {
+                error: ParseError::UnbalancedBrace { offset: 2 },
+                line: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        let error = |s| Router::from_json(s).unwrap_err().to_string();
+        assert_eq!(error("[]"), "line 1: expected an object");
+        assert_eq!(error("{\n\"a\": {}}"), "line 2: route `a` has no pattern");
+        assert_eq!(
+            error("{\"a\": {\"pattern\": \"\"}}"),
+            "line 1: route `a` has no handler"
+        );
+        assert_eq!(
+            error("{\"a\": {\"pattern\": \"\",\n\"other\": 1}}"),
+            "line 2: unknown key `other`"
+        );
+        assert_eq!(
+            error("{\"a\": {\"pattern\": 1}}"),
+            "line 1: expected a string"
+        );
+        assert_eq!(
+            error("{\"a\": {\"pattern\": \"\", \"handler\": \"\", \"priority\": 0.5}}"),
+            "line 1: expected an integer"
+        );
+        assert_eq!(
+            error("{\"a\": {\"pattern\": \"\", \"handler\": \"\"},\n\"a\": {}}"),
+            "line 2: duplicate key `a`"
+        );
+        assert_eq!(error("{\n\n"), "line 3: expected `\"`");
+    }
+
+    #[test]
+    fn test_from_json_too_deep() {
+        let s = format!("{{\"a\": {}", "[".repeat(json::MAX_DEPTH));
+        assert_eq!(
+            Router::from_json(&s).unwrap_err(),
+            ConfigError::TooDeep {
+                limit: json::MAX_DEPTH,
+                line: 1
+            }
+        );
+    }
+}
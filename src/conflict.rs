@@ -0,0 +1,25 @@
+use std::fmt;
+
+/// A problem with a route found by `Router::check`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// The pattern is the same as one that takes precedence over it, apart
+    /// from the names of its variables.
+    Duplicate { pattern: String, earlier: String },
+    /// The pattern can never match, because a more general pattern that
+    /// takes precedence over it matches every path it matches.
+    Shadowed { pattern: String, earlier: String },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Duplicate { pattern, earlier } => {
+                write!(f, "`{}` duplicates `{}`", pattern, earlier)
+            }
+            Conflict::Shadowed { pattern, earlier } => {
+                write!(f, "`{}` is shadowed by `{}`", pattern, earlier)
+            }
+        }
+    }
+}
@@ -0,0 +1,621 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+#[cfg(feature = "regex")]
+use crate::lazy_regex::LazyRegex;
+
+/// Converts between the text of a path segment and a typed value.
+///
+/// A converter is named in a pattern after the variable name, like
+/// `{department_id:int}`. During matching a segment is only accepted if the
+/// converter can parse it; `Pattern::expand_typed` uses `serialize` to
+/// produce the text for a value.
+pub trait Converter {
+    type Value;
+
+    /// Parse text into a value, or `None` if the text doesn't conform.
+    fn parse(&self, s: &str) -> Option<Self::Value>;
+
+    /// Serialize a value into text that `parse` accepts.
+    fn serialize(&self, value: &Self::Value) -> String;
+}
+
+/// Object-safe part of a converter, used by steps during matching and
+/// expansion.
+pub(crate) trait Validate: Send + Sync {
+    fn validate(&self, s: &str) -> bool;
+
+    /// Serialize a value, or `None` if it isn't of the converter's type.
+    fn serialize_any(&self, value: &dyn Any) -> Option<String>;
+}
+
+impl<C> Validate for C
+where
+    C: Converter + Send + Sync,
+    C::Value: 'static,
+{
+    fn validate(&self, s: &str) -> bool {
+        self.parse(s).is_some()
+    }
+
+    fn serialize_any(&self, value: &dyn Any) -> Option<String> {
+        value
+            .downcast_ref::<C::Value>()
+            .map(|value| Converter::serialize(self, value))
+    }
+}
+
+/// A value for a variable without a converter: a `String` or a `&str`.
+pub(crate) fn text(value: &dyn Any) -> Option<String> {
+    value
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| value.downcast_ref::<&str>().map(|s| s.to_string()))
+}
+
+/// A converter as referred to by name in a pattern.
+#[derive(Clone)]
+pub(crate) struct NamedConverter {
+    pub(crate) name: String,
+    converter: Arc<dyn Validate>,
+}
+
+impl NamedConverter {
+    pub(crate) fn validate(&self, s: &str) -> bool {
+        self.converter.validate(s)
+    }
+
+    pub(crate) fn serialize(&self, value: &dyn Any) -> Option<String> {
+        self.converter.serialize_any(value)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl NamedConverter {
+    /// A regex constraint, named `re:` followed by the regex. It accepts
+    /// text that the regex matches as a whole.
+    pub(crate) fn regex(regex: &str) -> Option<NamedConverter> {
+        let anchored = LazyRegex::new(format!("^(?:{})$", regex))?;
+        Some(NamedConverter {
+            name: format!("re:{}", regex),
+            converter: Arc::new(RegexConstraint(anchored)),
+        })
+    }
+}
+
+#[cfg(feature = "regex")]
+struct RegexConstraint(LazyRegex);
+
+#[cfg(feature = "regex")]
+impl Validate for RegexConstraint {
+    fn validate(&self, s: &str) -> bool {
+        self.0.get().is_some_and(|regex| regex.is_match(s))
+    }
+
+    fn serialize_any(&self, value: &dyn Any) -> Option<String> {
+        text(value)
+    }
+}
+
+impl fmt::Debug for NamedConverter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("NamedConverter").field(&self.name).finish()
+    }
+}
+
+/// Converters by the names used in patterns, like `int` in `{id:int}`.
+///
+/// A new registry has the built-in converters `int`, `float`, `bool`,
+/// `uuid`, `slug` and `date`. Custom converters can be added to it, and it
+/// can be given to `Pattern::with_converters` or `Router::with_converters`.
+#[derive(Debug, Clone)]
+pub struct ConverterRegistry {
+    converters: HashMap<String, NamedConverter>,
+    /// the same for registries known to have the same converters: clones,
+    /// and new registries with only the built-in converters
+    id: u64,
+}
+
+/// The identity of registries with only the built-in converters.
+const BUILTIN_ID: u64 = 0;
+
+/// A new registry identity.
+fn next_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(BUILTIN_ID + 1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+impl ConverterRegistry {
+    pub fn new() -> ConverterRegistry {
+        let mut registry = ConverterRegistry {
+            converters: HashMap::new(),
+            id: BUILTIN_ID,
+        };
+        registry.register("int", IntConverter);
+        registry.register("float", FloatConverter);
+        registry.register("bool", BoolConverter);
+        registry.register("uuid", UuidConverter);
+        registry.register("slug", SlugConverter);
+        registry.register("date", DateConverter);
+        registry.id = BUILTIN_ID;
+        registry
+    }
+
+    /// Register a converter under a name, replacing any converter that was
+    /// registered under it before.
+    pub fn register<C>(&mut self, name: &str, converter: C)
+    where
+        C: Converter + Send + Sync + 'static,
+        C::Value: 'static,
+    {
+        self.converters.insert(
+            name.to_owned(),
+            NamedConverter {
+                name: name.to_owned(),
+                converter: Arc::new(converter),
+            },
+        );
+        self.id = next_id();
+    }
+
+    /// An identity of the converters of this registry: registries with the
+    /// same identity have the same converters.
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Whether a converter is registered under a name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.converters.contains_key(name)
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<NamedConverter> {
+        self.converters.get(name).cloned()
+    }
+
+    /// A registry with just the given converters, under their names.
+    pub(crate) fn of<'a>(
+        converters: impl IntoIterator<Item = &'a NamedConverter>,
+    ) -> ConverterRegistry {
+        ConverterRegistry {
+            converters: converters
+                .into_iter()
+                .map(|converter| (converter.name.clone(), converter.clone()))
+                .collect(),
+            id: next_id(),
+        }
+    }
+
+    /// This registry with the converters of other added, replacing those
+    /// with the same name.
+    pub(crate) fn extended(&self, other: &ConverterRegistry) -> ConverterRegistry {
+        let mut converters = self.converters.clone();
+        converters.extend(
+            other
+                .converters
+                .iter()
+                .map(|(name, converter)| (name.clone(), converter.clone())),
+        );
+        ConverterRegistry {
+            converters,
+            id: next_id(),
+        }
+    }
+}
+
+impl Default for ConverterRegistry {
+    fn default() -> Self {
+        ConverterRegistry::new()
+    }
+}
+
+/// The registry with just the built-in converters.
+pub(crate) fn builtin() -> &'static ConverterRegistry {
+    static BUILTIN: OnceLock<ConverterRegistry> = OnceLock::new();
+    BUILTIN.get_or_init(ConverterRegistry::new)
+}
+
+/// Converts `int` variables to and from `i64`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IntConverter;
+
+impl Converter for IntConverter {
+    type Value = i64;
+
+    fn parse(&self, s: &str) -> Option<i64> {
+        // an explicit + sign would not survive a round trip
+        if s.starts_with('+') {
+            return None;
+        }
+        s.parse().ok()
+    }
+
+    fn serialize(&self, value: &i64) -> String {
+        value.to_string()
+    }
+}
+
+/// Converts `float` variables to and from `f64`.
+///
+/// Only plain decimal notation like `-1.5` is accepted, as that is what
+/// serialization produces.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FloatConverter;
+
+impl Converter for FloatConverter {
+    type Value = f64;
+
+    fn parse(&self, s: &str) -> Option<f64> {
+        let digits = s.strip_prefix('-').unwrap_or(s);
+        let mut fields = digits.splitn(2, '.');
+        let integer = fields.next()?;
+        let fraction = fields.next();
+        let is_digits = |f: &str| !f.is_empty() && f.bytes().all(|b| b.is_ascii_digit());
+        if !is_digits(integer) || !fraction.is_none_or(is_digits) {
+            return None;
+        }
+        s.parse().ok()
+    }
+
+    fn serialize(&self, value: &f64) -> String {
+        value.to_string()
+    }
+}
+
+/// Converts `bool` variables, `true` or `false`, to and from `bool`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoolConverter;
+
+impl Converter for BoolConverter {
+    type Value = bool;
+
+    fn parse(&self, s: &str) -> Option<bool> {
+        match s {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn serialize(&self, value: &bool) -> String {
+        value.to_string()
+    }
+}
+
+/// A UUID, as converted by `UuidConverter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(pub u128);
+
+/// Converts `uuid` variables in hyphenated form like
+/// `67e55044-10b1-426f-9247-bb680e5fe0c8` to and from `Uuid`.
+///
+/// Both upper and lower case hex digits are accepted; serialization uses
+/// lower case.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidConverter;
+
+impl Converter for UuidConverter {
+    type Value = Uuid;
+
+    fn parse(&self, s: &str) -> Option<Uuid> {
+        let groups: Vec<&str> = s.split('-').collect();
+        let lengths = groups.iter().map(|group| group.len());
+        if !lengths.eq([8, 4, 4, 4, 12].iter().cloned()) {
+            return None;
+        }
+        let hex = groups.concat();
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        u128::from_str_radix(&hex, 16).ok().map(Uuid)
+    }
+
+    fn serialize(&self, value: &Uuid) -> String {
+        let hex = format!("{:032x}", value.0);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+}
+
+/// Converts `slug` variables like `hello-world`: lower case letters and
+/// digits, in words separated by single hyphens.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SlugConverter;
+
+impl Converter for SlugConverter {
+    type Value = String;
+
+    fn parse(&self, s: &str) -> Option<String> {
+        let is_word = |word: &str| {
+            !word.is_empty()
+                && word
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        };
+        if s.split('-').all(is_word) {
+            Some(s.to_string())
+        } else {
+            None
+        }
+    }
+
+    fn serialize(&self, value: &String) -> String {
+        value.clone()
+    }
+}
+
+/// Converts variables with a translation table, like slugs localized for
+/// one locale: each slug in a path stands for a canonical value that is the
+/// same in all locales, such as an identifier.
+///
+/// Give the French router `produits/{slug:product}` a converter with the
+/// French slugs and the English one `products/{slug:product}` a converter
+/// with the English slugs, and `parse` gives the same canonical value for
+/// both, which `serialize` turns into the slug of the locale again, for
+/// instance in `Router::path_for_typed`. A canonical value without a slug
+/// serializes as itself, which doesn't parse.
+#[derive(Debug, Clone, Default)]
+pub struct TranslatedConverter {
+    canonical: HashMap<String, String>,
+    slugs: HashMap<String, String>,
+}
+
+impl TranslatedConverter {
+    pub fn new() -> TranslatedConverter {
+        TranslatedConverter::default()
+    }
+
+    /// Add a slug and the canonical value it stands for. A canonical value
+    /// can have several slugs, like an old one that still has to resolve;
+    /// it serializes as the first.
+    pub fn with(mut self, slug: &str, canonical: &str) -> TranslatedConverter {
+        self.canonical
+            .insert(slug.to_string(), canonical.to_string());
+        self.slugs
+            .entry(canonical.to_string())
+            .or_insert_with(|| slug.to_string());
+        self
+    }
+}
+
+impl Converter for TranslatedConverter {
+    type Value = String;
+
+    fn parse(&self, s: &str) -> Option<String> {
+        self.canonical.get(s).cloned()
+    }
+
+    fn serialize(&self, value: &String) -> String {
+        self.slugs.get(value).unwrap_or(value).clone()
+    }
+}
+
+/// A calendar date, as converted by `DateConverter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Create a date, or `None` if it doesn't exist in the calendar.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Date> {
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Date { year, month, day })
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts `date` variables in `YYYY-MM-DD` form to and from `Date`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DateConverter;
+
+impl Converter for DateConverter {
+    type Value = Date;
+
+    fn parse(&self, s: &str) -> Option<Date> {
+        let mut fields = s.split('-');
+        let year = fields.next()?;
+        let month = fields.next()?;
+        let day = fields.next()?;
+        if fields.next().is_some() || year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+        if !s.bytes().all(|b| b.is_ascii_digit() || b == b'-') {
+            return None;
+        }
+        Date::new(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+    }
+
+    fn serialize(&self, value: &Date) -> String {
+        format!("{:04}-{:02}-{:02}", value.year, value.month, value.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_int_converter() {
+        assert_eq!(IntConverter.parse("10"), Some(10));
+        assert_eq!(IntConverter.parse("-10"), Some(-10));
+        assert_eq!(IntConverter.parse("+10"), None);
+        assert_eq!(IntConverter.parse("ten"), None);
+        assert_eq!(IntConverter.parse(""), None);
+        assert_eq!(IntConverter.serialize(&10), "10");
+    }
+
+    #[test]
+    fn test_date_converter() {
+        assert_eq!(DateConverter.parse("2019-12-31"), Date::new(2019, 12, 31));
+        assert_eq!(DateConverter.parse("2020-02-29"), Date::new(2020, 2, 29));
+        assert_eq!(DateConverter.parse("2019-02-29"), None);
+        assert_eq!(DateConverter.parse("2019-13-01"), None);
+        assert_eq!(DateConverter.parse("2019-1-01"), None);
+        assert_eq!(DateConverter.parse("2019-+1-01"), None);
+        assert_eq!(DateConverter.parse("2019-01-01-01"), None);
+        assert_eq!(
+            DateConverter.serialize(&Date::new(2019, 1, 2).unwrap()),
+            "2019-01-02"
+        );
+    }
+
+    #[test]
+    fn test_float_converter() {
+        assert_eq!(FloatConverter.parse("1.5"), Some(1.5));
+        assert_eq!(FloatConverter.parse("-10"), Some(-10.0));
+        assert_eq!(FloatConverter.parse("1."), None);
+        assert_eq!(FloatConverter.parse(".5"), None);
+        assert_eq!(FloatConverter.parse("1e5"), None);
+        assert_eq!(FloatConverter.parse("inf"), None);
+        assert_eq!(FloatConverter.parse("+1"), None);
+        assert_eq!(FloatConverter.serialize(&1.5), "1.5");
+        assert_eq!(FloatConverter.serialize(&1e20), "100000000000000000000");
+    }
+
+    #[test]
+    fn test_bool_converter() {
+        assert_eq!(BoolConverter.parse("true"), Some(true));
+        assert_eq!(BoolConverter.parse("false"), Some(false));
+        assert_eq!(BoolConverter.parse("True"), None);
+        assert_eq!(BoolConverter.serialize(&false), "false");
+    }
+
+    #[test]
+    fn test_uuid_converter() {
+        let uuid = Uuid(0x67e5504410b1426f9247bb680e5fe0c8);
+        assert_eq!(
+            UuidConverter.parse("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            Some(uuid)
+        );
+        assert_eq!(
+            UuidConverter.parse("67E55044-10B1-426F-9247-BB680E5FE0C8"),
+            Some(uuid)
+        );
+        assert_eq!(
+            UuidConverter.parse("67e5504410b1426f9247bb680e5fe0c8"),
+            None
+        );
+        assert_eq!(
+            UuidConverter.parse("67e55044-10b1-426f-9247-bb680e5fe0cx"),
+            None
+        );
+        assert_eq!(
+            UuidConverter.parse("+7e55044-10b1-426f-9247-bb680e5fe0c8"),
+            None
+        );
+        assert_eq!(
+            UuidConverter.serialize(&uuid),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+        assert_eq!(
+            UuidConverter.serialize(&Uuid(1)),
+            "00000000-0000-0000-0000-000000000001"
+        );
+    }
+
+    #[test]
+    fn test_slug_converter() {
+        assert_eq!(
+            SlugConverter.parse("hello-world-2"),
+            Some("hello-world-2".to_string())
+        );
+        assert_eq!(SlugConverter.parse("Hello"), None);
+        assert_eq!(SlugConverter.parse("-hello"), None);
+        assert_eq!(SlugConverter.parse("hello--world"), None);
+        assert_eq!(SlugConverter.parse(""), None);
+    }
+
+    #[test]
+    fn test_translated_converter() {
+        let converter = TranslatedConverter::new()
+            .with("chaise-rouge", "red-chair")
+            .with("chaise-bleue", "blue-chair")
+            .with("fauteuil-rouge", "red-chair");
+        assert_eq!(
+            converter.parse("chaise-rouge"),
+            Some("red-chair".to_string())
+        );
+        assert_eq!(
+            converter.parse("fauteuil-rouge"),
+            Some("red-chair".to_string())
+        );
+        assert_eq!(converter.parse("red-chair"), None);
+        assert_eq!(
+            converter.serialize(&"red-chair".to_string()),
+            "chaise-rouge"
+        );
+        assert_eq!(
+            converter.serialize(&"blue-chair".to_string()),
+            "chaise-bleue"
+        );
+        assert_eq!(
+            converter.serialize(&"green-chair".to_string()),
+            "green-chair"
+        );
+    }
+
+    struct EvenConverter;
+
+    impl Converter for EvenConverter {
+        type Value = u32;
+
+        fn parse(&self, s: &str) -> Option<u32> {
+            s.parse().ok().filter(|n| n % 2 == 0)
+        }
+
+        fn serialize(&self, value: &u32) -> String {
+            value.to_string()
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = ConverterRegistry::new();
+        assert!(registry.get("int").unwrap().validate("10"));
+        assert!(!registry.get("int").unwrap().validate("x"));
+        assert!(registry.get("date").unwrap().validate("2019-01-02"));
+        assert!(registry.get("unknown").is_none());
+    }
+
+    #[test]
+    fn test_registry_register() {
+        let mut registry = ConverterRegistry::new();
+        registry.register("even", EvenConverter);
+        registry.register("int", EvenConverter);
+        assert!(registry.contains("even"));
+        assert!(registry.get("even").unwrap().validate("2"));
+        assert!(!registry.get("int").unwrap().validate("3"));
+        assert!(!ConverterRegistry::new().contains("even"));
+    }
+
+    #[test]
+    fn test_registry_extended() {
+        let mut other = ConverterRegistry::new();
+        other.register("even", EvenConverter);
+        let registry = builtin().extended(&other);
+        assert!(registry.contains("even"));
+        assert!(registry.contains("date"));
+    }
+}
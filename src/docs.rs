@@ -0,0 +1,109 @@
+use crate::router::RouteInfo;
+
+/// Descriptions and examples of the variables of a route, to attach to it
+/// as metadata.
+///
+/// They are exported with the variables by `Router::to_openapi` and
+/// `Router::to_json_schema`, and are available from the iteration of the
+/// routes with `RouteInfo::variable_doc`.
+///
+/// ```
+/// use traject::{Metadata, Router, VariableDocs};
+///
+/// let mut router = Router::new();
+/// let docs = VariableDocs::new()
+///     .with("id", "numeric user id")
+///     .with_example("id", "42");
+/// router
+///     .register_with_metadata("users/{id:int}", 1, Metadata::new().with(docs))
+///     .unwrap();
+/// let route = router.iter().next().unwrap();
+/// assert_eq!(route.variable_doc("id").unwrap().description, "numeric user id");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariableDocs {
+    docs: Vec<(String, VariableDoc)>,
+}
+
+/// The documentation of a variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariableDoc {
+    pub description: String,
+    pub example: Option<String>,
+}
+
+impl VariableDocs {
+    pub fn new() -> VariableDocs {
+        VariableDocs::default()
+    }
+
+    /// Describe a variable.
+    pub fn with(mut self, name: &str, description: &str) -> VariableDocs {
+        self.entry(name).description = description.to_string();
+        self
+    }
+
+    /// Give an example value of a variable.
+    pub fn with_example(mut self, name: &str, example: &str) -> VariableDocs {
+        self.entry(name).example = Some(example.to_string());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&VariableDoc> {
+        self.docs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, doc)| doc)
+    }
+
+    /// The documented variables, in the order they were first documented.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &VariableDoc)> {
+        self.docs.iter().map(|(name, doc)| (name.as_str(), doc))
+    }
+
+    fn entry(&mut self, name: &str) -> &mut VariableDoc {
+        let index = match self.docs.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.docs.push((name.to_string(), VariableDoc::default()));
+                self.docs.len() - 1
+            }
+        };
+        &mut self.docs[index].1
+    }
+}
+
+impl<'a, T> RouteInfo<'a, T> {
+    /// The documentation of a variable of the route, from the
+    /// `VariableDocs` in its metadata.
+    pub fn variable_doc(&self, name: &str) -> Option<&'a VariableDoc> {
+        self.metadata.get::<VariableDocs>()?.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variable_docs() {
+        let docs = VariableDocs::new()
+            .with_example("page", "2")
+            .with("id", "numeric user id")
+            .with("page", "the page")
+            .with_example("id", "42");
+        assert_eq!(
+            docs.get("id"),
+            Some(&VariableDoc {
+                description: "numeric user id".to_string(),
+                example: Some("42".to_string()),
+            })
+        );
+        assert_eq!(docs.get("page").unwrap().description, "the page");
+        assert_eq!(docs.get("other"), None);
+        assert_eq!(
+            docs.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            vec!["page", "id"]
+        );
+    }
+}
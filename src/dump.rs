@@ -0,0 +1,201 @@
+//! Rendering the routes of a router as a tree of steps, to audit them.
+
+use std::fmt::Write;
+
+use crate::conflict::Conflict;
+use crate::router::Router;
+
+/// A node in the tree of steps. It has a child for each distinct step text
+/// that follows it, in registration order.
+#[derive(Debug, Default)]
+struct Tree<'a> {
+    children: Vec<(&'a str, Tree<'a>)>,
+    /// the routes whose patterns end here
+    routes: Vec<usize>,
+}
+
+impl<T> Router<T> {
+    /// The routes as a tree of steps, in plain text.
+    ///
+    /// Each step is on its own line, indented under the step before it,
+    /// with converters as written in the pattern; an empty step, as in the
+    /// pattern `a/`, is shown as `(empty)`. A step that patterns end
+    /// on lists their routes by registration index, with their name if
+    /// they have one. Routes that can never match, as reported by `check`,
+    /// say why.
+    pub fn to_tree(&self) -> String {
+        let mut out = String::new();
+        write_tree(&self.tree(), &self.route_labels(), 0, &mut out);
+        out
+    }
+
+    /// The tree of `to_tree` as a Graphviz graph in the DOT language.
+    ///
+    /// Steps that patterns end on are boxes, red if one of their routes can
+    /// never match.
+    pub fn to_dot(&self) -> String {
+        let labels = self.route_labels();
+        let conflicts: Vec<usize> = self.conflicts().into_iter().map(|(i, _)| i).collect();
+        let mut out = String::from("digraph routes {\n    n0 [label=\"\", shape=point];\n");
+        let mut next = 1;
+        write_dot(&self.tree(), 0, &labels, &conflicts, &mut next, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    fn tree(&self) -> Tree<'_> {
+        let mut root = Tree::default();
+        for (index, route) in self.iter().enumerate() {
+            for steps in route.pattern.variants() {
+                let mut node = &mut root;
+                for step in steps {
+                    let position = node.children.iter().position(|(s, _)| *s == step.s);
+                    let position = position.unwrap_or_else(|| {
+                        node.children.push((&step.s, Tree::default()));
+                        node.children.len() - 1
+                    });
+                    node = &mut node.children[position].1;
+                }
+                node.routes.push(index);
+            }
+        }
+        root
+    }
+
+    /// A label for each route, like ``#1 detail (shadowed by `a/{x}`)``.
+    fn route_labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .iter()
+            .enumerate()
+            .map(|(index, route)| match route.name {
+                Some(name) => format!("#{} {}", index, name),
+                None => format!("#{}", index),
+            })
+            .collect();
+        for (index, conflict) in self.conflicts() {
+            let reason = match conflict {
+                Conflict::Duplicate { earlier, .. } => format!(" (duplicates `{}`)", earlier),
+                Conflict::Shadowed { earlier, .. } => format!(" (shadowed by `{}`)", earlier),
+            };
+            labels[index].push_str(&reason);
+        }
+        labels
+    }
+}
+
+fn step_text(step: &str) -> &str {
+    if step.is_empty() {
+        "(empty)"
+    } else {
+        step
+    }
+}
+
+fn join_labels(labels: &[String], routes: &[usize]) -> String {
+    let labels: Vec<&str> = routes.iter().map(|&i| labels[i].as_str()).collect();
+    labels.join(", ")
+}
+
+fn write_tree(tree: &Tree, labels: &[String], depth: usize, out: &mut String) {
+    for (step, child) in &tree.children {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(step_text(step));
+        if !child.routes.is_empty() {
+            out.push_str(" -> ");
+            out.push_str(&join_labels(labels, &child.routes));
+        }
+        out.push('\n');
+        write_tree(child, labels, depth + 1, out);
+    }
+}
+
+fn write_dot(
+    tree: &Tree,
+    id: usize,
+    labels: &[String],
+    conflicts: &[usize],
+    next: &mut usize,
+    out: &mut String,
+) {
+    for (step, child) in &tree.children {
+        let child_id = *next;
+        *next += 1;
+        let mut label = step_text(step).to_string();
+        for &route in &child.routes {
+            label.push('\n');
+            label.push_str(&labels[route]);
+        }
+        write!(out, "    n{} [label=\"{}\"", child_id, escape(&label)).unwrap();
+        if !child.routes.is_empty() {
+            out.push_str(", shape=box");
+            if child.routes.iter().any(|route| conflicts.contains(route)) {
+                out.push_str(", color=red");
+            }
+        }
+        writeln!(out, "];\n    n{} -> n{};", id, child_id).unwrap();
+        write_dot(child, child_id, labels, conflicts, next, out);
+    }
+}
+
+/// Escape text for a quoted DOT string.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> Router<i32> {
+        let mut router = Router::new();
+        router.register_named("root", "", 0).unwrap();
+        router
+            .register_named("detail", "departments/{d}/employees/{e}", 1)
+            .unwrap();
+        router
+            .register("departments/{d}/employees/{e:int}", 2)
+            .unwrap();
+        router.register("departments/{x}/employees/{y}", 3).unwrap();
+        router.register("a[/{b}]", 4).unwrap();
+        router
+    }
+
+    #[test]
+    fn test_to_tree() {
+        assert_eq!(
+            router().to_tree(),
+            "(empty) -> #0 root
+departments
+  {d}
+    employees
+      {e} -> #1 detail
+      {e:int} -> #2
+  {x}
+    employees
+      {y} -> #3 (duplicates `departments/{d}/employees/{e}`)
+a -> #4
+  {b} -> #4
+"
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = router().to_dot();
+        assert!(dot.starts_with("digraph routes {\n    n0 [label=\"\", shape=point];\n"));
+        assert!(dot.contains("    n2 [label=\"departments\"];\n    n0 -> n2;\n"));
+        assert!(dot.contains(
+            "[label=\"{y}\\n#3 (duplicates `departments/{d}/employees/{e}`)\", \
+             shape=box, color=red];"
+        ));
+        assert!(dot.contains("[label=\"{e}\\n#1 detail\", shape=box];"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
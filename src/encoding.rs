@@ -0,0 +1,260 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// Decode `%XX` escapes.
+///
+/// Returns `None` if an escape is malformed or the decoded bytes aren't
+/// UTF-8.
+pub fn percent_decode(s: &str) -> Option<Cow<'_, str>> {
+    if !s.contains('%') {
+        return Some(Cow::Borrowed(s));
+    }
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let high = hex_value(*bytes.get(i + 1)?)?;
+            let low = hex_value(*bytes.get(i + 2)?)?;
+            decoded.push(high << 4 | low);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok().map(Cow::Owned)
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Encode a value for use as (part of) a path segment.
+///
+/// Everything but unreserved characters, sub-delimiters, `:` and `@` is
+/// escaped, so the result never contains `/`, `?` or `#`.
+pub fn percent_encode(s: &str) -> Cow<'_, str> {
+    encode(s, is_pchar)
+}
+
+/// Encode a value that spans several segments, keeping its `/`.
+pub fn percent_encode_path(s: &str) -> Cow<'_, str> {
+    encode(s, |b| b == b'/' || is_pchar(b))
+}
+
+/// Encode a key or value in a query string, escaping `&`, `=` and `+` too.
+pub fn percent_encode_query(s: &str) -> Cow<'_, str> {
+    encode(s, |b| is_pchar(b) && !b"&=+".contains(&b))
+}
+
+/// Like `percent_encode`, also escaping `separator`, for the segments of a
+/// path whose steps are separated by something else than `/`.
+pub(crate) fn percent_encode_segment(s: &str, separator: char) -> Cow<'_, str> {
+    encode(s, |b| is_pchar(b) && !is_separator(b, separator))
+}
+
+/// Like `percent_encode_path`, keeping `separator` instead of `/`.
+pub(crate) fn percent_encode_rest(s: &str, separator: char) -> Cow<'_, str> {
+    encode(s, |b| {
+        is_separator(b, separator) || is_pchar(b) && b != b'/'
+    })
+}
+
+/// Turn an IRI, with non-ASCII characters in it, into a URI by
+/// percent-encoding their UTF-8 bytes. ASCII is left as it is.
+pub fn iri_to_uri(s: &str) -> Cow<'_, str> {
+    encode(s, |b| b.is_ascii())
+}
+
+/// Turn a URI into an IRI by decoding the escapes of non-ASCII characters,
+/// like `%C3%A9` to `é`. Escapes of ASCII, of non-ASCII whitespace or
+/// control characters, and escapes that aren't UTF-8 are left as they are.
+pub fn uri_to_iri(s: &str) -> Cow<'_, str> {
+    decode_non_ascii(s, None)
+}
+
+/// Like `uri_to_iri`, also leaving the escapes of `separator` as they are.
+pub(crate) fn decode_non_ascii(s: &str, separator: Option<char>) -> Cow<'_, str> {
+    if !s.contains('%') {
+        return Cow::Borrowed(s);
+    }
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('%') {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        match escaped_char(rest)
+            .filter(|c| !c.is_control() && !c.is_whitespace() && Some(*c) != separator)
+        {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[c.len_utf8() * 3..];
+            }
+            None => {
+                decoded.push('%');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    if decoded == s {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(decoded)
+    }
+}
+
+/// The non-ASCII character whose UTF-8 bytes are escaped at the start of a
+/// string.
+fn escaped_char(s: &str) -> Option<char> {
+    let byte = |i: usize| -> Option<u8> {
+        let escape = s.get(i * 3..i * 3 + 3)?.as_bytes();
+        if escape[0] != b'%' {
+            return None;
+        }
+        Some(hex_value(escape[1])? << 4 | hex_value(escape[2])?)
+    };
+    let first = byte(0)?;
+    let len = match first {
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return None,
+    };
+    let mut bytes = [first, 0, 0, 0];
+    for (i, b) in bytes.iter_mut().enumerate().take(len).skip(1) {
+        *b = byte(i)?;
+    }
+    std::str::from_utf8(&bytes[..len]).ok()?.chars().next()
+}
+
+fn is_separator(b: u8, separator: char) -> bool {
+    separator.is_ascii() && b == separator as u8
+}
+
+fn is_pchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&b)
+}
+
+fn encode(s: &str, keep: impl Fn(u8) -> bool) -> Cow<'_, str> {
+    if s.bytes().all(&keep) {
+        return Cow::Borrowed(s);
+    }
+    let mut encoded = String::with_capacity(s.len() * 3);
+    for b in s.bytes() {
+        if keep(b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    Cow::Owned(encoded)
+}
+
+/// A JSON string.
+pub(crate) fn json_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c < ' ' => write!(quoted, "\\u{:04x}", c as u32).expect("write to string"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("foo").unwrap(), "foo");
+        assert_eq!(percent_decode("foo%20bar").unwrap(), "foo bar");
+        assert_eq!(percent_decode("%2f%2F").unwrap(), "//");
+        assert_eq!(percent_decode("caf%C3%A9").unwrap(), "café");
+    }
+
+    #[test]
+    fn test_percent_decode_borrows_unescaped() {
+        assert!(matches!(percent_decode("foo"), Some(Cow::Borrowed("foo"))));
+    }
+
+    #[test]
+    fn test_percent_decode_invalid() {
+        assert_eq!(percent_decode("foo%"), None);
+        assert_eq!(percent_decode("foo%2"), None);
+        assert_eq!(percent_decode("foo%zz"), None);
+        assert_eq!(percent_decode("%C3"), None);
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("foo"), "foo");
+        assert_eq!(percent_encode("foo bar"), "foo%20bar");
+        assert_eq!(percent_encode("a/b?c#d%"), "a%2Fb%3Fc%23d%25");
+        assert_eq!(percent_encode("café"), "caf%C3%A9");
+        assert_eq!(percent_encode("a:b@c"), "a:b@c");
+    }
+
+    #[test]
+    fn test_percent_encode_path() {
+        assert_eq!(percent_encode_path("a b/c"), "a%20b/c");
+    }
+
+    #[test]
+    fn test_percent_encode_query() {
+        assert_eq!(percent_encode_query("a b&c=d+e/f"), "a%20b%26c%3Dd%2Be%2Ff");
+    }
+
+    #[test]
+    fn test_percent_encode_with_separator() {
+        assert_eq!(percent_encode_segment("a.b/c", '/'), "a.b%2Fc");
+        assert_eq!(percent_encode_segment("a.b/c", '.'), "a%2Eb%2Fc");
+        assert_eq!(percent_encode_segment("a→b", '→'), "a%E2%86%92b");
+        assert_eq!(percent_encode_rest("a.b/c", '/'), "a.b/c");
+        assert_eq!(percent_encode_rest("a.b/c", '.'), "a.b%2Fc");
+    }
+
+    #[test]
+    fn test_iri_to_uri() {
+        assert_eq!(
+            iri_to_uri("produits/été?q=crème"),
+            "produits/%C3%A9t%C3%A9?q=cr%C3%A8me"
+        );
+        assert!(matches!(iri_to_uri("a b/c%20"), Cow::Borrowed("a b/c%20")));
+    }
+
+    #[test]
+    fn test_uri_to_iri() {
+        assert_eq!(uri_to_iri("produits/%C3%A9t%c3%a9"), "produits/été");
+        assert_eq!(uri_to_iri("a%2Fb%20%E2%86%92"), "a%2Fb%20→");
+        assert_eq!(uri_to_iri("%C2%A0%C3%A9%C3"), "%C2%A0é%C3");
+        assert_eq!(uri_to_iri("%E9%C3%28"), "%E9%C3%28");
+        assert_eq!(decode_non_ascii("a%E2%86%92b", Some('→')), "a%E2%86%92b");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let s = "hello wörld/?#%";
+        assert_eq!(percent_decode(&percent_encode(s)).unwrap(), s);
+    }
+
+    #[test]
+    fn test_json_quote() {
+        assert_eq!(json_quote("a\"b\\c\n\u{1}é"), r#""a\"b\\c\n\u0001é""#);
+    }
+}
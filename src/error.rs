@@ -0,0 +1,381 @@
+use std::error;
+use std::fmt;
+
+/// An error parsing a pattern.
+///
+/// Offsets are byte offsets into the pattern string as it was given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A `{` without a matching `}`, or a `}` without a matching `{`.
+    UnbalancedBrace { offset: usize },
+    /// A variable name that isn't an identifier.
+    InvalidIdentifier { name: String, offset: usize },
+    /// A variable name that was already used earlier in the pattern.
+    DuplicateVariable { name: String, offset: usize },
+    /// A variable directly following another variable, so that there is
+    /// more than one way to tell where one ends and the next starts.
+    ConsecutiveVariables { offset: usize },
+    /// A converter name that isn't known.
+    UnknownConverter { name: String, offset: usize },
+    /// A wildcard variable that isn't the whole of the last step.
+    MisplacedWildcard { offset: usize },
+    /// A bracket that isn't part of trailing optional steps like `[/{page}]`.
+    MisplacedOptional { offset: usize },
+    /// A regex constraint like `{sku:re:[A-Z]+}` with a regex that doesn't
+    /// compile.
+    InvalidRegex { regex: String, offset: usize },
+    /// A query parameter that isn't like `key={value}` or `[key={value}]`.
+    InvalidQuery { offset: usize },
+    /// A regex constraint, when the crate is built without the `regex`
+    /// feature.
+    UnsupportedRegex { offset: usize },
+    /// A default value like `{page:int=1}` that is empty, or that the
+    /// converter of the variable doesn't accept.
+    InvalidDefault { value: String, offset: usize },
+    /// A pattern registered with `Router::register_grpc` that isn't a
+    /// service step and a method step. The offset is always 0, as this is
+    /// about the pattern as a whole.
+    InvalidGrpc { offset: usize },
+    /// A token variable given to `Router::register_with_token` that isn't
+    /// in the pattern. The offset is always 0.
+    UnknownTokenVariable { name: String, offset: usize },
+}
+
+impl ParseError {
+    /// The byte offset into the pattern where the problem is.
+    pub fn offset(&self) -> usize {
+        match self {
+            ParseError::UnbalancedBrace { offset }
+            | ParseError::InvalidIdentifier { offset, .. }
+            | ParseError::DuplicateVariable { offset, .. }
+            | ParseError::ConsecutiveVariables { offset }
+            | ParseError::UnknownConverter { offset, .. }
+            | ParseError::MisplacedWildcard { offset }
+            | ParseError::MisplacedOptional { offset }
+            | ParseError::InvalidRegex { offset, .. }
+            | ParseError::InvalidQuery { offset }
+            | ParseError::UnsupportedRegex { offset }
+            | ParseError::InvalidDefault { offset, .. }
+            | ParseError::InvalidGrpc { offset }
+            | ParseError::UnknownTokenVariable { offset, .. } => *offset,
+        }
+    }
+
+    /// Move the offset, for an error found in a part of a larger string.
+    pub(crate) fn shifted(mut self, by: usize) -> ParseError {
+        match &mut self {
+            ParseError::UnbalancedBrace { offset }
+            | ParseError::InvalidIdentifier { offset, .. }
+            | ParseError::DuplicateVariable { offset, .. }
+            | ParseError::ConsecutiveVariables { offset }
+            | ParseError::UnknownConverter { offset, .. }
+            | ParseError::MisplacedWildcard { offset }
+            | ParseError::MisplacedOptional { offset }
+            | ParseError::InvalidRegex { offset, .. }
+            | ParseError::InvalidQuery { offset }
+            | ParseError::UnsupportedRegex { offset }
+            | ParseError::InvalidDefault { offset, .. }
+            | ParseError::InvalidGrpc { offset }
+            | ParseError::UnknownTokenVariable { offset, .. } => *offset += by,
+        }
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnbalancedBrace { offset } => {
+                write!(f, "unbalanced brace at offset {}", offset)
+            }
+            ParseError::InvalidIdentifier { name, offset } => {
+                write!(f, "invalid variable name `{}` at offset {}", name, offset)
+            }
+            ParseError::DuplicateVariable { name, offset } => {
+                write!(f, "duplicate variable `{}` at offset {}", name, offset)
+            }
+            ParseError::ConsecutiveVariables { offset } => {
+                write!(f, "ambiguous consecutive variables at offset {}", offset)
+            }
+            ParseError::UnknownConverter { name, offset } => {
+                write!(f, "unknown converter `{}` at offset {}", name, offset)
+            }
+            ParseError::MisplacedWildcard { offset } => write!(
+                f,
+                "wildcard at offset {} is not the whole of the last step",
+                offset
+            ),
+            ParseError::MisplacedOptional { offset } => write!(
+                f,
+                "bracket at offset {} does not enclose trailing optional steps",
+                offset
+            ),
+            ParseError::InvalidRegex { regex, offset } => {
+                write!(f, "invalid regex `{}` at offset {}", regex, offset)
+            }
+            ParseError::InvalidQuery { offset } => {
+                write!(f, "invalid query parameter at offset {}", offset)
+            }
+            ParseError::UnsupportedRegex { offset } => write!(
+                f,
+                "regex constraint at offset {} needs the `regex` feature",
+                offset
+            ),
+            ParseError::InvalidDefault { value, offset } => {
+                write!(f, "invalid default value `{}` at offset {}", value, offset)
+            }
+            ParseError::InvalidGrpc { offset } => {
+                write!(
+                    f,
+                    "pattern at offset {} is not a gRPC service and method",
+                    offset
+                )
+            }
+            ParseError::UnknownTokenVariable { name, offset } => write!(
+                f,
+                "token variable `{}` at offset {} is not in the pattern",
+                name, offset
+            ),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// An error expanding a pattern into a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpandError {
+    /// No value was given for a variable in the pattern.
+    MissingVariable { name: String },
+    /// A value was given for a variable that isn't in the pattern.
+    ExtraVariable { name: String },
+    /// A value isn't accepted for a variable other than by its converter,
+    /// for instance because it contains the separator.
+    InvalidValue { name: String, value: String },
+    /// The converter of a variable doesn't accept the given value.
+    Conversion(ConversionError),
+    /// A typed value doesn't have the type of the variable's converter.
+    WrongType { name: String },
+    /// No route was registered with the name.
+    UnknownRoute { name: String },
+}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpandError::MissingVariable { name } => write!(f, "missing variable `{}`", name),
+            ExpandError::ExtraVariable { name } => {
+                write!(f, "variable `{}` is not in the pattern", name)
+            }
+            ExpandError::InvalidValue { name, value } => {
+                write!(f, "invalid value `{}` for variable `{}`", value, name)
+            }
+            ExpandError::Conversion(error) => error.fmt(f),
+            ExpandError::WrongType { name } => {
+                write!(f, "value of the wrong type for variable `{}`", name)
+            }
+            ExpandError::UnknownRoute { name } => write!(f, "no route named `{}`", name),
+        }
+    }
+}
+
+impl error::Error for ExpandError {}
+
+/// Whether a converter rejected a value matching a path or expanding a
+/// pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Decode,
+    Encode,
+}
+
+/// A converter that rejected the value of a variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    pub name: String,
+    pub value: String,
+    pub converter: String,
+    pub direction: Direction,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.direction {
+            Direction::Decode => write!(
+                f,
+                "converter `{}` can't decode `{}` captured by variable `{}`",
+                self.converter, self.value, self.name
+            ),
+            Direction::Encode => write!(
+                f,
+                "converter `{}` doesn't accept `{}` as a value of variable `{}`",
+                self.converter, self.value, self.name
+            ),
+        }
+    }
+}
+
+impl error::Error for ConversionError {}
+
+/// Why a router rejected a path, before matching it or because the route
+/// that matched it doesn't accept it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidPath {
+    /// A control character, NUL or whitespace, raw or percent-encoded, at
+    /// a byte offset into the path.
+    ForbiddenCharacter { character: char, offset: usize },
+    /// A percent-encoded separator at a byte offset into the path, with
+    /// `EncodedSeparator::Reject`.
+    EncodedSeparator { offset: usize },
+    /// An escape of an escape, like `%2520`, at a byte offset into the
+    /// path, that the `DoubleEncoding` policy doesn't allow.
+    DoubleEncoded { offset: usize },
+    /// A signed path whose signature is missing or doesn't verify.
+    BadSignature,
+    /// A signed path whose expiry time has passed.
+    Expired,
+    /// A path of a route with a token, whose token isn't valid.
+    InvalidToken,
+}
+
+impl fmt::Display for InvalidPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidPath::ForbiddenCharacter { character, offset } => write!(
+                f,
+                "forbidden character U+{:04X} at offset {}",
+                u32::from(*character),
+                offset
+            ),
+            InvalidPath::EncodedSeparator { offset } => {
+                write!(f, "encoded separator at offset {}", offset)
+            }
+            InvalidPath::DoubleEncoded { offset } => {
+                write!(f, "double-encoded escape at offset {}", offset)
+            }
+            InvalidPath::BadSignature => f.write_str("missing or invalid signature"),
+            InvalidPath::Expired => f.write_str("signed path has expired"),
+            InvalidPath::InvalidToken => f.write_str("invalid token"),
+        }
+    }
+}
+
+impl error::Error for InvalidPath {}
+
+/// Why `StaticFiles::resolve` didn't give a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileError {
+    /// A step that could lead out of the root, like `..`.
+    Traversal,
+    /// A symbolic link that the `Symlinks` policy doesn't follow.
+    Symlink,
+    /// A file without one of the allowed extensions.
+    Extension,
+    /// No file, or something other than a file, like a directory.
+    NotFound,
+    /// The file system gave another error.
+    Io { kind: std::io::ErrorKind },
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::Traversal => f.write_str("path leads out of the root"),
+            FileError::Symlink => f.write_str("path goes through a forbidden symbolic link"),
+            FileError::Extension => f.write_str("file extension isn't allowed"),
+            FileError::NotFound => f.write_str("file not found"),
+            FileError::Io { kind } => write!(f, "file system error: {}", kind),
+        }
+    }
+}
+
+impl error::Error for FileError {}
+
+/// An error loading a router from a routes file.
+#[cfg(feature = "config")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The file isn't valid, or doesn't have the expected structure.
+    Invalid { message: String, line: usize },
+    /// A pattern in the file doesn't parse.
+    Pattern { error: ParseError, line: usize },
+    /// Arrays and objects in the file are nested deeper than the limit.
+    TooDeep { limit: usize, line: usize },
+    /// The file has a newer version than the one this can load, or a
+    /// version that doesn't exist.
+    UnsupportedVersion { version: i32, line: usize },
+}
+
+#[cfg(feature = "config")]
+impl ConfigError {
+    /// The line in the file where the problem is.
+    pub fn line(&self) -> usize {
+        match self {
+            ConfigError::Invalid { line, .. }
+            | ConfigError::Pattern { line, .. }
+            | ConfigError::TooDeep { line, .. }
+            | ConfigError::UnsupportedVersion { line, .. } => *line,
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Invalid { message, line } => write!(f, "line {}: {}", line, message),
+            ConfigError::Pattern { error, line } => write!(f, "line {}: {}", line, error),
+            ConfigError::TooDeep { limit, line } => {
+                write!(f, "line {}: nested more than {} deep", line, limit)
+            }
+            ConfigError::UnsupportedVersion { version, line } => write!(
+                f,
+                "line {}: unsupported version {}, the newest supported is {}",
+                line,
+                version,
+                crate::config::RouteConfig::VERSION
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConfigError::Invalid { .. }
+            | ConfigError::TooDeep { .. }
+            | ConfigError::UnsupportedVersion { .. } => None,
+            ConfigError::Pattern { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_display() {
+        let error = ParseError::DuplicateVariable {
+            name: "a".to_string(),
+            offset: 5,
+        };
+        assert_eq!(error.to_string(), "duplicate variable `a` at offset 5");
+        assert_eq!(error.offset(), 5);
+    }
+
+    #[test]
+    fn test_parse_error_shifted() {
+        let error = ParseError::UnbalancedBrace { offset: 1 }.shifted(3);
+        assert_eq!(error, ParseError::UnbalancedBrace { offset: 4 });
+    }
+
+    #[test]
+    fn test_expand_error_display() {
+        let error = ExpandError::MissingVariable {
+            name: "a".to_string(),
+        };
+        assert_eq!(error.to_string(), "missing variable `a`");
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::converter::NamedConverter;
+use crate::pattern::Pattern;
+
+/// How many sets of values to try before giving up on a pattern.
+const ATTEMPTS: usize = 16;
+
+/// Generates a sample value.
+type Sample<R> = fn(&mut R) -> String;
+
+impl Pattern {
+    /// A path this pattern matches, with sample values for its variables:
+    /// numbers for `int` and `float`, random UUIDs for `uuid`, words for
+    /// `slug` and variables without a converter, and text a regex
+    /// constraint matches. The query has all the parameters of the
+    /// pattern.
+    ///
+    /// A variable with another converter gets the first of those samples
+    /// its converter accepts. `None` if no values were found that make a
+    /// path the pattern matches.
+    pub fn example_url<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<String> {
+        for _ in 0..ATTEMPTS {
+            let values = self
+                .names()
+                .map(|name| Some((name, self.sample(name, rng)?)))
+                .collect::<Option<Vec<_>>>()?;
+            let values: HashMap<&str, &str> = values
+                .iter()
+                .map(|(name, value)| (*name, value.as_str()))
+                .collect();
+            if let Ok(path) = self.expand(&values) {
+                if self.match_path(&path).is_some() {
+                    return Some(path);
+                }
+            }
+        }
+        None
+    }
+
+    fn sample<R: Rng + ?Sized>(&self, name: &str, rng: &mut R) -> Option<String> {
+        let converter = match self.converter(name)? {
+            None if self.is_wildcard(name) => {
+                return Some(format!("{}{}{}", word(rng), self.separator(), word(rng)))
+            }
+            None => return Some(word(rng)),
+            Some(converter) => converter,
+        };
+        if let Some(value) = regex_sample(converter, rng) {
+            return Some(value);
+        }
+        let samples: [(&str, Sample<R>); 7] = [
+            ("int", int),
+            ("float", float),
+            ("bool", boolean),
+            ("uuid", uuid),
+            ("date", date),
+            ("slug", slug),
+            ("", word),
+        ];
+        let preferred = samples.iter().find(|(name, _)| *name == converter.name);
+        preferred
+            .into_iter()
+            .chain(&samples)
+            .map(|(_, sample)| sample(rng))
+            .find(|value| converter.validate(value))
+    }
+}
+
+#[cfg(feature = "regex")]
+fn regex_sample<R: Rng + ?Sized>(converter: &NamedConverter, rng: &mut R) -> Option<String> {
+    let regex = converter.name.strip_prefix("re:")?;
+    let hir = regex_syntax::Parser::new().parse(regex).ok()?;
+    let mut value = String::new();
+    hir_sample(&hir, rng, &mut value)?;
+    Some(value).filter(|value| converter.validate(value))
+}
+
+#[cfg(not(feature = "regex"))]
+fn regex_sample<R: Rng + ?Sized>(_converter: &NamedConverter, _rng: &mut R) -> Option<String> {
+    None
+}
+
+/// Add text that the regex matches. Character classes are sampled from
+/// their printable ASCII characters, if they have any.
+#[cfg(feature = "regex")]
+fn hir_sample<R: Rng + ?Sized>(
+    hir: &regex_syntax::hir::Hir,
+    rng: &mut R,
+    value: &mut String,
+) -> Option<()> {
+    use regex_syntax::hir::{Class, HirKind};
+
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(literal) => value.push_str(std::str::from_utf8(&literal.0).ok()?),
+        HirKind::Class(Class::Unicode(class)) => {
+            let ranges: Vec<_> = class
+                .ranges()
+                .iter()
+                .map(|range| (u32::from(range.start()), u32::from(range.end())))
+                .collect();
+            value.push(char::from_u32(class_sample(&ranges, rng)?)?);
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            let ranges: Vec<_> = class
+                .ranges()
+                .iter()
+                .map(|range| (u32::from(range.start()), u32::from(range.end())))
+                .filter(|&(start, _)| start < 0x80)
+                .map(|(start, end)| (start, end.min(0x7f)))
+                .collect();
+            value.push(char::from_u32(class_sample(&ranges, rng)?)?);
+        }
+        HirKind::Repetition(repetition) => {
+            let max = repetition.max.unwrap_or(u32::MAX).min(repetition.min + 3);
+            for _ in 0..rng.gen_range(repetition.min, max + 1) {
+                hir_sample(&repetition.sub, rng, value)?;
+            }
+        }
+        HirKind::Capture(capture) => hir_sample(&capture.sub, rng, value)?,
+        HirKind::Concat(hirs) => {
+            for hir in hirs {
+                hir_sample(hir, rng, value)?;
+            }
+        }
+        HirKind::Alternation(hirs) => hir_sample(hirs.choose(rng)?, rng, value)?,
+    }
+    Some(())
+}
+
+/// A code point from inclusive ranges, preferring printable ASCII.
+#[cfg(feature = "regex")]
+fn class_sample<R: Rng + ?Sized>(ranges: &[(u32, u32)], rng: &mut R) -> Option<u32> {
+    let printable: Vec<_> = ranges
+        .iter()
+        .filter(|&&(start, end)| start <= 0x7e && end >= 0x21)
+        .map(|&(start, end)| (start.max(0x21), end.min(0x7e)))
+        .collect();
+    let ranges = if printable.is_empty() {
+        ranges
+    } else {
+        &printable
+    };
+    let &(start, end) = ranges.choose(rng)?;
+    Some(rng.gen_range(start, end + 1))
+}
+
+fn int<R: Rng + ?Sized>(rng: &mut R) -> String {
+    rng.gen_range(0, 10_000).to_string()
+}
+
+fn float<R: Rng + ?Sized>(rng: &mut R) -> String {
+    format!("{}.{}", rng.gen_range(0, 1000), rng.gen_range(0, 100))
+}
+
+fn boolean<R: Rng + ?Sized>(rng: &mut R) -> String {
+    rng.gen::<bool>().to_string()
+}
+
+fn uuid<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let mut bytes: [u8; 16] = rng.gen();
+    // version 4, variant 1
+    bytes[6] = bytes[6] & 0x0f | 0x40;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn date<R: Rng + ?Sized>(rng: &mut R) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        rng.gen_range(1970, 2100),
+        rng.gen_range(1, 13),
+        rng.gen_range(1, 29)
+    )
+}
+
+fn slug<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let words: Vec<_> = (0..rng.gen_range(1, 4)).map(|_| word(rng)).collect();
+    words.join("-")
+}
+
+fn word<R: Rng + ?Sized>(rng: &mut R) -> String {
+    (0..rng.gen_range(3, 9))
+        .map(|_| char::from(rng.gen_range(b'a', b'z' + 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::converter::{Converter, ConverterRegistry};
+
+    #[test]
+    fn test_example_url() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for s in [
+            "",
+            "users/{id:int}/posts/{slug:slug}",
+            "items/{id:uuid}.{format:bool}",
+            "{a}x{b}/{day:date}_{price:float}",
+            "files/{rest:*}",
+            "a[/{b}/{c:int=1}]?page={page:int}&[q={q}]",
+        ] {
+            let pattern = Pattern::new(s).unwrap();
+            for _ in 0..20 {
+                let path = pattern.example_url(&mut rng).expect(s);
+                assert!(pattern.match_path(&path).is_some(), "{} {}", s, path);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_example_url_regex() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let pattern = Pattern::new(r"sku/{sku:re:[A-Z]{3}-\d+}/{v:re:v(1|2)}").unwrap();
+        for _ in 0..20 {
+            let path = pattern.example_url(&mut rng).unwrap();
+            assert!(pattern.match_path(&path).is_some(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_example_url_custom_converter() {
+        struct Even;
+
+        impl Converter for Even {
+            type Value = u32;
+
+            fn parse(&self, s: &str) -> Option<u32> {
+                s.parse().ok().filter(|n| n % 2 == 0)
+            }
+
+            fn serialize(&self, value: &u32) -> String {
+                value.to_string()
+            }
+        }
+
+        struct Never;
+
+        impl Converter for Never {
+            type Value = String;
+
+            fn parse(&self, _s: &str) -> Option<String> {
+                None
+            }
+
+            fn serialize(&self, value: &String) -> String {
+                value.clone()
+            }
+        }
+
+        let mut converters = ConverterRegistry::new();
+        converters.register("even", Even);
+        converters.register("never", Never);
+        let mut rng = StdRng::seed_from_u64(0);
+        let even = Pattern::with_converters("n/{n:even}", &converters).unwrap();
+        let path = even.example_url(&mut rng).unwrap();
+        assert!(even.match_path(&path).is_some(), "{}", path);
+        let never = Pattern::with_converters("n/{n:never}", &converters).unwrap();
+        assert_eq!(never.example_url(&mut rng), None);
+    }
+}
@@ -0,0 +1,249 @@
+//! Mapping the rest of a path onto files under a root directory, for
+//! serving static files without letting paths escape the root.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::FileError;
+
+/// Which symbolic links `StaticFiles` follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Symlinks {
+    /// Links are followed as long as the file they lead to is under the
+    /// root, and the path of that file is what `resolve` returns.
+    #[default]
+    WithinRoot,
+    /// No step of the path under the root can be a link.
+    Deny,
+    /// Links are followed wherever they lead.
+    Follow,
+}
+
+/// The files under a root directory, as named by the capture of a
+/// wildcard variable like `file` in `static/{file:*}`.
+///
+/// `resolve` turns a capture into the path of a file under the root. It
+/// never returns a path outside the root: `..` steps, backslashes and NUL
+/// are rejected rather than normalized away, and so are symbolic links as
+/// `Symlinks` says. Leading separators are skipped like empty steps, so an
+/// absolute path names a file under the root too. It only returns regular
+/// files, and with an extension allow-list only files with one of those
+/// extensions.
+#[derive(Debug, Clone)]
+pub struct StaticFiles {
+    root: PathBuf,
+    symlinks: Symlinks,
+    extensions: Option<Vec<String>>,
+}
+
+impl StaticFiles {
+    pub fn new<P: Into<PathBuf>>(root: P) -> StaticFiles {
+        StaticFiles {
+            root: root.into(),
+            symlinks: Symlinks::default(),
+            extensions: None,
+        }
+    }
+
+    pub fn with_symlinks(mut self, symlinks: Symlinks) -> StaticFiles {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Only allow files with one of these extensions, like `css`, ignoring
+    /// ASCII case.
+    pub fn with_extensions(mut self, extensions: &[&str]) -> StaticFiles {
+        self.extensions = Some(extensions.iter().map(|e| e.to_string()).collect());
+        self
+    }
+
+    /// The path of the file that a capture names, like `css/site.css`.
+    ///
+    /// Empty and `.` steps are skipped. The capture should be decoded
+    /// already, as it is with percent-encoding enabled.
+    pub fn resolve(&self, relative: &str) -> Result<PathBuf, FileError> {
+        let mut path = self.root.clone();
+        for step in relative.split('/') {
+            if step.is_empty() || step == "." {
+                continue;
+            }
+            if step.contains(['\\', '\0']) || !is_normal(step) {
+                return Err(FileError::Traversal);
+            }
+            path.push(step);
+        }
+        if let Some(extensions) = &self.extensions {
+            let allowed = path
+                .extension()
+                .and_then(OsStr::to_str)
+                .is_some_and(|extension| {
+                    extensions
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(extension))
+                });
+            if !allowed {
+                return Err(FileError::Extension);
+            }
+        }
+        let path = match self.symlinks {
+            Symlinks::WithinRoot => {
+                let root = fs::canonicalize(&self.root).map_err(io_error)?;
+                let target = fs::canonicalize(&path).map_err(io_error)?;
+                if !target.starts_with(root) {
+                    return Err(FileError::Symlink);
+                }
+                target
+            }
+            Symlinks::Deny => {
+                let mut current = self.root.clone();
+                for component in path
+                    .strip_prefix(&self.root)
+                    .expect("under root")
+                    .components()
+                {
+                    current.push(component);
+                    let metadata = fs::symlink_metadata(&current).map_err(io_error)?;
+                    if metadata.file_type().is_symlink() {
+                        return Err(FileError::Symlink);
+                    }
+                }
+                path
+            }
+            Symlinks::Follow => path,
+        };
+        if fs::metadata(&path).map_err(io_error)?.is_file() {
+            Ok(path)
+        } else {
+            Err(FileError::NotFound)
+        }
+    }
+}
+
+/// Whether a step is a single plain file name, not a root, a prefix like a
+/// drive or `..`.
+fn is_normal(step: &str) -> bool {
+    let mut components = Path::new(step).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(name)), None) if name == step
+    )
+}
+
+fn io_error(error: io::Error) -> FileError {
+    match error.kind() {
+        io::ErrorKind::NotFound => FileError::NotFound,
+        kind => FileError::Io { kind },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory with `index.html`, `css/site.css` and `secret.txt`
+    /// next to it, removed when dropped.
+    struct Root(PathBuf);
+
+    impl Root {
+        fn new(name: &str) -> Root {
+            let dir =
+                std::env::temp_dir().join(format!("traject-files-{}-{}", name, std::process::id()));
+            let root = dir.join("root");
+            fs::create_dir_all(root.join("css")).unwrap();
+            fs::write(root.join("index.html"), "index").unwrap();
+            fs::write(root.join("css/site.css"), "css").unwrap();
+            fs::write(dir.join("secret.txt"), "secret").unwrap();
+            Root(dir)
+        }
+
+        fn root(&self) -> PathBuf {
+            self.0.join("root")
+        }
+
+        /// The canonical path of a file under the root.
+        fn file(&self, relative: &str) -> PathBuf {
+            fs::canonicalize(self.root().join(relative)).unwrap()
+        }
+    }
+
+    impl Drop for Root {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = Root::new("resolve");
+        let files = StaticFiles::new(dir.root());
+        assert_eq!(files.resolve("index.html"), Ok(dir.file("index.html")));
+        assert_eq!(
+            files.resolve("css//./site.css"),
+            Ok(dir.file("css/site.css"))
+        );
+        assert_eq!(files.resolve("missing.html"), Err(FileError::NotFound));
+        assert_eq!(files.resolve("css"), Err(FileError::NotFound));
+        assert_eq!(files.resolve(""), Err(FileError::NotFound));
+    }
+
+    #[test]
+    fn test_resolve_traversal() {
+        let dir = Root::new("traversal");
+        let files = StaticFiles::new(dir.root()).with_symlinks(Symlinks::Follow);
+        for relative in [
+            "../secret.txt",
+            "css/../../secret.txt",
+            "..",
+            "css\\..\\..\\secret.txt",
+            "index.html\0",
+        ] {
+            assert_eq!(
+                files.resolve(relative),
+                Err(FileError::Traversal),
+                "{}",
+                relative
+            );
+        }
+        // an absolute path is just steps under the root
+        assert_eq!(
+            files.resolve("/index.html"),
+            Ok(dir.root().join("index.html"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_extensions() {
+        let dir = Root::new("extensions");
+        let files = StaticFiles::new(dir.root()).with_extensions(&["css", "js"]);
+        // the extension is allowed, but there is no such file
+        assert_eq!(files.resolve("css/other.CSS"), Err(FileError::NotFound));
+        assert_eq!(files.resolve("css/site.css"), Ok(dir.file("css/site.css")));
+        assert_eq!(files.resolve("index.html"), Err(FileError::Extension));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_symlinks() {
+        let dir = Root::new("symlinks");
+        let root = dir.root();
+        std::os::unix::fs::symlink(dir.0.join("secret.txt"), root.join("secret.txt")).unwrap();
+        std::os::unix::fs::symlink(root.join("css"), root.join("styles")).unwrap();
+
+        let files = StaticFiles::new(&root);
+        assert_eq!(files.resolve("secret.txt"), Err(FileError::Symlink));
+        // the path the link leads to
+        assert_eq!(
+            files.resolve("styles/site.css"),
+            Ok(dir.file("css/site.css"))
+        );
+
+        let files = StaticFiles::new(&root).with_symlinks(Symlinks::Deny);
+        assert_eq!(files.resolve("styles/site.css"), Err(FileError::Symlink));
+        assert!(files.resolve("css/site.css").is_ok());
+
+        let files = StaticFiles::new(&root).with_symlinks(Symlinks::Follow);
+        assert_eq!(files.resolve("secret.txt"), Ok(root.join("secret.txt")));
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use crate::error::ParseError;
+use crate::syntax::{Span, Syntax};
+
+/// Rewrites patterns into one spelling, without changing what they match
+/// or expand to.
+///
+/// A formatted pattern has no leading separator, as `Pattern::as_str`
+/// gives it, and spells converters by the names given to `with_alias`.
+/// `check` reports the changes instead of making them, to fail a build on
+/// patterns that aren't formatted.
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    separator: char,
+    /// converter names by alias
+    aliases: HashMap<String, String>,
+}
+
+/// A change the formatter makes to a pattern: replace the text at the span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatEdit {
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl Default for Formatter {
+    fn default() -> Formatter {
+        Formatter::new()
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Formatter {
+        Formatter {
+            separator: '/',
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Format patterns with another separator than `/`.
+    pub fn with_separator(mut self, separator: char) -> Formatter {
+        self.separator = separator;
+        self
+    }
+
+    /// Spell the converter registered as `alias` as `converter`. They have
+    /// to be the same converter for the formatted pattern to mean the same.
+    pub fn with_alias(mut self, alias: &str, converter: &str) -> Formatter {
+        self.aliases
+            .insert(alias.to_string(), converter.to_string());
+        self
+    }
+
+    /// The pattern in canonical style.
+    pub fn format(&self, s: &str) -> Result<String, ParseError> {
+        let mut formatted = String::with_capacity(s.len());
+        let mut end = 0;
+        for edit in self.check(s)? {
+            formatted.push_str(&s[end..edit.span.start]);
+            formatted.push_str(&edit.replacement);
+            end = edit.span.end;
+        }
+        formatted.push_str(&s[end..]);
+        Ok(formatted)
+    }
+
+    /// The changes `format` makes to the pattern, in order. Empty if it is
+    /// formatted already.
+    pub fn check(&self, s: &str) -> Result<Vec<FormatEdit>, ParseError> {
+        let syntax = Syntax::with_separator(s, self.separator)?;
+        let mut edits: Vec<_> = syntax
+            .leading
+            .iter()
+            .map(|span| FormatEdit {
+                span: span.clone(),
+                replacement: String::new(),
+            })
+            .collect();
+        for variable in syntax.variables() {
+            let converter = variable.converter.as_ref();
+            if let Some((span, name)) =
+                converter.and_then(|span| Some((span, self.aliases.get(syntax.text(span))?)))
+            {
+                edits.push(FormatEdit {
+                    span: span.clone(),
+                    replacement: name.clone(),
+                });
+            }
+        }
+        Ok(edits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pattern::Pattern;
+
+    #[test]
+    fn test_format() {
+        let formatter = Formatter::new().with_alias("integer", "int");
+        assert_eq!(
+            formatter.format("/users/{id:integer=1}?page={p:integer}"),
+            Ok("users/{id:int=1}?page={p:int}".to_string())
+        );
+        assert_eq!(formatter.format("/"), Ok(String::new()));
+        // a regex constraint is left as is
+        assert_eq!(
+            formatter.format("{n:re:integer}"),
+            Ok("{n:re:integer}".to_string())
+        );
+        for s in ["/a/{b}[/{c:int}]", "/{{x}}/{rest:*}", "/a?v={v}"] {
+            let formatted = formatter.format(s).unwrap();
+            assert_eq!(Pattern::new(&formatted), Pattern::new(s), "{}", s);
+            assert_eq!(formatter.format(&formatted).as_ref(), Ok(&formatted));
+        }
+        assert_eq!(
+            Formatter::new().with_separator('.').format(".a.{b}"),
+            Ok("a.{b}".to_string())
+        );
+        assert_eq!(
+            formatter.format("a/{b"),
+            Err(ParseError::UnbalancedBrace { offset: 2 })
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let formatter = Formatter::new().with_alias("integer", "int");
+        assert_eq!(formatter.check("users/{id:int}"), Ok(Vec::new()));
+        assert_eq!(
+            formatter.check("/users/{id:integer}"),
+            Ok(vec![
+                FormatEdit {
+                    span: 0..1,
+                    replacement: String::new(),
+                },
+                FormatEdit {
+                    span: 11..18,
+                    replacement: "int".to_string(),
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,14 @@
+//! The maps used on hot paths: looking up literal segments while
+//! resolving and route names while generating paths.
+//!
+//! With the `fnv` feature these use the FNV hash, which is a lot faster
+//! than the default SipHash for short keys like these but doesn't resist
+//! collision attacks. Routes come from the application rather than from
+//! requests, so that's mostly a concern if routes are registered from
+//! untrusted input. Without the feature, the default hasher is kept.
+
+#[cfg(feature = "fnv")]
+pub(crate) type Map<K, V> = fnv::FnvHashMap<K, V>;
+
+#[cfg(not(feature = "fnv"))]
+pub(crate) type Map<K, V> = std::collections::HashMap<K, V>;
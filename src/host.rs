@@ -0,0 +1,176 @@
+use std::any::Any;
+
+use crate::matching::Match;
+use crate::router::{Resolution, Router};
+
+/// Routers for hosts, so that a path is resolved by the router for the
+/// host of the request.
+///
+/// A host is either exact, like `example.com`, or a wildcard for the hosts
+/// under a domain, like `*.example.com`, which takes `api.example.com` and
+/// `a.b.example.com` but not `example.com` itself. An exact host wins,
+/// and otherwise the wildcard with the longest domain, so
+/// `api.eu.example.com` goes to `*.eu.example.com` rather than to
+/// `*.example.com`. Hosts nothing is registered for go to the default
+/// router, if there is one.
+///
+/// Hosts are compared without their port and trailing dot, ignoring ASCII
+/// case.
+#[derive(Debug)]
+pub struct HostRouter<T> {
+    hosts: Vec<(String, Router<T>)>,
+    default: Option<Router<T>>,
+}
+
+impl<T> HostRouter<T> {
+    pub fn new() -> HostRouter<T> {
+        HostRouter {
+            hosts: Vec::new(),
+            default: None,
+        }
+    }
+
+    /// This host router with a router for a host, replacing the router
+    /// for the same host.
+    pub fn with_host(mut self, host: &str, router: Router<T>) -> HostRouter<T> {
+        let host = normalized(host);
+        self.hosts.retain(|(h, _)| *h != host);
+        self.hosts.push((host, router));
+        self
+    }
+
+    /// This host router with a router for the hosts nothing else is
+    /// registered for.
+    pub fn with_default(mut self, router: Router<T>) -> HostRouter<T> {
+        self.default = Some(router);
+        self
+    }
+
+    /// The router for a host, with the host it is registered for, or
+    /// `None` for the default router.
+    pub fn router_for(&self, host: &str) -> Option<(Option<&str>, &Router<T>)> {
+        let host = normalized(host);
+        let exact = self.hosts.iter().find(|(h, _)| *h == host);
+        let wildcard = || {
+            self.hosts
+                .iter()
+                .filter(|(h, _)| {
+                    h.strip_prefix('*')
+                        .is_some_and(|domain| domain.starts_with('.') && host.ends_with(domain))
+                })
+                .max_by_key(|(h, _)| h.len())
+        };
+        match exact.or_else(wildcard) {
+            Some((h, router)) => Some((Some(h.as_str()), router)),
+            None => self.default.as_ref().map(|router| (None, router)),
+        }
+    }
+
+    /// Resolve a path with the router for a host, like `Router::resolve`.
+    pub fn resolve<'a>(&'a self, host: &str, path: &'a str) -> Option<(&'a T, Match<'a>)> {
+        self.router_for(host)?.1.resolve(path)
+    }
+
+    /// Look up a path with the router for a host, like `Router::lookup`.
+    /// Without a router for the host the path isn't found.
+    pub fn lookup<'a>(&'a self, host: &str, path: &'a str) -> Resolution<'a, T> {
+        match self.router_for(host) {
+            Some((_, router)) => router.lookup(path),
+            None => Resolution::NotFound,
+        }
+    }
+
+    /// Look up a path like `lookup`, passing a context to the guards of
+    /// routes.
+    pub fn lookup_with<'a, C: Any>(
+        &'a self,
+        host: &str,
+        path: &'a str,
+        context: &C,
+    ) -> Resolution<'a, T> {
+        match self.router_for(host) {
+            Some((_, router)) => router.lookup_with(path, context),
+            None => Resolution::NotFound,
+        }
+    }
+}
+
+impl<T> Default for HostRouter<T> {
+    fn default() -> Self {
+        HostRouter::new()
+    }
+}
+
+/// A host without its port and trailing dot, in lowercase.
+fn normalized(host: &str) -> String {
+    let host = match host.rfind(':') {
+        // an IPv6 address in brackets has colons of its own
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    };
+    host.strip_suffix('.').unwrap_or(host).to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(value: &'static str) -> Router<&'static str> {
+        let mut router = Router::new();
+        router.register("{page}", value).unwrap();
+        router
+    }
+
+    fn hosts() -> HostRouter<&'static str> {
+        HostRouter::new()
+            .with_host("*.example.com", router("example"))
+            .with_host("*.eu.example.com", router("eu"))
+            .with_host("example.com", router("apex"))
+            .with_default(router("default"))
+    }
+
+    #[test]
+    fn test_resolve() {
+        let hosts = hosts();
+        let resolve = |host| *hosts.resolve(host, "index").unwrap().0;
+        assert_eq!(resolve("api.eu.example.com"), "eu");
+        assert_eq!(resolve("api.example.com"), "example");
+        assert_eq!(resolve("a.b.example.com"), "example");
+        assert_eq!(resolve("example.com"), "apex");
+        assert_eq!(resolve("eu.example.com"), "example");
+        assert_eq!(resolve("notexample.com"), "default");
+        assert_eq!(resolve("API.EU.Example.com:8080"), "eu");
+        assert_eq!(resolve("example.com."), "apex");
+    }
+
+    #[test]
+    fn test_router_for() {
+        let hosts = hosts();
+        assert_eq!(
+            hosts.router_for("db.eu.example.com").unwrap().0,
+            Some("*.eu.example.com")
+        );
+        assert_eq!(hosts.router_for("other.org").unwrap().0, None);
+        let hosts = HostRouter::new().with_host("example.com", router("apex"));
+        assert!(hosts.router_for("other.org").is_none());
+        assert!(matches!(
+            hosts.lookup("other.org", "index"),
+            Resolution::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_with_host_replaces() {
+        let hosts = HostRouter::new()
+            .with_host("example.com", router("old"))
+            .with_host("Example.com", router("new"));
+        assert_eq!(*hosts.resolve("example.com", "index").unwrap().0, "new");
+    }
+
+    #[test]
+    fn test_normalized() {
+        assert_eq!(normalized("Example.COM:443"), "example.com");
+        assert_eq!(normalized("[::1]:8080"), "[::1]");
+        assert_eq!(normalized("[::1]"), "[::1]");
+    }
+}
@@ -0,0 +1,248 @@
+use std::borrow::Cow;
+
+use crate::encoding;
+use crate::error::InvalidPath;
+use crate::pattern;
+
+/// What a router does with a percent-encoded separator, like `%2F`, in the
+/// path of a request target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodedSeparator {
+    /// It is part of the segment it is in: a variable captures it, decoded
+    /// with percent-encoding enabled, and building a path encodes a
+    /// separator in a variable's value. A catch-all decodes it like any
+    /// other escape, so its value doesn't tell `a%2Fb` from `a/b`. The
+    /// default.
+    #[default]
+    Data,
+    /// It is decoded before matching and separates segments like the
+    /// separator itself. A value with a separator in it can only be built
+    /// into a catch-all.
+    Separator,
+    /// A path with one is rejected, and a value with a separator in it can
+    /// only be built into a catch-all.
+    Reject,
+}
+
+/// What a router does with a path that looks double-encoded, with an
+/// escape like `%2520` that decodes to another escape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DoubleEncoding {
+    /// The path is matched as it is. The default.
+    #[default]
+    Allow,
+    /// A path that looks double-encoded is rejected.
+    Reject,
+    /// Escapes of escapes are decoded up to this many times, so `%2520`
+    /// becomes `%20` with one pass. A path that still looks double-encoded
+    /// after that is rejected.
+    Decode(usize),
+}
+
+/// The byte offset of the first escape of an escape, like `%2520`, in a
+/// target.
+pub(crate) fn find_double_encoding(target: &str) -> Option<usize> {
+    target.match_indices('%').map(|(i, _)| i).find(|&i| {
+        target
+            .get(i..i + 3)
+            .is_some_and(|escape| escape.eq_ignore_ascii_case("%25"))
+            && escaped_byte(&target[i + 2..]).is_some()
+    })
+}
+
+/// The target with its escapes of escapes decoded once, so `%2520` becomes
+/// `%20` but `%25` by itself stays.
+pub(crate) fn decode_double_encoding(target: &str) -> Cow<'_, str> {
+    let mut start = match find_double_encoding(target) {
+        Some(start) => start,
+        None => return Cow::Borrowed(target),
+    };
+    let mut decoded = target[..start].to_string();
+    while let Some(i) = find_double_encoding(&target[start..]) {
+        decoded.push_str(&target[start..start + i]);
+        decoded.push('%');
+        start += i + 3;
+    }
+    decoded.push_str(&target[start..]);
+    Cow::Owned(decoded)
+}
+
+/// Apply a double-encoding policy to a target.
+pub(crate) fn undouble(target: &str, policy: DoubleEncoding) -> Result<Cow<'_, str>, InvalidPath> {
+    let passes = match policy {
+        DoubleEncoding::Allow => return Ok(Cow::Borrowed(target)),
+        DoubleEncoding::Reject => 0,
+        DoubleEncoding::Decode(passes) => passes,
+    };
+    let mut target = Cow::Borrowed(target);
+    for _ in 0..passes {
+        target = match target {
+            Cow::Borrowed(target) => decode_double_encoding(target),
+            Cow::Owned(target) => Cow::Owned(decode_double_encoding(&target).into_owned()),
+        };
+    }
+    match find_double_encoding(&target) {
+        Some(offset) => Err(InvalidPath::DoubleEncoded { offset }),
+        None => Ok(target),
+    }
+}
+
+/// The byte offset of the first percent-encoded separator in the path of
+/// a target, before its query.
+pub(crate) fn find_encoded_separator(target: &str, separator: char) -> Option<usize> {
+    let (path, _) = pattern::split_query(target);
+    let encoded = encoded_separator(separator);
+    path.match_indices('%').map(|(i, _)| i).find(|&i| {
+        path.get(i..i + encoded.len())
+            .is_some_and(|escape| escape.eq_ignore_ascii_case(&encoded))
+    })
+}
+
+/// The target with the percent-encoded separators in its path decoded.
+pub(crate) fn decode_separators(target: &str, separator: char) -> Cow<'_, str> {
+    let mut start = match find_encoded_separator(target, separator) {
+        Some(start) => start,
+        None => return Cow::Borrowed(target),
+    };
+    let encoded = encoded_separator(separator);
+    let mut decoded = target[..start].to_string();
+    while let Some(i) = find_encoded_separator(&target[start..], separator) {
+        decoded.push_str(&target[start..start + i]);
+        decoded.push(separator);
+        start += i + encoded.len();
+    }
+    decoded.push_str(&target[start..]);
+    Cow::Owned(decoded)
+}
+
+fn encoded_separator(separator: char) -> String {
+    encoding::percent_encode_segment(separator.encode_utf8(&mut [0; 4]), separator).into_owned()
+}
+
+/// Check that a request target has no control characters, NUL bytes or
+/// whitespace in it.
+///
+/// Percent-encoded control characters like `%00` are rejected too, but an
+/// encoded space like `%20` is fine: only raw whitespace is.
+pub fn check_characters(target: &str) -> Result<(), InvalidPath> {
+    for (offset, character) in target.char_indices() {
+        if character.is_control() || character.is_whitespace() {
+            return Err(InvalidPath::ForbiddenCharacter { character, offset });
+        }
+        if character == '%' {
+            if let Some(byte) = escaped_byte(&target[offset..]) {
+                if byte.is_ascii_control() {
+                    return Err(InvalidPath::ForbiddenCharacter {
+                        character: char::from(byte),
+                        offset,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The byte a `%XX` escape at the start of a string stands for.
+fn escaped_byte(s: &str) -> Option<u8> {
+    let hex = s.get(1..3)?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u8::from_str_radix(hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_characters() {
+        assert_eq!(check_characters("a/b%20c?x=1"), Ok(()));
+        assert_eq!(check_characters("produits/café"), Ok(()));
+        assert_eq!(
+            check_characters("a/b\0"),
+            Err(InvalidPath::ForbiddenCharacter {
+                character: '\0',
+                offset: 3
+            })
+        );
+        assert_eq!(
+            check_characters("a b"),
+            Err(InvalidPath::ForbiddenCharacter {
+                character: ' ',
+                offset: 1
+            })
+        );
+        assert_eq!(
+            check_characters("a/\u{85}"),
+            Err(InvalidPath::ForbiddenCharacter {
+                character: '\u{85}',
+                offset: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_encoded_separator() {
+        assert_eq!(find_encoded_separator("a/b%2fc", '/'), Some(3));
+        assert_eq!(find_encoded_separator("a/b?c=%2F", '/'), None);
+        assert_eq!(find_encoded_separator("a.b%2Fc%2E", '.'), Some(7));
+        assert_eq!(find_encoded_separator("a%2", '/'), None);
+    }
+
+    #[test]
+    fn test_decode_separators() {
+        assert_eq!(decode_separators("a%2Fb%2fc?d=%2F", '/'), "a/b/c?d=%2F");
+        assert!(matches!(decode_separators("a/b", '/'), Cow::Borrowed(_)));
+        assert_eq!(decode_separators("a%2Eb", '.'), "a.b");
+    }
+
+    #[test]
+    fn test_find_double_encoding() {
+        assert_eq!(find_double_encoding("a/%2520"), Some(2));
+        assert_eq!(find_double_encoding("a/%252f"), Some(2));
+        assert_eq!(find_double_encoding("100%25"), None);
+        assert_eq!(find_double_encoding("%25zz%20"), None);
+    }
+
+    #[test]
+    fn test_undouble() {
+        assert_eq!(
+            undouble("a/%2520", DoubleEncoding::Allow).unwrap(),
+            "a/%2520"
+        );
+        assert_eq!(
+            undouble("a/%2520", DoubleEncoding::Reject),
+            Err(InvalidPath::DoubleEncoded { offset: 2 })
+        );
+        assert_eq!(undouble("a/%20", DoubleEncoding::Reject).unwrap(), "a/%20");
+        assert_eq!(
+            undouble("a/%2520/%25252F", DoubleEncoding::Decode(2)).unwrap(),
+            "a/%20/%2F"
+        );
+        assert_eq!(
+            undouble("a/%2525252F", DoubleEncoding::Decode(2)),
+            Err(InvalidPath::DoubleEncoded { offset: 2 })
+        );
+    }
+
+    #[test]
+    fn test_check_encoded_characters() {
+        assert_eq!(
+            check_characters("a/%0a"),
+            Err(InvalidPath::ForbiddenCharacter {
+                character: '\n',
+                offset: 2
+            })
+        );
+        assert_eq!(
+            check_characters("a/%7F"),
+            Err(InvalidPath::ForbiddenCharacter {
+                character: '\u{7f}',
+                offset: 2
+            })
+        );
+        assert_eq!(check_characters("a/%zz%4"), Ok(()));
+    }
+}
@@ -0,0 +1,308 @@
+//! A small JSON parser that keeps track of lines, for error messages.
+
+/// A JSON value and the line it starts on.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Spanned {
+    pub(crate) value: Value,
+    pub(crate) line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Spanned>),
+    /// keys in the order they appear in the text
+    Object(Vec<(String, Spanned)>),
+}
+
+/// How deep arrays and objects can be nested, so that parsing doesn't
+/// overflow the stack.
+pub(crate) const MAX_DEPTH: usize = 128;
+
+/// An error in the text, and the line it is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SyntaxError {
+    Invalid {
+        message: String,
+        line: usize,
+    },
+    /// Arrays and objects are nested deeper than `MAX_DEPTH`.
+    TooDeep {
+        line: usize,
+    },
+}
+
+pub(crate) fn parse(s: &str) -> Result<Spanned, SyntaxError> {
+    let mut parser = Parser {
+        s,
+        offset: 0,
+        line: 1,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.offset < s.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    offset: usize,
+    line: usize,
+    /// the number of arrays and objects the parser is in
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> SyntaxError {
+        SyntaxError::Invalid {
+            message: message.to_string(),
+            line: self.line,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.s[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SyntaxError> {
+        self.whitespace();
+        if self.next() != Some(expected) {
+            return Err(self.error(&format!("expected `{}`", expected)));
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Spanned, SyntaxError> {
+        self.whitespace();
+        let line = self.line;
+        let value = match self.peek() {
+            Some('{') => self.nested(Parser::object)?,
+            Some('[') => self.nested(Parser::array)?,
+            Some('"') => Value::String(self.string()?),
+            Some('t') => self.keyword("true", Value::Bool(true))?,
+            Some('f') => self.keyword("false", Value::Bool(false))?,
+            Some('n') => self.keyword("null", Value::Null)?,
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number()?,
+            Some(_) => return Err(self.error("expected a value")),
+            None => return Err(self.error("unexpected end of input")),
+        };
+        Ok(Spanned { value, line })
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Value, SyntaxError>,
+    ) -> Result<Value, SyntaxError> {
+        if self.depth == MAX_DEPTH {
+            return Err(SyntaxError::TooDeep { line: self.line });
+        }
+        self.depth += 1;
+        let value = parse(self)?;
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, SyntaxError> {
+        if !self.s[self.offset..].starts_with(keyword) {
+            return Err(self.error("expected a value"));
+        }
+        self.offset += keyword.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, SyntaxError> {
+        let start = self.offset;
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                break;
+            }
+            self.next();
+        }
+        self.s[start..self.offset]
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, SyntaxError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(c);
+                }
+                Some(c) if c < ' ' => return Err(self.error("control character in string")),
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, SyntaxError> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return std::char::from_u32(high).ok_or_else(|| self.error("invalid escape"));
+        }
+        // a surrogate pair
+        if self.next() != Some('\\') || self.next() != Some('u') {
+            return Err(self.error("invalid escape"));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("invalid escape"));
+        }
+        std::char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or_else(|| self.error("invalid escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, SyntaxError> {
+        let digits = self
+            .s
+            .get(self.offset..self.offset + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.offset += 4;
+        Ok(u32::from_str_radix(digits, 16).expect("hex digits"))
+    }
+
+    fn array(&mut self) -> Result<Value, SyntaxError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, SyntaxError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Object(members)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(s: &str) -> Value {
+        parse(s).unwrap().value
+    }
+
+    #[test]
+    fn test_parse_scalars() {
+        assert_eq!(value("null"), Value::Null);
+        assert_eq!(value(" true "), Value::Bool(true));
+        assert_eq!(value("-1.5e1"), Value::Number(-15.0));
+        assert_eq!(
+            value(r#""a\"\n\u00e9\ud83d\ude00""#),
+            Value::String("a\"\né😀".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_nested() {
+        let parsed = parse("{\n\"a\": [1,\n2],\n\"b\": {}\n}").unwrap();
+        assert_eq!(parsed.line, 1);
+        let members = match parsed.value {
+            Value::Object(members) => members,
+            _ => panic!("not an object"),
+        };
+        assert_eq!(members[0].0, "a");
+        assert_eq!(members[0].1.line, 2);
+        match &members[0].1.value {
+            Value::Array(items) => assert_eq!(items[1].line, 3),
+            _ => panic!("not an array"),
+        }
+        assert_eq!(members[1].1.value, Value::Object(vec![]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |s| match parse(s).unwrap_err() {
+            SyntaxError::Invalid { message, line } => (message, line),
+            error => panic!("{:?}", error),
+        };
+        assert_eq!(error("[1,\n2").1, 2);
+        assert_eq!(error("{\"a\" 1}").0, "expected `:`");
+        assert_eq!(error("\"abc").0, "unterminated string");
+        assert_eq!(error("nul").0, "expected a value");
+        assert_eq!(error("1 2").0, "trailing characters");
+        assert_eq!(error("\"\\x\"").0, "invalid escape");
+    }
+
+    #[test]
+    fn test_parse_too_deep() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse(&format!("\n{}", nested(MAX_DEPTH + 1))),
+            Err(SyntaxError::TooDeep { line: 2 })
+        );
+        assert_eq!(
+            parse(&"[".repeat(100_000)),
+            Err(SyntaxError::TooDeep { line: 1 })
+        );
+    }
+}
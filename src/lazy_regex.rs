@@ -0,0 +1,143 @@
+//! Regexes that are compiled when they are first used, and shared by
+//! everything that uses the same regex while it is in use.
+
+use regex::Regex;
+use regex_syntax::hir::{Hir, HirKind};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, Weak};
+
+/// Compiled regexes by their source. The entries don't keep the regexes
+/// alive: a regex is dropped with the last pattern that uses it, and its
+/// entry is removed when another regex is added.
+static COMPILED: OnceLock<Mutex<HashMap<String, Weak<Regex>>>> = OnceLock::new();
+
+/// A regex that is checked when it is made, but only compiled when it is
+/// first used.
+#[derive(Debug, Clone)]
+pub(crate) struct LazyRegex {
+    source: String,
+    regex: OnceLock<Option<Arc<Regex>>>,
+}
+
+impl LazyRegex {
+    /// A regex for `source`, or `None` if it isn't valid.
+    pub(crate) fn new(source: String) -> Option<LazyRegex> {
+        regex_syntax::Parser::new().parse(&source).ok()?;
+        Some(LazyRegex {
+            source,
+            regex: OnceLock::new(),
+        })
+    }
+
+    /// The compiled regex, or `None` if it is valid but too big to compile.
+    pub(crate) fn get(&self) -> Option<&Regex> {
+        self.regex.get_or_init(|| compile(&self.source)).as_deref()
+    }
+}
+
+fn compiled() -> std::sync::MutexGuard<'static, HashMap<String, Weak<Regex>>> {
+    COMPILED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+fn compile(source: &str) -> Option<Arc<Regex>> {
+    if let Some(regex) = compiled().get(source).and_then(Weak::upgrade) {
+        return Some(regex);
+    }
+    // compiled without holding the lock, so other regexes can be looked up
+    // meanwhile; if another thread compiled this one too, theirs is kept
+    let regex = Arc::new(Regex::new(source).ok()?);
+    let mut compiled = compiled();
+    if let Some(regex) = compiled.get(source).and_then(Weak::upgrade) {
+        return Some(regex);
+    }
+    compiled.retain(|_, regex| regex.strong_count() > 0);
+    compiled.insert(source.to_string(), Arc::downgrade(&regex));
+    Some(regex)
+}
+
+/// The number of capture groups in a regex, or `None` if it isn't valid.
+pub(crate) fn captures_len(source: &str) -> Option<usize> {
+    let hir = regex_syntax::Parser::new().parse(source).ok()?;
+    Some(hir.properties().explicit_captures_len())
+}
+
+/// The number of characters every match of a regex has, or `None` if
+/// matches can differ in length or the regex isn't valid.
+pub(crate) fn fixed_len(source: &str) -> Option<usize> {
+    fn len(hir: &Hir) -> Option<usize> {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => Some(0),
+            HirKind::Literal(literal) => {
+                Some(std::str::from_utf8(&literal.0).ok()?.chars().count())
+            }
+            HirKind::Class(_) => Some(1),
+            HirKind::Repetition(repetition) if repetition.max == Some(repetition.min) => {
+                Some(len(&repetition.sub)? * repetition.min as usize)
+            }
+            HirKind::Repetition(_) => None,
+            HirKind::Capture(capture) => len(&capture.sub),
+            HirKind::Concat(hirs) => hirs.iter().map(len).sum(),
+            HirKind::Alternation(hirs) => {
+                let first = len(&hirs[0])?;
+                hirs[1..]
+                    .iter()
+                    .all(|hir| len(hir) == Some(first))
+                    .then_some(first)
+            }
+        }
+    }
+    len(&regex_syntax::Parser::new().parse(source).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_regex() {
+        let regex = LazyRegex::new(r"^a\d+$".to_string()).unwrap();
+        assert!(regex.regex.get().is_none());
+        assert!(regex.get().unwrap().is_match("a12"));
+        assert!(LazyRegex::new("[a-".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_lazy_regex_shared() {
+        let first = LazyRegex::new(r"^shared\d$".to_string()).unwrap();
+        let second = LazyRegex::new(r"^shared\d$".to_string()).unwrap();
+        let source = |regex: &LazyRegex| regex.get().unwrap().as_str().as_ptr();
+        assert_eq!(source(&first), source(&second));
+    }
+
+    #[test]
+    fn test_lazy_regex_dropped() {
+        let regex = LazyRegex::new(r"^dropped\d$".to_string()).unwrap();
+        regex.get().unwrap();
+        drop(regex);
+        LazyRegex::new(r"^other\d$".to_string())
+            .unwrap()
+            .get()
+            .unwrap();
+        assert!(!compiled().contains_key(r"^dropped\d$"));
+    }
+
+    #[test]
+    fn test_captures_len() {
+        assert_eq!(captures_len(r"\d+"), Some(0));
+        assert_eq!(captures_len(r"(a)(?P<b>b)(?:c)"), Some(2));
+        assert_eq!(captures_len("(a"), None);
+    }
+
+    #[test]
+    fn test_fixed_len() {
+        assert_eq!(fixed_len(r"\d{4}"), Some(4));
+        assert_eq!(fixed_len(r"é[a-z](x|y)\b"), Some(3));
+        assert_eq!(fixed_len("jpg|png"), Some(3));
+        assert_eq!(fixed_len("jpg|jpeg"), None);
+        assert_eq!(fixed_len(r"\d+"), None);
+        assert_eq!(fixed_len("(a"), None);
+    }
+}
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ptr;
+
+use crate::encoding;
+use crate::error::ExpandError;
+use crate::matching::Match;
+use crate::router::{RouteInfo, Router};
+
+/// A link to another route, like a link relation in a hypermedia API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// `self`, `up`, or the name of a route under the matched one.
+    pub rel: String,
+    pub href: String,
+}
+
+impl<T> Router<T> {
+    /// Links for a match of this router, derived from its routes: `self`
+    /// to the matched path, `up` to the named route with the longest
+    /// pattern that the matched pattern extends, like `posts` for
+    /// `posts/{id}`, and a link named after each named route that extends
+    /// the matched pattern and needs no other variables, like
+    /// `edit_post` for `posts/{id}/edit`.
+    ///
+    /// Patterns extend others if their required steps start with steps of
+    /// the same shape; a variable in such a step takes the value of the
+    /// variable in the same place, so `posts/{post_id}/comments` is under
+    /// `posts/{id}`. Links to routes come in registration order. A match
+    /// that isn't of this router has no links.
+    pub fn links(&self, m: &Match) -> Vec<Link> {
+        let routes: Vec<RouteInfo<T>> = self.iter().collect();
+        // the index is only of this router if the metadata is too
+        let route = match m
+            .route()
+            .and_then(|index| routes.get(index))
+            .filter(|route| {
+                m.metadata()
+                    .is_some_and(|metadata| ptr::eq(route.metadata, metadata))
+            }) {
+            Some(route) => route,
+            None => return Vec::new(),
+        };
+        let mut links = Vec::new();
+        let href = match route.name {
+            Some(name) => self.path_for(name, &m.to_map()),
+            None => route.pattern.expand(&m.to_map()),
+        };
+        if let Ok(href) = href {
+            links.push(link("self", href));
+        }
+        // of the deepest parents, the first registered
+        let up = routes
+            .iter()
+            .rev()
+            .filter(|parent| parent.name.is_some())
+            .filter_map(|parent| Some((parent, route.pattern.extends(parent.pattern)?)))
+            .max_by_key(|(parent, _)| parent.pattern.depth());
+        if let Some((parent, variables)) = up {
+            if let Some(href) = self.linked(parent, m, variables.into_iter()) {
+                links.push(link("up", href));
+            }
+        }
+        for child in &routes {
+            let name = match child.name {
+                Some(name) => name,
+                None => continue,
+            };
+            if let Some(variables) = child.pattern.extends(route.pattern) {
+                let variables = variables.into_iter().map(|(mine, theirs)| (theirs, mine));
+                if let Some(href) = self.linked(child, m, variables) {
+                    links.push(link(name, href));
+                }
+            }
+        }
+        links
+    }
+
+    /// The path of a named route, with the values that a match has for
+    /// the variables paired with its variables.
+    fn linked<'v>(
+        &self,
+        route: &RouteInfo<T>,
+        m: &'v Match,
+        variables: impl Iterator<Item = (&'v str, &'v str)>,
+    ) -> Option<String> {
+        let values = variables
+            .map(|(theirs, mine)| Some((theirs, m.get(mine)?)))
+            .collect::<Option<HashMap<&str, &str>>>()?;
+        self.path_for(route.name?, &values).ok()
+    }
+}
+
+/// The links to the pages around a page of a paginated route, as given by
+/// `Router::pagination`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pagination {
+    pub first: String,
+    /// The page before, unless this is the first.
+    pub prev: Option<String>,
+    /// The page after, unless this is the last.
+    pub next: Option<String>,
+    pub last: String,
+}
+
+impl<T> Router<T> {
+    /// The links to the first, previous, next and last page of a named
+    /// route, for page `page` of `pages`. Pages are numbered from 1, page 0
+    /// is taken to be 1, and `variable` holds the number, in a step or a
+    /// query parameter.
+    ///
+    /// The link to page 1 leaves out the variable where the pattern allows
+    /// that, as with `[page={page:int}]` or `posts[/{page:int}]`, so it is
+    /// the same as the link to the unpaginated route. A route with no pages
+    /// still has page 1, and a page beyond the last has a previous page but
+    /// no next one.
+    pub fn pagination(
+        &self,
+        name: &str,
+        values: &HashMap<&str, &str>,
+        variable: &str,
+        page: u64,
+        pages: u64,
+    ) -> Result<Pagination, ExpandError> {
+        let mut values = values.clone();
+        values.remove(variable);
+        let page_path = |page: u64| {
+            let mut values = values.clone();
+            if page == 1 {
+                if let Ok(path) = self.path_for(name, &values) {
+                    return Ok(path);
+                }
+            }
+            let page = page.to_string();
+            values.insert(variable, &page);
+            self.path_for(name, &values)
+        };
+        let (page, last) = (page.max(1), pages.max(1));
+        Ok(Pagination {
+            first: page_path(1)?,
+            prev: match page {
+                1 => None,
+                page => Some(page_path((page - 1).min(last))?),
+            },
+            next: match page {
+                page if page < last => Some(page_path(page + 1)?),
+                _ => None,
+            },
+            last: page_path(last)?,
+        })
+    }
+}
+
+/// A JSON:API links object, like `{"self":"posts/1","related":"posts/1/author"}`,
+/// with the paths of named routes. It is built up from the values of a
+/// resource, starting with `Router::json_api_links`; `to_string` gives the
+/// JSON.
+#[derive(Debug, Clone)]
+pub struct JsonApiLinks<'r, T> {
+    router: &'r Router<T>,
+    name: String,
+    values: HashMap<String, String>,
+    members: Vec<(String, String)>,
+}
+
+impl<T> Router<T> {
+    /// A JSON:API links object with a `self` link to the named route, with
+    /// these values.
+    pub fn json_api_links(
+        &self,
+        name: &str,
+        values: &HashMap<&str, &str>,
+    ) -> Result<JsonApiLinks<'_, T>, ExpandError> {
+        let href = self.path_for(name, values)?;
+        Ok(JsonApiLinks {
+            router: self,
+            name: name.to_string(),
+            values: values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            members: vec![("self".to_string(), href)],
+        })
+    }
+}
+
+impl<T> JsonApiLinks<'_, T> {
+    /// Add a `related` link to another named route, like `post_author`,
+    /// with the values of the resource that its pattern has.
+    pub fn related(mut self, name: &str) -> Result<Self, ExpandError> {
+        let route = self
+            .router
+            .iter()
+            .find(|route| route.name == Some(name))
+            .ok_or_else(|| ExpandError::UnknownRoute {
+                name: name.to_string(),
+            })?;
+        let values: HashMap<&str, &str> = route
+            .pattern
+            .names()
+            .filter_map(|variable| Some((variable, self.values.get(variable)?.as_str())))
+            .collect();
+        let href = self.router.path_for(name, &values)?;
+        self.members.push(("related".to_string(), href));
+        Ok(self)
+    }
+
+    /// Add `first`, `prev`, `next` and `last` links to the pages around
+    /// page `page` of `pages` of the route of the resource, as
+    /// `Router::pagination` gives them.
+    pub fn pages(mut self, variable: &str, page: u64, pages: u64) -> Result<Self, ExpandError> {
+        let values = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let pagination = self
+            .router
+            .pagination(&self.name, &values, variable, page, pages)?;
+        let links = [
+            ("first", Some(pagination.first)),
+            ("prev", pagination.prev),
+            ("next", pagination.next),
+            ("last", Some(pagination.last)),
+        ];
+        for (rel, href) in links {
+            if let Some(href) = href {
+                self.members.push((rel.to_string(), href));
+            }
+        }
+        Ok(self)
+    }
+
+    /// The members in the order they were added, as `(name, path)`.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.members
+            .iter()
+            .map(|(rel, href)| (rel.as_str(), href.as_str()))
+    }
+}
+
+impl<T> fmt::Display for JsonApiLinks<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("{")?;
+        for (i, (rel, href)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(
+                f,
+                "{}:{}",
+                encoding::json_quote(rel),
+                encoding::json_quote(href)
+            )?;
+        }
+        f.write_str("}")
+    }
+}
+
+fn link(rel: &str, href: String) -> Link {
+    Link {
+        rel: rel.to_string(),
+        href,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::resource::{Resource, ResourceRoutes};
+
+    fn links(router: &Router<i32>, path: &str) -> Vec<(String, String)> {
+        let (_, m) = router.resolve(path).unwrap();
+        router
+            .links(&m)
+            .into_iter()
+            .map(|link| (link.rel, link.href))
+            .collect()
+    }
+
+    fn pairs(links: &[(&str, &str)]) -> Vec<(String, String)> {
+        links
+            .iter()
+            .map(|(rel, href)| (rel.to_string(), href.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_links() {
+        let mut router = Router::new();
+        let routes = |n| ResourceRoutes {
+            index: n,
+            show: n + 1,
+            new: Some(n + 2),
+            edit: Some(n + 3),
+        };
+        router.resource("posts", routes(0)).unwrap();
+        router
+            .resource(Resource::new("posts").nest("comments"), routes(10))
+            .unwrap();
+        router.register("posts/{id}/feed", 20).unwrap();
+
+        assert_eq!(
+            links(&router, "posts"),
+            pairs(&[("self", "posts"), ("new_post", "posts/new")])
+        );
+        assert_eq!(
+            links(&router, "posts/1"),
+            pairs(&[
+                ("self", "posts/1"),
+                ("up", "posts"),
+                ("edit_post", "posts/1/edit"),
+                ("post_comments", "posts/1/comments"),
+                ("new_post_comment", "posts/1/comments/new"),
+            ])
+        );
+        assert_eq!(
+            links(&router, "posts/1/comments/2"),
+            pairs(&[
+                ("self", "posts/1/comments/2"),
+                ("up", "posts/1/comments"),
+                ("edit_post_comment", "posts/1/comments/2/edit"),
+            ])
+        );
+        assert_eq!(
+            links(&router, "posts/1/feed"),
+            pairs(&[("self", "posts/1/feed"), ("up", "posts/1")])
+        );
+    }
+
+    #[test]
+    fn test_pagination() {
+        let mut router = Router::new();
+        router
+            .register_named("posts", "posts?[page={page:int}]", 1)
+            .unwrap();
+        router
+            .register_named("archive", "archive/{year}[/{page:int}]", 2)
+            .unwrap();
+        router
+            .register_named("comments", "comments?page={page:int}", 3)
+            .unwrap();
+        let pagination = |name, values: &[(&str, &str)], page, pages| {
+            router
+                .pagination(name, &values.iter().copied().collect(), "page", page, pages)
+                .unwrap()
+        };
+        assert_eq!(
+            pagination("posts", &[("page", "2")], 2, 3),
+            Pagination {
+                first: "posts".to_string(),
+                prev: Some("posts".to_string()),
+                next: Some("posts?page=3".to_string()),
+                last: "posts?page=3".to_string(),
+            }
+        );
+        assert_eq!(
+            pagination("archive", &[("year", "2020")], 1, 2),
+            Pagination {
+                first: "archive/2020".to_string(),
+                prev: None,
+                next: Some("archive/2020/2".to_string()),
+                last: "archive/2020/2".to_string(),
+            }
+        );
+        assert_eq!(
+            pagination("comments", &[], 3, 3),
+            Pagination {
+                first: "comments?page=1".to_string(),
+                prev: Some("comments?page=2".to_string()),
+                next: None,
+                last: "comments?page=3".to_string(),
+            }
+        );
+        let empty = pagination("comments", &[], 1, 0);
+        assert_eq!((empty.prev, empty.next), (None, None));
+        assert_eq!(empty.last, "comments?page=1");
+        let beyond = pagination("comments", &[], 7, 3);
+        assert_eq!(beyond.prev.as_deref(), Some("comments?page=3"));
+        assert_eq!(beyond.next, None);
+        assert_eq!(
+            router
+                .pagination("posts", &HashMap::new(), "page", 1, 1)
+                .unwrap(),
+            pagination("posts", &[], 0, 1)
+        );
+    }
+
+    #[test]
+    fn test_json_api_links() {
+        let mut router = Router::new();
+        router.register_named("post", "posts/{id}", 1).unwrap();
+        router
+            .register_named("post_author", "posts/{id}/author", 2)
+            .unwrap();
+        router
+            .register_named("post_comments", "posts/{id}/comments?page={page:int}", 3)
+            .unwrap();
+        let values = HashMap::from([("id", "1")]);
+        let links = router
+            .json_api_links("post", &values)
+            .unwrap()
+            .related("post_author")
+            .unwrap();
+        assert_eq!(
+            links.to_string(),
+            r#"{"self":"posts/1","related":"posts/1/author"}"#
+        );
+        assert_eq!(
+            router
+                .json_api_links("post", &values)
+                .unwrap()
+                .related("other")
+                .unwrap_err(),
+            ExpandError::UnknownRoute {
+                name: "other".to_string()
+            }
+        );
+
+        let values = HashMap::from([("id", "1"), ("page", "2")]);
+        let links = router
+            .json_api_links("post_comments", &values)
+            .unwrap()
+            .pages("page", 2, 3)
+            .unwrap();
+        assert_eq!(
+            links.iter().collect::<Vec<_>>(),
+            vec![
+                ("self", "posts/1/comments?page=2"),
+                ("first", "posts/1/comments?page=1"),
+                ("prev", "posts/1/comments?page=1"),
+                ("next", "posts/1/comments?page=3"),
+                ("last", "posts/1/comments?page=3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_other_router() {
+        let mut router = Router::new();
+        router.register("a", 1).unwrap();
+        let (_, m) = router.resolve("a").unwrap();
+        assert!(Router::<i32>::new().links(&m).is_empty());
+        assert!(router.links(&Match::default()).is_empty());
+    }
+}
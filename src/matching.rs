@@ -0,0 +1,179 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+use crate::metadata::Metadata;
+
+/// The variables captured by matching a path against a pattern.
+///
+/// Variables are kept in the order in which they appear in the pattern.
+/// Values borrow from the path, unless they had to be decoded.
+///
+/// Matches are equal if their variables are, whatever their metadata.
+#[derive(Debug, Clone, Default)]
+pub struct Match<'a> {
+    variables: Vec<(&'a str, Cow<'a, str>)>,
+    metadata: Option<&'a Metadata>,
+    /// the index of the route that matched in its router
+    route: Option<usize>,
+}
+
+impl<'a> Match<'a> {
+    pub(crate) fn new(variables: Vec<(&'a str, Cow<'a, str>)>) -> Match<'a> {
+        Match {
+            variables,
+            metadata: None,
+            route: None,
+        }
+    }
+
+    pub(crate) fn with_route(mut self, index: usize, metadata: &'a Metadata) -> Match<'a> {
+        self.metadata = Some(metadata);
+        self.route = Some(index);
+        self
+    }
+
+    /// The index of the route that matched in its router, if this is a
+    /// match of a router.
+    pub(crate) fn route(&self) -> Option<usize> {
+        self.route
+    }
+
+    /// The metadata of the route that matched, if this is a match of a
+    /// router rather than of a single pattern.
+    pub fn metadata(&self) -> Option<&'a Metadata> {
+        self.metadata
+    }
+
+    /// The value captured for variable `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.as_ref())
+    }
+
+    /// Iterate over `(name, value)` pairs in pattern order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &str)> + '_ {
+        self.variables
+            .iter()
+            .map(|(name, value)| (*name, value.as_ref()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    /// The captured variables as a map, suitable for passing to `expand`.
+    pub fn to_map(&self) -> HashMap<&'a str, &str> {
+        self.iter().collect()
+    }
+}
+
+impl<'a> FromIterator<(&'a str, &'a str)> for Match<'a> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, &'a str)>>(variables: I) -> Match<'a> {
+        Match::new(
+            variables
+                .into_iter()
+                .map(|(name, value)| (name, Cow::Borrowed(value)))
+                .collect(),
+        )
+    }
+}
+
+impl PartialEq for Match<'_> {
+    fn eq(&self, other: &Match) -> bool {
+        self.variables == other.variables
+    }
+}
+
+impl Eq for Match<'_> {}
+
+/// The variables captured by matching bytes against a pattern, with
+/// `Pattern::match_bytes`.
+///
+/// Values are the bytes of the path, which need not be UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BytesMatch<'a> {
+    variables: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> BytesMatch<'a> {
+    pub(crate) fn new(variables: Vec<(&'a str, &'a [u8])>) -> BytesMatch<'a> {
+        BytesMatch { variables }
+    }
+
+    /// The bytes captured for variable `name`.
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        self.variables
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// The value captured for variable `name` as text, with invalid UTF-8
+    /// replaced by `U+FFFD`.
+    pub fn get_lossy(&self, name: &str) -> Option<Cow<'a, str>> {
+        self.get(name).map(String::from_utf8_lossy)
+    }
+
+    /// Iterate over `(name, value)` pairs in pattern order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a [u8])> + '_ {
+        self.variables.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_match<'a>(variables: Vec<(&'a str, &'a str)>) -> Match<'a> {
+        variables.into_iter().collect()
+    }
+
+    #[test]
+    fn test_get() {
+        let m = new_match(vec![("a", "A"), ("b", "B")]);
+        assert_eq!(m.get("a"), Some("A"));
+        assert_eq!(m.get("b"), Some("B"));
+        assert_eq!(m.get("c"), None);
+        assert_eq!(m.len(), 2);
+    }
+
+    #[test]
+    fn test_iter_in_order() {
+        let m = new_match(vec![("b", "B"), ("a", "A")]);
+        assert_eq!(m.iter().collect::<Vec<_>>(), vec![("b", "B"), ("a", "A")]);
+    }
+
+    #[test]
+    fn test_bytes_match() {
+        let m = BytesMatch::new(vec![("a", b"A"), ("b", b"\xffB")]);
+        assert_eq!(m.get("a"), Some(&b"A"[..]));
+        assert_eq!(m.get_lossy("b").unwrap(), "\u{fffd}B");
+        assert_eq!(m.get("c"), None);
+        assert_eq!(
+            m.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let m = Match::default();
+        assert!(m.is_empty());
+        assert!(m.to_map().is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Values of any type attached to a route, at most one of each type.
+///
+/// Frameworks can use this to keep things like the permission a route
+/// requires, or its documentation, next to the route. The metadata of the
+/// route that matched is available from `Match::metadata`.
+///
+/// Cloning metadata shares its values rather than cloning them.
+#[derive(Default, Clone)]
+pub struct Metadata {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Metadata {
+    pub fn new() -> Metadata {
+        Metadata::default()
+    }
+
+    /// Add a value, return the value of the same type it replaces, unless
+    /// a clone of this metadata still shares that.
+    pub fn insert<V: Any + Send + Sync>(&mut self, value: V) -> Option<V> {
+        self.values
+            .insert(TypeId::of::<V>(), Arc::new(value))
+            .and_then(|previous| previous.downcast().ok())
+            .and_then(|previous| Arc::try_unwrap(previous).ok())
+    }
+
+    /// Like `insert`, for building metadata in one expression.
+    pub fn with<V: Any + Send + Sync>(mut self, value: V) -> Metadata {
+        self.insert(value);
+        self
+    }
+
+    /// The value of type `V`.
+    pub fn get<V: Any>(&self) -> Option<&V> {
+        self.values
+            .get(&TypeId::of::<V>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Metadata")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Permission(&'static str);
+
+    #[test]
+    fn test_metadata() {
+        let mut metadata = Metadata::new().with(Permission("read")).with(10u32);
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata.get::<Permission>(), Some(&Permission("read")));
+        assert_eq!(metadata.get::<u32>(), Some(&10));
+        assert_eq!(metadata.get::<u64>(), None);
+        assert_eq!(
+            metadata.insert(Permission("write")),
+            Some(Permission("read"))
+        );
+        assert_eq!(metadata.get::<Permission>(), Some(&Permission("write")));
+        assert!(Metadata::new().is_empty());
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut metadata = Metadata::new().with(Permission("read"));
+        let clone = metadata.clone();
+        assert_eq!(metadata.insert(Permission("write")), None);
+        assert_eq!(clone.get::<Permission>(), Some(&Permission("read")));
+    }
+}
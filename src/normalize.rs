@@ -0,0 +1,111 @@
+use std::borrow::Cow;
+
+use crate::pattern;
+
+/// Normalize the path of a request target.
+///
+/// Duplicate slashes are collapsed, and `.` and `..` segments are resolved,
+/// including percent-encoded ones like `%2e%2e`. A leading and trailing
+/// slash are kept, and so is the query.
+///
+/// Returns `None` if a `..` would go above the root.
+pub fn normalize(target: &str) -> Option<Cow<'_, str>> {
+    normalize_with_separator(target, '/')
+}
+
+/// Normalize a path with segments separated by another character than `/`.
+pub(crate) fn normalize_with_separator(target: &str, separator: char) -> Option<Cow<'_, str>> {
+    let (path, query) = pattern::split_query(target);
+    let mut segments: Vec<&str> = Vec::new();
+    let mut trailing = false;
+    for segment in path.split(separator) {
+        trailing = true;
+        match dots(segment) {
+            1 => {}
+            2 => {
+                segments.pop()?;
+            }
+            _ if segment.is_empty() => {}
+            _ => {
+                segments.push(segment);
+                trailing = false;
+            }
+        }
+    }
+    let mut normalized = String::with_capacity(target.len());
+    if path.starts_with(separator) {
+        normalized.push(separator);
+    }
+    normalized.push_str(&segments.join(separator.encode_utf8(&mut [0; 4])));
+    if trailing && !segments.is_empty() {
+        normalized.push(separator);
+    }
+    if let Some(query) = query {
+        normalized.push('?');
+        normalized.push_str(query);
+    }
+    if normalized == target {
+        Some(Cow::Borrowed(target))
+    } else {
+        Some(Cow::Owned(normalized))
+    }
+}
+
+/// The number of dots in a segment that is nothing but dots, plain or
+/// percent-encoded, or 0 for any other segment.
+fn dots(segment: &str) -> usize {
+    let mut rest = segment;
+    let mut count = 0;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            rest = after;
+        } else if rest
+            .get(..3)
+            .is_some_and(|escape| escape.eq_ignore_ascii_case("%2e"))
+        {
+            rest = &rest[3..];
+        } else {
+            return 0;
+        }
+        count += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("a/b").unwrap(), "a/b");
+        assert!(matches!(normalize("/a/b/").unwrap(), Cow::Borrowed(_)));
+        assert_eq!(normalize("a//b").unwrap(), "a/b");
+        assert_eq!(normalize("//a/./b/").unwrap(), "/a/b/");
+        assert_eq!(normalize("a/b/..").unwrap(), "a/");
+        assert_eq!(normalize("a/b/../c?x=../y").unwrap(), "a/c?x=../y");
+        assert_eq!(normalize("/a/..").unwrap(), "/");
+        assert_eq!(normalize("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_normalize_encoded_dots() {
+        assert_eq!(normalize("a/b/%2e%2E/c").unwrap(), "a/c");
+        assert_eq!(normalize("a/.%2e/c").unwrap(), "c");
+        assert_eq!(normalize("a/%2E./c/%2e").unwrap(), "c/");
+        assert_eq!(normalize("a/%2e%2e%2e").unwrap(), "a/%2e%2e%2e");
+    }
+
+    #[test]
+    fn test_normalize_with_separator() {
+        assert_eq!(normalize_with_separator("a::b:", ':').unwrap(), "a:b:");
+        assert_eq!(normalize_with_separator("a/b:..:c", ':').unwrap(), "c");
+    }
+
+    #[test]
+    fn test_normalize_escapes_root() {
+        assert_eq!(normalize(".."), None);
+        assert_eq!(normalize("/a/../../b"), None);
+        assert_eq!(normalize("a/%2e%2e/%2e%2e"), None);
+    }
+}
@@ -0,0 +1,472 @@
+//! Exporting routes as OpenAPI path templates, and their parameters as
+//! JSON Schemas.
+
+use std::fmt::{self, Write};
+
+use crate::docs::VariableDoc;
+use crate::encoding;
+use crate::router::{RouteInfo, Router};
+use crate::step::Step;
+
+/// Metadata that describes a route in the OpenAPI export.
+///
+/// Routes without it are exported as `get` operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    /// The HTTP method in lowercase, like `get`.
+    pub method: String,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Default for Operation {
+    fn default() -> Operation {
+        Operation {
+            method: "get".to_string(),
+            summary: None,
+            description: None,
+        }
+    }
+}
+
+/// A JSON value, as `Router::to_openapi` returns it. It displays as JSON
+/// text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// members in the order they were added
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// The member of an object with this key, if it is an object and has
+    /// one.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonValue::Null => f.write_str("null"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Number(n) => write!(f, "{}", n),
+            JsonValue::String(s) => f.write_str(&encoding::json_quote(s)),
+            JsonValue::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            }
+            JsonValue::Object(members) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}:{}", encoding::json_quote(key), value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+impl<T> Router<T> {
+    /// The routes as an OpenAPI 3 Paths Object.
+    ///
+    /// Each route is an operation on the path template of its pattern, so
+    /// `departments/{d:int}` becomes `/departments/{d}`, with an `operationId`
+    /// if the route is named. A pattern with optional steps has a path
+    /// template without them and one with them, as path parameters can't
+    /// be optional. Templates start with `/` and have the separator of the
+    /// router between their steps. Variables are parameters, with a schema
+    /// derived from their converter: `int`, `float`, `bool`, `uuid`, `date`
+    /// and regex converters have their own, other variables are strings.
+    ///
+    /// A path template only has one operation for each method, so of the
+    /// routes with the same template and method, only the first registered
+    /// is exported.
+    pub fn to_openapi(&self) -> JsonValue {
+        let default = Operation::default();
+        let mut paths: Vec<(String, Vec<(String, JsonValue)>)> = Vec::new();
+        for route in self.iter() {
+            let operation = route.metadata.get::<Operation>().unwrap_or(&default);
+            let variants: Vec<&[Step]> = route.pattern.variants().collect();
+            for steps in variants.into_iter().rev() {
+                let template = template(steps, route.pattern.separator());
+                let index = match paths.iter().position(|(t, _)| *t == template) {
+                    Some(index) => index,
+                    None => {
+                        paths.push((template, Vec::new()));
+                        paths.len() - 1
+                    }
+                };
+                let operations = &mut paths[index].1;
+                if operations
+                    .iter()
+                    .all(|(method, _)| *method != operation.method)
+                {
+                    operations.push((
+                        operation.method.clone(),
+                        operation_json(&route, steps, operation),
+                    ));
+                }
+            }
+        }
+        JsonValue::Object(
+            paths
+                .into_iter()
+                .map(|(template, operations)| (template, JsonValue::Object(operations)))
+                .collect(),
+        )
+    }
+}
+
+impl<T> Router<T> {
+    /// A JSON Schema for the variables of each route, keyed by the route
+    /// name, or by the pattern of an unnamed route. Of routes with the same
+    /// key, only the first registered is exported.
+    ///
+    /// A schema describes an object with the variables of the path and the
+    /// query as properties, typed by their converters as in `to_openapi`.
+    /// Variables are required unless they have a default, are in optional
+    /// steps, or are in an optional query parameter. Their `VariableDocs`
+    /// are descriptions and examples.
+    pub fn to_json_schema(&self) -> JsonValue {
+        let mut schemas: Vec<(String, JsonValue)> = Vec::new();
+        for route in self.iter() {
+            let key = route.name.unwrap_or_else(|| route.pattern.as_str());
+            if schemas.iter().all(|(k, _)| k != key) {
+                schemas.push((key.to_string(), params_schema(&route)));
+            }
+        }
+        JsonValue::Object(schemas)
+    }
+}
+
+/// The OpenAPI path template of the steps of a pattern, which always
+/// starts with `/`.
+fn template(steps: &[Step], separator: char) -> String {
+    let steps: Vec<String> = steps.iter().map(Step::template).collect();
+    format!("/{}", steps.join(separator.encode_utf8(&mut [0; 4])))
+}
+
+fn operation_json<T>(route: &RouteInfo<T>, steps: &[Step], operation: &Operation) -> JsonValue {
+    let mut members = Vec::new();
+    if let Some(name) = route.name {
+        members.push(("operationId".to_string(), string(name)));
+    }
+    if let Some(summary) = &operation.summary {
+        members.push(("summary".to_string(), string(summary)));
+    }
+    if let Some(description) = &operation.description {
+        members.push(("description".to_string(), string(description)));
+    }
+    let mut parameters = Vec::new();
+    for step in steps {
+        for (name, converter) in step.variables() {
+            let doc = route.variable_doc(name);
+            parameters.push(parameter(name, "path", true, schema(converter), doc));
+        }
+    }
+    for (key, value, optional) in route.pattern.query().params() {
+        let doc = value
+            .names
+            .first()
+            .and_then(|name| route.variable_doc(name));
+        parameters.push(parameter(key, "query", !optional, query_schema(value), doc));
+    }
+    if !parameters.is_empty() {
+        members.push(("parameters".to_string(), JsonValue::Array(parameters)));
+    }
+    members.push(("responses".to_string(), JsonValue::Object(Vec::new())));
+    JsonValue::Object(members)
+}
+
+/// The schema of the variables of a route.
+fn params_schema<T>(route: &RouteInfo<T>) -> JsonValue {
+    let pattern = route.pattern;
+    let required_steps = pattern.variants().last().unwrap_or(&[]);
+    let path = pattern.variants().next().unwrap_or(&[]).iter().map(|step| {
+        let optional = !required_steps.iter().any(|s| std::ptr::eq(s, step));
+        (step, optional)
+    });
+    let query = pattern
+        .query()
+        .params()
+        .map(|(_, value, optional)| (value, optional));
+    let mut properties = Vec::new();
+    let mut required = Vec::new();
+    for (step, optional) in path.chain(query) {
+        for (name, converter) in step.variables() {
+            let mut schema = schema(converter);
+            let default = step.defaults().find(|(n, _)| *n == name);
+            if let (Some((_, value)), JsonValue::Object(members)) = (default, &mut schema) {
+                members.push(("default".to_string(), default_json(converter, value)));
+            }
+            if let (Some(doc), JsonValue::Object(members)) = (route.variable_doc(name), &mut schema)
+            {
+                members.push(("description".to_string(), string(&doc.description)));
+                if let Some(example) = &doc.example {
+                    let examples = vec![default_json(converter, example)];
+                    members.push(("examples".to_string(), JsonValue::Array(examples)));
+                }
+            }
+            if !optional && default.is_none() {
+                required.push(string(name));
+            }
+            properties.push((name.to_string(), schema));
+        }
+    }
+    object(vec![
+        ("$schema", string(JSON_SCHEMA)),
+        ("type", string("object")),
+        ("properties", JsonValue::Object(properties)),
+        ("required", JsonValue::Array(required)),
+        ("additionalProperties", JsonValue::Bool(false)),
+    ])
+}
+
+/// The JSON Schema dialect of `Router::to_json_schema`.
+const JSON_SCHEMA: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A default value as JSON of the type of its converter.
+fn default_json(converter: Option<&str>, value: &str) -> JsonValue {
+    match converter {
+        Some("int") | Some("float") => value
+            .parse()
+            .map_or_else(|_| string(value), JsonValue::Number),
+        Some("bool") => value
+            .parse()
+            .map_or_else(|_| string(value), JsonValue::Bool),
+        _ => string(value),
+    }
+}
+
+fn parameter(
+    name: &str,
+    location: &str,
+    required: bool,
+    schema: JsonValue,
+    doc: Option<&VariableDoc>,
+) -> JsonValue {
+    let mut members = vec![("name", string(name)), ("in", string(location))];
+    if let Some(doc) = doc {
+        members.push(("description", string(&doc.description)));
+    }
+    members.push(("required", JsonValue::Bool(required)));
+    members.push(("schema", schema));
+    if let Some(example) = doc.and_then(|doc| doc.example.as_deref()) {
+        members.push(("example", string(example)));
+    }
+    object(members)
+}
+
+/// The schema of a variable with a converter.
+fn schema(converter: Option<&str>) -> JsonValue {
+    let string_with =
+        |key: &str, value: &str| object(vec![("type", string("string")), (key, string(value))]);
+    match converter {
+        Some("int") => object(vec![("type", string("integer"))]),
+        Some("float") => object(vec![("type", string("number"))]),
+        Some("bool") => object(vec![("type", string("boolean"))]),
+        Some("uuid") => string_with("format", "uuid"),
+        Some("date") => string_with("format", "date"),
+        Some(converter) if converter.starts_with("re:") => {
+            string_with("pattern", &format!("^(?:{})$", &converter[3..]))
+        }
+        _ => object(vec![("type", string("string"))]),
+    }
+}
+
+/// The schema of a query parameter: that of its variable if the value is
+/// nothing but a variable, otherwise a string.
+fn query_schema(value: &Step) -> JsonValue {
+    match value.variables().next() {
+        Some((_, converter)) if value.is_variable() => schema(converter),
+        _ => schema(None),
+    }
+}
+
+fn object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn string(s: &str) -> JsonValue {
+    JsonValue::String(s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docs::VariableDocs;
+    use crate::metadata::Metadata;
+
+    #[test]
+    fn test_to_openapi() {
+        let mut router = Router::new();
+        router
+            .register_named("department", "departments/{d:int}", 1)
+            .unwrap();
+        router.register("departments/{d}", 2).unwrap();
+        let update = Operation {
+            method: "put".to_string(),
+            summary: Some("Update \"a\" department".to_string()),
+            description: None,
+        };
+        router
+            .register_with_metadata("departments/{d}", 3, Metadata::new().with(update))
+            .unwrap();
+        router
+            .register("v{major}.{minor}/search?q={q}&[page={p:int}]", 4)
+            .unwrap();
+        router.register("", 5).unwrap();
+        assert_eq!(
+            router.to_openapi().to_string(),
+            concat!(
+                r#"{"/departments/{d}":{"#,
+                r#""get":{"operationId":"department","parameters":["#,
+                r#"{"name":"d","in":"path","required":true,"schema":{"type":"integer"}}"#,
+                r#"],"responses":{}},"#,
+                r#""put":{"summary":"Update \"a\" department","parameters":["#,
+                r#"{"name":"d","in":"path","required":true,"schema":{"type":"string"}}"#,
+                r#"],"responses":{}}},"#,
+                r#""/v{major}.{minor}/search":{"get":{"parameters":["#,
+                r#"{"name":"major","in":"path","required":true,"schema":{"type":"string"}},"#,
+                r#"{"name":"minor","in":"path","required":true,"schema":{"type":"string"}},"#,
+                r#"{"name":"q","in":"query","required":true,"schema":{"type":"string"}},"#,
+                r#"{"name":"page","in":"query","required":false,"schema":{"type":"integer"}}"#,
+                r#"],"responses":{}}},"#,
+                r#""/":{"get":{"responses":{}}}}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_openapi_optional_steps() {
+        let mut router = Router::new();
+        router.register("articles[/{page:int}]", 1).unwrap();
+        let openapi = router.to_openapi();
+        assert_eq!(
+            openapi.get("/articles").unwrap().to_string(),
+            r#"{"get":{"responses":{}}}"#
+        );
+        assert_eq!(
+            openapi.get("/articles/{page}").unwrap().to_string(),
+            concat!(
+                r#"{"get":{"parameters":["#,
+                r#"{"name":"page","in":"path","required":true,"schema":{"type":"integer"}}"#,
+                r#"],"responses":{}}}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_openapi_template() {
+        let mut router = Router::new().with_separator('.');
+        router.register("{{draft}}.{id}", 1).unwrap();
+        let openapi = router.to_openapi();
+        assert!(openapi.get("/%7Bdraft%7D.{id}").is_some());
+        assert_eq!(openapi.get("/{draft}.{id}"), None);
+    }
+
+    #[test]
+    fn test_to_json_schema() {
+        let mut router = Router::new();
+        router
+            .register_named("article", "articles/{id:int}[/{tab=info}]", 1)
+            .unwrap();
+        router
+            .register(
+                "search/{{all}}?q={q}&[page={page:int=1}]&[exact={e:bool}]",
+                2,
+            )
+            .unwrap();
+        router.register("", 4).unwrap();
+        assert_eq!(
+            router.to_json_schema().to_string(),
+            concat!(
+                r#"{"article":{"$schema":"https://json-schema.org/draft/2020-12/schema","#,
+                r#""type":"object","properties":{"id":{"type":"integer"},"#,
+                r#""tab":{"type":"string","default":"info"}},"#,
+                r#""required":["id"],"additionalProperties":false},"#,
+                r#""search/{{all}}?q={q}&[page={page:int=1}]&[exact={e:bool}]":{"#,
+                r#""$schema":"https://json-schema.org/draft/2020-12/schema","#,
+                r#""type":"object","properties":{"q":{"type":"string"},"#,
+                r#""page":{"type":"integer","default":1},"e":{"type":"boolean"}},"#,
+                r#""required":["q"],"additionalProperties":false},"#,
+                r#""":{"$schema":"https://json-schema.org/draft/2020-12/schema","#,
+                r#""type":"object","properties":{},"required":[],"additionalProperties":false}}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_variable_docs() {
+        let mut router = Router::new();
+        let docs = VariableDocs::new()
+            .with("id", "numeric user id")
+            .with_example("id", "42")
+            .with("q", "search terms");
+        router
+            .register_with_metadata("users/{id:int}?q={q}", 1, Metadata::new().with(docs))
+            .unwrap();
+        let openapi = router.to_openapi();
+        let get = openapi.get("/users/{id}").unwrap().get("get").unwrap();
+        assert_eq!(
+            get.get("parameters").unwrap().to_string(),
+            concat!(
+                r#"[{"name":"id","in":"path","description":"numeric user id","#,
+                r#""required":true,"schema":{"type":"integer"},"example":"42"},"#,
+                r#"{"name":"q","in":"query","description":"search terms","#,
+                r#""required":true,"schema":{"type":"string"}}]"#,
+            )
+        );
+        let schema = router.to_json_schema();
+        let properties = schema
+            .get("users/{id:int}?q={q}")
+            .unwrap()
+            .get("properties");
+        assert_eq!(
+            properties.unwrap().to_string(),
+            concat!(
+                r#"{"id":{"type":"integer","description":"numeric user id","examples":[42]},"#,
+                r#""q":{"type":"string","description":"search terms"}}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_schema() {
+        assert_eq!(
+            schema(Some("uuid")).to_string(),
+            r#"{"type":"string","format":"uuid"}"#
+        );
+        assert_eq!(
+            schema(Some(r"re:[A-Z]{3}-\d+")).to_string(),
+            r#"{"type":"string","pattern":"^(?:[A-Z]{3}-\\d+)$"}"#
+        );
+        assert_eq!(schema(Some("slug")).to_string(), r#"{"type":"string"}"#);
+    }
+}
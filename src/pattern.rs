@@ -0,0 +1,2165 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+use crate::converter::{self, ConverterRegistry, NamedConverter};
+use crate::encoding;
+use crate::error::{ConversionError, Direction, ExpandError, ParseError};
+use crate::matching::{BytesMatch, Match};
+use crate::query::Query;
+use crate::step::Step;
+use crate::syntax::Syntax;
+
+/// A path pattern such as `departments/{department_id}/employees/{employee_id}`.
+///
+/// A pattern is a sequence of steps separated by `/`, or another separator
+/// given to `with_separator`. Each step can contain literal text as well as
+/// variables. Variable names have to be unique across the whole pattern.
+///
+/// A step always matches a whole segment of the path, never part of it.
+/// Every variable captures at least one character, and stops at the first
+/// occurrence of the literal text that follows it: `{a}x{b}` matches
+/// `fooxbarxbaz` with `a` as `foo` and `b` as `barxbaz`.
+///
+/// A variable can have a regex constraint, like `{sku:re:[A-Z]{3}-\d+}`, so
+/// that it only captures text the regex matches as a whole. Regex
+/// constraints need the `regex` feature, which is on by default.
+///
+/// Variables can only follow each other without literal text between them
+/// if that splits a segment in one way, so all but one of them need a regex
+/// constraint of a fixed length, like `{year:int}{month:re:\d{2}}`.
+///
+/// Literal braces are written doubled: `{{draft}}` matches the segment
+/// `{draft}`.
+///
+/// The last step can be a wildcard like `{rest:*}`, which captures the
+/// rest of the path, slashes included.
+///
+/// A pattern can end in optional steps between brackets, like
+/// `articles[/{page}]`, which matches both `articles` and `articles/3`.
+///
+/// A variable can have a default value after a `=`, like `{page:int=1}`.
+/// A match without the optional step or query parameter of the variable
+/// has the default as its value, and `expand` uses the default for a
+/// variable that isn't supplied. Regex constraints can't have defaults.
+///
+/// A pattern can also describe query parameters after a `?`, like
+/// `articles/{id}?version={v}&[lang={l}]`. The value of a parameter is
+/// matched like a step, with the same converters: `page={page:int}` only
+/// matches a number, and `expand` rejects other values for it with
+/// `ExpandError::Conversion` as it does for a step. Parameters between
+/// brackets are optional, and parameters the pattern doesn't describe are
+/// ignored. Without a `?` the query isn't matched at all.
+///
+/// With percent-encoding enabled, segments are percent-decoded before they
+/// are matched, and `expand` percent-encodes the path it produces.
+///
+/// Literal text matches in the same case only, unless the pattern is made
+/// case-insensitive; see `Case`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    s: String,
+    steps: Vec<Step>,
+    /// the steps after this many are optional
+    required: usize,
+    query: Query,
+    /// the character between steps, `/` unless parsed with another
+    separator: char,
+    percent_encoding: bool,
+    case: Case,
+}
+
+/// How a pattern treats the case of the paths it matches.
+///
+/// Case-insensitive matching only ignores the case of ASCII letters. Either
+/// way `expand` spells literal text as the pattern does, so the pattern
+/// gives the canonical case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Case {
+    /// Literal text only matches in the same case. The default.
+    #[default]
+    Sensitive,
+    /// Literal text matches in any case. Captured values are kept as they
+    /// are.
+    Insensitive,
+    /// Literal text matches in any case, and captured values are
+    /// lowercased.
+    Lowercase,
+}
+
+impl Pattern {
+    /// Parse a pattern with the built-in converters. A leading `/` is
+    /// ignored.
+    pub fn new(s: &str) -> Result<Pattern, ParseError> {
+        Pattern::with_converters(s, converter::builtin())
+    }
+
+    /// Parse a pattern, looking up converters in a registry.
+    pub fn with_converters(s: &str, converters: &ConverterRegistry) -> Result<Pattern, ParseError> {
+        Pattern::parse(s, converters, '/')
+    }
+
+    /// Parse a pattern with steps separated by another character than `/`,
+    /// like `{subdomain}.example.com` with `.`. A leading separator is
+    /// ignored, and optional steps start with the separator too, like
+    /// `sensors[.{id}]`.
+    ///
+    /// # Panics
+    ///
+    /// If the separator is one of `{}[]?\`, which have a meaning in
+    /// patterns.
+    pub fn with_separator(s: &str, separator: char) -> Result<Pattern, ParseError> {
+        Pattern::parse(s, converter::builtin(), separator)
+    }
+
+    pub(crate) fn parse(
+        s: &str,
+        converters: &ConverterRegistry,
+        separator: char,
+    ) -> Result<Pattern, ParseError> {
+        assert_separator(separator);
+        let leading = s.len();
+        let s = strip_leading(s, separator);
+        let leading = leading - s.len();
+
+        let (path_s, query) = match find_top_level(s, '?').next() {
+            Some(i) => {
+                let query =
+                    Query::new(&s[i + 1..], converters).map_err(|e| e.shifted(leading + i + 1))?;
+                (&s[..i], query)
+            }
+            None => (s, Query::default()),
+        };
+        let (step_strs, required) = split_steps(path_s, separator)?;
+        let mut steps = Vec::new();
+        let mut name_set = HashSet::new();
+        // offset of the `*` of a wildcard step, which has to be the last
+        let mut wildcard_offset = None;
+        for (step_offset, step_s) in step_strs {
+            let offset = leading + step_offset;
+            if let Some(offset) = wildcard_offset {
+                return Err(ParseError::MisplacedWildcard { offset });
+            }
+            let step = Step::with_converters(step_s, converters).map_err(|e| e.shifted(offset))?;
+            if step.wildcard {
+                wildcard_offset = Some(offset + step.offsets[0] + step.names[0].len() + 1);
+            }
+            for (name, name_offset) in step.names.iter().zip(&step.offsets) {
+                if !name_set.insert(name.clone()) {
+                    return Err(ParseError::DuplicateVariable {
+                        name: name.clone(),
+                        offset: offset + name_offset,
+                    });
+                }
+            }
+            steps.push(step);
+        }
+        if let Some((name, offset)) = query.offsets().find(|(name, _)| name_set.contains(*name)) {
+            return Err(ParseError::DuplicateVariable {
+                name: name.to_string(),
+                offset: leading + path_s.len() + 1 + offset,
+            });
+        }
+        Ok(Pattern {
+            s: s.to_owned(),
+            steps,
+            required,
+            query,
+            separator,
+            percent_encoding: false,
+            case: Case::default(),
+        })
+    }
+
+    /// Enable or disable percent-decoding on match and percent-encoding on
+    /// expand.
+    pub fn with_percent_encoding(mut self, enabled: bool) -> Pattern {
+        self.set_percent_encoding(enabled);
+        self
+    }
+
+    pub(crate) fn set_percent_encoding(&mut self, enabled: bool) {
+        self.percent_encoding = enabled;
+    }
+
+    /// Set how the case of paths is treated.
+    pub fn with_case(mut self, case: Case) -> Pattern {
+        self.set_case(case);
+        self
+    }
+
+    pub(crate) fn set_case(&mut self, case: Case) {
+        self.case = case;
+        let insensitive = case != Case::Sensitive;
+        for step in &mut self.steps {
+            step.set_case_insensitive(insensitive);
+        }
+        self.query.set_case_insensitive(insensitive);
+    }
+
+    /// The pattern string, without leading separator.
+    pub fn as_str(&self) -> &str {
+        &self.s
+    }
+
+    #[cfg(any(feature = "openapi", feature = "examples", feature = "typescript"))]
+    pub(crate) fn separator(&self) -> char {
+        self.separator
+    }
+
+    #[cfg(any(feature = "openapi", feature = "typescript"))]
+    pub(crate) fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// The variable names in this pattern, in order of appearance.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.steps
+            .iter()
+            .flat_map(|step| step.names.iter().map(String::as_str))
+            .chain(self.query.names())
+    }
+
+    /// Whether the literal text of a step contains text, like `admin` does
+    /// in `admin/users` and `v1/admin-{page}`.
+    pub fn has_literal(&self, text: &str) -> bool {
+        self.steps
+            .iter()
+            .any(|step| step.parts.iter().any(|part| part.contains(text)))
+    }
+
+    /// Whether a variable uses the converter registered under a name.
+    pub fn uses_converter(&self, name: &str) -> bool {
+        self.names()
+            .any(|n| matches!(self.converter(n), Some(Some(converter)) if converter.name == name))
+    }
+
+    /// A name for the pattern, made of its literal text and the names of its
+    /// variables joined by `_`, so `users/{id:int}/posts` is named
+    /// `users_id_posts`. Other characters than letters, digits and `_` are
+    /// left out, as is the query. The name of a pattern without any of
+    /// those, like the empty pattern, is `root`.
+    pub fn derived_name(&self) -> String {
+        let words: Vec<&str> = self
+            .steps
+            .iter()
+            .flat_map(Step::pieces)
+            .flat_map(word_parts)
+            .collect();
+        if words.is_empty() {
+            "root".to_string()
+        } else {
+            words.join("_")
+        }
+    }
+
+    /// Whether the pattern looks like a gRPC path, `{service}/{method}`:
+    /// exactly two required steps separated by `/`, neither of them a
+    /// wildcard, and no query.
+    pub(crate) fn is_grpc(&self) -> bool {
+        self.separator == '/'
+            && self.steps.len() == 2
+            && self.required == 2
+            && self.query.is_empty()
+            && self.steps.iter().all(|step| !step.wildcard)
+    }
+
+    /// Whether a variable is matched within a single segment, so it isn't
+    /// a catch-all.
+    pub(crate) fn in_segment(&self, name: &str) -> bool {
+        self.steps
+            .iter()
+            .any(|step| !step.wildcard && step.names.iter().any(|n| n == name))
+    }
+
+    /// Match a full path, return the captured variables.
+    ///
+    /// The path can have a query after a `?`, which is matched against the
+    /// query parameters of the pattern.
+    pub fn match_path<'a>(&'a self, path: &'a str) -> Option<Match<'a>> {
+        let (path, query) = split_query(path);
+        self.variants().find_map(|steps| {
+            let (mut variables, rest) =
+                self.match_steps(steps, Segments::path(path, self.separator))?;
+            if !rest.is_end() {
+                return None;
+            }
+            variables.extend(self.match_query(query)?);
+            Some(self.matched(variables))
+        })
+    }
+
+    /// Why a path doesn't match, if that is because a converter rejected
+    /// the value of a variable: the first variable that was rejected. The
+    /// path has to have the segments and literals of the pattern, and the
+    /// query isn't checked.
+    pub fn conversion_failure(&self, path: &str) -> Option<ConversionError> {
+        let path = split_query(path).0;
+        self.variants().find_map(|steps| {
+            let mut segments = Segments::path(path, self.separator);
+            let mut failure = None;
+            for step in steps {
+                if step.wildcard {
+                    segments.rest().filter(|rest| !rest.is_empty())?;
+                    return failure;
+                }
+                let segment = self.decode(segments.next()?)?;
+                if step.match_segment(&segment).is_some() {
+                    continue;
+                }
+                let (name, value, converter) = step.rejection(&segment)?;
+                failure.get_or_insert_with(|| ConversionError {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    converter: converter.to_string(),
+                    direction: Direction::Decode,
+                });
+            }
+            if !segments.is_end() {
+                return None;
+            }
+            failure
+        })
+    }
+
+    /// Match a path that is already split into segments, return the
+    /// captured variables.
+    ///
+    /// A wildcard captures the remaining segments joined by `/`. There is
+    /// no query, so required query parameters never match.
+    pub fn match_segments<'a>(&'a self, segments: &'a [&'a str]) -> Option<Match<'a>> {
+        self.variants().find_map(|steps| {
+            let (mut variables, rest) =
+                self.match_steps(steps, Segments::Split(segments, self.separator))?;
+            if !rest.is_end() {
+                return None;
+            }
+            variables.extend(self.match_query(None)?);
+            Some(self.matched(variables))
+        })
+    }
+
+    /// Match the start of a path, return the captured variables and the
+    /// remainder of the path that the pattern didn't consume.
+    ///
+    /// Optional steps are consumed if they match. The remainder doesn't
+    /// include the separating `/`.
+    ///
+    /// Like with `match_path` a query is matched against the query
+    /// parameters; it isn't part of the remainder.
+    pub fn match_prefix<'a>(&'a self, path: &'a str) -> Option<(Match<'a>, &'a str)> {
+        let (path, query) = split_query(path);
+        self.variants().find_map(|steps| {
+            let (mut variables, rest) =
+                self.match_steps(steps, Segments::path(path, self.separator))?;
+            variables.extend(self.match_query(query)?);
+            let rest = match rest {
+                Segments::Path(rest, _) => rest.unwrap_or(""),
+                Segments::Split(..) => unreachable!("matched a path"),
+            };
+            Some((self.matched(variables), rest))
+        })
+    }
+
+    /// The defaults of the variables in the optional steps that `steps`
+    /// leaves out.
+    fn omitted_defaults(&self, steps: &[Step]) -> impl Iterator<Item = (&str, &str)> {
+        self.steps[steps.len()..].iter().flat_map(Step::defaults)
+    }
+
+    /// The match for the captured variables, lowercased with
+    /// `Case::Lowercase`.
+    fn matched<'a>(&self, mut variables: Vec<(&'a str, Cow<'a, str>)>) -> Match<'a> {
+        if self.case == Case::Lowercase {
+            for (_, value) in &mut variables {
+                if value.chars().any(char::is_uppercase) {
+                    *value = Cow::Owned(value.to_lowercase());
+                }
+            }
+        }
+        Match::new(variables)
+    }
+
+    /// Match a full path like `match_path`, but push the captured variables
+    /// onto a vector instead of returning a `Match`.
+    ///
+    /// The vector is cleared first, so it can be reused for many matches
+    /// without allocating. Values are borrowed from the path, or are
+    /// defaults; unlike
+    /// `match_path` they are never percent-decoded, even with
+    /// percent-encoding enabled, nor lowercased with `Case::Lowercase`.
+    /// Returns whether the path matched.
+    pub fn match_into<'a>(
+        &'a self,
+        path: &'a str,
+        variables: &mut Vec<(&'a str, &'a str)>,
+    ) -> bool {
+        let (path, query) = split_query(path);
+        self.variants().any(|steps| {
+            variables.clear();
+            self.match_steps_into(steps, path, variables) == Some(None)
+                && (self.query.is_empty() || self.query.match_query_into(query, variables))
+        })
+    }
+
+    /// Match a full path given as bytes, which need not be UTF-8, return the
+    /// captured bytes.
+    ///
+    /// Segments that are UTF-8 match like they do with `match_path`. Other
+    /// segments only match a step that is just a variable without a
+    /// converter, or a wildcard, which capture them as they are. A query
+    /// only matches query parameters if it is UTF-8. Like with `match_into`
+    /// values aren't percent-decoded.
+    pub fn match_bytes<'a>(&'a self, path: &'a [u8]) -> Option<BytesMatch<'a>> {
+        let (path, query) = split_query_bytes(path);
+        self.variants().find_map(|steps| {
+            let mut variables = Vec::new();
+            if !self.match_steps_bytes(steps, path, &mut variables) {
+                return None;
+            }
+            if !self.query.is_empty() {
+                let query = match query {
+                    Some(query) => Some(std::str::from_utf8(query).ok()?),
+                    None => None,
+                };
+                let mut captured = Vec::new();
+                if !self.query.match_query_into(query, &mut captured) {
+                    return None;
+                }
+                variables.extend(
+                    captured
+                        .into_iter()
+                        .map(|(name, value)| (name, value.as_bytes())),
+                );
+            }
+            Some(BytesMatch::new(variables))
+        })
+    }
+
+    /// Match a platform path or string, like `match_bytes` with the bytes
+    /// `OsStr::as_encoded_bytes` gives. On Unix these are the bytes of the
+    /// path as the operating system has them.
+    pub fn match_os_str<'a>(&'a self, path: &'a OsStr) -> Option<BytesMatch<'a>> {
+        self.match_bytes(path.as_encoded_bytes())
+    }
+
+    /// Match a full path given as bytes like `match_path`, first replacing
+    /// invalid UTF-8 with `U+FFFD`.
+    pub fn match_bytes_lossy<'a>(&'a self, path: &'a [u8]) -> Option<Match<'a>> {
+        match String::from_utf8_lossy(path) {
+            Cow::Borrowed(path) => self.match_path(path),
+            Cow::Owned(path) => self.match_path_owned(&path),
+        }
+    }
+
+    /// Like `match_steps_into`, for a whole path given as bytes.
+    fn match_steps_bytes<'a>(
+        &'a self,
+        steps: &'a [Step],
+        path: &'a [u8],
+        variables: &mut Vec<(&'a str, &'a [u8])>,
+    ) -> bool {
+        let mut buffer = [0; 4];
+        let separator = self.separator.encode_utf8(&mut buffer).as_bytes();
+        let mut rest = Some(path.strip_prefix(separator).unwrap_or(path));
+        let mut captured = Vec::new();
+        for step in steps {
+            let remaining = match rest {
+                Some(remaining) => remaining,
+                None => return false,
+            };
+            if step.wildcard {
+                if remaining.is_empty() {
+                    return false;
+                }
+                variables.push((step.names[0].as_str(), remaining));
+                return true;
+            }
+            let (segment, next) = match find_bytes(remaining, separator) {
+                Some(i) => (&remaining[..i], Some(&remaining[i + separator.len()..])),
+                None => (remaining, None),
+            };
+            rest = next;
+            match std::str::from_utf8(segment) {
+                Ok(segment) => {
+                    captured.clear();
+                    if !step.match_segment_into(segment, &mut captured) {
+                        return false;
+                    }
+                    variables.extend(
+                        captured
+                            .iter()
+                            .map(|(name, value)| (*name, value.as_bytes())),
+                    );
+                }
+                Err(_) if step.is_catch_all() => {
+                    variables.push((step.names[0].as_str(), segment));
+                }
+                Err(_) => return false,
+            }
+        }
+        variables.extend(
+            self.omitted_defaults(steps)
+                .map(|(name, value)| (name, value.as_bytes())),
+        );
+        rest.is_none()
+    }
+
+    /// Match a full path like `match_path`, copying the values so that the
+    /// match doesn't borrow from the path.
+    pub(crate) fn match_path_owned<'a>(&'a self, path: &str) -> Option<Match<'a>> {
+        Some(self.owned_match(&self.match_path(path)?))
+    }
+
+    /// A copy of a match of this pattern that doesn't borrow from the path.
+    pub(crate) fn owned_match<'a>(&'a self, m: &Match) -> Match<'a> {
+        let variables = m
+            .iter()
+            .map(|(name, value)| {
+                let name = self.names().find(|n| *n == name).expect("name in pattern");
+                (name, Cow::Owned(value.to_string()))
+            })
+            .collect();
+        Match::new(variables)
+    }
+
+    fn match_query<'a>(&'a self, query: Option<&'a str>) -> Option<Vec<(&'a str, Cow<'a, str>)>> {
+        if self.query.is_empty() {
+            return Some(Vec::new());
+        }
+        self.query.match_query(query, self.percent_encoding)
+    }
+
+    /// The step sequences this pattern matches: with optional steps first,
+    /// then without.
+    pub(crate) fn variants(&self) -> impl Iterator<Item = &[Step]> {
+        let without = if self.required < self.steps.len() {
+            Some(&self.steps[..self.required])
+        } else {
+            None
+        };
+        std::iter::once(&self.steps[..]).chain(without)
+    }
+
+    /// Match steps against the first segments, return the captured
+    /// variables and the segments after them.
+    fn match_steps<'a>(
+        &'a self,
+        steps: &'a [Step],
+        mut segments: Segments<'a>,
+    ) -> Option<MatchedSteps<'a>> {
+        let mut variables = Vec::new();
+        for step in steps {
+            if step.wildcard {
+                let rest = segments.rest().filter(|rest| !rest.is_empty())?;
+                let value = match rest {
+                    Cow::Borrowed(rest) => self.decode(rest)?,
+                    Cow::Owned(rest) => Cow::Owned(self.decode(&rest)?.into_owned()),
+                };
+                variables.push((step.names[0].as_str(), value));
+                return Some((variables, Segments::Path(None, self.separator)));
+            }
+            variables.extend(step.match_decoded(self.decode(segments.next()?)?)?);
+        }
+        variables.extend(
+            self.omitted_defaults(steps)
+                .map(|(name, value)| (name, Cow::Borrowed(value))),
+        );
+        Some((variables, segments))
+    }
+
+    /// Like `match_steps`, but without decoding, pushing the variables and
+    /// returning just the rest of the path.
+    fn match_steps_into<'a>(
+        &'a self,
+        steps: &'a [Step],
+        path: &'a str,
+        variables: &mut Vec<(&'a str, &'a str)>,
+    ) -> Option<Option<&'a str>> {
+        let mut rest = Some(strip_leading(path, self.separator));
+        for step in steps {
+            let remaining = rest?;
+            if step.wildcard {
+                if remaining.is_empty() {
+                    return None;
+                }
+                variables.push((step.names[0].as_str(), remaining));
+                return Some(None);
+            }
+            let (segment, next) = next_segment(remaining, self.separator);
+            rest = next;
+            if !step.match_segment_into(segment, variables) {
+                return None;
+            }
+        }
+        variables.extend(self.omitted_defaults(steps));
+        Some(rest)
+    }
+
+    fn decode<'a>(&self, s: &'a str) -> Option<Cow<'a, str>> {
+        if self.percent_encoding {
+            encoding::percent_decode(s)
+        } else {
+            Some(Cow::Borrowed(s))
+        }
+    }
+
+    /// Whether this pattern has the same shape as other, apart from the
+    /// names of its variables, so they match exactly the same paths.
+    pub(crate) fn same_shape(&self, other: &Pattern) -> bool {
+        self.separator == other.separator
+            && self.query.same_shape(&other.query)
+            && self.required == other.required
+            && self.steps.len() == other.steps.len()
+            && self
+                .steps
+                .iter()
+                .zip(&other.steps)
+                .all(|(mine, theirs)| mine.same_shape(theirs))
+    }
+
+    /// Whether this pattern matches every path that other matches.
+    ///
+    /// Like `Step::generalizes` this is conservative, and only says yes if
+    /// this pattern doesn't describe a query or describes the same query.
+    pub(crate) fn generalizes(&self, other: &Pattern) -> bool {
+        self.separator == other.separator
+            && (self.query.is_empty() || self.query.same_shape(&other.query))
+            && other
+                .variants()
+                .all(|theirs| self.variants().any(|mine| steps_generalize(mine, theirs)))
+    }
+
+    /// The specificity of each step, to order patterns by precedence, and
+    /// then the number of required query parameters.
+    ///
+    /// Compared lexicographically, a literal step beats a variable step in
+    /// the same position, so longer literal prefixes come first. Among
+    /// patterns with the same steps, those that require more query
+    /// parameters come first.
+    pub(crate) fn specificity(&self) -> Specificity {
+        (
+            self.steps.iter().map(Step::specificity).collect(),
+            Reverse(self.query.required()),
+        )
+    }
+
+    /// If the required steps of this pattern start with those of other,
+    /// and there are more, the variables of other paired with those of this
+    /// pattern in the same place.
+    pub(crate) fn extends<'a>(&'a self, other: &'a Pattern) -> Option<Vec<(&'a str, &'a str)>> {
+        let (mine, theirs) = (&self.steps[..self.required], &other.steps[..other.required]);
+        if self.separator != other.separator
+            || mine.len() <= theirs.len()
+            || !theirs.iter().zip(mine).all(|(t, m)| t.same_shape(m))
+        {
+            return None;
+        }
+        Some(
+            theirs
+                .iter()
+                .zip(mine)
+                .flat_map(|(t, m)| t.names.iter().zip(&m.names))
+                .map(|(t, m)| (t.as_str(), m.as_str()))
+                .collect(),
+        )
+    }
+
+    /// The number of steps in the pattern.
+    pub(crate) fn depth(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Expand the pattern into a path, using variable values.
+    ///
+    /// All variables in the pattern have to be supplied, and no variables
+    /// that are not in the pattern can be, except that variables with a
+    /// default can be left out. Optional steps are left out if none of
+    /// their variables are supplied, or only with their defaults.
+    pub fn expand(&self, values: &HashMap<&str, &str>) -> Result<String, ExpandError> {
+        self.expand_text(values)
+    }
+
+    /// Expand the pattern like `expand` into an IRI, which keeps non-ASCII
+    /// characters as they are rather than percent-encoding them, so
+    /// `produits/{slug}` expands to `produits/crème` rather than
+    /// `produits/cr%C3%A8me`. Characters that have to be escaped in a URI
+    /// are still escaped with percent-encoding enabled; `iri_to_uri` turns
+    /// the result into a URI.
+    ///
+    /// Matching takes both forms with percent-encoding enabled.
+    pub fn expand_iri(&self, values: &HashMap<&str, &str>) -> Result<String, ExpandError> {
+        let uri = self.expand_text(values)?;
+        Ok(encoding::decode_non_ascii(&uri, Some(self.separator)).into_owned())
+    }
+
+    /// Expand the pattern like `expand`, with typed values that are
+    /// serialized by the converters of their variables: `{id:int}` takes an
+    /// `i64`, `{when:date}` a `Date`. Variables without a converter or with
+    /// a regex constraint take a `String` or a `&str`.
+    pub fn expand_typed(&self, values: &[(&str, &dyn Any)]) -> Result<String, ExpandError> {
+        let serialized = self.serialized(values)?;
+        self.expand_text(
+            &serialized
+                .iter()
+                .map(|(name, text)| (*name, text.as_str()))
+                .collect(),
+        )
+    }
+
+    /// Bind some variables to values, giving a pattern with the others
+    /// still open: `tenants/{tenant}/users/{id}` with `tenant` as `acme`
+    /// gives `tenants/acme/users/{id}`. Converters check the values as
+    /// `expand` does.
+    ///
+    /// A value has to be written as literal text: it can't be empty or
+    /// contain `[`, `]`, `?` or `&`, and only a wildcard can take the
+    /// separator, between non-empty segments.
+    pub fn partial(&self, values: &[(&str, &str)]) -> Result<Pattern, ExpandError> {
+        for (name, value) in values {
+            let converter = self
+                .converter(name)
+                .ok_or_else(|| ExpandError::ExtraVariable {
+                    name: name.to_string(),
+                })?;
+            if let Some(converter) = converter {
+                if !converter.validate(value) {
+                    return Err(ExpandError::Conversion(ConversionError {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                        converter: converter.name.clone(),
+                        direction: Direction::Encode,
+                    }));
+                }
+            }
+            let literal = if self.in_segment(name) {
+                !value.is_empty() && !value.contains(self.separator)
+            } else {
+                !value.split(self.separator).any(str::is_empty)
+            };
+            if !literal || value.contains(['[', ']', '?', '&']) {
+                return Err(ExpandError::InvalidValue {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+        let syntax = Syntax::with_separator(&self.s, self.separator).expect("parsed pattern");
+        let mut s = String::new();
+        let mut end = 0;
+        for variable in syntax.variables() {
+            let name = syntax.text(&variable.name);
+            if let Some((_, value)) = values.iter().find(|(n, _)| *n == name) {
+                s.push_str(&self.s[end..variable.span.start]);
+                s.push_str(&value.replace('{', "{{").replace('}', "}}"));
+                end = variable.span.end;
+            }
+        }
+        s.push_str(&self.s[end..]);
+        let converters = self
+            .names()
+            .filter_map(|name| self.converter(name).flatten());
+        let pattern = Pattern::parse(&s, &ConverterRegistry::of(converters), self.separator)
+            .expect("pattern with literal values");
+        Ok(pattern
+            .with_percent_encoding(self.percent_encoding)
+            .with_case(self.case))
+    }
+
+    /// Pin one variable to a value like `partial`: `api/{version}/users`
+    /// with `version` as `v2` gives `api/v2/users`, which a router tries
+    /// before the pattern it came from.
+    pub fn specialize(&self, name: &str, value: &str) -> Result<Pattern, ExpandError> {
+        self.partial(&[(name, value)])
+    }
+
+    /// The converter of a variable: `None` if there is no such variable,
+    /// `Some(None)` if it has no converter.
+    pub(crate) fn converter(&self, name: &str) -> Option<Option<&NamedConverter>> {
+        self.steps
+            .iter()
+            .find_map(|step| step.converter(name))
+            .or_else(|| self.query.converter(name))
+    }
+
+    /// Whether a variable is a wildcard.
+    #[cfg(feature = "examples")]
+    pub(crate) fn is_wildcard(&self, name: &str) -> bool {
+        self.steps
+            .iter()
+            .any(|step| step.wildcard && step.names.iter().any(|n| n == name))
+    }
+
+    /// Typed values serialized by the converters of their variables.
+    pub(crate) fn serialized<'v>(
+        &self,
+        values: &[(&'v str, &dyn Any)],
+    ) -> Result<Vec<(&'v str, String)>, ExpandError> {
+        let mut serialized = Vec::new();
+        for (name, value) in values {
+            let converter = self
+                .converter(name)
+                .ok_or_else(|| ExpandError::ExtraVariable {
+                    name: name.to_string(),
+                })?;
+            let text = match converter {
+                Some(converter) => converter.serialize(*value),
+                None => converter::text(*value),
+            };
+            let text = text.ok_or_else(|| ExpandError::WrongType {
+                name: name.to_string(),
+            })?;
+            serialized.push((*name, text));
+        }
+        Ok(serialized)
+    }
+
+    fn expand_text(&self, values: &HashMap<&str, &str>) -> Result<String, ExpandError> {
+        for name in values.keys() {
+            if !self.names().any(|n| n == *name) {
+                return Err(ExpandError::ExtraVariable {
+                    name: name.to_string(),
+                });
+            }
+        }
+        let optional = &self.steps[self.required..];
+        let steps = if optional.iter().any(|step| step.overridden(values)) {
+            &self.steps[..]
+        } else {
+            &self.steps[..self.required]
+        };
+        let segments = steps
+            .iter()
+            .map(|step| step.expand(values, self.percent_encoding.then_some(self.separator)))
+            .collect::<Result<Vec<String>, ExpandError>>()?;
+        let path = segments.join(self.separator.encode_utf8(&mut [0; 4]));
+        let query = self.query.expand(values, self.percent_encoding)?;
+        if query.is_empty() {
+            Ok(path)
+        } else {
+            Ok(format!("{}?{}", path, query))
+        }
+    }
+}
+
+/// A pattern that is parsed when it is first used, so that it can be a
+/// `static`.
+///
+/// Make one with the `pattern!` macro of `traject-derive`, which checks at
+/// compile time that the pattern parses with the built-in converters.
+#[derive(Debug)]
+pub struct StaticPattern {
+    s: &'static str,
+    pattern: OnceLock<Pattern>,
+}
+
+impl StaticPattern {
+    /// A pattern that is parsed on first use, which panics if it doesn't
+    /// parse.
+    pub const fn new(s: &'static str) -> StaticPattern {
+        StaticPattern {
+            s,
+            pattern: OnceLock::new(),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.s
+    }
+
+    /// The parsed pattern.
+    pub fn get(&self) -> &Pattern {
+        self.pattern.get_or_init(|| match Pattern::new(self.s) {
+            Ok(pattern) => pattern,
+            Err(error) => panic!("invalid pattern `{}`: {}", self.s, error),
+        })
+    }
+}
+
+impl Deref for StaticPattern {
+    type Target = Pattern;
+
+    fn deref(&self) -> &Pattern {
+        self.get()
+    }
+}
+
+/// How specific a pattern is, lower is more specific.
+pub(crate) type Specificity = (Vec<u8>, Reverse<usize>);
+
+type MatchedSteps<'a> = (Vec<(&'a str, Cow<'a, str>)>, Segments<'a>);
+
+/// The segments of a path that are still to be matched, and the
+/// separator between them.
+#[derive(Debug, Clone, Copy)]
+enum Segments<'a> {
+    /// the rest of a path string, `None` past the last segment
+    Path(Option<&'a str>, char),
+    Split(&'a [&'a str], char),
+}
+
+impl<'a> Segments<'a> {
+    fn path(path: &'a str, separator: char) -> Segments<'a> {
+        Segments::Path(Some(strip_leading(path, separator)), separator)
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        match self {
+            Segments::Path(rest, separator) => {
+                let (segment, next) = next_segment((*rest)?, *separator);
+                *rest = next;
+                Some(segment)
+            }
+            Segments::Split(segments, _) => {
+                let (segment, rest) = segments.split_first()?;
+                *segments = rest;
+                Some(segment)
+            }
+        }
+    }
+
+    /// the remaining segments as a path, `None` if there are none
+    fn rest(&self) -> Option<Cow<'a, str>> {
+        match self {
+            Segments::Path(rest, _) => rest.map(Cow::Borrowed),
+            Segments::Split([], _) => None,
+            Segments::Split([segment], _) => Some(Cow::Borrowed(segment)),
+            Segments::Split(segments, separator) => Some(Cow::Owned(
+                segments.join(separator.encode_utf8(&mut [0; 4])),
+            )),
+        }
+    }
+
+    fn is_end(&self) -> bool {
+        matches!(self, Segments::Path(None, _) | Segments::Split([], _))
+    }
+}
+
+fn steps_generalize(mine: &[Step], theirs: &[Step]) -> bool {
+    for (i, step) in mine.iter().enumerate() {
+        if step.wildcard {
+            // a wildcard matches any non-empty rest of the path
+            let rest = &theirs[i.min(theirs.len())..];
+            return match rest {
+                [] => false,
+                [only] => !(only.is_literal() && only.text().is_empty()),
+                _ => true,
+            };
+        }
+        match theirs.get(i) {
+            Some(other) if !other.wildcard && step.generalizes(other) => {}
+            _ => return false,
+        }
+    }
+    mine.len() == theirs.len()
+}
+
+/// The text of a step and its offset in the pattern.
+pub(crate) type StepStr<'a> = (usize, &'a str);
+
+/// Split a pattern into its steps with their offsets, and return how many
+/// steps are required. Optional steps are in a trailing `[/...]`.
+pub(crate) fn split_steps(
+    s: &str,
+    separator: char,
+) -> Result<(Vec<StepStr<'_>>, usize), ParseError> {
+    let mut open = None;
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if depth > 0 => escaped = true,
+            '{' | '}' if depth == 0 && s[i + 1..].starts_with(c) => escaped = true,
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '[' if depth == 0 && open.is_none() => open = Some(i),
+            ']' if depth == 0 && open.is_some() && i == s.len() - 1 => {}
+            '[' | ']' if depth == 0 => return Err(ParseError::MisplacedOptional { offset: i }),
+            _ => {}
+        }
+    }
+    let (required_s, optional_s) = match open {
+        None => (s, None),
+        Some(open) => {
+            if !s.ends_with(']') || !s[open + 1..].starts_with(separator) {
+                return Err(ParseError::MisplacedOptional { offset: open });
+            }
+            let start = open + 1 + separator.len_utf8();
+            (&s[..open], Some((start, &s[start..s.len() - 1])))
+        }
+    };
+    let mut steps = split_top_level(required_s, 0, separator);
+    let required = steps.len();
+    if let Some((offset, optional_s)) = optional_s {
+        steps.extend(split_top_level(optional_s, offset, separator));
+    }
+    Ok((steps, required))
+}
+
+/// The runs of letters, digits and `_` in literal text.
+fn word_parts(s: &str) -> impl Iterator<Item = &str> {
+    s.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map(|word| word.trim_matches('_'))
+        .filter(|word| !word.is_empty())
+}
+
+/// Split on the separators that aren't within braces, as a regex
+/// constraint can contain any character.
+pub(crate) fn split_top_level(s: &str, offset: usize, separator: char) -> Vec<StepStr<'_>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for i in find_top_level(s, separator) {
+        parts.push((offset + start, &s[start..i]));
+        start = i + separator.len_utf8();
+    }
+    parts.push((offset + start, &s[start..]));
+    parts
+}
+
+/// The offsets of the occurrences of c that aren't within braces.
+pub(crate) fn find_top_level(s: &str, c: char) -> impl Iterator<Item = usize> + '_ {
+    let mut depth = 0usize;
+    let mut escaped = false;
+    s.char_indices().filter_map(move |(i, current)| {
+        match current {
+            _ if escaped => escaped = false,
+            '\\' if depth > 0 => escaped = true,
+            '{' | '}' if depth == 0 && s[i + 1..].starts_with(current) => escaped = true,
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ if current == c && depth == 0 => return Some(i),
+            _ => {}
+        }
+        None
+    })
+}
+
+/// Split the next segment off a path, return it and the rest of the path,
+/// if any.
+fn next_segment(path: &str, separator: char) -> (&str, Option<&str>) {
+    match path.find(separator) {
+        Some(i) => (&path[..i], Some(&path[i + separator.len_utf8()..])),
+        None => (path, None),
+    }
+}
+
+/// Like `split_query`, for a request target given as bytes.
+pub(crate) fn split_query_bytes(target: &[u8]) -> (&[u8], Option<&[u8]>) {
+    match target.iter().position(|&b| b == b'?') {
+        Some(i) => (&target[..i], Some(&target[i + 1..])),
+        None => (target, None),
+    }
+}
+
+/// The offset of the first occurrence of needle in bytes.
+fn find_bytes(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+    bytes
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Split a path given as bytes into its segments, like `split_path`.
+pub(crate) fn split_path_bytes(path: &[u8], separator: char) -> Vec<&[u8]> {
+    let mut buffer = [0; 4];
+    let separator = separator.encode_utf8(&mut buffer).as_bytes();
+    let mut rest = path.strip_prefix(separator).unwrap_or(path);
+    let mut segments = Vec::new();
+    while let Some(i) = find_bytes(rest, separator) {
+        segments.push(&rest[..i]);
+        rest = &rest[i + separator.len()..];
+    }
+    segments.push(rest);
+    segments
+}
+
+/// Split a request target into its path and its query, if any.
+pub(crate) fn split_query(target: &str) -> (&str, Option<&str>) {
+    match target.find('?') {
+        Some(i) => (&target[..i], Some(&target[i + 1..])),
+        None => (target, None),
+    }
+}
+
+pub(crate) fn assert_separator(separator: char) {
+    assert!(
+        !"{}[]?\\".contains(separator),
+        "`{}` can't be a separator",
+        separator
+    );
+}
+
+fn strip_leading(s: &str, separator: char) -> &str {
+    s.strip_prefix(separator).unwrap_or(s)
+}
+
+/// The request target with a trailing separator added to its path, or
+/// removed if it has one. `None` for the root, which has no other spelling.
+pub(crate) fn toggle_trailing_slash(target: &str, separator: char) -> Option<String> {
+    let (path, query) = split_query(target);
+    let toggled = match path.strip_suffix(separator) {
+        Some(stripped) => stripped.to_string(),
+        None => format!("{}{}", path, separator),
+    };
+    if strip_leading(path, separator).is_empty() {
+        return None;
+    }
+    Some(match query {
+        Some(query) => format!("{}?{}", toggled, query),
+        None => toggled,
+    })
+}
+
+/// Split a path into the segments that are matched against steps.
+pub(crate) fn split_path(path: &str, separator: char) -> impl Iterator<Item = &str> {
+    strip_leading(path, separator).split(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::{Converter, IntConverter};
+
+    #[test]
+    fn test_pattern_new() {
+        let pattern = Pattern::new("departments/{department_id}/employees/{employee_id}").unwrap();
+        assert_eq!(
+            pattern.as_str(),
+            "departments/{department_id}/employees/{employee_id}"
+        );
+        assert_eq!(
+            pattern.names().collect::<Vec<_>>(),
+            vec!["department_id", "employee_id"]
+        );
+    }
+
+    #[test]
+    fn test_static_pattern() {
+        static PATTERN: StaticPattern = StaticPattern::new("a/{b:int}");
+        assert_eq!(PATTERN.as_str(), "a/{b:int}");
+        assert_eq!(PATTERN.match_path("a/1").unwrap().get("b"), Some("1"));
+        assert!(std::ptr::eq(PATTERN.get(), PATTERN.get()));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid pattern `a/{b`")]
+    fn test_static_pattern_invalid() {
+        StaticPattern::new("a/{b").get();
+    }
+
+    #[test]
+    fn test_pattern_case() {
+        let pattern = Pattern::new("Docs/{page}?Lang={l}").unwrap();
+        assert!(pattern.match_path("docs/Intro?lang=EN").is_none());
+        let pattern = pattern.with_case(Case::Lowercase);
+        let m = pattern.match_path("docs/Intro?lang=EN").unwrap();
+        assert_eq!(
+            m.iter().collect::<Vec<_>>(),
+            vec![("page", "intro"), ("l", "en")]
+        );
+        let mut variables = Vec::new();
+        assert!(pattern.match_into("DOCS/Intro?LANG=en", &mut variables));
+        assert_eq!(variables, vec![("page", "Intro"), ("l", "en")]);
+    }
+
+    #[test]
+    fn test_match_segments() {
+        let pattern = Pattern::new("departments/{d}/employees[/{e:int}]").unwrap();
+        let m = pattern
+            .match_segments(&["departments", "a/b", "employees", "3"])
+            .unwrap();
+        assert_eq!(m.iter().collect::<Vec<_>>(), vec![("d", "a/b"), ("e", "3")]);
+        assert!(pattern
+            .match_segments(&["departments", "a", "employees"])
+            .is_some());
+        assert!(pattern.match_segments(&["departments", "a"]).is_none());
+        assert!(pattern
+            .match_segments(&["departments", "a", "employees", "x"])
+            .is_none());
+    }
+
+    #[test]
+    fn test_match_segments_wildcard() {
+        let pattern = Pattern::new("static/{path:*}").unwrap();
+        let m = pattern
+            .match_segments(&["static", "css", "a%20b.css"])
+            .unwrap();
+        assert_eq!(m.get("path"), Some("css/a%20b.css"));
+        let pattern = pattern.with_percent_encoding(true);
+        let m = pattern
+            .match_segments(&["static", "css", "a%20b.css"])
+            .unwrap();
+        assert_eq!(m.get("path"), Some("css/a b.css"));
+        let m = pattern.match_segments(&["static", "x"]).unwrap();
+        assert_eq!(m.get("path"), Some("x"));
+        assert!(pattern.match_segments(&["static"]).is_none());
+        assert!(pattern.match_segments(&["static", ""]).is_none());
+    }
+
+    #[test]
+    fn test_match_segments_query() {
+        let pattern = Pattern::new("a?[v={v}]").unwrap();
+        assert!(pattern.match_segments(&["a"]).is_some());
+        let pattern = Pattern::new("a?v={v}").unwrap();
+        assert!(pattern.match_segments(&["a"]).is_none());
+    }
+
+    #[test]
+    fn test_pattern_separator() {
+        let pattern = Pattern::with_separator("{subdomain}.example.com", '.').unwrap();
+        let m = pattern.match_path("shop.example.com").unwrap();
+        assert_eq!(m.get("subdomain"), Some("shop"));
+        assert!(pattern.match_path("a.shop.example.com").is_none());
+        let values = [("subdomain", "blog")].iter().cloned().collect();
+        assert_eq!(pattern.expand(&values).unwrap(), "blog.example.com");
+    }
+
+    #[test]
+    fn test_pattern_separator_optional_and_wildcard() {
+        let pattern = Pattern::with_separator("sensors[.{id:int}]", '.').unwrap();
+        assert!(pattern.match_path("sensors").is_some());
+        assert_eq!(
+            pattern.match_path("sensors.3").unwrap().get("id"),
+            Some("3")
+        );
+        assert_eq!(
+            Pattern::with_separator("sensors[/{id}]", '.'),
+            Err(ParseError::MisplacedOptional { offset: 7 })
+        );
+        let pattern = Pattern::with_separator("home/{room}/{rest:*}", '/').unwrap();
+        assert_eq!(pattern, Pattern::new("home/{room}/{rest:*}").unwrap());
+        let pattern = Pattern::with_separator("home:{room}", ':').unwrap();
+        let (m, rest) = pattern.match_prefix("home:kitchen:temp").unwrap();
+        assert_eq!((m.get("room"), rest), (Some("kitchen"), "temp"));
+        let pattern = Pattern::with_separator("home.{room}.{rest:*}", '.').unwrap();
+        let m = pattern
+            .match_segments(&["home", "kitchen", "temp", "max"])
+            .unwrap();
+        assert_eq!(m.get("rest"), Some("temp.max"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pattern_separator_invalid() {
+        let _ = Pattern::with_separator("a?b", '?');
+    }
+
+    #[test]
+    fn test_match_bytes() {
+        let pattern = Pattern::new("files/{name}/v{version:int}").unwrap();
+        let m = pattern.match_bytes(b"files/caf\xe9/v2").unwrap();
+        assert_eq!(m.get("name"), Some(&b"caf\xe9"[..]));
+        assert_eq!(m.get("version"), Some(&b"2"[..]));
+        assert!(pattern.match_bytes(b"files/a/v\xff").is_none());
+        assert!(pattern.match_bytes(b"files/a/vx").is_none());
+        assert!(pattern.match_bytes(b"files/a/v2/more").is_none());
+        let pattern = Pattern::new("x{name}").unwrap();
+        assert!(pattern.match_bytes(b"x\xff").is_none());
+        assert!(pattern.match_bytes(b"xa").is_some());
+    }
+
+    #[test]
+    fn test_match_bytes_wildcard_and_query() {
+        let pattern = Pattern::new("static/{path:*}").unwrap();
+        let m = pattern.match_bytes(b"/static/a\xff/b").unwrap();
+        assert_eq!(m.get("path"), Some(&b"a\xff/b"[..]));
+        let pattern = Pattern::new("a[/{b}]?v={v}").unwrap();
+        let m = pattern.match_bytes(b"a?v=1").unwrap();
+        assert_eq!(m.iter().collect::<Vec<_>>(), vec![("v", &b"1"[..])]);
+        assert!(pattern.match_bytes(b"a/b?v=\xff").is_none());
+        assert!(pattern.match_bytes(b"a/b").is_none());
+    }
+
+    #[test]
+    fn test_match_bytes_lossy() {
+        let pattern = Pattern::new("files/{name}").unwrap();
+        let m = pattern.match_bytes_lossy(b"files/caf\xe9").unwrap();
+        assert_eq!(m.get("name"), Some("caf\u{fffd}"));
+        let m = pattern.match_bytes_lossy(b"files/x").unwrap();
+        assert_eq!(m.get("name"), Some("x"));
+    }
+
+    #[test]
+    fn test_match_os_str() {
+        let pattern = Pattern::new("home/{user}/{path:*}").unwrap();
+        let path = std::path::Path::new("/home/ann/notes/todo.txt");
+        let m = pattern.match_os_str(path.as_os_str()).unwrap();
+        assert_eq!(m.get("user"), Some(&b"ann"[..]));
+        assert_eq!(m.get("path"), Some(&b"notes/todo.txt"[..]));
+    }
+
+    #[test]
+    fn test_toggle_trailing_slash() {
+        let toggle = |target| toggle_trailing_slash(target, '/');
+        assert_eq!(toggle("a/b"), Some("a/b/".to_string()));
+        assert_eq!(toggle("/a/b/"), Some("/a/b".to_string()));
+        assert_eq!(toggle("a/?x=1"), Some("a?x=1".to_string()));
+        assert_eq!(toggle("/"), None);
+        assert_eq!(toggle("?x=1"), None);
+        assert_eq!(toggle_trailing_slash("a.b", '.'), Some("a.b.".to_string()));
+    }
+
+    #[test]
+    fn test_match_path_owned() {
+        let pattern = Pattern::new("a/{b}[/{c}]?q={q}").unwrap();
+        let path = "a/1?q=2".to_string();
+        let m = pattern.match_path_owned(&path).unwrap();
+        drop(path);
+        assert_eq!(m.iter().collect::<Vec<_>>(), vec![("b", "1"), ("q", "2")]);
+    }
+
+    #[test]
+    fn test_pattern_leading_slash() {
+        let pattern = Pattern::new("/foo/{bar}").unwrap();
+        assert_eq!(pattern.as_str(), "foo/{bar}");
+        assert_eq!(pattern.match_path("/foo/x").unwrap().get("bar"), Some("x"));
+        assert_eq!(pattern.match_path("foo/x").unwrap().get("bar"), Some("x"));
+    }
+
+    #[test]
+    fn test_pattern_invalid_step() {
+        assert_eq!(
+            Pattern::new("foo/{bar"),
+            Err(ParseError::UnbalancedBrace { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_pattern_error_offset_leading_slash() {
+        assert_eq!(
+            Pattern::new("/foo/x{1}"),
+            Err(ParseError::InvalidIdentifier {
+                name: "1".to_string(),
+                offset: 7
+            })
+        );
+    }
+
+    #[test]
+    fn test_pattern_duplicate_variable_across_steps() {
+        assert_eq!(
+            Pattern::new("foo/{bar}/baz/{bar}"),
+            Err(ParseError::DuplicateVariable {
+                name: "bar".to_string(),
+                offset: 15
+            })
+        );
+    }
+
+    #[test]
+    fn test_match_path() {
+        let pattern = Pattern::new("departments/{department_id}/employees/{employee_id}").unwrap();
+        let m = pattern.match_path("departments/10/employees/20").unwrap();
+        assert_eq!(m.get("department_id"), Some("10"));
+        assert_eq!(m.get("employee_id"), Some("20"));
+        assert_eq!(m.get("other"), None);
+    }
+
+    #[test]
+    fn test_conversion_failure() {
+        let pattern = Pattern::new("users/{id:int}/posts/{post:int}").unwrap();
+        assert_eq!(
+            pattern.conversion_failure("users/x/posts/y"),
+            Some(ConversionError {
+                name: "id".to_string(),
+                value: "x".to_string(),
+                converter: "int".to_string(),
+                direction: Direction::Decode,
+            })
+        );
+        assert_eq!(
+            pattern
+                .conversion_failure("users/1/posts/y?page=2")
+                .map(|error| error.name),
+            Some("post".to_string())
+        );
+        assert_eq!(pattern.conversion_failure("users/1/posts/2"), None);
+        assert_eq!(pattern.conversion_failure("users/x/posts"), None);
+        assert_eq!(pattern.conversion_failure("people/x/posts/2"), None);
+        assert_eq!(pattern.conversion_failure("users/x/posts/2/3"), None);
+    }
+
+    #[test]
+    fn test_has_literal() {
+        let pattern = Pattern::new("v1/admin-{page}/{{draft}}").unwrap();
+        assert!(pattern.has_literal("admin"));
+        assert!(pattern.has_literal("{draft}"));
+        assert!(!pattern.has_literal("page"));
+    }
+
+    #[test]
+    fn test_uses_converter() {
+        let pattern = Pattern::new("users/{id:uuid}?page={page:int}").unwrap();
+        assert!(pattern.uses_converter("uuid"));
+        assert!(pattern.uses_converter("int"));
+        assert!(!pattern.uses_converter("date"));
+    }
+
+    #[test]
+    fn test_match_path_no_variables() {
+        let pattern = Pattern::new("foo/bar").unwrap();
+        assert!(pattern.match_path("foo/bar").unwrap().is_empty());
+        assert!(pattern.match_path("foo/baz").is_none());
+    }
+
+    #[test]
+    fn test_match_path_wrong_length() {
+        let pattern = Pattern::new("foo/{bar}").unwrap();
+        assert!(pattern.match_path("foo").is_none());
+        assert!(pattern.match_path("foo/bar/baz").is_none());
+    }
+
+    #[test]
+    fn test_match_path_multiple_variables_in_step() {
+        let pattern = Pattern::new("foo/start{a}middle{b}end/{c}").unwrap();
+        assert_eq!(
+            pattern
+                .match_path("foo/startAmiddleBend/C")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![("a", "A"), ("b", "B"), ("c", "C")]
+        );
+    }
+
+    #[test]
+    fn test_pattern_escaped_braces() {
+        // an escaped brace doesn't open a variable, so `/` still separates
+        let pattern = Pattern::new("{{/}}").unwrap();
+        assert!(pattern.match_path("{/}").is_some());
+        let pattern = Pattern::new("api/{{v}}/{a}{{x}}").unwrap();
+        assert_eq!(
+            pattern
+                .match_path("api/{v}/1{x}")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![("a", "1")]
+        );
+        assert!(pattern.match_path("api/v/1{x}").is_none());
+        let values = [("a", "1")].iter().cloned().collect();
+        assert_eq!(pattern.expand(&values).unwrap(), "api/{v}/1{x}");
+    }
+
+    #[test]
+    fn test_expand() {
+        let pattern = Pattern::new("departments/{department_id}/employees/{employee_id}").unwrap();
+        let mut values = HashMap::new();
+        values.insert("department_id", "10");
+        values.insert("employee_id", "20");
+        assert_eq!(
+            pattern.expand(&values).unwrap(),
+            "departments/10/employees/20"
+        );
+    }
+
+    #[test]
+    fn test_expand_no_variables() {
+        let pattern = Pattern::new("foo/bar").unwrap();
+        assert_eq!(pattern.expand(&HashMap::new()).unwrap(), "foo/bar");
+    }
+
+    #[test]
+    fn test_expand_missing_variable() {
+        let pattern = Pattern::new("foo/{a}/{b}").unwrap();
+        let mut values = HashMap::new();
+        values.insert("a", "A");
+        assert_eq!(
+            pattern.expand(&values),
+            Err(ExpandError::MissingVariable {
+                name: "b".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_expand_extra_variable() {
+        let pattern = Pattern::new("foo/{a}").unwrap();
+        let mut values = HashMap::new();
+        values.insert("a", "A");
+        values.insert("b", "B");
+        assert_eq!(
+            pattern.expand(&values),
+            Err(ExpandError::ExtraVariable {
+                name: "b".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_expand_roundtrip() {
+        let pattern = Pattern::new("foo/{a}x/{b}").unwrap();
+        let mut values = HashMap::new();
+        values.insert("a", "A");
+        values.insert("b", "B");
+        let path = pattern.expand(&values).unwrap();
+        assert_eq!(pattern.match_path(&path).unwrap().to_map(), values);
+    }
+
+    #[test]
+    fn test_match_path_converter() {
+        let pattern = Pattern::new("departments/{department_id:int}/{when:date}").unwrap();
+        let m = pattern.match_path("departments/10/2019-01-02").unwrap();
+        assert_eq!(m.get("department_id"), Some("10"));
+        assert_eq!(m.get("when"), Some("2019-01-02"));
+        assert!(pattern.match_path("departments/x/2019-01-02").is_none());
+        assert!(pattern.match_path("departments/10/yesterday").is_none());
+    }
+
+    #[test]
+    fn test_expand_converter() {
+        let pattern = Pattern::new("departments/{department_id:int}").unwrap();
+        let id = IntConverter.serialize(&10);
+        let mut values = HashMap::new();
+        values.insert("department_id", id.as_str());
+        assert_eq!(pattern.expand(&values).unwrap(), "departments/10");
+    }
+
+    #[test]
+    fn test_partial() {
+        let pattern = Pattern::new("tenants/{tenant}/users/{id:int}?tab={tab}").unwrap();
+        let partial = pattern.partial(&[("tenant", "acme")]).unwrap();
+        assert_eq!(partial.as_str(), "tenants/acme/users/{id:int}?tab={tab}");
+        assert!(partial.match_path("tenants/acme/users/x?tab=a").is_none());
+        let values = HashMap::from([("id", "1"), ("tab", "a")]);
+        assert_eq!(
+            partial.expand(&values).unwrap(),
+            "tenants/acme/users/1?tab=a"
+        );
+        let partial = partial.partial(&[("id", "1"), ("tab", "{a}")]).unwrap();
+        assert_eq!(partial.as_str(), "tenants/acme/users/1?tab={{a}}");
+        assert!(partial.match_path("tenants/acme/users/1?tab={a}").is_some());
+        assert_eq!(
+            pattern.partial(&[("id", "x")]).map_err(|e| e.to_string()),
+            Err("converter `int` doesn't accept `x` as a value of variable `id`".to_string())
+        );
+        assert_eq!(
+            pattern.partial(&[("tenant", "a/b")]),
+            Err(ExpandError::InvalidValue {
+                name: "tenant".to_string(),
+                value: "a/b".to_string()
+            })
+        );
+        assert_eq!(
+            pattern.partial(&[("other", "a")]),
+            Err(ExpandError::ExtraVariable {
+                name: "other".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_specialize() {
+        let pattern = Pattern::new("api/{version}/users/{id}").unwrap();
+        let specialized = pattern.specialize("version", "v2").unwrap();
+        assert_eq!(specialized.as_str(), "api/v2/users/{id}");
+        assert!(specialized.specificity() < pattern.specificity());
+        assert!(specialized.match_path("api/v1/users/1").is_none());
+        assert!(pattern.specialize("version", "").is_err());
+    }
+
+    #[test]
+    fn test_partial_wildcard() {
+        let pattern = Pattern::new("files/{root}[/{rest:*}]").unwrap();
+        let partial = pattern.partial(&[("rest", "a/b")]).unwrap();
+        assert_eq!(partial.as_str(), "files/{root}[/a/b]");
+        assert!(partial.match_path("files/x/a/b").is_some());
+        assert!(partial.match_path("files/x").is_some());
+        assert!(pattern.partial(&[("rest", "a//b")]).is_err());
+    }
+
+    #[test]
+    fn test_partial_converters() {
+        let mut converters = ConverterRegistry::new();
+        converters.register("number", IntConverter);
+        let pattern = Pattern::with_converters("{a:number}/{b:number}", &converters)
+            .unwrap()
+            .with_percent_encoding(true);
+        let partial = pattern.partial(&[("a", "2")]).unwrap();
+        assert!(partial.match_path("2/4").is_some());
+        assert!(partial.match_path("2/x").is_none());
+        let values = HashMap::from([("b", "4")]);
+        assert_eq!(partial.expand(&values).unwrap(), "2/4");
+        let expected = Pattern::with_converters("2/{b:number}", &converters).unwrap();
+        assert_eq!(partial, expected.with_percent_encoding(true));
+    }
+
+    #[test]
+    fn test_expand_typed() {
+        let pattern = Pattern::new("departments/{d:int}/{when:date}/{name}?v={v:bool}").unwrap();
+        let when = crate::Date::new(2019, 1, 2).unwrap();
+        let path = pattern
+            .expand_typed(&[("d", &10i64), ("when", &when), ("name", &"x"), ("v", &true)])
+            .unwrap();
+        assert_eq!(path, "departments/10/2019-01-02/x?v=true");
+        assert_eq!(
+            pattern.expand_typed(&[("d", &"10")]),
+            Err(ExpandError::WrongType {
+                name: "d".to_string()
+            })
+        );
+        assert_eq!(
+            pattern.expand_typed(&[("other", &1i64)]),
+            Err(ExpandError::ExtraVariable {
+                name: "other".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_pattern_with_converters() {
+        struct Upper;
+
+        impl crate::Converter for Upper {
+            type Value = String;
+
+            fn parse(&self, s: &str) -> Option<String> {
+                if s.bytes().all(|b| b.is_ascii_uppercase()) {
+                    Some(s.to_string())
+                } else {
+                    None
+                }
+            }
+
+            fn serialize(&self, value: &String) -> String {
+                value.clone()
+            }
+        }
+
+        let mut converters = ConverterRegistry::new();
+        converters.register("upper", Upper);
+        let pattern = Pattern::with_converters("a/{b:upper}/{c:int}", &converters).unwrap();
+        assert!(pattern.match_path("a/ABC/1").is_some());
+        assert!(pattern.match_path("a/abc/1").is_none());
+        assert!(pattern.clone().match_path("a/ABC/1").is_some());
+        assert_eq!(
+            Pattern::new("a/{b:upper}"),
+            Err(ParseError::UnknownConverter {
+                name: "upper".to_string(),
+                offset: 5
+            })
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_pattern_regex_constraint() {
+        let pattern = Pattern::new(r"items/{sku:re:[^/]+/\d{2}}/{name}").unwrap();
+        assert_eq!(pattern.names().collect::<Vec<_>>(), vec!["sku", "name"]);
+        assert!(pattern.match_path("items/abc/12/x").is_none());
+        let pattern = Pattern::new(r"items/{sku:re:[A-Z]{3}-\d+}[/{page:re:\d+}]").unwrap();
+        assert_eq!(
+            pattern.match_path("items/ABC-1").unwrap().get("sku"),
+            Some("ABC-1")
+        );
+        assert_eq!(
+            pattern.match_path("items/ABC-1/2").unwrap().get("page"),
+            Some("2")
+        );
+        assert!(pattern.match_path("items/ABC-1/x").is_none());
+        assert_eq!(
+            Pattern::new("a/{b:re:(}").unwrap_err(),
+            ParseError::InvalidRegex {
+                regex: "(".to_string(),
+                offset: 8
+            }
+        );
+    }
+
+    #[test]
+    fn test_match_path_query() {
+        let pattern = Pattern::new("articles/{id}?version={v:int}&[lang={l}]").unwrap();
+        assert_eq!(pattern.names().collect::<Vec<_>>(), vec!["id", "v", "l"]);
+        let m = pattern.match_path("articles/1?version=2&other=x").unwrap();
+        assert_eq!(
+            m.to_map(),
+            [("id", "1"), ("v", "2")].iter().cloned().collect()
+        );
+        let m = pattern.match_path("articles/1?lang=en&version=2").unwrap();
+        assert_eq!(m.get("l"), Some("en"));
+        assert!(pattern.match_path("articles/1").is_none());
+        assert!(pattern.match_path("articles/1?version=x").is_none());
+    }
+
+    #[test]
+    fn test_match_path_query_ignored() {
+        let pattern = Pattern::new("articles/{id}").unwrap();
+        assert_eq!(
+            pattern
+                .match_path("articles/1?version=2")
+                .unwrap()
+                .get("id"),
+            Some("1")
+        );
+    }
+
+    #[test]
+    fn test_match_prefix_query() {
+        let pattern = Pattern::new("articles/{id}?version={v}").unwrap();
+        let (m, rest) = pattern.match_prefix("articles/1/edit?version=2").unwrap();
+        assert_eq!(m.get("v"), Some("2"));
+        assert_eq!(rest, "edit");
+    }
+
+    #[test]
+    fn test_expand_query() {
+        let pattern = Pattern::new("articles/{id}?version={v}&[lang={l}]").unwrap();
+        let values = [("id", "1"), ("v", "2")].iter().cloned().collect();
+        assert_eq!(pattern.expand(&values).unwrap(), "articles/1?version=2");
+        let values = [("id", "1"), ("v", "a b"), ("l", "en")]
+            .iter()
+            .cloned()
+            .collect();
+        let pattern = pattern.with_percent_encoding(true);
+        assert_eq!(
+            pattern.expand(&values).unwrap(),
+            "articles/1?version=a%20b&lang=en"
+        );
+        assert_eq!(
+            pattern
+                .match_path("articles/1?version=a%20b")
+                .unwrap()
+                .get("v"),
+            Some("a b")
+        );
+    }
+
+    #[test]
+    fn test_query_errors() {
+        assert_eq!(
+            Pattern::new("/a/{x}?b={x}").unwrap_err(),
+            ParseError::DuplicateVariable {
+                name: "x".to_string(),
+                offset: 10
+            }
+        );
+        assert_eq!(
+            Pattern::new("/a?b={x}&c").unwrap_err(),
+            ParseError::InvalidQuery { offset: 9 }
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_pattern_query_regex_constraint() {
+        // a `?` in a regex constraint doesn't start the query
+        let pattern = Pattern::new(r"a/{x:re:y?z}").unwrap();
+        assert_eq!(pattern.match_path("a/z").unwrap().get("x"), Some("z"));
+    }
+
+    fn match_into<'a>(pattern: &'a Pattern, path: &'a str) -> Option<Vec<(&'a str, &'a str)>> {
+        let mut variables = Vec::new();
+        if pattern.match_into(path, &mut variables) {
+            Some(variables)
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn test_match_into() {
+        let pattern = Pattern::new("a/{b}/c{d:int}/e[/{f}]?g={h}").unwrap();
+        assert_eq!(
+            match_into(&pattern, "a/1/c2/e?g=3"),
+            Some(vec![("b", "1"), ("d", "2"), ("h", "3")])
+        );
+        assert_eq!(
+            match_into(&pattern, "a/1/c2/e/4?g=3"),
+            Some(vec![("b", "1"), ("d", "2"), ("f", "4"), ("h", "3")])
+        );
+        assert_eq!(match_into(&pattern, "a/1/cx/e?g=3"), None);
+        assert_eq!(match_into(&pattern, "a/1/c2/e"), None);
+        assert_eq!(match_into(&pattern, "a/1/c2/e/4/5?g=3"), None);
+    }
+
+    #[test]
+    fn test_match_into_reuses_vec() {
+        let pattern = Pattern::new("{a}/{rest:*}").unwrap();
+        let mut variables = vec![("x", "y")];
+        assert!(pattern.match_into("1/2/3", &mut variables));
+        assert_eq!(variables, vec![("a", "1"), ("rest", "2/3")]);
+        assert!(!pattern.match_into("1", &mut variables));
+    }
+
+    #[test]
+    fn test_match_into_agrees_with_match_path() {
+        let pattern = Pattern::new("a/{b}x{c}/d").unwrap();
+        for path in &["a/1x2/d", "a/1x2x3/d", "a/x/d", "a/1x2", "b/1x2/d"] {
+            let expected = pattern.match_path(path).map(|m| {
+                m.iter()
+                    .map(|(n, v)| (n, v.to_string()))
+                    .collect::<Vec<_>>()
+            });
+            let actual = match_into(&pattern, path).map(|variables| {
+                variables
+                    .into_iter()
+                    .map(|(n, v)| (n, v.to_string()))
+                    .collect::<Vec<_>>()
+            });
+            assert_eq!(actual, expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_match_prefix() {
+        let pattern = Pattern::new("a/{b}").unwrap();
+        let (m, rest) = pattern.match_prefix("a/1/c/d").unwrap();
+        assert_eq!(m.get("b"), Some("1"));
+        assert_eq!(rest, "c/d");
+    }
+
+    #[test]
+    fn test_match_prefix_whole_path() {
+        let pattern = Pattern::new("a/{b}").unwrap();
+        let (m, rest) = pattern.match_prefix("/a/1").unwrap();
+        assert_eq!(m.get("b"), Some("1"));
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn test_match_prefix_too_short() {
+        let pattern = Pattern::new("a/{b}").unwrap();
+        assert!(pattern.match_prefix("a").is_none());
+        assert!(pattern.match_prefix("b/1/c").is_none());
+    }
+
+    #[test]
+    fn test_match_path_wildcard() {
+        let pattern = Pattern::new("static/{path:*}").unwrap();
+        let m = pattern.match_path("static/css/site.css").unwrap();
+        assert_eq!(m.get("path"), Some("css/site.css"));
+        let m = pattern.match_path("static/logo.png").unwrap();
+        assert_eq!(m.get("path"), Some("logo.png"));
+        assert!(pattern.match_path("static").is_none());
+        assert!(pattern.match_path("static/").is_none());
+        assert!(pattern.match_path("other/logo.png").is_none());
+    }
+
+    #[test]
+    fn test_match_prefix_wildcard() {
+        let pattern = Pattern::new("static/{path:*}").unwrap();
+        let (m, rest) = pattern.match_prefix("static/a/b").unwrap();
+        assert_eq!(m.get("path"), Some("a/b"));
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn test_wildcard_not_last() {
+        assert_eq!(
+            Pattern::new("static/{path:*}/foo"),
+            Err(ParseError::MisplacedWildcard { offset: 13 })
+        );
+    }
+
+    #[test]
+    fn test_expand_wildcard() {
+        let pattern = Pattern::new("static/{path:*}").unwrap();
+        let mut values = HashMap::new();
+        values.insert("path", "css/site.css");
+        assert_eq!(pattern.expand(&values).unwrap(), "static/css/site.css");
+    }
+
+    #[test]
+    fn test_match_path_optional() {
+        let pattern = Pattern::new("articles[/{page}]").unwrap();
+        assert!(pattern.match_path("articles").unwrap().is_empty());
+        assert_eq!(
+            pattern.match_path("articles/3").unwrap().get("page"),
+            Some("3")
+        );
+        assert!(pattern.match_path("articles/3/4").is_none());
+        assert!(pattern.match_path("other/3").is_none());
+    }
+
+    #[test]
+    fn test_match_path_optional_multiple_steps() {
+        let pattern = Pattern::new("a/{b}[/c/{d}]").unwrap();
+        assert_eq!(pattern.match_path("a/1").unwrap().get("b"), Some("1"));
+        assert_eq!(pattern.match_path("a/1/c/2").unwrap().get("d"), Some("2"));
+        assert!(pattern.match_path("a/1/c").is_none());
+    }
+
+    #[test]
+    fn test_match_prefix_optional() {
+        let pattern = Pattern::new("articles[/{page:int}]").unwrap();
+        let (m, rest) = pattern.match_prefix("articles/3/edit").unwrap();
+        assert_eq!(m.get("page"), Some("3"));
+        assert_eq!(rest, "edit");
+        let (m, rest) = pattern.match_prefix("articles/edit").unwrap();
+        assert!(m.is_empty());
+        assert_eq!(rest, "edit");
+    }
+
+    #[test]
+    fn test_expand_optional() {
+        let pattern = Pattern::new("articles[/{page}]").unwrap();
+        assert_eq!(pattern.expand(&HashMap::new()).unwrap(), "articles");
+        let mut values = HashMap::new();
+        values.insert("page", "3");
+        assert_eq!(pattern.expand(&values).unwrap(), "articles/3");
+    }
+
+    #[test]
+    fn test_expand_optional_partial() {
+        let pattern = Pattern::new("a[/{b}/{c}]").unwrap();
+        let mut values = HashMap::new();
+        values.insert("b", "1");
+        assert_eq!(
+            pattern.expand(&values),
+            Err(ExpandError::MissingVariable {
+                name: "c".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_defaults() {
+        let pattern = Pattern::new("articles/{lang=en}[/{page:int=1}]?[sort={s=date}]").unwrap();
+        let m = pattern.match_path("articles/nl").unwrap();
+        assert_eq!(
+            m.iter().collect::<Vec<_>>(),
+            vec![("lang", "nl"), ("page", "1"), ("s", "date")]
+        );
+        let m = pattern.match_path("articles/nl/3?sort=title").unwrap();
+        assert_eq!(
+            m.iter().collect::<Vec<_>>(),
+            vec![("lang", "nl"), ("page", "3"), ("s", "title")]
+        );
+        let mut variables = Vec::new();
+        assert!(pattern.match_into("articles/nl", &mut variables));
+        assert_eq!(
+            variables,
+            vec![("lang", "nl"), ("page", "1"), ("s", "date")]
+        );
+        let bytes = pattern.match_bytes(b"articles/nl").unwrap();
+        assert_eq!(bytes.get("page"), Some(&b"1"[..]));
+
+        assert_eq!(pattern.expand(&HashMap::new()).unwrap(), "articles/en");
+        let mut values = HashMap::new();
+        values.insert("page", "1");
+        values.insert("s", "date");
+        assert_eq!(pattern.expand(&values).unwrap(), "articles/en");
+        values.insert("page", "2");
+        assert_eq!(pattern.expand(&values).unwrap(), "articles/en/2");
+        values.insert("s", "title");
+        assert_eq!(pattern.expand(&values).unwrap(), "articles/en/2?sort=title");
+    }
+
+    #[test]
+    fn test_default_errors() {
+        assert_eq!(
+            Pattern::new("a/{page:int=first}"),
+            Err(ParseError::InvalidDefault {
+                value: "first".to_string(),
+                offset: 12
+            })
+        );
+        assert_eq!(
+            Pattern::new("a/{page=}"),
+            Err(ParseError::InvalidDefault {
+                value: "".to_string(),
+                offset: 8
+            })
+        );
+        assert_eq!(
+            Pattern::new("{a:bad=1}"),
+            Err(ParseError::UnknownConverter {
+                name: "bad".to_string(),
+                offset: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_optional_errors() {
+        assert_eq!(
+            Pattern::new("a[/b]/c"),
+            Err(ParseError::MisplacedOptional { offset: 4 })
+        );
+        assert_eq!(
+            Pattern::new("a[b]"),
+            Err(ParseError::MisplacedOptional { offset: 1 })
+        );
+        assert_eq!(
+            Pattern::new("a[/b"),
+            Err(ParseError::MisplacedOptional { offset: 1 })
+        );
+        assert_eq!(
+            Pattern::new("a]"),
+            Err(ParseError::MisplacedOptional { offset: 1 })
+        );
+        assert_eq!(
+            Pattern::new("a[/{b}/{b}]"),
+            Err(ParseError::DuplicateVariable {
+                name: "b".to_string(),
+                offset: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_match_path_percent_decoding() {
+        let pattern = Pattern::new("hello world/{name}")
+            .unwrap()
+            .with_percent_encoding(true);
+        let m = pattern.match_path("hello%20world/caf%C3%A9").unwrap();
+        assert_eq!(m.get("name"), Some("café"));
+        assert!(pattern.match_path("hello%20world/%zz").is_none());
+    }
+
+    #[test]
+    fn test_match_path_no_percent_decoding() {
+        let pattern = Pattern::new("a/{name}").unwrap();
+        let m = pattern.match_path("a/caf%C3%A9").unwrap();
+        assert_eq!(m.get("name"), Some("caf%C3%A9"));
+    }
+
+    #[test]
+    fn test_match_path_percent_decoding_wildcard() {
+        let pattern = Pattern::new("static/{path:*}")
+            .unwrap()
+            .with_percent_encoding(true);
+        let m = pattern.match_path("static/a%20b/c").unwrap();
+        assert_eq!(m.get("path"), Some("a b/c"));
+    }
+
+    #[test]
+    fn test_expand_percent_encoding() {
+        let pattern = Pattern::new("hello world/{name}/{rest:*}")
+            .unwrap()
+            .with_percent_encoding(true);
+        let mut values = HashMap::new();
+        values.insert("name", "a/b c");
+        values.insert("rest", "d e/f");
+        let path = pattern.expand(&values).unwrap();
+        assert_eq!(path, "hello%20world/a%2Fb%20c/d%20e/f");
+        assert_eq!(
+            pattern.match_path(&path).unwrap().get("name"),
+            Some("a/b c")
+        );
+    }
+
+    #[test]
+    fn test_derived_name() {
+        let name = |s| Pattern::new(s).unwrap().derived_name();
+        assert_eq!(name("users/{id:int}/posts"), "users_id_posts");
+        assert_eq!(name("/files/{path:*}"), "files_path");
+        assert_eq!(name("v1.2/{x}-{y}.json?page={page}"), "v1_2_x_y_json");
+        assert_eq!(name("a//_b_"), "a_b");
+        assert_eq!(name("articles[/{page}]"), "articles_page");
+        assert_eq!(name(""), "root");
+        assert_eq!(name("{{-}}"), "root");
+    }
+
+    #[test]
+    fn test_expand_iri() {
+        let pattern = Pattern::new("produits/été/{slug}?q={q}")
+            .unwrap()
+            .with_percent_encoding(true);
+        let values = [("slug", "crème brûlée"), ("q", "à/b")]
+            .iter()
+            .cloned()
+            .collect();
+        let uri = pattern.expand(&values).unwrap();
+        assert_eq!(
+            uri,
+            "produits/%C3%A9t%C3%A9/cr%C3%A8me%20br%C3%BBl%C3%A9e?q=%C3%A0%2Fb"
+        );
+        let iri = pattern.expand_iri(&values).unwrap();
+        assert_eq!(iri, "produits/été/crème%20brûlée?q=à%2Fb");
+        assert_eq!(encoding::iri_to_uri(&iri), uri);
+        for path in [&uri, &iri] {
+            let m = pattern.match_path(path).unwrap();
+            assert_eq!(
+                (m.get("slug"), m.get("q")),
+                (Some("crème brûlée"), Some("à/b"))
+            );
+        }
+        let pattern = Pattern::with_separator("a→{b}", '→')
+            .unwrap()
+            .with_percent_encoding(true);
+        let values = [("b", "c→é")].iter().cloned().collect();
+        assert_eq!(pattern.expand_iri(&values).unwrap(), "a→c%E2%86%92é");
+    }
+
+    #[test]
+    fn test_pattern_multibyte_separator() {
+        let pattern = Pattern::with_separator("home→{room}→{rest:*}", '→').unwrap();
+        let m = pattern.match_path("home→kitchen→temp→max").unwrap();
+        assert_eq!(m.get("room"), Some("kitchen"));
+        assert_eq!(m.get("rest"), Some("temp→max"));
+        let values = [("room", "hall"), ("rest", "a→b")]
+            .iter()
+            .cloned()
+            .collect();
+        let path = pattern.expand(&values).unwrap();
+        assert_eq!(path, "home→hall→a→b");
+        assert_eq!(pattern.match_path(&path).unwrap().get("rest"), Some("a→b"));
+    }
+
+    #[test]
+    fn test_expand_percent_encoding_separator() {
+        let pattern = Pattern::with_separator("{host}.example.com", '.')
+            .unwrap()
+            .with_percent_encoding(true);
+        let values = [("host", "a.b")].iter().cloned().collect();
+        let path = pattern.expand(&values).unwrap();
+        assert_eq!(path, "a%2Eb.example.com");
+        assert_eq!(pattern.match_path(&path).unwrap().get("host"), Some("a.b"));
+        let pattern = Pattern::with_separator("files.{rest:*}", '.')
+            .unwrap()
+            .with_percent_encoding(true);
+        let values = [("rest", "a.b/c")].iter().cloned().collect();
+        let path = pattern.expand(&values).unwrap();
+        assert_eq!(path, "files.a.b%2Fc");
+        assert_eq!(
+            pattern.match_path(&path).unwrap().get("rest"),
+            Some("a.b/c")
+        );
+    }
+
+    fn generalizes(a: &str, b: &str) -> bool {
+        Pattern::new(a)
+            .unwrap()
+            .generalizes(&Pattern::new(b).unwrap())
+    }
+
+    #[test]
+    fn test_generalizes() {
+        assert!(generalizes("a/{x}", "a/b"));
+        assert!(generalizes("a/{x}", "a/{y:int}"));
+        assert!(generalizes("{x}/{y}", "a/b"));
+        assert!(!generalizes("a/b", "a/{x}"));
+        assert!(!generalizes("a/{x}", "a/b/c"));
+        assert!(!generalizes("a/{x}/c", "a/b"));
+    }
+
+    #[test]
+    fn test_generalizes_wildcard() {
+        assert!(generalizes("a/{x:*}", "a/b/c"));
+        assert!(generalizes("a/{x:*}", "a/{y:*}"));
+        assert!(generalizes("{x:*}", "a/{y}"));
+        assert!(!generalizes("a/{x:*}", "a"));
+        assert!(!generalizes("a/{x}", "a/{y:*}"));
+    }
+
+    #[test]
+    fn test_generalizes_optional() {
+        assert!(generalizes("a[/{x}]", "a"));
+        assert!(generalizes("a[/{x}]", "a/b"));
+        assert!(generalizes("a[/{x}]", "a[/b]"));
+        assert!(!generalizes("a/{x}", "a[/b]"));
+    }
+
+    #[test]
+    fn test_same_shape() {
+        let pattern = Pattern::new("a/{x:int}[/{y}]").unwrap();
+        assert!(pattern.same_shape(&Pattern::new("a/{z:int}[/{w}]").unwrap()));
+        assert!(!pattern.same_shape(&Pattern::new("a/{z:int}/{w}").unwrap()));
+        assert!(!pattern.same_shape(&Pattern::new("a/{z}[/{w}]").unwrap()));
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::error::ExpandError;
+use crate::router::Router;
+
+/// The permission a route requires, like `articles.edit`, kept in its
+/// metadata. `Router::path_for_if` leaves out paths to routes the current
+/// user doesn't have the permission for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Permission {
+    key: String,
+}
+
+impl Permission {
+    pub fn new(key: &str) -> Permission {
+        Permission {
+            key: key.to_string(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl<T> Router<T> {
+    /// Generate the path for a named route like `path_for`, or `None` if
+    /// the route has a `Permission` that `allowed` doesn't grant, so that
+    /// links to it can be left out. Routes without one are always allowed.
+    pub fn path_for_if<F>(
+        &self,
+        name: &str,
+        values: &HashMap<&str, &str>,
+        allowed: F,
+    ) -> Result<Option<String>, ExpandError>
+    where
+        F: FnOnce(&str) -> bool,
+    {
+        let permission = self
+            .metadata(name)
+            .and_then(|metadata| metadata.get::<Permission>());
+        if permission.is_some_and(|permission| !allowed(permission.key())) {
+            return Ok(None);
+        }
+        self.path_for(name, values).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_for_if() {
+        let mut router = Router::new();
+        router
+            .register_named("article", "articles/{id}", 1)
+            .unwrap();
+        router
+            .register_named("edit", "articles/{id}/edit", 2)
+            .unwrap();
+        router
+            .metadata_mut("edit")
+            .unwrap()
+            .insert(Permission::new("articles.edit"));
+        let values = HashMap::from([("id", "1")]);
+        let reader = |permission: &str| permission == "articles.read";
+        assert_eq!(
+            router.path_for_if("article", &values, reader),
+            Ok(Some("articles/1".to_string()))
+        );
+        assert_eq!(router.path_for_if("edit", &values, reader), Ok(None));
+        assert_eq!(
+            router.path_for_if("edit", &values, |permission| permission == "articles.edit"),
+            Ok(Some("articles/1/edit".to_string()))
+        );
+        assert_eq!(
+            router.path_for_if("missing", &values, reader),
+            Err(ExpandError::UnknownRoute {
+                name: "missing".to_string()
+            })
+        );
+        assert_eq!(
+            router
+                .metadata("edit")
+                .unwrap()
+                .get::<Permission>()
+                .unwrap()
+                .key(),
+            "articles.edit"
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::converter::{ConverterRegistry, NamedConverter};
+use crate::encoding;
+use crate::error::{ExpandError, ParseError};
+use crate::pattern;
+use crate::step::Step;
+
+/// The query parameters a pattern describes, like `version={v}&[lang={l}]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Query {
+    params: Vec<Param>,
+    /// keys and literal text in values match in any ASCII case
+    case_insensitive: bool,
+}
+
+/// A query parameter like `version={v}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Param {
+    key: String,
+    value: Step,
+    /// offset of the value in the query part of the pattern
+    offset: usize,
+    optional: bool,
+}
+
+impl Query {
+    /// Parse the query part of a pattern, after the `?`. Offsets in errors
+    /// are relative to the start of `s`.
+    pub(crate) fn new(s: &str, converters: &ConverterRegistry) -> Result<Query, ParseError> {
+        let mut params = Vec::new();
+        for (offset, param_s) in pattern::split_top_level(s, 0, '&') {
+            let (offset, param_s, optional) =
+                match param_s.strip_prefix('[').and_then(|p| p.strip_suffix(']')) {
+                    Some(inner) => (offset + 1, inner, true),
+                    None => (offset, param_s, false),
+                };
+            let (key, value_s) = match param_s.find('=') {
+                Some(i) if i > 0 && !param_s[..i].contains(['{', '}', '[', ']']) => {
+                    (&param_s[..i], &param_s[i + 1..])
+                }
+                _ => return Err(ParseError::InvalidQuery { offset }),
+            };
+            let value_offset = offset + key.len() + 1;
+            if value_s.contains(['[', ']']) {
+                return Err(ParseError::MisplacedOptional {
+                    offset: value_offset + value_s.find(['[', ']']).unwrap(),
+                });
+            }
+            let value =
+                Step::with_converters(value_s, converters).map_err(|e| e.shifted(value_offset))?;
+            if value.wildcard {
+                return Err(ParseError::MisplacedWildcard {
+                    offset: value_offset + value.offsets[0] + value.names[0].len() + 1,
+                });
+            }
+            params.push(Param {
+                key: key.to_string(),
+                value,
+                offset: value_offset,
+                optional,
+            });
+        }
+        Ok(Query {
+            params,
+            case_insensitive: false,
+        })
+    }
+
+    pub(crate) fn set_case_insensitive(&mut self, enabled: bool) {
+        self.case_insensitive = enabled;
+        for param in &mut self.params {
+            param.value.set_case_insensitive(enabled);
+        }
+    }
+
+    fn is_key(&self, param: &Param, key: &str) -> bool {
+        if self.case_insensitive {
+            param.key.eq_ignore_ascii_case(key)
+        } else {
+            param.key == key
+        }
+    }
+
+    /// The keys with their values, and whether they are optional.
+    #[cfg(any(feature = "openapi", feature = "typescript"))]
+    pub(crate) fn params(&self) -> impl Iterator<Item = (&str, &Step, bool)> {
+        self.params
+            .iter()
+            .map(|param| (param.key.as_str(), &param.value, param.optional))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.params
+            .iter()
+            .flat_map(|param| param.value.names.iter().map(String::as_str))
+    }
+
+    /// The variable names with their offsets, relative to the start of the
+    /// query part of the pattern.
+    pub(crate) fn offsets(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.params.iter().flat_map(|param| {
+            param
+                .value
+                .names
+                .iter()
+                .zip(&param.value.offsets)
+                .map(move |(name, offset)| (name.as_str(), param.offset + offset))
+        })
+    }
+
+    /// The converter of a variable, like `Step::converter`.
+    pub(crate) fn converter(&self, name: &str) -> Option<Option<&NamedConverter>> {
+        self.params
+            .iter()
+            .find_map(|param| param.value.converter(name))
+    }
+
+    /// The number of parameters that have to be in the query.
+    pub(crate) fn required(&self) -> usize {
+        self.params.iter().filter(|param| !param.optional).count()
+    }
+
+    /// Whether this describes the same parameters as other, apart from the
+    /// names of their variables.
+    pub(crate) fn same_shape(&self, other: &Query) -> bool {
+        self.params.len() == other.params.len()
+            && self.params.iter().zip(&other.params).all(|(mine, theirs)| {
+                mine.key == theirs.key
+                    && mine.optional == theirs.optional
+                    && mine.value.same_shape(&theirs.value)
+            })
+    }
+
+    /// Match the query of a request target, return the captured variables.
+    ///
+    /// Parameters that aren't described are ignored. If a key appears more
+    /// than once, the first value counts.
+    pub(crate) fn match_query<'a>(
+        &'a self,
+        query: Option<&'a str>,
+        decode: bool,
+    ) -> Option<Vec<(&'a str, Cow<'a, str>)>> {
+        let mut pairs = Vec::new();
+        for pair in query.into_iter().flat_map(|query| query.split('&')) {
+            let (key, value) = match pair.find('=') {
+                Some(i) => (&pair[..i], &pair[i + 1..]),
+                None => (pair, ""),
+            };
+            if decode {
+                pairs.push((
+                    encoding::percent_decode(key)?,
+                    encoding::percent_decode(value)?,
+                ));
+            } else {
+                pairs.push((Cow::Borrowed(key), Cow::Borrowed(value)));
+            }
+        }
+        let mut variables = Vec::new();
+        for param in &self.params {
+            match pairs.iter().find(|(key, _)| self.is_key(param, key)) {
+                Some((_, value)) => {
+                    variables.extend(param.value.match_decoded(value.clone())?);
+                }
+                None if param.optional => variables.extend(
+                    param
+                        .value
+                        .defaults()
+                        .map(|(name, value)| (name, Cow::Borrowed(value))),
+                ),
+                None => return None,
+            }
+        }
+        Some(variables)
+    }
+
+    /// Like `match_query` but without decoding, pushing the variables.
+    pub(crate) fn match_query_into<'a>(
+        &'a self,
+        query: Option<&'a str>,
+        variables: &mut Vec<(&'a str, &'a str)>,
+    ) -> bool {
+        let query = query.unwrap_or("");
+        for param in &self.params {
+            let value = query.split('&').find_map(|pair| match pair.find('=') {
+                Some(i) if self.is_key(param, &pair[..i]) => Some(&pair[i + 1..]),
+                None if self.is_key(param, pair) => Some(""),
+                _ => None,
+            });
+            match value {
+                Some(value) => {
+                    if !param.value.match_segment_into(value, variables) {
+                        return false;
+                    }
+                }
+                None if param.optional => variables.extend(param.value.defaults()),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Expand into a query string, without the `?`. Optional parameters
+    /// are left out if none of their variables are supplied, or only with
+    /// their defaults.
+    pub(crate) fn expand(
+        &self,
+        values: &HashMap<&str, &str>,
+        encode: bool,
+    ) -> Result<String, ExpandError> {
+        let mut pairs = Vec::new();
+        for param in &self.params {
+            if param.optional && !param.value.overridden(values) {
+                continue;
+            }
+            let value = param.value.expand(values, None)?;
+            if encode {
+                pairs.push(format!(
+                    "{}={}",
+                    encoding::percent_encode_query(&param.key),
+                    encoding::percent_encode_query(&value)
+                ));
+            } else {
+                pairs.push(format!("{}={}", param.key, value));
+            }
+        }
+        Ok(pairs.join("&"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter;
+
+    fn query(s: &str) -> Query {
+        Query::new(s, converter::builtin()).unwrap()
+    }
+
+    fn match_query<'a>(query: &'a Query, s: &'a str) -> Option<Vec<(&'a str, String)>> {
+        let variables = query.match_query(Some(s), true)?;
+        Some(
+            variables
+                .into_iter()
+                .map(|(name, value)| (name, value.into_owned()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_query_new() {
+        let q = query("version={v:int}&[lang={l}]&format=json");
+        assert_eq!(q.names().collect::<Vec<_>>(), vec!["v", "l"]);
+        assert_eq!(q.required(), 2);
+        assert_eq!(q.offsets().collect::<Vec<_>>(), vec![("v", 9), ("l", 23)]);
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let error = |s| Query::new(s, converter::builtin()).unwrap_err();
+        assert_eq!(error("a={x}&{b}"), ParseError::InvalidQuery { offset: 6 });
+        assert_eq!(error("a={x}&=1"), ParseError::InvalidQuery { offset: 6 });
+        assert_eq!(error("[a={x}"), ParseError::InvalidQuery { offset: 0 });
+        assert_eq!(
+            error("a={x:bad}"),
+            ParseError::UnknownConverter {
+                name: "bad".to_string(),
+                offset: 5
+            }
+        );
+        assert_eq!(
+            error("a={x:*}"),
+            ParseError::MisplacedWildcard { offset: 5 }
+        );
+        assert_eq!(error("a=x[y]"), ParseError::MisplacedOptional { offset: 3 });
+    }
+
+    #[test]
+    fn test_match_query() {
+        let q = query("version={v:int}&[lang={l}]");
+        assert_eq!(
+            match_query(&q, "version=2"),
+            Some(vec![("v", "2".to_string())])
+        );
+        assert_eq!(
+            match_query(&q, "lang=en&x=y&version=2&version=3"),
+            Some(vec![("v", "2".to_string()), ("l", "en".to_string())])
+        );
+        assert_eq!(match_query(&q, "lang=en"), None);
+        assert_eq!(match_query(&q, "version=x"), None);
+        assert_eq!(match_query(&q, "version=2&lang="), None);
+        assert_eq!(q.match_query(None, false), None);
+    }
+
+    #[test]
+    fn test_match_query_decode() {
+        let q = query("q={q}");
+        assert_eq!(
+            match_query(&q, "q=caf%C3%A9%26"),
+            Some(vec![("q", "café&".to_string())])
+        );
+        assert_eq!(match_query(&q, "q=%zz"), None);
+    }
+
+    #[test]
+    fn test_match_query_into() {
+        let q = query("version={v:int}&[lang={l}]");
+        let mut variables = Vec::new();
+        assert!(q.match_query_into(Some("lang=en&version=2"), &mut variables));
+        assert_eq!(variables, vec![("v", "2"), ("l", "en")]);
+        assert!(!q.match_query_into(Some("lang=en"), &mut variables));
+        assert!(!q.match_query_into(None, &mut variables));
+    }
+
+    #[test]
+    fn test_match_query_case_insensitive() {
+        let mut q = query("Version=V{v}");
+        assert_eq!(match_query(&q, "version=v2"), None);
+        q.set_case_insensitive(true);
+        assert_eq!(
+            match_query(&q, "VERSION=v2"),
+            Some(vec![("v", "2".to_string())])
+        );
+        let mut variables = Vec::new();
+        assert!(q.match_query_into(Some("version=V3"), &mut variables));
+        assert_eq!(variables, vec![("v", "3")]);
+    }
+
+    #[test]
+    fn test_query_defaults() {
+        let q = query("version={v=1}&[lang={l=en}]");
+        assert_eq!(
+            match_query(&q, "version=2"),
+            Some(vec![("v", "2".to_string()), ("l", "en".to_string())])
+        );
+        assert_eq!(match_query(&q, "lang=nl"), None);
+        assert_eq!(q.expand(&HashMap::new(), false).unwrap(), "version=1");
+        let values = [("l", "en")].iter().cloned().collect();
+        assert_eq!(q.expand(&values, false).unwrap(), "version=1");
+    }
+
+    #[test]
+    fn test_expand_query() {
+        let q = query("version={v}&[lang={l}]");
+        let values = [("v", "2")].iter().cloned().collect();
+        assert_eq!(q.expand(&values, false).unwrap(), "version=2");
+        let values = [("v", "a&b"), ("l", "en")].iter().cloned().collect();
+        assert_eq!(q.expand(&values, true).unwrap(), "version=a%26b&lang=en");
+        assert_eq!(
+            q.expand(&HashMap::new(), false),
+            Err(ExpandError::MissingVariable {
+                name: "v".to_string()
+            })
+        );
+    }
+}
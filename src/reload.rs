@@ -0,0 +1,102 @@
+use std::sync::{PoisonError, RwLock};
+
+use crate::change::RouteChange;
+use crate::router::{Router, SharedRouter};
+
+/// A router whose routes can be replaced while other threads resolve
+/// paths, for instance to reload routes from configuration.
+///
+/// Threads `load` the current router and resolve paths with it. Replacing
+/// the routes doesn't affect routers that were loaded before, so a request
+/// is resolved against one consistent set of routes. Loading only holds a
+/// lock for as long as it takes to clone an `Arc`, never while resolving.
+#[derive(Debug)]
+pub struct ReloadableRouter<T> {
+    current: RwLock<SharedRouter<T>>,
+}
+
+impl<T> ReloadableRouter<T> {
+    pub fn new(router: Router<T>) -> ReloadableRouter<T> {
+        ReloadableRouter {
+            current: RwLock::new(router.build()),
+        }
+    }
+
+    /// The current router.
+    pub fn load(&self) -> SharedRouter<T> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Replace the router, return the one it replaces.
+    pub fn store(&self, router: Router<T>) -> SharedRouter<T> {
+        let router = router.build();
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        std::mem::replace(&mut *current, router)
+    }
+
+    /// Replace the router, return how its routes changed.
+    pub fn reload(&self, router: Router<T>) -> Vec<RouteChange>
+    where
+        T: PartialEq,
+    {
+        let previous = self.store(router);
+        previous.diff(&self.load())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(routes: &[(&str, i32)]) -> Router<i32> {
+        let mut router = Router::new();
+        for (pattern, value) in routes {
+            router.register(pattern, *value).unwrap();
+        }
+        router
+    }
+
+    #[test]
+    fn test_reload() {
+        let reloadable = ReloadableRouter::new(router(&[("a", 1)]));
+        let before = reloadable.load();
+        let changes = reloadable.reload(router(&[("a", 2), ("b", 3)]));
+        assert_eq!(
+            changes,
+            vec![
+                RouteChange::Changed {
+                    route: "a".to_string()
+                },
+                RouteChange::Added {
+                    route: "b".to_string()
+                },
+            ]
+        );
+        // routers loaded before keep their routes
+        assert_eq!(*before.resolve("a").unwrap().0, 1);
+        assert!(before.resolve("b").is_none());
+        assert_eq!(*reloadable.load().resolve("a").unwrap().0, 2);
+    }
+
+    #[test]
+    fn test_reload_concurrently() {
+        let reloadable = ReloadableRouter::new(router(&[("a/{x}", 0)]));
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        let router = reloadable.load();
+                        assert!(router.resolve("a/1").is_some());
+                    }
+                });
+            }
+            for value in 1..10 {
+                reloadable.store(router(&[("a/{x}", value)]));
+            }
+        });
+        assert_eq!(*reloadable.load().resolve("a/1").unwrap().0, 9);
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use std::time::{Duration, Instant};
+
+use crate::metadata::Metadata;
+use crate::router::{Resolution, Router};
+
+/// What happened to a corpus of paths looked up by `Router::replay`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Replay {
+    /// Each route by its name, or its pattern if it has none, with the
+    /// number of paths it was found for, in registration order.
+    pub hits: Vec<(String, usize)>,
+    /// The number of paths that resolved to a fallback.
+    pub fallbacks: usize,
+    /// The number of paths that resolved to a redirect.
+    pub redirects: usize,
+    /// The paths that weren't found or were rejected, in corpus order.
+    pub unmatched: Vec<String>,
+    /// The number of paths looked up.
+    pub paths: usize,
+    /// The time spent looking up all paths.
+    pub total: Duration,
+    /// The time spent looking up the slowest path.
+    pub slowest: Duration,
+}
+
+impl Replay {
+    /// The mean time spent looking up a path.
+    pub fn mean(&self) -> Duration {
+        match self.paths {
+            0 => Duration::default(),
+            paths => self.total / paths as u32,
+        }
+    }
+}
+
+impl<T> Router<T> {
+    /// Look up each path of a corpus, for instance paths captured from
+    /// production traffic, and collect how often each route was found,
+    /// which paths weren't, and how long lookups took.
+    pub fn replay<I, S>(&self, paths: I) -> Replay
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut replay = Replay {
+            hits: self
+                .iter()
+                .map(|info| (info.name.unwrap_or(info.pattern.as_str()).to_string(), 0))
+                .collect(),
+            ..Replay::default()
+        };
+        // matches carry the metadata of their route, which tells routes
+        // apart even if their values don't
+        let routes: HashMap<*const Metadata, usize> = self
+            .iter()
+            .enumerate()
+            .map(|(index, info)| (info.metadata as *const Metadata, index))
+            .collect();
+        for path in paths {
+            let path = path.as_ref();
+            let start = Instant::now();
+            let resolution = self.lookup(path);
+            let elapsed = start.elapsed();
+            replay.paths += 1;
+            replay.total += elapsed;
+            replay.slowest = replay.slowest.max(elapsed);
+            match resolution {
+                Resolution::Found(_, m) => {
+                    let index = m
+                        .metadata()
+                        .and_then(|metadata| routes.get(&(metadata as *const Metadata)).copied());
+                    if let Some(index) = index {
+                        replay.hits[index].1 += 1;
+                    }
+                }
+                Resolution::Fallback(..) => replay.fallbacks += 1,
+                Resolution::RedirectTo(_) => replay.redirects += 1,
+                Resolution::Rejected(_) | Resolution::WrongScheme(..) | Resolution::NotFound => {
+                    replay.unmatched.push(path.to_string())
+                }
+            }
+        }
+        replay
+    }
+
+    /// Look up each line of a corpus like `replay`, skipping empty lines.
+    pub fn replay_lines<R: BufRead>(&self, reader: R) -> io::Result<Replay> {
+        let lines = reader.lines().collect::<io::Result<Vec<String>>>()?;
+        Ok(self.replay(
+            lines
+                .iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::router::TrailingSlash;
+
+    #[test]
+    fn test_replay() {
+        let mut router = Router::new().with_trailing_slash(TrailingSlash::Redirect);
+        router.register_named("user", "users/{id}", ()).unwrap();
+        router.register("users/new", ()).unwrap();
+        router.register("posts", ()).unwrap();
+        router.set_fallback(());
+        let replay = router.replay(["users/1", "users/new", "users/2", "posts/", "about"]);
+        assert_eq!(
+            replay.hits,
+            vec![
+                ("user".to_string(), 2),
+                ("users/new".to_string(), 1),
+                ("posts".to_string(), 0),
+            ]
+        );
+        assert_eq!(replay.redirects, 1);
+        assert_eq!(replay.fallbacks, 1);
+        assert!(replay.unmatched.is_empty());
+        assert_eq!(replay.paths, 5);
+        assert!(replay.slowest <= replay.total);
+        assert!(replay.mean() <= replay.slowest);
+    }
+
+    #[test]
+    fn test_replay_lines() {
+        let mut router = Router::new();
+        router.register("users/{id:int}", 1).unwrap();
+        let corpus = "users/1\n\nusers/x\n users/2 \nusers/%00\n";
+        let replay = router.replay_lines(corpus.as_bytes()).unwrap();
+        assert_eq!(replay.hits, vec![("users/{id:int}".to_string(), 2)]);
+        assert_eq!(replay.unmatched, vec!["users/x", "users/%00"]);
+        assert_eq!(replay.paths, 4);
+    }
+}
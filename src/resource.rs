@@ -0,0 +1,264 @@
+//! Registering the conventional routes of a REST resource at once.
+
+use crate::error::ParseError;
+use crate::router::Router;
+
+/// The values for the routes of a resource, as `Router::resource`
+/// registers them.
+///
+/// The router doesn't know about HTTP methods, so it can't tell a POST
+/// from a GET, and the seven conventional actions share four paths:
+/// `index` is also where a new item is created, and `show` is also where
+/// an item is updated or deleted. Their values dispatch on the method.
+/// The names of those actions are aliases, so that `create_post` gives
+/// the path of `posts` and `update_post` and `delete_post` that of
+/// `post`. Routes for the forms to create and edit an item are left out
+/// if there is no value for them, as in an API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceRoutes<T> {
+    /// `posts`, named `posts`: listing and creating items.
+    pub index: T,
+    /// `posts/{id}`, named `post`: showing, updating and deleting an item.
+    pub show: T,
+    /// `posts/new`, named `new_post`: the form to create an item.
+    pub new: Option<T>,
+    /// `posts/{id}/edit`, named `edit_post`: the form to edit an item.
+    pub edit: Option<T>,
+}
+
+/// The name of a resource, like `posts`, and of a single item of it, like
+/// `post`.
+///
+/// A resource can be nested under the items of another, as comments are
+/// under posts: `Resource::new("posts").nest("comments")` is at
+/// `posts/{post_id}/comments`, and the names of its routes start with
+/// `post_`, as in `post_comments` and `edit_post_comment`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    name: String,
+    singular: String,
+    /// the resources this is nested under, outermost first
+    parents: Vec<Resource>,
+}
+
+impl Resource {
+    /// A resource whose single item is named without the trailing `s`.
+    pub fn new(name: &str) -> Resource {
+        Resource {
+            name: name.to_string(),
+            singular: name.strip_suffix('s').unwrap_or(name).to_string(),
+            parents: Vec::new(),
+        }
+    }
+
+    /// A resource nested under the items of this one. The variable for an
+    /// item of this one is its singular name followed by `_id`.
+    pub fn nest(&self, name: &str) -> Resource {
+        let mut parents = self.parents.clone();
+        parents.push(Resource {
+            parents: Vec::new(),
+            ..self.clone()
+        });
+        Resource {
+            parents,
+            ..Resource::new(name)
+        }
+    }
+
+    /// The path of the resource, with the given separator, and what the
+    /// names of its routes start with.
+    fn prefixes(&self, separator: char) -> (String, String) {
+        let mut path = String::new();
+        let mut name = String::new();
+        for parent in &self.parents {
+            path.push_str(&format!(
+                "{}{}{{{}_id}}{}",
+                parent.name, separator, parent.singular, separator
+            ));
+            name.push_str(&format!("{}_", parent.singular));
+        }
+        path.push_str(&self.name);
+        (path, name)
+    }
+
+    /// Name a single item otherwise, as for `people` and `person`.
+    pub fn with_singular(mut self, singular: &str) -> Resource {
+        self.singular = singular.to_string();
+        self
+    }
+}
+
+impl From<&str> for Resource {
+    fn from(name: &str) -> Resource {
+        Resource::new(name)
+    }
+}
+
+impl<T> Router<T> {
+    /// Register the routes of a resource, like `posts`, as
+    /// `ResourceRoutes` describes. Paths start with the name of the
+    /// resource, and so do the names of routes for all items; the names of
+    /// routes for a single item use its singular name. The paths and names
+    /// of a nested resource start with those of its parents.
+    pub fn resource(
+        &mut self,
+        resource: impl Into<Resource>,
+        routes: ResourceRoutes<T>,
+    ) -> Result<(), ParseError> {
+        let resource = resource.into();
+        let separator = self.separator();
+        let (path, prefix) = resource.prefixes(separator);
+        let singular = format!("{}{}", prefix, resource.singular);
+        let member = format!("{}{}{{id}}", path, separator);
+        self.register_named(&format!("{}{}", prefix, resource.name), &path, routes.index)?;
+        self.register_named(&singular, &member, routes.show)?;
+        self.alias(
+            &format!("create_{}", singular),
+            &format!("{}{}", prefix, resource.name),
+        );
+        self.alias(&format!("update_{}", singular), &singular);
+        self.alias(&format!("delete_{}", singular), &singular);
+        if let Some(new) = routes.new {
+            let pattern = format!("{}{}new", path, separator);
+            self.register_named(&format!("new_{}", singular), &pattern, new)?;
+        }
+        if let Some(edit) = routes.edit {
+            let pattern = format!("{}{}edit", member, separator);
+            self.register_named(&format!("edit_{}", singular), &pattern, edit)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_resource() {
+        let mut router = Router::new();
+        let routes = ResourceRoutes {
+            index: "index",
+            show: "show",
+            new: Some("new"),
+            edit: Some("edit"),
+        };
+        router.resource("posts", routes).unwrap();
+        assert_eq!(*router.resolve("posts").unwrap().0, "index");
+        assert_eq!(*router.resolve("posts/new").unwrap().0, "new");
+        let (value, m) = router.resolve("posts/3").unwrap();
+        assert_eq!((*value, m.get("id")), ("show", Some("3")));
+        assert_eq!(*router.resolve("posts/3/edit").unwrap().0, "edit");
+        let values = HashMap::from([("id", "3")]);
+        assert_eq!(router.path_for("posts", &HashMap::new()).unwrap(), "posts");
+        assert_eq!(
+            router.path_for("new_post", &HashMap::new()).unwrap(),
+            "posts/new"
+        );
+        assert_eq!(router.path_for("post", &values).unwrap(), "posts/3");
+        assert_eq!(
+            router.path_for("edit_post", &values).unwrap(),
+            "posts/3/edit"
+        );
+        assert_eq!(
+            router.path_for("create_post", &HashMap::new()).unwrap(),
+            "posts"
+        );
+        assert_eq!(router.path_for("update_post", &values).unwrap(), "posts/3");
+        assert_eq!(router.path_for("delete_post", &values).unwrap(), "posts/3");
+        let names: Vec<_> = router.iter().filter_map(|route| route.name).collect();
+        assert_eq!(names, ["posts", "post", "new_post", "edit_post"]);
+    }
+
+    #[test]
+    fn test_nested_resource() {
+        let mut router = Router::new();
+        let routes = |level| ResourceRoutes {
+            index: (level, "index"),
+            show: (level, "show"),
+            new: Some((level, "new")),
+            edit: Some((level, "edit")),
+        };
+        let posts = Resource::new("posts");
+        let comments = posts.nest("comments");
+        let likes = comments.nest("likes");
+        router.resource(posts, routes(0)).unwrap();
+        router.resource(comments, routes(1)).unwrap();
+        router.resource(likes, routes(2)).unwrap();
+        let (value, m) = router.resolve("posts/1/comments/2/edit").unwrap();
+        assert_eq!(*value, (1, "edit"));
+        assert_eq!((m.get("post_id"), m.get("id")), (Some("1"), Some("2")));
+        assert_eq!(
+            *router.resolve("posts/1/comments/2/likes").unwrap().0,
+            (2, "index")
+        );
+        let names: Vec<_> = router.iter().filter_map(|route| route.name).collect();
+        assert_eq!(
+            names[4..],
+            [
+                "post_comments",
+                "post_comment",
+                "new_post_comment",
+                "edit_post_comment",
+                "post_comment_likes",
+                "post_comment_like",
+                "new_post_comment_like",
+                "edit_post_comment_like"
+            ]
+        );
+        let values = HashMap::from([("post_id", "1"), ("comment_id", "2"), ("id", "3")]);
+        assert_eq!(
+            router.path_for("post_comment_like", &values).unwrap(),
+            "posts/1/comments/2/likes/3"
+        );
+        let values = HashMap::from([("post_id", "1")]);
+        assert_eq!(
+            router.path_for("new_post_comment", &values).unwrap(),
+            "posts/1/comments/new"
+        );
+        assert_eq!(
+            router.path_for("create_post_comment", &values).unwrap(),
+            "posts/1/comments"
+        );
+    }
+
+    #[test]
+    fn test_nested_resource_singular() {
+        let people = Resource::new("people").with_singular("person");
+        let mut router = Router::new();
+        let routes = ResourceRoutes {
+            index: 1,
+            show: 2,
+            new: None,
+            edit: None,
+        };
+        let addresses = people.nest("addresses").with_singular("address");
+        router.resource(addresses, routes).unwrap();
+        let (value, m) = router.resolve("people/7/addresses/1").unwrap();
+        assert_eq!((*value, m.get("person_id")), (2, Some("7")));
+        let values = HashMap::from([("person_id", "7"), ("id", "1")]);
+        assert_eq!(
+            router.path_for("person_address", &values).unwrap(),
+            "people/7/addresses/1"
+        );
+    }
+
+    #[test]
+    fn test_resource_without_forms() {
+        let mut router = Router::new().with_separator(':');
+        let routes = ResourceRoutes {
+            index: 1,
+            show: 2,
+            new: None,
+            edit: None,
+        };
+        let resource = Resource::new("people").with_singular("person");
+        router.resource(resource, routes).unwrap();
+        assert_eq!(router.iter().count(), 2);
+        assert_eq!(*router.resolve("people:new").unwrap().0, 2);
+        let values = HashMap::from([("id", "1")]);
+        assert_eq!(router.path_for("person", &values).unwrap(), "people:1");
+    }
+}
@@ -0,0 +1,3370 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::cache::PatternCache;
+use crate::change::RouteChange;
+use crate::conflict::Conflict;
+use crate::converter::ConverterRegistry;
+use crate::encoding;
+use crate::error::{ConversionError, ExpandError, InvalidPath, ParseError};
+use crate::hash::Map;
+use crate::hygiene::{self, DoubleEncoding, EncodedSeparator};
+use crate::matching::{BytesMatch, Match};
+use crate::metadata::Metadata;
+use crate::normalize;
+use crate::pattern::{self, Case, Pattern, Specificity};
+use crate::syntax::Syntax;
+use crate::trie::Node;
+
+#[derive(Debug)]
+struct Route<T> {
+    pattern: Arc<Pattern>,
+    value: T,
+    priority: i32,
+    specificity: Specificity,
+    metadata: Metadata,
+    guard: Option<Guard>,
+    /// the variable with the token, for a route registered with a token
+    token: Option<String>,
+}
+
+impl<T> Route<T> {
+    /// Whether the route can win for a match, given the context it is
+    /// resolved with. A route with a guard never wins without a context,
+    /// and a route with a token only wins while its token is checked.
+    fn passes(&self, m: &Match, context: Option<&dyn Any>) -> bool {
+        if self.token.is_some() && !context.is_some_and(|context| context.is::<TokenCheck>()) {
+            return false;
+        }
+        match (&self.guard, context) {
+            (None, _) => true,
+            (Some(guard), Some(context)) => (guard.0)(m, context),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+type GuardFn = Arc<dyn Fn(&Match, &dyn Any) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+struct Guard(GuardFn);
+
+impl fmt::Debug for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Guard")
+    }
+}
+
+type FactoryFn<T> = Box<dyn Fn(&Match) -> Option<T> + Send + Sync>;
+
+struct Factory<T> {
+    pattern: Arc<Pattern>,
+    factory: FactoryFn<T>,
+}
+
+impl<T> fmt::Debug for Factory<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Factory")
+            .field("pattern", &self.pattern)
+            .finish()
+    }
+}
+
+/// The context of a lookup that checks tokens.
+struct TokenCheck;
+
+/// Checks the tokens of routes registered with `Router::register_with_token`,
+/// like the token of an invitation or of a link to reset a password.
+pub trait TokenValidator {
+    /// Whether a token is valid, using it up if it is, so that it isn't
+    /// valid again.
+    fn consume(&self, token: &str) -> impl Future<Output = bool> + Send;
+}
+
+/// A value for the paths that no route matches.
+#[derive(Debug, Clone)]
+struct Fallback<T> {
+    /// the paths it is for start with this, or with anything if `None`
+    prefix: Option<Arc<Pattern>>,
+    value: T,
+}
+
+/// The parent of an object, if it has one, and the object's own variables.
+type Located<T> = (Option<T>, Vec<(&'static str, String)>);
+
+type InverseFn<T> = Box<dyn Fn(&T) -> Option<Located<T>> + Send + Sync>;
+
+struct Inverse<T> {
+    pattern: Arc<Pattern>,
+    inverse: InverseFn<T>,
+}
+
+impl<T> fmt::Debug for Inverse<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Inverse")
+            .field("pattern", &self.pattern)
+            .finish()
+    }
+}
+
+type LookupFn = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Fills in a variable from the value of another, for `path_for`.
+#[derive(Clone)]
+struct Manifest {
+    variable: String,
+    key: String,
+    lookup: LookupFn,
+}
+
+impl fmt::Debug for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Manifest")
+            .field("variable", &self.variable)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// A redirect registered with `Router::register_redirect`.
+#[derive(Debug, Clone)]
+struct Redirect {
+    from: Arc<Pattern>,
+    to: Arc<Pattern>,
+    query: QueryPolicy,
+    /// the keys of the query parameters `from` describes
+    described: Vec<String>,
+}
+
+impl Redirect {
+    /// The path a path redirects to, if this redirect is for it.
+    fn target(&self, path: &str) -> Option<String> {
+        let m = self.from.match_path(path)?;
+        let values = m
+            .iter()
+            .filter(|(name, _)| self.to.names().any(|n| n == *name))
+            .collect();
+        let target = self.to.expand(&values).ok()?;
+        let (target, target_query) = target.split_once('?').unwrap_or((&target, ""));
+        let query = path.split_once('?').map_or("", |(_, query)| query);
+        let key = |param: &str| param.split('=').next().unwrap_or_default().to_string();
+        let params: Vec<&str> = match self.query {
+            QueryPolicy::Drop => vec![target_query],
+            QueryPolicy::Pass => vec![target_query, query],
+            QueryPolicy::Merge => {
+                let set: Vec<String> = target_query.split('&').map(key).collect();
+                let kept = query.split('&').filter(|param| {
+                    let key = key(param);
+                    !set.contains(&key) && !self.described.contains(&key)
+                });
+                std::iter::once(target_query).chain(kept).collect()
+            }
+        };
+        let params: Vec<&str> = params.into_iter().filter(|p| !p.is_empty()).collect();
+        if params.is_empty() {
+            Some(target.to_string())
+        } else {
+            Some(format!("{}?{}", target, params.join("&")))
+        }
+    }
+}
+
+/// A registry of patterns, each mapped to a value.
+///
+/// Paths are resolved against the registered patterns in order of
+/// precedence; the first pattern that matches wins. Patterns with a higher
+/// priority come first. Among patterns with the same priority, a literal
+/// step beats a variable step in the same position, a variable with literal
+/// text or a converter beats a bare variable, and a bare variable beats a
+/// wildcard. So `a/b` is tried before `a/{x}`, whatever the registration
+/// order. Patterns that are equally specific are tried in registration
+/// order. With `TieBreak::FirstRegistered` all patterns with the same
+/// priority are.
+///
+/// Patterns can also be registered with a factory, which constructs a value
+/// from the matched variables. These are used by `consume`. Its inverse,
+/// which gives the variables for an object, is used by `locate`.
+///
+/// With percent-encoding enabled, all patterns in the router decode paths
+/// on match and encode them on expansion.
+///
+/// With normalization enabled, paths are normalized before they are
+/// matched, so that `a/./b//c/../d` matches `a/b/d`. With the character
+/// check enabled, paths with control characters or whitespace are rejected
+/// before that.
+///
+/// By default `a/b/` and `a/b` are different paths; see `TrailingSlash`
+/// for the alternatives.
+///
+/// Routes can have a guard, which has to pass for the route to win; see
+/// `register_with_guard`. A path that no route matches resolves to nothing;
+/// `lookup` gives the fallback set with `set_fallback` for it instead.
+///
+/// Whatever lists routes, like `iter`, `match_all`, `check` and `diff`,
+/// lists them in an order that only depends on the order they were
+/// registered in, never on hashing, so it's the same on every run.
+///
+/// A router is `Send` and `Sync` if its values are: resolving only needs
+/// `&self`, so threads can resolve paths concurrently. Registering needs
+/// `&mut self`; once all routes are registered, `build` turns the router
+/// into a `SharedRouter` that can't be changed anymore and is cheap to
+/// clone for each thread.
+#[derive(Debug)]
+pub struct Router<T> {
+    routes: Vec<Route<T>>,
+    trie: Node,
+    factories: Vec<Factory<T>>,
+    inverses: Vec<Inverse<T>>,
+    fallbacks: Vec<Fallback<T>>,
+    manifests: Vec<Manifest>,
+    redirects: Vec<Redirect>,
+    pattern_cache: Option<PatternCache>,
+    /// route indexes by name
+    names: Map<String, usize>,
+    converters: ConverterRegistry,
+    separator: char,
+    percent_encoding: bool,
+    case: Case,
+    normalize: bool,
+    check_characters: bool,
+    encoded_separator: EncodedSeparator,
+    double_encoding: DoubleEncoding,
+    automatic_names: bool,
+    tie_break: TieBreak,
+    strict: bool,
+    trailing_slash: TrailingSlash,
+}
+
+/// Which of the routes with the same priority that match a path wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TieBreak {
+    /// The most specific pattern wins, whatever the registration order,
+    /// and of equally specific patterns the first registered. The default.
+    #[default]
+    MostSpecific,
+    /// The first registered pattern wins, as in routers that try their
+    /// routes in turn.
+    FirstRegistered,
+}
+
+/// How a router treats a path that only matches with a trailing slash
+/// added or removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingSlash {
+    /// `a/b/` and `a/b` are different paths, and each only matches a
+    /// pattern spelled the same way. The default.
+    #[default]
+    Strict,
+    /// `a/b/` and `a/b` are the same path. A path that doesn't match is
+    /// tried with its trailing slash added or removed.
+    Equivalent,
+    /// A path that only matches with its trailing slash added or removed
+    /// resolves to `Resolution::RedirectTo` that path.
+    Redirect,
+}
+
+/// A registered route, as given by `Router::iter`.
+#[derive(Debug)]
+pub struct RouteInfo<'a, T> {
+    pub name: Option<&'a str>,
+    pub pattern: &'a Pattern,
+    pub value: &'a T,
+    pub priority: i32,
+    pub metadata: &'a Metadata,
+}
+
+/// What a redirect does with the query of the path it redirects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueryPolicy {
+    /// The target has just the query parameters of its pattern.
+    Drop,
+    /// The query is added as it is after those of the target. The default.
+    #[default]
+    Pass,
+    /// The parameters of the query are added after those of the target,
+    /// except those the target sets and those the pattern redirected from
+    /// describes.
+    Merge,
+}
+
+/// The outcome of looking up a path in a router.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolution<'a, T> {
+    /// The value of the route that matched, and its variables.
+    Found(&'a T, Match<'a>),
+    /// The path only matches spelled differently, as this path. The
+    /// request should be redirected there.
+    RedirectTo(String),
+    /// No route matches, but a fallback does: its value, the variables in
+    /// the prefix it was mounted under, and the rest of the path after
+    /// that prefix.
+    Fallback(&'a T, Match<'a>, Cow<'a, str>),
+    /// The path was rejected before it was matched.
+    Rejected(InvalidPath),
+    /// A route matches, but not for the scheme the path was requested
+    /// with: its value and variables. See `Router::lookup_url`.
+    WrongScheme(&'a T, Match<'a>),
+    NotFound,
+}
+
+impl<T> Router<T> {
+    pub fn new() -> Router<T> {
+        Router {
+            routes: Vec::new(),
+            trie: Node::default(),
+            factories: Vec::new(),
+            inverses: Vec::new(),
+            fallbacks: Vec::new(),
+            manifests: Vec::new(),
+            redirects: Vec::new(),
+            pattern_cache: None,
+            names: Map::default(),
+            converters: ConverterRegistry::new(),
+            separator: '/',
+            percent_encoding: false,
+            case: Case::default(),
+            normalize: false,
+            check_characters: false,
+            encoded_separator: EncodedSeparator::default(),
+            double_encoding: DoubleEncoding::default(),
+            automatic_names: false,
+            tie_break: TieBreak::default(),
+            strict: false,
+            trailing_slash: TrailingSlash::default(),
+        }
+    }
+
+    /// Separate steps by another character than `/`, as
+    /// `Pattern::with_separator` does. Paths are split by it too.
+    ///
+    /// # Panics
+    ///
+    /// If patterns were already registered, or the separator can't be used
+    /// in patterns.
+    pub fn with_separator(mut self, separator: char) -> Router<T> {
+        assert!(
+            self.patterns_mut().next().is_none(),
+            "the separator has to be set before patterns are registered"
+        );
+        pattern::assert_separator(separator);
+        self.separator = separator;
+        self
+    }
+
+    pub(crate) fn separator(&self) -> char {
+        self.separator
+    }
+
+    /// Enable or disable percent-encoding for all patterns, including those
+    /// registered later.
+    pub fn with_percent_encoding(mut self, enabled: bool) -> Router<T> {
+        self.percent_encoding = enabled;
+        for pattern in self.patterns_mut() {
+            pattern.set_percent_encoding(enabled);
+        }
+        self
+    }
+
+    /// Set how the case of paths is treated for all patterns, including
+    /// those registered later.
+    pub fn with_case(mut self, case: Case) -> Router<T> {
+        self.case = case;
+        for pattern in self.patterns_mut() {
+            pattern.set_case(case);
+        }
+        self
+    }
+
+    /// The patterns of this router, to change options of. A pattern that is
+    /// shared through a `PatternCache` is copied first.
+    fn patterns_mut(&mut self) -> impl Iterator<Item = &mut Pattern> {
+        self.routes
+            .iter_mut()
+            .map(|route| &mut route.pattern)
+            .chain(
+                self.factories
+                    .iter_mut()
+                    .map(|factory| &mut factory.pattern),
+            )
+            .chain(self.inverses.iter_mut().map(|inverse| &mut inverse.pattern))
+            .chain(
+                self.fallbacks
+                    .iter_mut()
+                    .filter_map(|fallback| fallback.prefix.as_mut()),
+            )
+            .map(Arc::make_mut)
+    }
+
+    /// Enable or disable normalizing paths before they are matched, as
+    /// `normalize` does. Paths that escape the root don't match anything.
+    pub fn with_normalization(mut self, enabled: bool) -> Router<T> {
+        self.normalize = enabled;
+        self
+    }
+
+    /// Enable or disable rejecting paths with control characters, NUL
+    /// bytes or whitespace, as `check_characters` does. `lookup` gives
+    /// `Resolution::Rejected` for them. Paths are checked after double
+    /// encoding is decoded, with offsets into the decoded path.
+    pub fn with_character_check(mut self, enabled: bool) -> Router<T> {
+        self.check_characters = enabled;
+        self
+    }
+
+    /// Set what a percent-encoded separator in a path means, in matching
+    /// and in `path_for`. With `EncodedSeparator::Reject`, `lookup` gives
+    /// `Resolution::Rejected` for a path with one, and `resolve_segments`,
+    /// `resolve_bytes` and `consume` give nothing. These match paths as
+    /// they are, so with `EncodedSeparator::Separator` an encoded
+    /// separator stays data for them.
+    pub fn with_encoded_separator(mut self, policy: EncodedSeparator) -> Router<T> {
+        self.encoded_separator = policy;
+        self
+    }
+
+    /// Set what is done with paths that look double-encoded, like
+    /// `a/%252F`. These are decoded or rejected before the other options
+    /// apply, so a `%2F` that was double-encoded is a separator with
+    /// `EncodedSeparator::Separator`.
+    pub fn with_double_encoding(mut self, policy: DoubleEncoding) -> Router<T> {
+        self.double_encoding = policy;
+        self
+    }
+
+    /// Enable or disable naming the routes registered later that aren't
+    /// given a name, after their pattern as `Pattern::derived_name` names
+    /// it. If another route already has that name, a number is added, as
+    /// in `users_id_2`, so names depend on the registration order only.
+    ///
+    /// A route registered later under an explicit name takes that name
+    /// over, as with `register_named`.
+    pub fn with_automatic_names(mut self, enabled: bool) -> Router<T> {
+        self.automatic_names = enabled;
+        self
+    }
+
+    /// Set which route wins of the routes with the same priority that match
+    /// a path. This also decides the order of `match_all` and what `check`
+    /// reports.
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Router<T> {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Enable or disable checking each route as it is registered, like
+    /// `check` does, to catch mistakes in development:
+    /// `with_strict(cfg!(debug_assertions))` only checks in debug builds.
+    ///
+    /// # Panics
+    ///
+    /// When a route is registered later that duplicates a route or is
+    /// shadowed by one, or that shadows a route registered before.
+    pub fn with_strict(mut self, enabled: bool) -> Router<T> {
+        self.strict = enabled;
+        self
+    }
+
+    /// Fill in `variable` for `path_for` by looking up the value of the
+    /// variable `key` in a manifest, unless it is given.
+    ///
+    /// This is meant for cache busting: with an asset manifest that maps
+    /// `logo.png` to the hash of its content, the route
+    /// `assets/{hash}/{file:*}` only needs `file` to generate
+    /// `assets/3f2a/logo.png`. A `key` that isn't in the manifest is an
+    /// `ExpandError::InvalidValue`. Manifests apply to all routes with
+    /// `variable`, including mounted ones.
+    pub fn with_manifest<F>(mut self, variable: &str, key: &str, manifest: F) -> Router<T>
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.manifests.push(Manifest {
+            variable: variable.to_string(),
+            key: key.to_string(),
+            lookup: Arc::new(manifest),
+        });
+        self
+    }
+
+    /// Share the patterns of this router with other routers that use the
+    /// same cache, as `PatternCache` describes.
+    pub fn with_pattern_cache(mut self, cache: PatternCache) -> Router<T> {
+        self.pattern_cache = Some(cache);
+        self
+    }
+
+    /// Set how paths with and without a trailing slash are related.
+    pub fn with_trailing_slash(mut self, trailing_slash: TrailingSlash) -> Router<T> {
+        self.trailing_slash = trailing_slash;
+        self
+    }
+
+    /// Use a registry to look up the converters of patterns registered
+    /// later.
+    pub fn with_converters(mut self, converters: ConverterRegistry) -> Router<T> {
+        self.converters = converters;
+        self
+    }
+
+    /// Parse a pattern with the options of this router.
+    fn pattern(&self, s: &str) -> Result<Arc<Pattern>, ParseError> {
+        self.pattern_with_converters(s, &self.converters)
+    }
+
+    fn pattern_with_converters(
+        &self,
+        s: &str,
+        converters: &ConverterRegistry,
+    ) -> Result<Arc<Pattern>, ParseError> {
+        match &self.pattern_cache {
+            Some(cache) => cache.get_with(
+                s,
+                converters,
+                self.separator,
+                self.percent_encoding,
+                self.case,
+            ),
+            None => Ok(Arc::new(
+                Pattern::parse(s, converters, self.separator)?
+                    .with_percent_encoding(self.percent_encoding)
+                    .with_case(self.case),
+            )),
+        }
+    }
+
+    /// Register a value for a pattern, with the default priority of 0.
+    pub fn register(&mut self, pattern: &str, value: T) -> Result<(), ParseError> {
+        self.register_with_priority(pattern, value, 0)
+    }
+
+    /// Register a value for a pattern with an explicit priority.
+    ///
+    /// A pattern with a higher priority takes precedence over one with a
+    /// lower priority, however specific that one is.
+    pub fn register_with_priority(
+        &mut self,
+        pattern: &str,
+        value: T,
+        priority: i32,
+    ) -> Result<(), ParseError> {
+        let pattern = self.pattern(pattern)?;
+        self.insert_route(pattern, value, priority, Metadata::new(), None);
+        Ok(())
+    }
+
+    /// Register a value for a pattern with metadata, which is available
+    /// from the matches of the route.
+    pub fn register_with_metadata(
+        &mut self,
+        pattern: &str,
+        value: T,
+        metadata: Metadata,
+    ) -> Result<(), ParseError> {
+        let pattern = self.pattern(pattern)?;
+        self.insert_route(pattern, value, 0, metadata, None);
+        Ok(())
+    }
+
+    /// Register a value for a pattern with a guard, so that the route only
+    /// wins if its guard passes for the match and the context the path is
+    /// resolved with. Otherwise resolution goes on with the next route.
+    ///
+    /// Guarded routes are only resolved by `resolve_with` and `lookup_with`,
+    /// with a context of type `C`; other ways of resolving skip them.
+    pub fn register_with_guard<C, G>(
+        &mut self,
+        pattern: &str,
+        value: T,
+        guard: G,
+    ) -> Result<(), ParseError>
+    where
+        C: Any,
+        G: Fn(&Match, &C) -> bool + Send + Sync + 'static,
+    {
+        let pattern = self.pattern(pattern)?;
+        let guard = Guard(Arc::new(move |m: &Match, context: &dyn Any| {
+            context
+                .downcast_ref::<C>()
+                .is_some_and(|context| guard(m, context))
+        }));
+        self.insert_route(pattern, value, 0, Metadata::new(), Some(guard));
+        Ok(())
+    }
+
+    /// Register a value for a pattern whose variable `token` holds a token
+    /// that has to be valid for the route to win, like `invites/{token}`.
+    ///
+    /// Routes with a token are only resolved by `lookup_with_token`, which
+    /// checks the token; other ways of resolving skip them. The variable
+    /// has to be in the pattern.
+    pub fn register_with_token(
+        &mut self,
+        pattern: &str,
+        value: T,
+        token: &str,
+    ) -> Result<(), ParseError> {
+        let pattern = self.pattern(pattern)?;
+        if !pattern.names().any(|name| name == token) {
+            return Err(ParseError::UnknownTokenVariable {
+                name: token.to_string(),
+                offset: 0,
+            });
+        }
+        self.insert_route(pattern, value, 0, Metadata::new(), None);
+        self.routes.last_mut().expect("a route").token = Some(token.to_string());
+        Ok(())
+    }
+
+    /// Register a value for a gRPC method, with a pattern like
+    /// `helloworld.Greeter/SayHello` or `{package}.Greeter/{method}`.
+    ///
+    /// A gRPC path is always `/{package}.{Service}/{Method}`, so the pattern
+    /// has to be exactly two steps without a wildcard, optional steps or a
+    /// query, in a router that separates steps by `/`. A service step can
+    /// have dots in it and use converters like any other step. A path with
+    /// a trailing slash doesn't match, unless the router's `TrailingSlash`
+    /// says it should.
+    pub fn register_grpc(&mut self, pattern: &str, value: T) -> Result<(), ParseError> {
+        let pattern = self.pattern(pattern)?;
+        if !pattern.is_grpc() {
+            return Err(ParseError::InvalidGrpc { offset: 0 });
+        }
+        self.insert_route(pattern, value, 0, Metadata::new(), None);
+        Ok(())
+    }
+
+    /// Register a value for a pattern under a name, so that paths for it
+    /// can be generated with `path_for`.
+    ///
+    /// Registering a name again makes it refer to the new route.
+    pub fn register_named(
+        &mut self,
+        name: &str,
+        pattern: &str,
+        value: T,
+    ) -> Result<(), ParseError> {
+        self.register_named_with_priority(name, pattern, value, 0)
+    }
+
+    /// Register a value for a pattern under a name, with an explicit
+    /// priority.
+    pub fn register_named_with_priority(
+        &mut self,
+        name: &str,
+        pattern: &str,
+        value: T,
+        priority: i32,
+    ) -> Result<(), ParseError> {
+        self.register_with_priority(pattern, value, priority)?;
+        let index = self.routes.len() - 1;
+        if self.automatic_names {
+            self.names.retain(|_, named| *named != index);
+        }
+        self.names.insert(name.to_string(), index);
+        Ok(())
+    }
+
+    /// Let `alias` refer to the route named `name` as well, if there is
+    /// one.
+    pub(crate) fn alias(&mut self, alias: &str, name: &str) {
+        if let Some(&index) = self.names.get(name) {
+            self.names.insert(alias.to_string(), index);
+        }
+    }
+
+    /// Generate the path for a named route, using variable values.
+    pub fn path_for(
+        &self,
+        name: &str,
+        values: &HashMap<&str, &str>,
+    ) -> Result<String, ExpandError> {
+        let index = self
+            .names
+            .get(name)
+            .ok_or_else(|| ExpandError::UnknownRoute {
+                name: name.to_string(),
+            })?;
+        let pattern = &self.routes[*index].pattern;
+        let looked_up = self
+            .manifests
+            .iter()
+            .filter(|manifest| {
+                !values.contains_key(manifest.variable.as_str())
+                    && pattern.names().any(|name| name == manifest.variable)
+            })
+            .map(|manifest| {
+                let key = values.get(manifest.key.as_str()).ok_or_else(|| {
+                    ExpandError::MissingVariable {
+                        name: manifest.key.clone(),
+                    }
+                })?;
+                let value = (manifest.lookup)(key).ok_or_else(|| ExpandError::InvalidValue {
+                    name: manifest.key.clone(),
+                    value: key.to_string(),
+                })?;
+                Ok((manifest.variable.as_str(), value))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if looked_up.is_empty() {
+            self.expand_route(pattern, values)
+        } else {
+            let mut values = values.clone();
+            values.extend(
+                looked_up
+                    .iter()
+                    .map(|(name, value)| (*name, value.as_str())),
+            );
+            self.expand_route(pattern, &values)
+        }
+    }
+
+    fn expand_route(
+        &self,
+        pattern: &Pattern,
+        values: &HashMap<&str, &str>,
+    ) -> Result<String, ExpandError> {
+        if self.encoded_separator != EncodedSeparator::Data {
+            let separated = values
+                .iter()
+                .find(|(name, value)| value.contains(self.separator) && pattern.in_segment(name));
+            if let Some((name, value)) = separated {
+                return Err(ExpandError::InvalidValue {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+        pattern.expand(values)
+    }
+
+    /// Generate the path for a named route like `path_for`, with typed
+    /// values that are serialized by the converters of their variables, as
+    /// with `Pattern::expand_typed`.
+    pub fn path_for_typed(
+        &self,
+        name: &str,
+        values: &[(&str, &dyn Any)],
+    ) -> Result<String, ExpandError> {
+        let index = self
+            .names
+            .get(name)
+            .ok_or_else(|| ExpandError::UnknownRoute {
+                name: name.to_string(),
+            })?;
+        let serialized = self.routes[*index].pattern.serialized(values)?;
+        self.path_for(
+            name,
+            &serialized
+                .iter()
+                .map(|(name, text)| (*name, text.as_str()))
+                .collect(),
+        )
+    }
+
+    /// The metadata of a named route.
+    pub fn metadata(&self, name: &str) -> Option<&Metadata> {
+        let index = *self.names.get(name)?;
+        Some(&self.routes[index].metadata)
+    }
+
+    /// The metadata of a named route, to add to.
+    pub fn metadata_mut(&mut self, name: &str) -> Option<&mut Metadata> {
+        let index = *self.names.get(name)?;
+        Some(&mut self.routes[index].metadata)
+    }
+
+    /// Iterate over the registered routes in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = RouteInfo<'_, T>> {
+        self.routes
+            .iter()
+            .zip(self.route_names())
+            .map(|(route, name)| RouteInfo {
+                name,
+                pattern: &route.pattern,
+                value: &route.value,
+                priority: route.priority,
+                metadata: &route.metadata,
+            })
+    }
+
+    /// The name of each route, if it has any. Of several names the
+    /// shortest is used, and of those the first alphabetically.
+    fn route_names(&self) -> Vec<Option<&str>> {
+        let mut names: Vec<Option<&str>> = vec![None; self.routes.len()];
+        for (name, index) in &self.names {
+            let shown = &mut names[*index];
+            if shown.is_none_or(|shown| (shown.len(), shown) > (name.len(), name.as_str())) {
+                *shown = Some(name);
+            }
+        }
+        names
+    }
+
+    fn insert_route(
+        &mut self,
+        pattern: Arc<Pattern>,
+        value: T,
+        priority: i32,
+        metadata: Metadata,
+        guard: Option<Guard>,
+    ) {
+        let index = self.routes.len();
+        if self.automatic_names && !self.names.values().any(|named| *named == index) {
+            let name = pattern.derived_name();
+            let name = (1..)
+                .map(|n| match n {
+                    1 => name.clone(),
+                    n => format!("{}_{}", name, n),
+                })
+                .find(|name| !self.names.contains_key(name))
+                .expect("a free name");
+            self.names.insert(name, index);
+        }
+        self.trie.insert(&pattern, index);
+        let specificity = pattern.specificity();
+        self.routes.push(Route {
+            pattern,
+            value,
+            priority,
+            specificity,
+            metadata,
+            guard,
+            token: None,
+        });
+        if self.strict {
+            if let Some(conflict) = self.conflict_with(index) {
+                panic!("conflicting route: {}", conflict);
+            }
+        }
+    }
+
+    /// Redirect the paths a pattern matches that no route does to a target
+    /// pattern, expanded with the variables of the match, so that `lookup`
+    /// resolves them to `Resolution::RedirectTo`. The query of the path is
+    /// kept as `query` says. Redirects are tried in registration order,
+    /// skipping those whose target can't be expanded.
+    ///
+    /// The trailing slash redirects of `TrailingSlash::Redirect` always
+    /// keep the query as it is.
+    pub fn register_redirect(
+        &mut self,
+        from: &str,
+        to: &str,
+        query: QueryPolicy,
+    ) -> Result<(), ParseError> {
+        let from = self.pattern(from)?;
+        let to = self.pattern(to)?;
+        let syntax = Syntax::with_separator(from.as_str(), self.separator)?;
+        let described = syntax
+            .params
+            .iter()
+            .map(|param| syntax.text(&param.key).to_string())
+            .collect();
+        self.redirects.push(Redirect {
+            from,
+            to,
+            query,
+            described,
+        });
+        Ok(())
+    }
+
+    /// Set the value for paths that no route matches, replacing the one
+    /// set before. `lookup` returns it as `Resolution::Fallback`; `resolve`
+    /// still returns nothing for these paths.
+    ///
+    /// When this router is mounted, it becomes the fallback for the paths
+    /// under the prefix it is mounted under.
+    pub fn set_fallback(&mut self, value: T) {
+        self.fallbacks.retain(|fallback| fallback.prefix.is_some());
+        self.fallbacks.push(Fallback {
+            prefix: None,
+            value,
+        });
+    }
+
+    /// Mount all routes, factories, inverses, fallbacks and redirects of
+    /// another router under a prefix. Route names are kept as they are.
+    ///
+    /// The prefix can contain variables, like `tenants/{tenant}`; their
+    /// matches are merged with those of the mounted patterns. Errors in the
+    /// prefix have offsets into the prefix; a variable in a mounted pattern
+    /// that is already in the prefix is reported with an offset into the
+    /// combined pattern. Nothing is mounted if there is an error. The
+    /// mounted patterns get the percent-encoding option of this router, but
+    /// keep the converters of the mounted router.
+    ///
+    /// Inverses of the mounted router have to give the variables in the
+    /// prefix too, or `locate` doesn't find a path for their objects.
+    pub fn mount(&mut self, prefix: &str, router: Router<T>) -> Result<(), ParseError> {
+        self.pattern(prefix)?;
+        let prefix = prefix
+            .trim_start_matches(self.separator)
+            .trim_end_matches(self.separator);
+        // the mounted patterns keep the converters they were parsed with
+        let converters = self.converters.extended(&router.converters);
+        let join = |pattern: &Pattern| {
+            self.pattern_with_converters(
+                &join_pattern(prefix, pattern.as_str(), self.separator),
+                &converters,
+            )
+        };
+        let routes = router
+            .routes
+            .iter()
+            .map(|route| join(&route.pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        let factories = router
+            .factories
+            .iter()
+            .map(|factory| join(&factory.pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        let inverses = router
+            .inverses
+            .iter()
+            .map(|inverse| join(&inverse.pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fallbacks = router
+            .fallbacks
+            .iter()
+            .map(|fallback| {
+                let pattern = match &fallback.prefix {
+                    Some(pattern) => join_pattern(prefix, pattern.as_str(), self.separator),
+                    None => prefix.to_string(),
+                };
+                if pattern.is_empty() {
+                    Ok(None)
+                } else {
+                    self.pattern_with_converters(&pattern, &converters)
+                        .map(Some)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let redirects = router
+            .redirects
+            .iter()
+            .map(|redirect| Ok((join(&redirect.from)?, join(&redirect.to)?)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let offset = self.routes.len();
+        for (name, index) in router.names {
+            self.names.insert(name, offset + index);
+        }
+        for (pattern, route) in routes.into_iter().zip(router.routes) {
+            self.insert_route(
+                pattern,
+                route.value,
+                route.priority,
+                route.metadata,
+                route.guard,
+            );
+            self.routes.last_mut().expect("a route").token = route.token;
+        }
+        for (pattern, factory) in factories.into_iter().zip(router.factories) {
+            self.factories.push(Factory {
+                pattern,
+                factory: factory.factory,
+            });
+        }
+        for (pattern, inverse) in inverses.into_iter().zip(router.inverses) {
+            self.inverses.push(Inverse {
+                pattern,
+                inverse: inverse.inverse,
+            });
+        }
+        for (prefix, fallback) in fallbacks.into_iter().zip(router.fallbacks) {
+            if prefix.is_none() {
+                self.fallbacks.retain(|fallback| fallback.prefix.is_some());
+            }
+            self.fallbacks.push(Fallback {
+                prefix,
+                value: fallback.value,
+            });
+        }
+        for ((from, to), redirect) in redirects.into_iter().zip(router.redirects) {
+            self.redirects.push(Redirect {
+                from,
+                to,
+                ..redirect
+            });
+        }
+        Ok(())
+    }
+
+    /// This router with the routes, factories, inverses, fallbacks and
+    /// redirects of another added after its own, as if it was mounted
+    /// without a prefix.
+    pub fn or(mut self, other: Router<T>) -> Result<Router<T>, ParseError> {
+        self.mount("", other)?;
+        Ok(self)
+    }
+
+    /// This router with its routes, factories, inverses, fallbacks and
+    /// redirects moved under a prefix, as if they were mounted there. The
+    /// options stay.
+    pub fn with_prefix(mut self, prefix: &str) -> Result<Router<T>, ParseError> {
+        let routes = Router {
+            routes: mem::take(&mut self.routes),
+            trie: mem::take(&mut self.trie),
+            factories: mem::take(&mut self.factories),
+            inverses: mem::take(&mut self.inverses),
+            fallbacks: mem::take(&mut self.fallbacks),
+            redirects: mem::take(&mut self.redirects),
+            names: mem::take(&mut self.names),
+            converters: self.converters.clone(),
+            ..Router::new()
+        };
+        self.mount(prefix, routes)?;
+        Ok(self)
+    }
+
+    /// A router with the routes a predicate holds for, with the same
+    /// options, fallbacks and names. Route patterns and metadata are shared
+    /// rather than parsed again. Factories and inverses are left out.
+    pub fn filtered<P>(&self, mut predicate: P) -> Router<T>
+    where
+        P: FnMut(&RouteInfo<'_, T>) -> bool,
+        T: Clone,
+    {
+        let mut router = Router {
+            routes: Vec::new(),
+            trie: Node::default(),
+            factories: Vec::new(),
+            inverses: Vec::new(),
+            fallbacks: self.fallbacks.clone(),
+            manifests: self.manifests.clone(),
+            redirects: self.redirects.clone(),
+            pattern_cache: self.pattern_cache.clone(),
+            names: Map::default(),
+            converters: self.converters.clone(),
+            separator: self.separator,
+            percent_encoding: self.percent_encoding,
+            case: self.case,
+            normalize: self.normalize,
+            check_characters: self.check_characters,
+            encoded_separator: self.encoded_separator,
+            double_encoding: self.double_encoding,
+            automatic_names: self.automatic_names,
+            tie_break: self.tie_break,
+            strict: self.strict,
+            trailing_slash: self.trailing_slash,
+        };
+        for (index, (route, info)) in self.routes.iter().zip(self.iter()).enumerate() {
+            if !predicate(&info) {
+                continue;
+            }
+            for (name, _) in self.names.iter().filter(|(_, named)| **named == index) {
+                router.names.insert(name.clone(), router.routes.len());
+            }
+            router.insert_route(
+                route.pattern.clone(),
+                route.value.clone(),
+                route.priority,
+                route.metadata.clone(),
+                route.guard.clone(),
+            );
+            if let Some(last) = router.routes.last_mut() {
+                last.token.clone_from(&route.token);
+            }
+        }
+        router
+    }
+
+    /// This router with `f` applied to the values of its routes, factories
+    /// and fallbacks. Inverses are left out, as they locate values of the
+    /// old type.
+    pub fn map_values<U, F>(self, f: F) -> Router<U>
+    where
+        F: Fn(T) -> U + Send + Sync + 'static,
+        T: 'static,
+    {
+        let f = Arc::new(f);
+        Router {
+            routes: self
+                .routes
+                .into_iter()
+                .map(|route| Route {
+                    pattern: route.pattern,
+                    value: f(route.value),
+                    priority: route.priority,
+                    specificity: route.specificity,
+                    metadata: route.metadata,
+                    guard: route.guard,
+                    token: route.token,
+                })
+                .collect(),
+            trie: self.trie,
+            factories: self
+                .factories
+                .into_iter()
+                .map(|factory| {
+                    let (f, factory_fn) = (f.clone(), factory.factory);
+                    Factory {
+                        pattern: factory.pattern,
+                        factory: Box::new(move |m: &Match| factory_fn(m).map(|value| f(value))),
+                    }
+                })
+                .collect(),
+            inverses: Vec::new(),
+            fallbacks: self
+                .fallbacks
+                .into_iter()
+                .map(|fallback| Fallback {
+                    prefix: fallback.prefix,
+                    value: f(fallback.value),
+                })
+                .collect(),
+            manifests: self.manifests,
+            redirects: self.redirects,
+            pattern_cache: self.pattern_cache,
+            names: self.names,
+            converters: self.converters,
+            separator: self.separator,
+            percent_encoding: self.percent_encoding,
+            case: self.case,
+            normalize: self.normalize,
+            check_characters: self.check_characters,
+            encoded_separator: self.encoded_separator,
+            double_encoding: self.double_encoding,
+            automatic_names: self.automatic_names,
+            tie_break: self.tie_break,
+            strict: self.strict,
+            trailing_slash: self.trailing_slash,
+        }
+    }
+
+    /// Compare the routes of this router to those of a newer one.
+    ///
+    /// Removed and changed routes come in the order they were registered
+    /// in this router, followed by added routes in the order they were
+    /// registered in the other.
+    pub fn diff(&self, other: &Router<T>) -> Vec<RouteChange>
+    where
+        T: PartialEq,
+    {
+        let mine = self.route_keys();
+        let theirs = other.route_keys();
+        let mut unmatched: Vec<usize> = (0..other.routes.len()).collect();
+        let mut changes = Vec::new();
+        for (index, key) in mine.iter().enumerate() {
+            let route = key.to_string();
+            let position = match unmatched.iter().position(|&i| theirs[i] == *key) {
+                Some(position) => position,
+                None => {
+                    changes.push(RouteChange::Removed { route });
+                    continue;
+                }
+            };
+            let (old, new) = (
+                &self.routes[index],
+                &other.routes[unmatched.remove(position)],
+            );
+            if old.pattern.as_str() != new.pattern.as_str()
+                || old.priority != new.priority
+                || old.value != new.value
+            {
+                changes.push(RouteChange::Changed { route });
+            }
+        }
+        changes.extend(unmatched.into_iter().map(|i| RouteChange::Added {
+            route: theirs[i].to_string(),
+        }));
+        changes
+    }
+
+    /// The name of each route, or its pattern if it has none.
+    fn route_keys(&self) -> Vec<&str> {
+        self.routes
+            .iter()
+            .zip(self.route_names())
+            .map(|(route, name)| name.unwrap_or(route.pattern.as_str()))
+            .collect()
+    }
+
+    /// The key to sort a route by, smallest first.
+    fn precedence(&self, index: usize) -> (Reverse<i32>, Option<&Specificity>, usize) {
+        let route = &self.routes[index];
+        let specificity = match self.tie_break {
+            TieBreak::MostSpecific => Some(&route.specificity),
+            TieBreak::FirstRegistered => None,
+        };
+        (Reverse(route.priority), specificity, index)
+    }
+
+    /// Resolve a path to the registered value and the captured variables.
+    ///
+    /// The path can be a request target with a query, which is matched
+    /// against patterns that describe query parameters.
+    ///
+    /// A path that no route matches resolves to nothing, even if there is a
+    /// fallback for it, and so does a path that would redirect with
+    /// `TrailingSlash::Redirect`. Use `lookup` to get the fallback or the
+    /// path to redirect to.
+    pub fn resolve<'a>(&'a self, path: &'a str) -> Option<(&'a T, Match<'a>)> {
+        resolved(self.lookup(path))
+    }
+
+    /// Resolve a path like `resolve`, passing a context to the guards of
+    /// routes registered with `register_with_guard`.
+    pub fn resolve_with<'a, C: Any>(
+        &'a self,
+        path: &'a str,
+        context: &C,
+    ) -> Option<(&'a T, Match<'a>)> {
+        resolved(self.lookup_with(path, context))
+    }
+
+    /// Why no route matches a path, if that is because a converter rejected
+    /// the value of a variable: `Pattern::conversion_failure` of the first
+    /// registered route that has one. `None` if a route matches the path.
+    pub fn conversion_failure(&self, path: &str) -> Option<ConversionError> {
+        let path = self.prepared(path).ok()?;
+        if !self.matches(&path, true, None, &shown).is_empty() {
+            return None;
+        }
+        self.routes
+            .iter()
+            .find_map(|route| route.pattern.conversion_failure(&path))
+    }
+
+    /// Look up a path like `resolve`, taking the trailing slash policy
+    /// into account.
+    pub fn lookup<'a>(&'a self, path: &'a str) -> Resolution<'a, T> {
+        self.lookup_in(path, None, &shown)
+    }
+
+    /// Look up a path like `lookup`, passing a context to the guards of
+    /// routes.
+    pub fn lookup_with<'a, C: Any>(&'a self, path: &'a str, context: &C) -> Resolution<'a, T> {
+        self.lookup_in(path, Some(context), &shown)
+    }
+
+    /// Look up a path like `lookup`, including the routes registered with
+    /// a token. If one of them wins, its token is consumed by `validator`,
+    /// and if it isn't valid the path is rejected as
+    /// `InvalidPath::InvalidToken`, rather than being looked up further.
+    /// Routes with a guard are skipped, as with `lookup`.
+    pub async fn lookup_with_token<'a, V: TokenValidator>(
+        &'a self,
+        path: &'a str,
+        validator: &V,
+    ) -> Resolution<'a, T> {
+        let resolution = self.lookup_in(path, Some(&TokenCheck), &shown);
+        let token = match &resolution {
+            Resolution::Found(_, m) => m
+                .route()
+                .and_then(|index| self.routes[index].token.as_deref())
+                .map(|token| m.get(token).unwrap_or_default().to_string()),
+            _ => None,
+        };
+        match token {
+            Some(token) if !validator.consume(&token).await => {
+                Resolution::Rejected(InvalidPath::InvalidToken)
+            }
+            _ => resolution,
+        }
+    }
+
+    /// Look up a path with the routes whose metadata `shown` holds for.
+    pub(crate) fn lookup_in<'a>(
+        &'a self,
+        path: &'a str,
+        context: Option<&dyn Any>,
+        shown: &dyn Fn(&Metadata) -> bool,
+    ) -> Resolution<'a, T> {
+        let path = match self.prepared(path) {
+            Ok(path) => path,
+            Err(resolution) => return resolution,
+        };
+        if let Some((value, m)) = self.matches(&path, true, context, shown).pop() {
+            return Resolution::Found(value, m);
+        }
+        if let Some(target) = self
+            .redirects
+            .iter()
+            .find_map(|redirect| redirect.target(&path))
+        {
+            let leading = path.starts_with(self.separator);
+            return Resolution::RedirectTo(if leading {
+                format!("{}{}", self.separator, target)
+            } else {
+                target
+            });
+        }
+        if self.trailing_slash == TrailingSlash::Strict {
+            return self.fallback(path);
+        }
+        let toggled = match pattern::toggle_trailing_slash(&path, self.separator) {
+            Some(toggled) => Cow::Owned(toggled),
+            None => return self.fallback(path),
+        };
+        match self.matches(&toggled, true, context, shown).pop() {
+            None => self.fallback(path),
+            Some(_) if self.trailing_slash == TrailingSlash::Redirect => {
+                Resolution::RedirectTo(toggled.into_owned())
+            }
+            Some((value, m)) => Resolution::Found(value, m),
+        }
+    }
+
+    /// Resolve a path to every route that matches it, in order of
+    /// precedence. The first is the one `resolve` returns.
+    ///
+    /// With `TrailingSlash::Equivalent` the routes that match with the
+    /// trailing slash added or removed follow. Routes with a guard are left
+    /// out, as with `resolve`.
+    pub fn match_all<'a>(&'a self, path: &'a str) -> Vec<(&'a T, Match<'a>)> {
+        self.match_all_in(path, None, &shown)
+    }
+
+    /// Resolve a path to every route that matches it like `match_all`,
+    /// passing a context to the guards of routes. The routes whose guard
+    /// doesn't pass are left out.
+    pub fn match_all_with<'a, C: Any>(
+        &'a self,
+        path: &'a str,
+        context: &C,
+    ) -> Vec<(&'a T, Match<'a>)> {
+        self.match_all_in(path, Some(context), &shown)
+    }
+
+    pub(crate) fn match_all_in<'a>(
+        &'a self,
+        path: &'a str,
+        context: Option<&dyn Any>,
+        shown: &dyn Fn(&Metadata) -> bool,
+    ) -> Vec<(&'a T, Match<'a>)> {
+        let path = match self.prepared(path) {
+            Ok(path) => path,
+            Err(_) => return Vec::new(),
+        };
+        let mut matches = self.matches(&path, false, context, shown);
+        if self.trailing_slash == TrailingSlash::Equivalent {
+            if let Some(toggled) = pattern::toggle_trailing_slash(&path, self.separator) {
+                matches.extend(self.matches(&Cow::Owned(toggled), false, context, shown));
+            }
+        }
+        matches
+    }
+
+    /// Resolve the start of a path to the route whose pattern consumes the
+    /// most of it, return its value, the captured variables and the rest
+    /// of the path after the matched steps.
+    ///
+    /// Of the routes that consume as much, the one that takes precedence
+    /// wins. Like with `Pattern::match_prefix` optional steps are consumed
+    /// if they match, and a query is matched but isn't part of the rest.
+    /// Unlike `resolve` this tries every route in turn.
+    pub fn resolve_prefix<'a>(&'a self, path: &'a str) -> Option<(&'a T, Match<'a>, Cow<'a, str>)> {
+        match self.prepared(path).ok()? {
+            Cow::Borrowed(path) => {
+                let (index, m, rest) = self.longest_prefix(path)?;
+                let route = &self.routes[index];
+                Some((
+                    &route.value,
+                    m.with_route(index, &route.metadata),
+                    Cow::Borrowed(rest),
+                ))
+            }
+            Cow::Owned(path) => {
+                let (index, m, rest) = self.longest_prefix(&path)?;
+                let route = &self.routes[index];
+                Some((
+                    &route.value,
+                    route
+                        .pattern
+                        .owned_match(&m)
+                        .with_route(index, &route.metadata),
+                    Cow::Owned(rest.to_string()),
+                ))
+            }
+        }
+    }
+
+    /// The index of the route whose pattern consumes the most of the
+    /// start of a path, its match and the rest of the path.
+    fn longest_prefix<'a, 'p>(&'a self, path: &'p str) -> Option<(usize, Match<'p>, &'p str)>
+    where
+        'a: 'p,
+    {
+        let mut order: Vec<usize> = (0..self.routes.len()).collect();
+        order.sort_by_key(|&index| self.precedence(index));
+        let mut longest: Option<(usize, Match, &str)> = None;
+        for index in order {
+            let route = &self.routes[index];
+            if let Some((m, rest)) = route
+                .pattern
+                .match_prefix(path)
+                .filter(|(m, _)| route.passes(m, None))
+            {
+                if longest
+                    .as_ref()
+                    .is_none_or(|(_, _, longest)| rest.len() < longest.len())
+                {
+                    longest = Some((index, m, rest));
+                }
+            }
+        }
+        longest
+    }
+
+    /// The fallback for a path that no route matches.
+    fn fallback<'a>(&'a self, path: Cow<'a, str>) -> Resolution<'a, T> {
+        match path {
+            Cow::Borrowed(path) => match self.find_fallback(path) {
+                Some((fallback, m, rest)) => {
+                    Resolution::Fallback(&fallback.value, m, Cow::Borrowed(rest))
+                }
+                None => Resolution::NotFound,
+            },
+            Cow::Owned(path) => match self.find_fallback(&path) {
+                Some((fallback, m, rest)) => {
+                    let m = match &fallback.prefix {
+                        Some(prefix) => prefix.owned_match(&m),
+                        None => Match::default(),
+                    };
+                    Resolution::Fallback(&fallback.value, m, Cow::Owned(rest.to_string()))
+                }
+                None => Resolution::NotFound,
+            },
+        }
+    }
+
+    /// The fallback with the deepest prefix that a path starts with, the
+    /// variables in that prefix and the rest of the path. Among prefixes of
+    /// the same depth the first set wins.
+    fn find_fallback<'a, 'p>(
+        &'a self,
+        path: &'p str,
+    ) -> Option<(&'a Fallback<T>, Match<'p>, &'p str)>
+    where
+        'a: 'p,
+    {
+        let mut fallbacks: Vec<&Fallback<T>> = self.fallbacks.iter().collect();
+        // stable sort, so the order they were set in is kept
+        fallbacks.sort_by_key(|fallback| {
+            Reverse(fallback.prefix.as_ref().map_or(0, |prefix| prefix.depth()))
+        });
+        fallbacks
+            .into_iter()
+            .find_map(|fallback| match &fallback.prefix {
+                Some(prefix) => {
+                    let (m, rest) = prefix.match_prefix(path)?;
+                    Some((fallback, m, rest))
+                }
+                None => {
+                    let path = pattern::split_query(path).0;
+                    let rest = path.strip_prefix(self.separator).unwrap_or(path);
+                    Some((fallback, Match::default(), rest))
+                }
+            })
+    }
+
+    /// The path checked and normalized as the options say, or the
+    /// resolution for it if it gets rejected or escapes the root.
+    fn prepared<'a, 'r>(&self, path: &'a str) -> Result<Cow<'a, str>, Resolution<'r, T>> {
+        let path = hygiene::undouble(path, self.double_encoding).map_err(Resolution::Rejected)?;
+        // after undoubling, so that a double-encoded `%2500` is caught too
+        if self.check_characters {
+            hygiene::check_characters(&path).map_err(Resolution::Rejected)?;
+        }
+        let path = match self.encoded_separator {
+            EncodedSeparator::Data => path,
+            EncodedSeparator::Separator => match path {
+                Cow::Borrowed(path) => hygiene::decode_separators(path, self.separator),
+                Cow::Owned(path) => {
+                    Cow::Owned(hygiene::decode_separators(&path, self.separator).into_owned())
+                }
+            },
+            EncodedSeparator::Reject => {
+                match hygiene::find_encoded_separator(&path, self.separator) {
+                    Some(offset) => {
+                        return Err(Resolution::Rejected(InvalidPath::EncodedSeparator {
+                            offset,
+                        }))
+                    }
+                    None => path,
+                }
+            }
+        };
+        if !self.normalize {
+            return Ok(path);
+        }
+        match path {
+            Cow::Borrowed(path) => normalize::normalize_with_separator(path, self.separator),
+            Cow::Owned(path) => normalize::normalize_with_separator(&path, self.separator)
+                .map(|normalized| Cow::Owned(normalized.into_owned())),
+        }
+        .ok_or(Resolution::NotFound)
+    }
+
+    /// The routes that match a path in order of precedence, or just the
+    /// first, leaving out those with a guard that doesn't pass and those
+    /// not shown. Matches only borrow from a borrowed path.
+    fn matches<'a>(
+        &'a self,
+        path: &Cow<'a, str>,
+        first: bool,
+        context: Option<&dyn Any>,
+        shown: &dyn Fn(&Metadata) -> bool,
+    ) -> Vec<(&'a T, Match<'a>)> {
+        let mut matches = Vec::new();
+        for index in self.candidates(path).unwrap_or_default() {
+            let route = &self.routes[index];
+            if !shown(&route.metadata) {
+                continue;
+            }
+            let m = match path {
+                Cow::Borrowed(path) => route.pattern.match_path(path),
+                Cow::Owned(path) => route.pattern.match_path_owned(path),
+            };
+            if let Some(m) = m.filter(|m| route.passes(m, context)) {
+                matches.push((&route.value, m.with_route(index, &route.metadata)));
+                if first {
+                    break;
+                }
+            }
+        }
+        matches
+    }
+
+    /// Resolve a path that is already split into segments, like `resolve`.
+    ///
+    /// The segments are matched as they are: they aren't normalized or
+    /// undoubled, and the trailing slash policy doesn't apply. They are
+    /// still checked, so a segment the character check or a double
+    /// encoding policy rejects resolves to nothing.
+    pub fn resolve_segments<'a>(&'a self, segments: &'a [&'a str]) -> Option<(&'a T, Match<'a>)> {
+        if segments.iter().any(|segment| self.rejected(segment)) {
+            return None;
+        }
+        self.segment_candidates(segments.iter().copied())?
+            .into_iter()
+            .find_map(|index| {
+                let route = &self.routes[index];
+                route
+                    .pattern
+                    .match_segments(segments)
+                    .filter(|m| route.passes(m, None))
+                    .map(|m| (&route.value, m.with_route(index, &route.metadata)))
+            })
+    }
+
+    /// Resolve a path given as bytes, which need not be UTF-8, like
+    /// `Pattern::match_bytes` does.
+    ///
+    /// As paths aren't decoded, the path isn't percent-decoded either, and
+    /// it isn't normalized or undoubled. It is still checked like
+    /// `resolve_segments` checks segments.
+    pub fn resolve_bytes<'a>(&'a self, path: &'a [u8]) -> Option<(&'a T, BytesMatch<'a>)> {
+        if self.rejected(&String::from_utf8_lossy(path)) {
+            return None;
+        }
+        let (path_bytes, _) = pattern::split_query_bytes(path);
+        // a segment that isn't UTF-8 can only match a variable
+        let segments: Vec<&str> = pattern::split_path_bytes(path_bytes, self.separator)
+            .into_iter()
+            .map(|segment| std::str::from_utf8(segment).unwrap_or("\u{fffd}"))
+            .collect();
+        let mut candidates = self.trie.candidates(&segments);
+        candidates.sort_by_key(|&index| self.precedence(index));
+        candidates.into_iter().find_map(|index| {
+            let route = &self.routes[index];
+            if route.guard.is_some() || route.token.is_some() {
+                return None;
+            }
+            route.pattern.match_bytes(path).map(|m| (&route.value, m))
+        })
+    }
+
+    /// Whether the checks of `prepared` reject a path, for the ways of
+    /// resolving that match paths as they are.
+    fn rejected(&self, path: &str) -> bool {
+        let path = match hygiene::undouble(path, self.double_encoding) {
+            Ok(path) => path,
+            Err(_) => return true,
+        };
+        (self.check_characters && hygiene::check_characters(&path).is_err())
+            || (self.encoded_separator == EncodedSeparator::Reject
+                && hygiene::find_encoded_separator(&path, self.separator).is_some())
+    }
+
+    /// The routes that may match a path, in order of precedence, or `None`
+    /// if the path can't be decoded.
+    fn candidates(&self, path: &str) -> Option<Vec<usize>> {
+        self.segment_candidates(pattern::split_path(
+            pattern::split_query(path).0,
+            self.separator,
+        ))
+    }
+
+    fn segment_candidates<'s>(
+        &self,
+        segments: impl Iterator<Item = &'s str>,
+    ) -> Option<Vec<usize>> {
+        let segments = segments
+            .map(|segment| {
+                if self.percent_encoding {
+                    encoding::percent_decode(segment)
+                } else {
+                    Some(segment.into())
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let segments: Vec<&str> = segments.iter().map(|segment| segment.as_ref()).collect();
+        let mut candidates = self.trie.candidates(&segments);
+        candidates.sort_by_key(|&index| self.precedence(index));
+        Some(candidates)
+    }
+
+    /// Check the registered patterns for routes that can never match.
+    ///
+    /// Every route is compared to the routes that take precedence over it;
+    /// the first one that it duplicates or is shadowed by is reported.
+    /// Conflicts are reported in order of precedence.
+    pub fn check(&self) -> Vec<Conflict> {
+        self.conflicts()
+            .into_iter()
+            .map(|(_, conflict)| conflict)
+            .collect()
+    }
+
+    /// The conflicts `check` reports, with the index of the route that
+    /// can't match.
+    pub(crate) fn conflicts(&self) -> Vec<(usize, Conflict)> {
+        let mut order: Vec<usize> = (0..self.routes.len()).collect();
+        order.sort_by_key(|&index| self.precedence(index));
+        let mut conflicts = Vec::new();
+        for (i, &index) in order.iter().enumerate() {
+            let route = &self.routes[index];
+            let pattern = route.pattern.as_str().to_string();
+            for &earlier in &order[..i] {
+                // a route with a guard can let paths through to later ones
+                if self.routes[earlier].guard.is_some() {
+                    continue;
+                }
+                let earlier = &self.routes[earlier].pattern;
+                if earlier.same_shape(&route.pattern) {
+                    conflicts.push((
+                        index,
+                        Conflict::Duplicate {
+                            pattern,
+                            earlier: earlier.as_str().to_string(),
+                        },
+                    ));
+                    break;
+                }
+                if earlier.generalizes(&route.pattern) {
+                    conflicts.push((
+                        index,
+                        Conflict::Shadowed {
+                            pattern,
+                            earlier: earlier.as_str().to_string(),
+                        },
+                    ));
+                    break;
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// The first conflict between a route and the others, either way.
+    fn conflict_with(&self, index: usize) -> Option<Conflict> {
+        let precedence = self.precedence(index);
+        (0..self.routes.len())
+            .filter(|&other| other != index)
+            .find_map(|other| {
+                let (earlier, later) = if self.precedence(other) < precedence {
+                    (other, index)
+                } else {
+                    (index, other)
+                };
+                if self.routes[earlier].guard.is_some() {
+                    return None;
+                }
+                let pattern = self.routes[later].pattern.as_str().to_string();
+                let earlier = &self.routes[earlier].pattern;
+                let later = &self.routes[later].pattern;
+                if earlier.same_shape(later) {
+                    Some(Conflict::Duplicate {
+                        pattern,
+                        earlier: earlier.as_str().to_string(),
+                    })
+                } else if earlier.generalizes(later) {
+                    Some(Conflict::Shadowed {
+                        pattern,
+                        earlier: earlier.as_str().to_string(),
+                    })
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Register a factory for a pattern.
+    ///
+    /// The factory is called with the matched variables and can return
+    /// `None` if there is no object for them, for instance when an id isn't
+    /// in the database.
+    pub fn register_factory<F>(&mut self, pattern: &str, factory: F) -> Result<(), ParseError>
+    where
+        F: Fn(&Match) -> Option<T> + Send + Sync + 'static,
+    {
+        let pattern = self.pattern(pattern)?;
+        self.factories.push(Factory {
+            pattern,
+            factory: Box::new(factory),
+        });
+        Ok(())
+    }
+
+    /// Consume a path by traversal.
+    ///
+    /// The path is walked step by step to find the deepest factory pattern
+    /// that matches its start. That factory constructs the object, which is
+    /// returned together with the remainder of the path that wasn't consumed.
+    /// If the factory returns `None` shallower patterns are tried; among
+    /// patterns of the same depth the first registered wins.
+    ///
+    /// The path is checked like `resolve_segments` checks segments, but
+    /// as the remainder is part of it, it isn't decoded or normalized.
+    pub fn consume<'a>(&'a self, path: &'a str) -> Option<(T, &'a str)> {
+        if self.rejected(path) {
+            return None;
+        }
+        let mut candidates: Vec<&Factory<T>> = self.factories.iter().collect();
+        // stable sort, so registration order is kept for equal depths
+        candidates.sort_by_key(|factory| std::cmp::Reverse(factory.pattern.depth()));
+        candidates.into_iter().find_map(|factory| {
+            let (m, rest) = factory.pattern.match_prefix(path)?;
+            (factory.factory)(&m).map(|obj| (obj, rest))
+        })
+    }
+
+    /// Register an inverse for a pattern.
+    ///
+    /// The inverse is given an object and returns its parent, or `None`
+    /// for an object directly under the root, along with the variables that
+    /// identify the object under its parent. It returns `None` altogether
+    /// if the object isn't located by this pattern. The pattern describes
+    /// the full path, so its other variables come from the inverses of the
+    /// object's parents.
+    pub fn register_inverse<F>(&mut self, pattern: &str, inverse: F) -> Result<(), ParseError>
+    where
+        F: Fn(&T) -> Option<(Option<T>, Vec<(&'static str, String)>)> + Send + Sync + 'static,
+    {
+        let pattern = self.pattern(pattern)?;
+        self.inverses.push(Inverse {
+            pattern,
+            inverse: Box::new(inverse),
+        });
+        Ok(())
+    }
+
+    /// Locate an object: reconstruct the path that `consume` would turn
+    /// back into it.
+    ///
+    /// Inverses are tried in registration order; the first one that returns
+    /// variables for the object gives the pattern of its path. The
+    /// variables of its parents are collected by walking up to the root,
+    /// trying the inverses again for each parent; a variable of an object
+    /// overrides one of the same name of its parents.
+    pub fn locate(&self, obj: &T) -> Option<String> {
+        self.inverses.iter().find_map(|inverse| {
+            let (mut parent, mut variables) = (inverse.inverse)(obj)?;
+            while let Some(obj) = parent {
+                let (grandparent, parent_variables) = self
+                    .inverses
+                    .iter()
+                    .find_map(|inverse| (inverse.inverse)(&obj))?;
+                variables.extend(parent_variables);
+                parent = grandparent;
+            }
+            let mut values = HashMap::new();
+            for (name, value) in &variables {
+                values.entry(*name).or_insert(value.as_str());
+            }
+            inverse.pattern.expand(&values).ok()
+        })
+    }
+}
+
+/// Shows all routes.
+fn shown(_: &Metadata) -> bool {
+    true
+}
+
+/// The value and match of a resolution, if it found a route.
+pub(crate) fn resolved<T>(resolution: Resolution<'_, T>) -> Option<(&T, Match<'_>)> {
+    match resolution {
+        Resolution::Found(value, m) => Some((value, m)),
+        _ => None,
+    }
+}
+
+/// Join a prefix and a pattern into a single pattern string.
+fn join_pattern(prefix: &str, pattern: &str, separator: char) -> String {
+    if prefix.is_empty() || pattern.starts_with(['[', '?']) {
+        format!("{}{}", prefix, pattern)
+    } else if pattern.is_empty() {
+        prefix.to_string()
+    } else {
+        format!("{}{}{}", prefix, separator, pattern)
+    }
+}
+
+impl<T> Default for Router<T> {
+    fn default() -> Self {
+        Router::new()
+    }
+}
+
+impl<T> Router<T> {
+    /// Freeze the router, so it can be shared.
+    pub fn build(self) -> SharedRouter<T> {
+        SharedRouter {
+            router: Arc::new(self),
+        }
+    }
+}
+
+/// A router that is done registering routes, made by `Router::build`.
+///
+/// It gives access to everything a `&Router` does. Clones share the same
+/// routes, so cloning is cheap whatever the number of routes.
+#[derive(Debug)]
+pub struct SharedRouter<T> {
+    router: Arc<Router<T>>,
+}
+
+impl<T> Clone for SharedRouter<T> {
+    fn clone(&self) -> Self {
+        SharedRouter {
+            router: Arc::clone(&self.router),
+        }
+    }
+}
+
+impl<T> Deref for SharedRouter<T> {
+    type Target = Router<T>;
+
+    fn deref(&self) -> &Router<T> {
+        &self.router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::{Converter, TranslatedConverter};
+    use crate::error::Direction;
+
+    #[test]
+    fn test_resolve() {
+        let mut router = Router::new();
+        router.register("a/{b}/c", 1).unwrap();
+        router.register("a/{b}/d", 2).unwrap();
+        let (value, m) = router.resolve("a/1/c").unwrap();
+        assert_eq!(*value, 1);
+        assert_eq!(m.get("b"), Some("1"));
+        let (value, m) = router.resolve("a/2/d").unwrap();
+        assert_eq!(*value, 2);
+        assert_eq!(m.get("b"), Some("2"));
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<Router<String>>();
+        assert_send_sync::<SharedRouter<String>>();
+    }
+
+    #[test]
+    fn test_build_shared() {
+        let mut router = Router::new();
+        router.register("a/{b}", 1).unwrap();
+        router
+            .register_factory("c/{d:int}", |m| m.get("d")?.parse().ok())
+            .unwrap();
+        let router = router.build();
+        let clone = router.clone();
+        assert!(Arc::ptr_eq(&router.router, &clone.router));
+        std::thread::scope(|scope| {
+            for i in 0..4 {
+                let router = router.clone();
+                scope.spawn(move || {
+                    let path = format!("a/{}", i);
+                    let (value, m) = router.resolve(&path).unwrap();
+                    assert_eq!((*value, m.get("b")), (1, Some(i.to_string().as_str())));
+                    assert_eq!(router.consume("c/7"), Some((7, "")));
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut old = Router::new();
+        old.register("a", 1).unwrap();
+        old.register_named("detail", "b/{x}", 2).unwrap();
+        old.register("c", 3).unwrap();
+        old.register("d", 4).unwrap();
+        let mut new = Router::new();
+        new.register("e", 5).unwrap();
+        new.register_with_priority("d", 4, 1).unwrap();
+        new.register_named("detail", "b/{x:int}", 2).unwrap();
+        new.register("a", 1).unwrap();
+        let changes: Vec<String> = old.diff(&new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "`detail` was changed",
+                "`c` was removed",
+                "`d` was changed",
+                "`e` was added"
+            ]
+        );
+        assert!(new.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn test_metadata() {
+        #[derive(Debug, PartialEq)]
+        struct Permission(&'static str);
+
+        let mut router = Router::new();
+        router
+            .register_with_metadata("a/{x}", 1, Metadata::new().with(Permission("read")))
+            .unwrap();
+        router.register_named("b", "b", 2).unwrap();
+        router
+            .metadata_mut("b")
+            .unwrap()
+            .insert(Permission("write"));
+        assert!(router.metadata_mut("c").is_none());
+        let (_, m) = router.resolve("a/1").unwrap();
+        assert_eq!(
+            m.metadata().unwrap().get::<Permission>(),
+            Some(&Permission("read"))
+        );
+        let m = router.resolve_segments(&["b"]).unwrap().1;
+        assert_eq!(
+            m.metadata().unwrap().get::<Permission>(),
+            Some(&Permission("write"))
+        );
+        assert_eq!(
+            router.resolve("a/1").unwrap().1,
+            Match::new(vec![("x", "1".into())])
+        );
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut router = Router::new();
+        router.register_with_priority("a/{x}", 1, 2).unwrap();
+        router.register_named("b", "b", 2).unwrap();
+        let routes: Vec<_> = router
+            .iter()
+            .map(|route| {
+                (
+                    route.name,
+                    route.pattern.as_str(),
+                    *route.value,
+                    route.priority,
+                )
+            })
+            .collect();
+        assert_eq!(routes, vec![(None, "a/{x}", 1, 2), (Some("b"), "b", 2, 0)]);
+        assert!(router.iter().all(|route| route.metadata.is_empty()));
+    }
+
+    #[test]
+    fn test_filtered() {
+        let mut router = Router::new().with_automatic_names(true);
+        router.register_named("user", "users/{id}", 1).unwrap();
+        router.register("posts/{id}", 2).unwrap();
+        router
+            .register_with_guard("admin", 3, |_, admin: &bool| *admin)
+            .unwrap();
+        let filtered = router.filtered(|route| *route.value != 2);
+        let names: Vec<_> = filtered.iter().map(|route| route.name).collect();
+        assert_eq!(names, vec![Some("user"), Some("admin")]);
+        assert!(filtered.resolve("posts/1").is_none());
+        assert_eq!(filtered.resolve_with("admin", &true).unwrap().0, &3);
+        assert!(filtered.resolve_with("admin", &false).is_none());
+        let values = HashMap::from([("id", "1")]);
+        assert_eq!(filtered.path_for("user", &values).unwrap(), "users/1");
+        assert!(std::ptr::eq(
+            filtered.iter().next().unwrap().pattern,
+            router.iter().next().unwrap().pattern
+        ));
+    }
+
+    #[test]
+    fn test_register_redirect() {
+        let mut router = Router::new();
+        router.register("articles/{id}", 1).unwrap();
+        router
+            .register_redirect("posts/{id}", "articles/{id}", QueryPolicy::Pass)
+            .unwrap();
+        router
+            .register_redirect("old/{id}", "articles/{id}", QueryPolicy::Drop)
+            .unwrap();
+        router
+            .register_redirect(
+                "legacy/{id}?page={page}",
+                "articles/{id}?p={page}&v=2",
+                QueryPolicy::Merge,
+            )
+            .unwrap();
+        let redirect = |path| match router.lookup(path) {
+            Resolution::RedirectTo(target) => Some(target),
+            _ => None,
+        };
+        assert_eq!(
+            redirect("posts/1?tab=a").as_deref(),
+            Some("articles/1?tab=a")
+        );
+        assert_eq!(redirect("/posts/1").as_deref(), Some("/articles/1"));
+        assert_eq!(redirect("old/1?tab=a").as_deref(), Some("articles/1"));
+        assert_eq!(
+            redirect("legacy/1?page=3&v=1&tab=a").as_deref(),
+            Some("articles/1?p=3&v=2&tab=a")
+        );
+        assert_eq!(redirect("articles/1"), None);
+        assert_eq!(redirect("other/1"), None);
+    }
+
+    #[test]
+    fn test_mount_redirect() {
+        let mut sub = Router::new();
+        sub.register("articles/{id}", 1).unwrap();
+        sub.register_redirect("posts/{id}", "articles/{id}", QueryPolicy::Drop)
+            .unwrap();
+        let mut router = Router::new();
+        router.mount("blog/{blog}", sub).unwrap();
+        assert_eq!(
+            router.lookup("blog/a/posts/1?x=1"),
+            Resolution::RedirectTo("blog/a/articles/1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_no_match() {
+        let mut router = Router::new();
+        router.register("a/{b}/c", 1).unwrap();
+        assert!(router.resolve("a/1").is_none());
+        assert!(router.resolve("x/1/c/d").is_none());
+    }
+
+    #[test]
+    fn test_resolve_first_registered_wins() {
+        let mut router = Router::new();
+        router.register("a/{b}", 1).unwrap();
+        router.register("a/{c}", 2).unwrap();
+        assert_eq!(*router.resolve("a/x").unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_resolve_many_routes() {
+        let mut router = Router::new();
+        for i in 0..100 {
+            router.register(&format!("r{}/{{x}}/e", i), i).unwrap();
+            router
+                .register(&format!("r{}/fixed/e", i), 1000 + i)
+                .unwrap();
+        }
+        router.register("{a}/{b}/{c}", 2000).unwrap();
+        assert_eq!(*router.resolve("r50/1/e").unwrap().0, 50);
+        assert_eq!(*router.resolve("r50/fixed/e").unwrap().0, 1050);
+        assert_eq!(*router.resolve("r50/1/f").unwrap().0, 2000);
+        assert!(router.resolve("r50/1").is_none());
+    }
+
+    #[test]
+    fn test_resolve_converter_falls_through() {
+        let mut router = Router::new();
+        router.register("a/{b:int}", 1).unwrap();
+        router.register("a/{b}", 2).unwrap();
+        assert_eq!(*router.resolve("a/10").unwrap().0, 1);
+        assert_eq!(*router.resolve("a/x").unwrap().0, 2);
+    }
+
+    #[test]
+    fn test_match_all() {
+        let mut router = Router::new();
+        router.register("a/{b}", 1).unwrap();
+        router.register("a/{b:int}", 2).unwrap();
+        router.register("a/{rest:*}", 3).unwrap();
+        router.register("a/10", 4).unwrap();
+        router.register("b/{c}", 5).unwrap();
+        let matches = router.match_all("a/10");
+        let values: Vec<i32> = matches.iter().map(|(value, _)| **value).collect();
+        assert_eq!(values, vec![4, 2, 1, 3]);
+        assert_eq!(matches[1].1.get("b"), Some("10"));
+        assert_eq!(matches[3].1.get("rest"), Some("10"));
+        let values: Vec<i32> = router
+            .match_all("a/x")
+            .iter()
+            .map(|(value, _)| **value)
+            .collect();
+        assert_eq!(values, vec![1, 3]);
+        assert!(router.match_all("c").is_empty());
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let mut router = Router::new().with_tie_break(TieBreak::FirstRegistered);
+        router.register("a/{b}", 1).unwrap();
+        router.register("a/x", 2).unwrap();
+        router.register_with_priority("b/{c:*}", 3, 1).unwrap();
+        router.register_with_priority("b/x", 4, 1).unwrap();
+        assert_eq!(*router.resolve("a/x").unwrap().0, 1);
+        assert_eq!(*router.resolve("b/x").unwrap().0, 3);
+        let values: Vec<i32> = router.match_all("b/x").iter().map(|(v, _)| **v).collect();
+        assert_eq!(values, vec![3, 4]);
+        assert_eq!(router.check().len(), 2);
+        let router = router.with_tie_break(TieBreak::MostSpecific);
+        assert_eq!(*router.resolve("a/x").unwrap().0, 2);
+        assert_eq!(*router.resolve("b/x").unwrap().0, 4);
+        assert!(router.check().is_empty());
+    }
+
+    #[test]
+    fn test_deterministic_order() {
+        let router = || {
+            let mut mounted = Router::new();
+            mounted.register_named("detail", "{id}", 5).unwrap();
+            mounted.register("{id}/edit", 6).unwrap();
+            let mut router = Router::new().with_automatic_names(true);
+            router.register("users/{id}", 1).unwrap();
+            router.register_named("user", "users/{name}", 2).unwrap();
+            router.register("users/{id}", 3).unwrap();
+            router.register("{x}/{y}", 4).unwrap();
+            router.mount("posts", mounted).unwrap();
+            router
+        };
+        let first = router();
+        let names: Vec<_> = first.iter().map(|route| route.name).collect();
+        assert_eq!(
+            names,
+            vec![
+                Some("users_id"),
+                Some("user"),
+                Some("users_id_2"),
+                Some("x_y"),
+                Some("detail"),
+                Some("posts_id_edit"),
+            ]
+        );
+        for _ in 0..10 {
+            let other = router();
+            let other_names: Vec<_> = other.iter().map(|route| route.name).collect();
+            assert_eq!(other_names, names);
+            assert_eq!(other.check(), first.check());
+            assert_eq!(other.match_all("users/1"), first.match_all("users/1"));
+            assert_eq!(other.to_tree(), first.to_tree());
+        }
+    }
+
+    /// Poll a future that never waits to completion.
+    fn ready<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+        match future.as_mut().poll(&mut context) {
+            std::task::Poll::Ready(output) => output,
+            std::task::Poll::Pending => panic!("future is pending"),
+        }
+    }
+
+    struct Tokens(std::sync::Mutex<Vec<&'static str>>);
+
+    impl TokenValidator for Tokens {
+        fn consume(&self, token: &str) -> impl Future<Output = bool> + Send {
+            let mut tokens = self.0.lock().unwrap();
+            let valid = match tokens.iter().position(|t| *t == token) {
+                Some(index) => {
+                    tokens.remove(index);
+                    true
+                }
+                None => false,
+            };
+            std::future::ready(valid)
+        }
+    }
+
+    #[test]
+    fn test_register_with_token() {
+        let mut router = Router::new();
+        router
+            .register_with_token("invites/{token}", 1, "token")
+            .unwrap();
+        router.register("invites/{token}/terms", 2).unwrap();
+        let tokens = Tokens(std::sync::Mutex::new(vec!["abc"]));
+        let lookup = |path| ready(router.lookup_with_token(path, &tokens));
+
+        assert_eq!(router.lookup("invites/abc"), Resolution::NotFound);
+        assert!(router.resolve_bytes(b"invites/abc").is_none());
+        match lookup("invites/abc") {
+            Resolution::Found(value, m) => {
+                assert_eq!(*value, 1);
+                assert_eq!(m.get("token"), Some("abc"));
+            }
+            resolution => panic!("unexpected {:?}", resolution),
+        }
+        assert_eq!(
+            lookup("invites/abc"),
+            Resolution::Rejected(InvalidPath::InvalidToken)
+        );
+        assert_eq!(lookup("other"), Resolution::NotFound);
+        assert!(matches!(
+            lookup("invites/xyz/terms"),
+            Resolution::Found(&2, _)
+        ));
+
+        let mut mounted = Router::new();
+        mounted.mount("app", router).unwrap();
+        assert_eq!(mounted.lookup("app/invites/abc"), Resolution::NotFound);
+
+        assert_eq!(
+            Router::new().register_with_token("invites/{id}", 1, "token"),
+            Err(ParseError::UnknownTokenVariable {
+                name: "token".to_string(),
+                offset: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        let mut router = Router::new().with_strict(true);
+        router.register("a/x", 1).unwrap();
+        router.register("a/{b}", 2).unwrap();
+        router.register("a/{b}/c", 3).unwrap();
+        router
+            .register_with_guard("b/{c}", 4, |_, _: &()| true)
+            .unwrap();
+        router.register("b/x", 5).unwrap();
+        assert!(router.check().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting route: `a/{c}` duplicates `a/{b}`")]
+    fn test_strict_duplicate() {
+        let mut router = Router::new().with_strict(true);
+        router.register("a/{b}", 1).unwrap();
+        router.register("a/{c}", 2).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting route: `a/{b:int}` is shadowed by `a/{c:*}`")]
+    fn test_strict_shadows() {
+        let mut router = Router::new().with_strict(true);
+        router.register("a/{b:int}", 1).unwrap();
+        router.register_with_priority("a/{c:*}", 2, 1).unwrap();
+    }
+
+    #[test]
+    fn test_match_all_with() {
+        let mut router = Router::new();
+        router
+            .register_with_guard("a/{b}", 1, |_, admin: &bool| *admin)
+            .unwrap();
+        router
+            .register_with_guard("a/{b}", 2, |m, _: &bool| m.get("b") == Some("x"))
+            .unwrap();
+        router.register("a/{rest:*}", 3).unwrap();
+        let values = |matches: Vec<(&i32, Match)>| -> Vec<i32> {
+            matches.iter().map(|(value, _)| **value).collect()
+        };
+        assert_eq!(values(router.match_all("a/x")), vec![3]);
+        assert_eq!(values(router.match_all_with("a/x", &true)), vec![1, 2, 3]);
+        assert_eq!(values(router.match_all_with("a/y", &false)), vec![3]);
+        assert_eq!(values(router.match_all_with("a/x", &"other")), vec![3]);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let mut router = Router::new();
+        router.register("Employees/{name}", 1).unwrap();
+        assert!(router.resolve("employees/Ann").is_none());
+        let mut router = router.with_case(Case::Insensitive);
+        router.register("about", 2).unwrap();
+        let (value, m) = router.resolve("EMPLOYEES/Ann").unwrap();
+        assert_eq!((*value, m.get("name")), (1, Some("Ann")));
+        assert_eq!(*router.resolve("About").unwrap().0, 2);
+        let values = [("name", "Ann")].iter().cloned().collect();
+        assert_eq!(
+            router.routes[0].pattern.expand(&values).unwrap(),
+            "Employees/Ann"
+        );
+    }
+
+    #[test]
+    fn test_case_lowercase() {
+        let mut router = Router::new().with_case(Case::Lowercase);
+        router.register("employees/{name}", 1).unwrap();
+        let (_, m) = router.resolve("Employees/ÅSA").unwrap();
+        assert_eq!(m.get("name"), Some("åsa"));
+    }
+
+    #[test]
+    fn test_resolve_segments() {
+        let mut router = Router::new().with_percent_encoding(true);
+        router.register("a/{b}/c", 1).unwrap();
+        router.register("a/{b}/{rest:*}", 2).unwrap();
+        let (value, m) = router.resolve_segments(&["a", "x%20y", "c"]).unwrap();
+        assert_eq!((*value, m.get("b")), (1, Some("x y")));
+        let (value, m) = router.resolve_segments(&["a", "1", "d", "e"]).unwrap();
+        assert_eq!((*value, m.get("rest")), (2, Some("d/e")));
+        assert!(router.resolve_segments(&["a", "1"]).is_none());
+        assert!(router.resolve_segments(&["a", "%zz", "c"]).is_none());
+    }
+
+    #[test]
+    fn test_separator() {
+        let mut router = Router::new()
+            .with_separator('.')
+            .with_trailing_slash(TrailingSlash::Redirect);
+        router.register("{tenant}.example.com", 1).unwrap();
+        router.register("example.com", 2).unwrap();
+        let (value, m) = router.resolve("acme.example.com").unwrap();
+        assert_eq!((*value, m.get("tenant")), (1, Some("acme")));
+        assert_eq!(*router.resolve("example.com").unwrap().0, 2);
+        assert_eq!(
+            router.lookup("example.com."),
+            Resolution::RedirectTo("example.com".to_string())
+        );
+        let mut mounted = Router::new().with_separator('.');
+        mounted.register("{room}", 3).unwrap();
+        router.mount("home.", mounted).unwrap();
+        assert_eq!(*router.resolve("home.kitchen").unwrap().0, 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_separator_after_register() {
+        let mut router = Router::new();
+        router.register("a", 1).unwrap();
+        let _ = router.with_separator('.');
+    }
+
+    #[test]
+    fn test_resolve_bytes() {
+        let mut router = Router::new();
+        router.register("files/{name}", 1).unwrap();
+        router.register("files/index", 2).unwrap();
+        router.register("files/{name}/{rest:*}", 3).unwrap();
+        let (value, m) = router.resolve_bytes(b"files/\xff").unwrap();
+        assert_eq!((*value, m.get("name")), (1, Some(&b"\xff"[..])));
+        assert_eq!(*router.resolve_bytes(b"files/index").unwrap().0, 2);
+        let (value, m) = router.resolve_bytes(b"files/a/\xfe/b").unwrap();
+        assert_eq!((*value, m.get("rest")), (3, Some(&b"\xfe/b"[..])));
+        assert!(router.resolve_bytes(b"other/\xff").is_none());
+    }
+
+    #[test]
+    fn test_normalization() {
+        let mut router = Router::new();
+        router.register("a/{b}", 1).unwrap();
+        assert!(router.resolve("a/./x").is_none());
+        let router = router.with_normalization(true);
+        let (value, m) = router.resolve("/a//c/../x?q").unwrap();
+        assert_eq!((*value, m.get("b")), (1, Some("x")));
+        assert!(router.resolve("a/x/../..").is_none());
+        assert!(router.resolve("../a/x").is_none());
+        assert_eq!(router.match_all("a/%2e/x").len(), 1);
+    }
+
+    #[test]
+    fn test_character_check() {
+        let mut router = Router::new();
+        router.register("a/{b}", 1).unwrap();
+        assert!(router.resolve("a/x y").is_some());
+        let router = router.with_character_check(true);
+        assert_eq!(
+            router.lookup("a/x y"),
+            Resolution::Rejected(InvalidPath::ForbiddenCharacter {
+                character: ' ',
+                offset: 3
+            })
+        );
+        assert!(router.resolve("a/x%00").is_none());
+        assert!(router.match_all("a/x\t").is_empty());
+        assert!(router.resolve("a/x%20y").is_some());
+    }
+
+    #[test]
+    fn test_character_check_segments_bytes() {
+        let mut router = Router::new()
+            .with_character_check(true)
+            .with_double_encoding(DoubleEncoding::Decode(1));
+        router.register("a/{b}", 1).unwrap();
+        router
+            .register_factory("a/{b}", |m| m.get("b").map(|_| 2))
+            .unwrap();
+        assert!(router.resolve_segments(&["a", "x"]).is_some());
+        assert!(router.resolve_segments(&["a", "x y"]).is_none());
+        assert!(router.resolve_segments(&["a", "x%2500"]).is_none());
+        assert!(router.resolve_bytes(b"a/x").is_some());
+        assert!(router.resolve_bytes(b"a/x\x00").is_none());
+        assert!(router.resolve_bytes(b"a/\xffx%00").is_none());
+        assert_eq!(router.consume("a/x/rest"), Some((2, "rest")));
+        assert_eq!(router.consume("a/x\t/rest"), None);
+    }
+
+    #[test]
+    fn test_encoded_separator_data() {
+        let mut router = Router::new().with_percent_encoding(true);
+        router.register_named("segment", "a/{b}", 1).unwrap();
+        router.register_named("rest", "c/{d:*}", 2).unwrap();
+        assert_eq!(router.resolve("a/x%2Fy").unwrap().1.get("b"), Some("x/y"));
+        assert_eq!(
+            router.resolve("c/x%2Fy/z").unwrap().1.get("d"),
+            Some("x/y/z")
+        );
+        let values = HashMap::from([("b", "x/y")]);
+        assert_eq!(router.path_for("segment", &values).unwrap(), "a/x%2Fy");
+    }
+
+    #[test]
+    fn test_encoded_separator_separator() {
+        let mut router = Router::new()
+            .with_percent_encoding(true)
+            .with_normalization(true)
+            .with_encoded_separator(EncodedSeparator::Separator);
+        router.register_named("segment", "a/{b}", 1).unwrap();
+        router.register_named("rest", "c/{d:*}", 2).unwrap();
+        assert!(router.resolve("a/x%2Fy").is_none());
+        // segments are already split, so the separator stays data
+        assert!(router.resolve_segments(&["a", "x%2Fy"]).is_some());
+        assert_eq!(
+            router.resolve("c/x%2fy/z").unwrap().1.get("d"),
+            Some("x/y/z")
+        );
+        assert_eq!(*router.resolve("c/x%2F..%2F..%2Fa/y").unwrap().0, 1);
+        assert_eq!(router.resolve("a/y?q=%2F").unwrap().1.get("b"), Some("y"));
+        let values = HashMap::from([("b", "x/y")]);
+        assert_eq!(
+            router.path_for("segment", &values),
+            Err(ExpandError::InvalidValue {
+                name: "b".to_string(),
+                value: "x/y".to_string()
+            })
+        );
+        let values = HashMap::from([("d", "x/y")]);
+        assert_eq!(router.path_for("rest", &values).unwrap(), "c/x/y");
+    }
+
+    #[test]
+    fn test_encoded_separator_reject() {
+        let mut router = Router::new()
+            .with_percent_encoding(true)
+            .with_encoded_separator(EncodedSeparator::Reject);
+        router.register_named("segment", "a/{b}", 1).unwrap();
+        assert_eq!(
+            router.lookup("a/x%2fy"),
+            Resolution::Rejected(InvalidPath::EncodedSeparator { offset: 3 })
+        );
+        assert!(router.resolve("a/x%2Ey").is_some());
+        let values = HashMap::from([("b", "x/y")]);
+        assert!(router.path_for("segment", &values).is_err());
+        assert!(router.resolve_segments(&["a", "x%2fy"]).is_none());
+        assert!(router.resolve_segments(&["a", "x%2Ey"]).is_some());
+        assert!(router.resolve_bytes(b"a/x%2Fy").is_none());
+    }
+
+    #[test]
+    fn test_double_encoding() {
+        let mut router = Router::new()
+            .with_percent_encoding(true)
+            .with_double_encoding(DoubleEncoding::Reject);
+        router.register("a/{b}", 1).unwrap();
+        assert_eq!(
+            router.lookup("a/x%2520y"),
+            Resolution::Rejected(InvalidPath::DoubleEncoded { offset: 3 })
+        );
+        assert_eq!(router.resolve("a/100%25").unwrap().1.get("b"), Some("100%"));
+        let router = router
+            .with_double_encoding(DoubleEncoding::Decode(1))
+            .with_encoded_separator(EncodedSeparator::Reject);
+        assert_eq!(router.resolve("a/x%2520y").unwrap().1.get("b"), Some("x y"));
+        assert_eq!(
+            router.lookup("a/x%252Fy"),
+            Resolution::Rejected(InvalidPath::EncodedSeparator { offset: 3 })
+        );
+    }
+
+    #[test]
+    fn test_double_encoding_character_check() {
+        let mut router = Router::new()
+            .with_percent_encoding(true)
+            .with_character_check(true)
+            .with_double_encoding(DoubleEncoding::Decode(1));
+        router.register("a/{b}", 1).unwrap();
+        assert!(matches!(
+            router.lookup("a/x%2500"),
+            Resolution::Rejected(InvalidPath::ForbiddenCharacter {
+                character: '\0',
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_normalization_redirect() {
+        let mut router = Router::new()
+            .with_normalization(true)
+            .with_trailing_slash(TrailingSlash::Redirect);
+        router.register("a/{b}/", 1).unwrap();
+        assert_eq!(
+            router.lookup("a/./x"),
+            Resolution::RedirectTo("a/x/".to_string())
+        );
+    }
+
+    #[test]
+    fn test_trailing_slash_strict() {
+        let mut router = Router::new();
+        router.register("a/{b}", 1).unwrap();
+        router.register("c/", 2).unwrap();
+        assert_eq!(*router.resolve("a/x").unwrap().0, 1);
+        assert!(router.resolve("a/x/").is_none());
+        assert_eq!(router.lookup("c"), Resolution::NotFound);
+    }
+
+    #[test]
+    fn test_trailing_slash_equivalent() {
+        let mut router = Router::new().with_trailing_slash(TrailingSlash::Equivalent);
+        router.register("a/{b}", 1).unwrap();
+        router.register("c/{d}/", 2).unwrap();
+        router.register("e", 3).unwrap();
+        router.register("e/", 4).unwrap();
+        let (value, m) = router.resolve("a/x/").unwrap();
+        assert_eq!((*value, m.get("b")), (1, Some("x")));
+        let (value, m) = router.resolve("c/y?q=1").unwrap();
+        assert_eq!((*value, m.get("d")), (2, Some("y")));
+        // an exact match wins
+        assert_eq!(*router.resolve("e/").unwrap().0, 4);
+        let values: Vec<i32> = router
+            .match_all("e")
+            .iter()
+            .map(|(value, _)| **value)
+            .collect();
+        assert_eq!(values, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_trailing_slash_redirect() {
+        let mut router = Router::new().with_trailing_slash(TrailingSlash::Redirect);
+        router.register("a/{b}", 1).unwrap();
+        router.register("c/", 2).unwrap();
+        assert_eq!(
+            router.lookup("a/x/?q=1"),
+            Resolution::RedirectTo("a/x?q=1".to_string())
+        );
+        assert_eq!(
+            router.lookup("/c"),
+            Resolution::RedirectTo("/c/".to_string())
+        );
+        assert!(router.resolve("c").is_none());
+        assert_eq!(router.lookup("d/"), Resolution::NotFound);
+        match router.lookup("a/x") {
+            Resolution::Found(value, m) => assert_eq!((*value, m.get("b")), (1, Some("x"))),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resolve_wildcard() {
+        let mut router = Router::new();
+        router.register("static/favicon.ico", 1).unwrap();
+        router.register("static/{path:*}", 2).unwrap();
+        assert_eq!(*router.resolve("static/favicon.ico").unwrap().0, 1);
+        let (value, m) = router.resolve("static/css/site.css").unwrap();
+        assert_eq!(*value, 2);
+        assert_eq!(m.get("path"), Some("css/site.css"));
+    }
+
+    #[test]
+    fn test_resolve_percent_encoding() {
+        let mut router = Router::new();
+        router.register("hello world/{name}", 1).unwrap();
+        let mut router = router.with_percent_encoding(true);
+        router.register("b/{name}", 2).unwrap();
+        let (value, m) = router.resolve("hello%20world/caf%C3%A9").unwrap();
+        assert_eq!(*value, 1);
+        assert_eq!(m.get("name"), Some("café"));
+        let (value, m) = router.resolve("b/x%2Fy").unwrap();
+        assert_eq!(*value, 2);
+        assert_eq!(m.get("name"), Some("x/y"));
+        assert!(router.resolve("b/%zz").is_none());
+    }
+
+    #[test]
+    fn test_resolve_literal_beats_variable() {
+        let mut router = Router::new();
+        router.register("a/{x}", 1).unwrap();
+        router.register("a/b", 2).unwrap();
+        assert_eq!(*router.resolve("a/b").unwrap().0, 2);
+        assert_eq!(*router.resolve("a/c").unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_resolve_longer_literal_prefix_wins() {
+        let mut router = Router::new();
+        router.register("{x}/b/c", 1).unwrap();
+        router.register("a/{y}/c", 2).unwrap();
+        router.register("a/b/{z}", 3).unwrap();
+        assert_eq!(*router.resolve("a/b/c").unwrap().0, 3);
+        assert_eq!(*router.resolve("a/x/c").unwrap().0, 2);
+        assert_eq!(*router.resolve("x/b/c").unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_resolve_specificity_of_variables() {
+        let mut router = Router::new();
+        router.register("a/{rest:*}", 1).unwrap();
+        router.register("a/{x}", 2).unwrap();
+        router.register("a/{x:int}", 3).unwrap();
+        router.register("a/v{x}", 4).unwrap();
+        assert_eq!(*router.resolve("a/10").unwrap().0, 3);
+        assert_eq!(*router.resolve("a/v10").unwrap().0, 4);
+        assert_eq!(*router.resolve("a/x").unwrap().0, 2);
+        assert_eq!(*router.resolve("a/x/y").unwrap().0, 1);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_resolve_regex_constraint_beats_unconstrained() {
+        let mut router = Router::new();
+        router.register("products/{name}", 1).unwrap();
+        router
+            .register(r"products/{sku:re:[A-Z]{3}-\d+}", 2)
+            .unwrap();
+        assert_eq!(*router.resolve("products/ABC-12").unwrap().0, 2);
+        assert_eq!(*router.resolve("products/shoes").unwrap().0, 1);
+        assert_eq!(router.check(), vec![]);
+    }
+
+    #[test]
+    fn test_resolve_query() {
+        let mut router = Router::new();
+        router.register("articles/{id}", 1).unwrap();
+        router.register("articles/{id}?version={v}", 2).unwrap();
+        let (value, m) = router.resolve("articles/1?version=3").unwrap();
+        assert_eq!(*value, 2);
+        assert_eq!(m.get("v"), Some("3"));
+        assert_eq!(*router.resolve("articles/1?lang=en").unwrap().0, 1);
+        assert_eq!(*router.resolve("articles/1").unwrap().0, 1);
+        assert_eq!(router.check(), vec![]);
+    }
+
+    #[test]
+    fn test_resolve_priority() {
+        let mut router = Router::new();
+        router.register("a/b", 1).unwrap();
+        router.register_with_priority("a/{x}", 2, 1).unwrap();
+        router.register_with_priority("a/c", 3, -1).unwrap();
+        assert_eq!(*router.resolve("a/b").unwrap().0, 2);
+        assert_eq!(*router.resolve("a/c").unwrap().0, 2);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut router = Router::new();
+        router.register("a/{x}", 1).unwrap();
+        router.register("a/b", 2).unwrap();
+        router.register("a/{y}", 3).unwrap();
+        router.register("b/c", 4).unwrap();
+        router.register_with_priority("b/{x}", 5, 1).unwrap();
+        assert_eq!(
+            router.check(),
+            vec![
+                Conflict::Shadowed {
+                    pattern: "b/c".to_string(),
+                    earlier: "b/{x}".to_string()
+                },
+                Conflict::Duplicate {
+                    pattern: "a/{y}".to_string(),
+                    earlier: "a/{x}".to_string()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_no_conflicts() {
+        let mut router = Router::new();
+        router.register("a/{x:int}", 1).unwrap();
+        router.register("a/{x}", 2).unwrap();
+        router.register("a/{x}/b", 3).unwrap();
+        assert_eq!(router.check(), vec![]);
+    }
+
+    #[test]
+    fn test_combinators() {
+        let users = || {
+            let mut users = Router::new();
+            users.register_named("user", "users/{id}", "user").unwrap();
+            users
+                .register_factory("users/{id}", |m| {
+                    Some(if m.get("id") == Some("me") {
+                        "me"
+                    } else {
+                        "other"
+                    })
+                })
+                .unwrap();
+            users.set_fallback("users fallback");
+            users
+        };
+        let mut posts = Router::new();
+        posts.register_named("post", "posts/{id}", "post").unwrap();
+
+        let router = users()
+            .or(posts)
+            .unwrap()
+            .with_prefix("api/{version}")
+            .unwrap()
+            .map_values(str::len);
+        let (value, m) = router.resolve("api/v1/users/1").unwrap();
+        assert_eq!(
+            (*value, m.get("version"), m.get("id")),
+            (4, Some("v1"), Some("1"))
+        );
+        assert_eq!(*router.resolve("api/v1/posts/1").unwrap().0, 4);
+        assert!(router.resolve("users/1").is_none());
+        assert_eq!(router.consume("api/v1/users/me"), Some((2, "")));
+        assert!(matches!(
+            router.lookup("api/v1/other"),
+            Resolution::Fallback(&14, _, _)
+        ));
+        let values = HashMap::from([("version", "v2"), ("id", "3")]);
+        assert_eq!(router.path_for("post", &values).unwrap(), "api/v2/posts/3");
+
+        let router = users().map_values(String::from).with_prefix("").unwrap();
+        assert_eq!(router.resolve("users/1").unwrap().0, "user");
+    }
+
+    #[test]
+    fn test_mount() {
+        let mut api = Router::new();
+        api.register("users/{id}", 1).unwrap();
+        api.register("", 2).unwrap();
+        api.register("pages[/{page}]", 3).unwrap();
+        api.register("?q={q}", 4).unwrap();
+        let mut router = Router::new();
+        router.register("{x}/{y}/users/{z}", 0).unwrap();
+        router.mount("/api/v1/", api).unwrap();
+        assert_eq!(*router.resolve("api/v1/users/10").unwrap().0, 1);
+        assert_eq!(*router.resolve("api/v1").unwrap().0, 2);
+        assert_eq!(*router.resolve("api/v1/pages/3").unwrap().0, 3);
+        assert_eq!(*router.resolve("api/v1?q=x").unwrap().0, 4);
+        assert_eq!(*router.resolve("api/v2/users/10").unwrap().0, 0);
+    }
+
+    #[test]
+    fn test_mount_prefix_variables() {
+        let mut sub = Router::new();
+        sub.register("users/{id}", 1).unwrap();
+        let mut router = Router::new();
+        router.mount("tenants/{tenant}", sub).unwrap();
+        let (value, m) = router.resolve("tenants/acme/users/10").unwrap();
+        assert_eq!(*value, 1);
+        assert_eq!(m.get("tenant"), Some("acme"));
+        assert_eq!(m.get("id"), Some("10"));
+    }
+
+    #[test]
+    fn test_fallback() {
+        let mut router = Router::new();
+        router.register("a/{b}", 1).unwrap();
+        assert_eq!(router.lookup("x/y"), Resolution::NotFound);
+        router.set_fallback(0);
+        router.set_fallback(404);
+        assert_eq!(*router.resolve("a/1").unwrap().0, 1);
+        assert_eq!(
+            router.lookup("/x/y?q=1"),
+            Resolution::Fallback(&404, Match::default(), Cow::Borrowed("x/y"))
+        );
+        assert_eq!(router.resolve("a/1/2"), None);
+    }
+
+    #[test]
+    fn test_fallback_mounted() {
+        let mut admin = Router::new();
+        admin.register("users", 1).unwrap();
+        admin.set_fallback(2);
+        let mut router = Router::new();
+        router.set_fallback(3);
+        router.mount("tenants/{tenant}/admin", admin).unwrap();
+        let router = router.with_normalization(true);
+        assert_eq!(*router.resolve("tenants/acme/admin/users").unwrap().0, 1);
+        match router.lookup("tenants/acme/admin/users/10") {
+            Resolution::Fallback(value, m, rest) => {
+                assert_eq!(
+                    (*value, m.get("tenant"), rest.as_ref()),
+                    (2, Some("acme"), "users/10")
+                )
+            }
+            resolution => panic!("{:?}", resolution),
+        }
+        match router.lookup("tenants/./acme//admin") {
+            Resolution::Fallback(value, m, rest) => {
+                assert_eq!(
+                    (*value, m.get("tenant"), rest.as_ref()),
+                    (2, Some("acme"), "")
+                )
+            }
+            resolution => panic!("{:?}", resolution),
+        }
+        assert_eq!(
+            router.lookup("tenants/acme"),
+            Resolution::Fallback(&3, Match::default(), Cow::Borrowed("tenants/acme"))
+        );
+    }
+
+    #[test]
+    fn test_guard() {
+        struct Request {
+            host: &'static str,
+        }
+
+        let mut router = Router::new();
+        router
+            .register_with_guard("{page}", 1, |_, request: &Request| {
+                request.host == "admin.example.com"
+            })
+            .unwrap();
+        router
+            .register_with_guard("{page}", 2, |m, _: &Request| {
+                m.get("page") != Some("secret")
+            })
+            .unwrap();
+        router.register("{page}", 3).unwrap();
+        let resolve = |host, path| {
+            router
+                .resolve_with(path, &Request { host })
+                .map(|(value, _)| *value)
+        };
+        assert_eq!(resolve("admin.example.com", "home"), Some(1));
+        assert_eq!(resolve("example.com", "home"), Some(2));
+        assert_eq!(resolve("example.com", "secret"), Some(3));
+        assert_eq!(*router.resolve("home").unwrap().0, 3);
+        assert_eq!(*router.resolve_with("home", &"other context").unwrap().0, 3);
+        assert!(router.check().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_prefix() {
+        let mut router = Router::new();
+        router.register("wiki", 1).unwrap();
+        router.register("wiki/{page}", 2).unwrap();
+        router.register("wiki/{page:int}", 3).unwrap();
+        router
+            .register("wiki/{page}/history[/{rev:int}]", 4)
+            .unwrap();
+        let (value, m, rest) = router.resolve_prefix("wiki/home/edit").unwrap();
+        assert_eq!(
+            (*value, m.get("page"), rest.as_ref()),
+            (2, Some("home"), "edit")
+        );
+        let (value, _, rest) = router.resolve_prefix("wiki/10/edit?x=1").unwrap();
+        assert_eq!((*value, rest.as_ref()), (3, "edit"));
+        let (value, m, rest) = router.resolve_prefix("wiki/home/history/3/diff").unwrap();
+        assert_eq!(
+            (*value, m.get("rev"), rest.as_ref()),
+            (4, Some("3"), "diff")
+        );
+        let (value, _, rest) = router.resolve_prefix("wiki").unwrap();
+        assert_eq!((*value, rest.as_ref()), (1, ""));
+        assert!(router.resolve_prefix("blog/wiki").is_none());
+        let router = router.with_normalization(true);
+        let (value, m, rest) = router.resolve_prefix("wiki//home/./edit").unwrap();
+        assert_eq!(
+            (*value, m.get("page"), rest.as_ref()),
+            (2, Some("home"), "edit")
+        );
+    }
+
+    #[test]
+    fn test_mount_errors() {
+        let mut sub = Router::new();
+        sub.register("a", 1).unwrap();
+        sub.register("b/{id}", 2).unwrap();
+        let mut router = Router::new();
+        assert_eq!(
+            router.mount("x/{id", Router::new()).unwrap_err(),
+            ParseError::UnbalancedBrace { offset: 2 }
+        );
+        assert_eq!(
+            router.mount("x/{id}", sub).unwrap_err(),
+            ParseError::DuplicateVariable {
+                name: "id".to_string(),
+                offset: 10
+            }
+        );
+        assert!(router.resolve("x/1/a").is_none());
+    }
+
+    #[test]
+    fn test_path_for() {
+        let mut router = Router::new();
+        router
+            .register_named("employee_detail", "departments/{d}/employees/{e}", 1)
+            .unwrap();
+        let values = [("d", "1"), ("e", "2")].iter().cloned().collect();
+        assert_eq!(
+            router.path_for("employee_detail", &values).unwrap(),
+            "departments/1/employees/2"
+        );
+        assert_eq!(
+            router.path_for("department_detail", &values),
+            Err(ExpandError::UnknownRoute {
+                name: "department_detail".to_string()
+            })
+        );
+        assert_eq!(
+            router.path_for("employee_detail", &HashMap::new()),
+            Err(ExpandError::MissingVariable {
+                name: "d".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_path_for_renamed() {
+        let mut router = Router::new();
+        router.register_named("home", "index", 1).unwrap();
+        router.register_named("home", "", 2).unwrap();
+        assert_eq!(router.path_for("home", &HashMap::new()).unwrap(), "");
+        assert_eq!(*router.resolve("index").unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_path_for_mounted() {
+        let mut sub = Router::new();
+        sub.register("other", 1).unwrap();
+        sub.register_named("user", "users/{id}", 2).unwrap();
+        let mut router = Router::new();
+        router.register("x", 0).unwrap();
+        router.mount("tenants/{tenant}", sub).unwrap();
+        let values = [("tenant", "acme"), ("id", "10")].iter().cloned().collect();
+        assert_eq!(
+            router.path_for("user", &values).unwrap(),
+            "tenants/acme/users/10"
+        );
+    }
+
+    struct EvenConverter;
+
+    impl crate::Converter for EvenConverter {
+        type Value = u32;
+
+        fn parse(&self, s: &str) -> Option<u32> {
+            s.parse().ok().filter(|n| n % 2 == 0)
+        }
+
+        fn serialize(&self, value: &u32) -> String {
+            value.to_string()
+        }
+    }
+
+    #[test]
+    fn test_with_converters() {
+        let mut converters = ConverterRegistry::new();
+        converters.register("even", EvenConverter);
+        let mut router = Router::new().with_converters(converters);
+        router.register("a/{x:even}", 1).unwrap();
+        router.register("a/{x:uuid}", 2).unwrap();
+        router.register("a/{x}", 3).unwrap();
+        assert_eq!(*router.resolve("a/2").unwrap().0, 1);
+        assert_eq!(*router.resolve("a/3").unwrap().0, 3);
+        assert_eq!(
+            *router
+                .resolve("a/67e55044-10b1-426f-9247-bb680e5fe0c8")
+                .unwrap()
+                .0,
+            2
+        );
+    }
+
+    #[test]
+    fn test_translated_slugs() {
+        let localized = |prefix, converter| {
+            let mut converters = ConverterRegistry::new();
+            converters.register("product", converter);
+            let mut router = Router::new().with_converters(converters);
+            router
+                .register_named("product", &format!("{}/{{slug:product}}", prefix), ())
+                .unwrap();
+            router
+        };
+        let fr = TranslatedConverter::new().with("chaise-rouge", "red-chair");
+        let en = TranslatedConverter::new().with("red-chair-1", "red-chair");
+        let fr_router = localized("produits", fr.clone());
+        let en_router = localized("products", en.clone());
+
+        let slug = fr_router.resolve("produits/chaise-rouge").unwrap().1;
+        assert_eq!(fr.parse(slug.get("slug").unwrap()).unwrap(), "red-chair");
+        let slug = en_router.resolve("products/red-chair-1").unwrap().1;
+        assert_eq!(en.parse(slug.get("slug").unwrap()).unwrap(), "red-chair");
+        assert!(fr_router.resolve("produits/red-chair-1").is_none());
+
+        let canonical = "red-chair".to_string();
+        let values: &[(&str, &dyn Any)] = &[("slug", &canonical)];
+        assert_eq!(
+            fr_router.path_for_typed("product", values).unwrap(),
+            "produits/chaise-rouge"
+        );
+        assert_eq!(
+            en_router.path_for_typed("product", values).unwrap(),
+            "products/red-chair-1"
+        );
+        assert_eq!(
+            en_router.path_for_typed("product", &[("slug", &1)]),
+            Err(ExpandError::WrongType {
+                name: "slug".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_with_manifest() {
+        let manifest: HashMap<&str, &str> =
+            HashMap::from([("logo.png", "3f2a"), ("css/site.css", "9b1c")]);
+        let mut router = Router::new().with_manifest("hash", "file", move |file| {
+            manifest.get(file).map(|hash| hash.to_string())
+        });
+        router
+            .register_named("asset", "assets/{hash}/{file:*}", 1)
+            .unwrap();
+        router.register_named("page", "pages/{file}", 2).unwrap();
+        let path_for = |name, values: &[(&str, &str)]| {
+            router.path_for(name, &values.iter().copied().collect())
+        };
+        assert_eq!(
+            path_for("asset", &[("file", "logo.png")]).unwrap(),
+            "assets/3f2a/logo.png"
+        );
+        assert_eq!(
+            path_for("asset", &[("file", "css/site.css")]).unwrap(),
+            "assets/9b1c/css/site.css"
+        );
+        assert_eq!(
+            path_for("asset", &[("file", "logo.png"), ("hash", "old")]).unwrap(),
+            "assets/old/logo.png"
+        );
+        assert_eq!(
+            path_for("page", &[("file", "about")]).unwrap(),
+            "pages/about"
+        );
+        assert_eq!(
+            path_for("asset", &[("file", "missing.png")]),
+            Err(ExpandError::InvalidValue {
+                name: "file".to_string(),
+                value: "missing.png".to_string()
+            })
+        );
+        assert_eq!(
+            path_for("asset", &[]),
+            Err(ExpandError::MissingVariable {
+                name: "file".to_string()
+            })
+        );
+        assert!(router.resolve("assets/3f2a/logo.png").is_some());
+    }
+
+    #[test]
+    fn test_automatic_names() {
+        let mut router = Router::new().with_automatic_names(true);
+        router.register("users/{id:int}/posts", 1).unwrap();
+        router.register("users/{id}", 2).unwrap();
+        router.register("users/{id:uuid}", 3).unwrap();
+        router.register_named("user", "users/{user}/", 4).unwrap();
+        router.register_named("users_id_3", "", 5).unwrap();
+        router.register("users/{name}/", 6).unwrap();
+        let names: Vec<_> = router.iter().map(|route| route.name).collect();
+        assert_eq!(
+            names,
+            [
+                Some("users_id_posts"),
+                Some("users_id"),
+                Some("users_id_2"),
+                Some("user"),
+                Some("users_id_3"),
+                Some("users_name")
+            ]
+        );
+        let values = HashMap::from([("id", "5")]);
+        assert_eq!(
+            router.path_for("users_id_posts", &values).unwrap(),
+            "users/5/posts"
+        );
+        let mut sub = Router::new();
+        sub.register_named("detail", "{slug}", 7).unwrap();
+        sub.register("", 8).unwrap();
+        router.mount("blog", sub).unwrap();
+        assert_eq!(router.path_for("blog", &HashMap::new()).unwrap(), "blog");
+        assert!(router.path_for("blog_slug", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_register_grpc() {
+        let mut router = Router::new();
+        router
+            .register_grpc("helloworld.Greeter/SayHello", 1)
+            .unwrap();
+        router
+            .register_grpc("{package}.Greeter/{method}", 2)
+            .unwrap();
+        router.register_grpc("{service}/{method}", 3).unwrap();
+        assert_eq!(
+            *router.resolve("/helloworld.Greeter/SayHello").unwrap().0,
+            1
+        );
+        let (value, m) = router.resolve("/helloworld.v1.Greeter/SayBye").unwrap();
+        assert_eq!((*value, m.get("package")), (2, Some("helloworld.v1")));
+        assert_eq!(
+            router
+                .resolve("/grpc.health.v1.Health/Check")
+                .unwrap()
+                .1
+                .get("service"),
+            Some("grpc.health.v1.Health")
+        );
+        assert!(router.resolve("/helloworld.Greeter/SayHello/").is_none());
+        assert!(router.resolve("/helloworld.Greeter").is_none());
+    }
+
+    #[test]
+    fn test_register_grpc_invalid() {
+        let mut router = Router::new();
+        let error = Err(ParseError::InvalidGrpc { offset: 0 });
+        assert_eq!(router.register_grpc("a.B/C/d", 1), error);
+        assert_eq!(router.register_grpc("a.B", 1), error);
+        assert_eq!(router.register_grpc("a.B/{rest:*}", 1), error);
+        assert_eq!(router.register_grpc("a.B/C[/{m}]", 1), error);
+        assert_eq!(router.register_grpc("a.B/C?x={x}", 1), error);
+        assert!(router.resolve("a.B/C/d").is_none());
+        let mut router = Router::new().with_separator(':');
+        assert_eq!(router.register_grpc("a.B:C", 1), error);
+    }
+
+    #[test]
+    fn test_with_converters_query() {
+        let mut converters = ConverterRegistry::new();
+        converters.register("even", EvenConverter);
+        let mut router = Router::new().with_converters(converters);
+        router
+            .register_named("list", "a/{x:even}?page={page:even}", 1)
+            .unwrap();
+        assert_eq!(
+            router.resolve("a/2?page=4").unwrap().1.get("page"),
+            Some("4")
+        );
+        assert!(router.resolve("a/2?page=3").is_none());
+        let invalid = |name: &str| {
+            ExpandError::Conversion(ConversionError {
+                name: name.to_string(),
+                value: "3".to_string(),
+                converter: "even".to_string(),
+                direction: Direction::Encode,
+            })
+        };
+        let values = HashMap::from([("x", "3"), ("page", "2")]);
+        assert_eq!(router.path_for("list", &values), Err(invalid("x")));
+        let values = HashMap::from([("x", "2"), ("page", "3")]);
+        assert_eq!(router.path_for("list", &values), Err(invalid("page")));
+    }
+
+    #[test]
+    fn test_conversion_failure() {
+        let mut router = Router::new();
+        router.register("users/{id:int}", 1).unwrap();
+        router.register("users/{id:int}/edit", 2).unwrap();
+        router.register("users/{name}/edit", 3).unwrap();
+        let failure = router.conversion_failure("users/x").unwrap();
+        assert_eq!((failure.name.as_str(), failure.value.as_str()), ("id", "x"));
+        assert_eq!(failure.direction, Direction::Decode);
+        assert_eq!(
+            failure.to_string(),
+            "converter `int` can't decode `x` captured by variable `id`"
+        );
+        assert_eq!(router.conversion_failure("users/x/edit"), None);
+        assert_eq!(router.conversion_failure("users/1"), None);
+        assert_eq!(router.conversion_failure("posts/x"), None);
+    }
+
+    #[test]
+    fn test_mount_keeps_converters() {
+        let mut converters = ConverterRegistry::new();
+        converters.register("even", EvenConverter);
+        let mut sub = Router::new().with_converters(converters);
+        sub.register("{x:even}", 1).unwrap();
+        let mut router = Router::new();
+        router.mount("a", sub).unwrap();
+        assert_eq!(*router.resolve("a/2").unwrap().0, 1);
+        assert!(router.resolve("a/3").is_none());
+        assert!(router.register("{x:even}", 2).is_err());
+    }
+
+    #[test]
+    fn test_register_invalid_pattern() {
+        let mut router = Router::new();
+        assert!(router.register("a/{b", 1).is_err());
+        assert!(router.resolve("a/x").is_none());
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Model {
+        Department(String),
+        Employee(String, String),
+    }
+
+    fn traversal_router() -> Router<Model> {
+        let mut router = Router::new();
+        router
+            .register_factory("departments/{d}", |m| {
+                Some(Model::Department(m.get("d")?.to_string()))
+            })
+            .unwrap();
+        router
+            .register_factory("departments/{d}/employees/{e}", |m| {
+                if m.get("e")? == "missing" {
+                    return None;
+                }
+                Some(Model::Employee(
+                    m.get("d")?.to_string(),
+                    m.get("e")?.to_string(),
+                ))
+            })
+            .unwrap();
+        router
+    }
+
+    fn locating_router() -> Router<Model> {
+        let mut router = traversal_router();
+        router
+            .register_inverse("departments/{d}", |obj| match obj {
+                Model::Department(d) => Some((None, vec![("d", d.clone())])),
+                _ => None,
+            })
+            .unwrap();
+        router
+            .register_inverse("departments/{d}/employees/{e}", |obj| match obj {
+                Model::Employee(d, e) => {
+                    Some((Some(Model::Department(d.clone())), vec![("e", e.clone())]))
+                }
+                _ => None,
+            })
+            .unwrap();
+        router
+    }
+
+    #[test]
+    fn test_consume() {
+        let router = traversal_router();
+        assert_eq!(
+            router.consume("departments/1"),
+            Some((Model::Department("1".to_string()), ""))
+        );
+        assert_eq!(
+            router.consume("departments/1/employees/2"),
+            Some((Model::Employee("1".to_string(), "2".to_string()), ""))
+        );
+    }
+
+    #[test]
+    fn test_consume_remainder() {
+        let router = traversal_router();
+        assert_eq!(
+            router.consume("departments/1/employees/2/edit"),
+            Some((Model::Employee("1".to_string(), "2".to_string()), "edit"))
+        );
+        assert_eq!(
+            router.consume("departments/1/view"),
+            Some((Model::Department("1".to_string()), "view"))
+        );
+    }
+
+    #[test]
+    fn test_consume_factory_returns_none() {
+        let router = traversal_router();
+        assert_eq!(
+            router.consume("departments/1/employees/missing"),
+            Some((Model::Department("1".to_string()), "employees/missing"))
+        );
+    }
+
+    #[test]
+    fn test_consume_no_match() {
+        let router = traversal_router();
+        assert_eq!(router.consume("other/1"), None);
+    }
+
+    #[test]
+    fn test_locate() {
+        let router = locating_router();
+        assert_eq!(
+            router.locate(&Model::Department("1".to_string())),
+            Some("departments/1".to_string())
+        );
+        assert_eq!(
+            router.locate(&Model::Employee("1".to_string(), "2".to_string())),
+            Some("departments/1/employees/2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_locate_roundtrip() {
+        let router = locating_router();
+        let employee = Model::Employee("1".to_string(), "2".to_string());
+        let path = router.locate(&employee).unwrap();
+        assert_eq!(router.consume(&path), Some((employee, "")));
+    }
+
+    #[test]
+    fn test_mount_traversal() {
+        let mut router = Router::new();
+        router.mount("company", locating_router()).unwrap();
+        let employee = Model::Employee("1".to_string(), "2".to_string());
+        assert_eq!(
+            router.consume("company/departments/1/employees/2"),
+            Some((employee.clone(), ""))
+        );
+        assert_eq!(
+            router.locate(&employee),
+            Some("company/departments/1/employees/2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_locate_parent_not_located() {
+        let mut router = traversal_router();
+        router
+            .register_inverse("departments/{d}/employees/{e}", |obj| match obj {
+                Model::Employee(d, e) => {
+                    Some((Some(Model::Department(d.clone())), vec![("e", e.clone())]))
+                }
+                _ => None,
+            })
+            .unwrap();
+        let employee = Model::Employee("1".to_string(), "2".to_string());
+        assert_eq!(router.locate(&employee), None);
+    }
+
+    #[test]
+    fn test_locate_no_inverse() {
+        let router = traversal_router();
+        assert_eq!(router.locate(&Model::Department("1".to_string())), None);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::matching::Match;
+use crate::router::{Resolution, Router};
+
+/// The schemes a route can be requested with, like just `https`, or `ws`
+/// and `wss` for a route that upgrades to a WebSocket, kept in its
+/// metadata. Routes without them take any scheme.
+///
+/// Schemes are only checked by `Router::lookup_url`, which knows the
+/// scheme of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schemes {
+    schemes: Vec<String>,
+}
+
+impl Schemes {
+    pub fn new<I, S>(schemes: I) -> Schemes
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Schemes {
+            schemes: schemes
+                .into_iter()
+                .map(|scheme| scheme.as_ref().to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Just `https`.
+    pub fn https() -> Schemes {
+        Schemes::new(["https"])
+    }
+
+    /// `ws` and `wss`, for WebSocket routes.
+    pub fn websocket() -> Schemes {
+        Schemes::new(["ws", "wss"])
+    }
+
+    /// Whether a scheme is one of these, ignoring ASCII case.
+    pub fn allows(&self, scheme: &str) -> bool {
+        self.schemes
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.schemes.iter().map(String::as_str)
+    }
+}
+
+impl<T> Router<T> {
+    /// Look up the path of a full URL like `lookup`, like
+    /// `https://example.com/users/1?tab=posts`, checking the `Schemes` of
+    /// the routes that match it against the scheme of the URL. If routes
+    /// match, but none for the scheme, the first of them is given as
+    /// `Resolution::WrongScheme`, so that the request can be redirected,
+    /// for instance to `https`.
+    ///
+    /// A URL without a scheme is looked up as a path without checking
+    /// schemes.
+    pub fn lookup_url<'a>(&'a self, url: &'a str) -> Resolution<'a, T> {
+        match split_url(url) {
+            Some((scheme, path)) => self.lookup_scheme(scheme, path),
+            None => self.lookup(url),
+        }
+    }
+
+    /// Look up a path like `lookup_url`, requested with a scheme.
+    pub fn lookup_scheme<'a>(&'a self, scheme: &str, path: &'a str) -> Resolution<'a, T> {
+        let allows = |m: &Match| {
+            m.metadata()
+                .and_then(|metadata| metadata.get::<Schemes>())
+                .is_none_or(|schemes| schemes.allows(scheme))
+        };
+        match self.lookup(path) {
+            Resolution::Found(value, m) if !allows(&m) => self
+                .match_all(path)
+                .into_iter()
+                .find(|(_, m)| allows(m))
+                .map_or(Resolution::WrongScheme(value, m), |(value, m)| {
+                    Resolution::Found(value, m)
+                }),
+            resolution => resolution,
+        }
+    }
+}
+
+/// The scheme of a URL and its path with the query, without its
+/// authority or fragment.
+fn split_url(url: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    if !valid {
+        return None;
+    }
+    let rest = rest.split('#').next().unwrap_or(rest);
+    let path = rest.find(['/', '?']).map_or("", |i| &rest[i..]);
+    Some((scheme, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::metadata::Metadata;
+
+    fn router() -> Router<i32> {
+        let mut router = Router::new();
+        let https = Metadata::new().with(Schemes::https());
+        router.register_with_metadata("account", 1, https).unwrap();
+        let websocket = Metadata::new().with(Schemes::websocket());
+        router.register_with_metadata("live", 2, websocket).unwrap();
+        router.register("live", 3).unwrap();
+        router.register("about", 4).unwrap();
+        router
+    }
+
+    #[test]
+    fn test_lookup_url() {
+        let router = router();
+        assert!(matches!(
+            router.lookup_url("https://example.com/account"),
+            Resolution::Found(&1, _)
+        ));
+        assert!(matches!(
+            router.lookup_url("http://example.com/account"),
+            Resolution::WrongScheme(&1, _)
+        ));
+        assert!(matches!(
+            router.lookup_url("HTTP://example.com/about?x=1#top"),
+            Resolution::Found(&4, _)
+        ));
+        assert!(matches!(
+            router.lookup_url("ftp://example.com/missing"),
+            Resolution::NotFound
+        ));
+        assert!(matches!(
+            router.lookup_url("account"),
+            Resolution::Found(&1, _)
+        ));
+    }
+
+    #[test]
+    fn test_lookup_scheme() {
+        let router = router();
+        assert!(matches!(
+            router.lookup_scheme("wss", "live"),
+            Resolution::Found(&2, _)
+        ));
+        // another route for the path takes other schemes
+        assert!(matches!(
+            router.lookup_scheme("https", "live"),
+            Resolution::Found(&3, _)
+        ));
+    }
+
+    #[test]
+    fn test_split_url() {
+        assert_eq!(
+            split_url("https://example.com:8080/a/b?c=d#e"),
+            Some(("https", "/a/b?c=d"))
+        );
+        assert_eq!(split_url("wss://example.com"), Some(("wss", "")));
+        assert_eq!(split_url("https://example.com?q"), Some(("https", "?q")));
+        assert_eq!(split_url("/a://b"), None);
+        assert_eq!(split_url("a/b"), None);
+    }
+
+    #[test]
+    fn test_schemes() {
+        let schemes = Schemes::new(["HTTPS"]);
+        assert!(schemes.allows("https"));
+        assert!(schemes.allows("Https"));
+        assert!(!schemes.allows("http"));
+        assert_eq!(
+            Schemes::websocket().iter().collect::<Vec<_>>(),
+            vec!["ws", "wss"]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::router::{RouteInfo, Router};
+
+impl<T> Router<T> {
+    /// The routes a predicate holds for, in registration order.
+    pub fn find<'a, P>(&'a self, mut predicate: P) -> impl Iterator<Item = RouteInfo<'a, T>>
+    where
+        P: FnMut(&RouteInfo<'a, T>) -> bool + 'a,
+    {
+        self.iter().filter(move |route| predicate(route))
+    }
+
+    /// The routes with literal text in their pattern, as by
+    /// `Pattern::has_literal`.
+    pub fn find_literal<'a>(&'a self, text: &'a str) -> impl Iterator<Item = RouteInfo<'a, T>> {
+        self.find(move |route| route.pattern.has_literal(text))
+    }
+
+    /// The routes with a variable that uses the converter registered under
+    /// a name.
+    pub fn find_converter<'a>(&'a self, name: &'a str) -> impl Iterator<Item = RouteInfo<'a, T>> {
+        self.find(move |route| route.pattern.uses_converter(name))
+    }
+
+    /// The routes with more than a number of variables, in their path and
+    /// query together.
+    pub fn find_variables_over(&self, count: usize) -> impl Iterator<Item = RouteInfo<'_, T>> {
+        self.find(move |route| route.pattern.names().count() > count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> Router<i32> {
+        let mut router = Router::new();
+        router
+            .register_named("users", "admin/users/{id:uuid}", 1)
+            .unwrap();
+        router
+            .register("orgs/{org}/teams/{team}?page={page:int}", 2)
+            .unwrap();
+        router.register("about", 3).unwrap();
+        router
+    }
+
+    fn values<'a>(routes: impl Iterator<Item = RouteInfo<'a, i32>>) -> Vec<i32> {
+        routes.map(|route| *route.value).collect()
+    }
+
+    #[test]
+    fn test_find() {
+        let router = router();
+        assert_eq!(values(router.find(|route| route.name.is_some())), vec![1]);
+        assert_eq!(values(router.find(|route| *route.value > 1)), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_find_literal() {
+        let router = router();
+        assert_eq!(values(router.find_literal("admin")), vec![1]);
+        assert_eq!(values(router.find_literal("o")), vec![2, 3]);
+        assert!(values(router.find_literal("org}")).is_empty());
+    }
+
+    #[test]
+    fn test_find_converter() {
+        let router = router();
+        assert_eq!(values(router.find_converter("uuid")), vec![1]);
+        assert_eq!(values(router.find_converter("int")), vec![2]);
+        assert!(values(router.find_converter("date")).is_empty());
+    }
+
+    #[test]
+    fn test_find_variables_over() {
+        let router = router();
+        assert_eq!(values(router.find_variables_over(0)), vec![1, 2]);
+        assert_eq!(values(router.find_variables_over(2)), vec![2]);
+        assert!(values(router.find_variables_over(3)).is_empty());
+    }
+}
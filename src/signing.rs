@@ -0,0 +1,329 @@
+//! Signed links that expire, like download links: the path gets an expiry
+//! time and an HMAC-SHA256 signature as query parameters.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{ExpandError, InvalidPath};
+use crate::pattern;
+use crate::router::{Resolution, Router};
+
+/// Signs paths with a secret key, and verifies them.
+///
+/// A signed path has two more query parameters, by default
+/// `expires=<seconds since the epoch>&signature=<hex>`, with the signature
+/// last. The signature covers everything before it, so changing the path,
+/// the query or the expiry time invalidates it. Patterns don't need to
+/// describe these parameters, as parameters they don't describe are
+/// ignored.
+#[derive(Clone)]
+pub struct UrlSigner {
+    key: Vec<u8>,
+    expires: String,
+    signature: String,
+}
+
+impl UrlSigner {
+    pub fn new(key: &[u8]) -> UrlSigner {
+        UrlSigner {
+            key: key.to_vec(),
+            expires: "expires".to_string(),
+            signature: "signature".to_string(),
+        }
+    }
+
+    /// Name the query parameters for the expiry time and the signature
+    /// otherwise.
+    pub fn with_parameters(mut self, expires: &str, signature: &str) -> UrlSigner {
+        self.expires = expires.to_string();
+        self.signature = signature.to_string();
+        self
+    }
+
+    /// The path with the expiry time and the signature added to its query.
+    pub fn sign(&self, path: &str, expires: SystemTime) -> String {
+        let seconds = expires
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs();
+        let joiner = if path.contains('?') { '&' } else { '?' };
+        let message = format!("{}{}{}={}", path, joiner, self.expires, seconds);
+        let signature = hex(&hmac_sha256(&self.key, message.as_bytes()));
+        format!("{}&{}={}", message, self.signature, signature)
+    }
+
+    /// Check that a path was signed with this key and hasn't expired at
+    /// `now`.
+    pub fn verify(&self, target: &str, now: SystemTime) -> Result<(), InvalidPath> {
+        let marker = format!("&{}=", self.signature);
+        let (message, signature) = target
+            .rfind(&marker)
+            .map(|i| (&target[..i], &target[i + marker.len()..]))
+            .ok_or(InvalidPath::BadSignature)?;
+        let expected = hex(&hmac_sha256(&self.key, message.as_bytes()));
+        if !constant_time_eq(signature.as_bytes(), expected.as_bytes()) {
+            return Err(InvalidPath::BadSignature);
+        }
+        // the expiry time `sign` adds is the last parameter before the
+        // signature, after any the path already had
+        let expires = pattern::split_query(message)
+            .1
+            .and_then(|query| query.rsplit('&').next())
+            .and_then(|param| param.strip_prefix(self.expires.as_str())?.strip_prefix('='))
+            .and_then(|seconds| seconds.parse().ok())
+            .ok_or(InvalidPath::BadSignature)?;
+        if now > UNIX_EPOCH + Duration::from_secs(expires) {
+            return Err(InvalidPath::Expired);
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for UrlSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // never show the key
+        f.debug_struct("UrlSigner")
+            .field("expires", &self.expires)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl<T> Router<T> {
+    /// Generate the path for a named route like `path_for`, signed to be
+    /// valid until `expires`.
+    pub fn path_for_signed(
+        &self,
+        name: &str,
+        values: &std::collections::HashMap<&str, &str>,
+        signer: &UrlSigner,
+        expires: SystemTime,
+    ) -> Result<String, ExpandError> {
+        Ok(signer.sign(&self.path_for(name, values)?, expires))
+    }
+
+    /// Look up a path like `lookup` if `signer` verifies it at `now`, and
+    /// otherwise reject it as `InvalidPath::BadSignature` or
+    /// `InvalidPath::Expired`.
+    pub fn lookup_signed<'a>(
+        &'a self,
+        path: &'a str,
+        signer: &UrlSigner,
+        now: SystemTime,
+    ) -> Resolution<'a, T> {
+        match signer.verify(path, now) {
+            Ok(()) => self.lookup(path),
+            Err(invalid) => Resolution::Rejected(invalid),
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<u8>>();
+    let mut inner = pad(0x36);
+    inner.extend_from_slice(message);
+    let mut outer = pad(0x5c);
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((message.len() as u64) * 8).to_be_bytes());
+    for chunk in padded.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231, test cases 2 and 6
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let signer = UrlSigner::new(b"secret");
+        let expires = UNIX_EPOCH + Duration::from_secs(1000);
+        let signed = signer.sign("files/report.pdf", expires);
+        assert!(signed.starts_with("files/report.pdf?expires=1000&signature="));
+        let before = UNIX_EPOCH + Duration::from_secs(999);
+        assert_eq!(signer.verify(&signed, before), Ok(()));
+        assert_eq!(signer.verify(&signed, expires), Ok(()));
+        assert_eq!(
+            signer.verify(&signed, expires + Duration::from_secs(1)),
+            Err(InvalidPath::Expired)
+        );
+        let tampered = signed.replace("expires=1000", "expires=2000");
+        assert_eq!(
+            signer.verify(&tampered, before),
+            Err(InvalidPath::BadSignature)
+        );
+        let tampered = signed.replace("report", "secret");
+        assert_eq!(
+            signer.verify(&tampered, before),
+            Err(InvalidPath::BadSignature)
+        );
+        assert_eq!(
+            UrlSigner::new(b"other").verify(&signed, before),
+            Err(InvalidPath::BadSignature)
+        );
+        assert_eq!(
+            signer.verify("files/report.pdf", before),
+            Err(InvalidPath::BadSignature)
+        );
+
+        let signer = signer.with_parameters("e", "s");
+        let signed = signer.sign("files?page=2", expires);
+        assert!(signed.starts_with("files?page=2&e=1000&s="));
+        assert_eq!(signer.verify(&signed, before), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_own_expiry() {
+        let signer = UrlSigner::new(b"secret");
+        let expires = UNIX_EPOCH + Duration::from_secs(1000);
+        let signed = signer.sign("files?expires=9999999999", expires);
+        assert_eq!(
+            signer.verify(&signed, expires + Duration::from_secs(1)),
+            Err(InvalidPath::Expired)
+        );
+        assert_eq!(signer.verify(&signed, expires), Ok(()));
+    }
+
+    #[test]
+    fn test_lookup_signed() {
+        let mut router = Router::new();
+        router
+            .register_named("download", "files/{name}", 1)
+            .unwrap();
+        let signer = UrlSigner::new(b"secret");
+        let expires = UNIX_EPOCH + Duration::from_secs(1000);
+        let values = HashMap::from([("name", "report.pdf")]);
+        let path = router
+            .path_for_signed("download", &values, &signer, expires)
+            .unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(500);
+        match router.lookup_signed(&path, &signer, now) {
+            Resolution::Found(value, m) => {
+                assert_eq!(*value, 1);
+                assert_eq!(m.get("name"), Some("report.pdf"));
+            }
+            resolution => panic!("unexpected {:?}", resolution),
+        }
+        assert_eq!(
+            router.lookup_signed("files/report.pdf", &signer, now),
+            Resolution::Rejected(InvalidPath::BadSignature)
+        );
+        assert_eq!(
+            router.lookup_signed(&path, &signer, now + Duration::from_secs(1000)),
+            Resolution::Rejected(InvalidPath::Expired)
+        );
+    }
+}
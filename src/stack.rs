@@ -0,0 +1,189 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use crate::error::ExpandError;
+use crate::matching::Match;
+use crate::router::{Resolution, Router};
+
+/// Routers stacked in layers, like plugin routes over application routes
+/// over framework defaults, that are tried in turn.
+///
+/// A path resolves in the first layer that has a route for it. Only if no
+/// layer does, the first layer with a fallback for it gives the fallback.
+/// Each layer keeps its own options, so a path one layer rejects can still
+/// resolve in the next.
+#[derive(Debug)]
+pub struct RouterStack<T> {
+    layers: Vec<(String, Router<T>)>,
+}
+
+impl<T> RouterStack<T> {
+    pub fn new() -> RouterStack<T> {
+        RouterStack { layers: Vec::new() }
+    }
+
+    /// This stack with a layer added under the layers before it.
+    pub fn with_layer(mut self, name: &str, router: Router<T>) -> RouterStack<T> {
+        self.layers.push((name.to_string(), router));
+        self
+    }
+
+    /// The layers from the top down, with their names.
+    pub fn layers(&self) -> impl Iterator<Item = (&str, &Router<T>)> {
+        self.layers
+            .iter()
+            .map(|(name, router)| (name.as_str(), router))
+    }
+
+    /// Resolve a path to the name of the layer that matched it, the value
+    /// of its route and the captured variables.
+    pub fn resolve<'a>(&'a self, path: &'a str) -> Option<(&'a str, &'a T, Match<'a>)> {
+        match self.lookup(path) {
+            (Some(layer), Resolution::Found(value, m)) => Some((layer, value, m)),
+            _ => None,
+        }
+    }
+
+    /// Look up a path in each layer like `Router::lookup`, and give the
+    /// name of the layer that answered along with its answer. A path
+    /// that no layer has a route or fallback for is rejected as the first
+    /// layer that rejects it does, or isn't found.
+    pub fn lookup<'a>(&'a self, path: &'a str) -> (Option<&'a str>, Resolution<'a, T>) {
+        self.lookup_by(|router| router.lookup(path))
+    }
+
+    /// Look up a path like `lookup`, passing a context to the guards of
+    /// routes.
+    pub fn lookup_with<'a, C: Any>(
+        &'a self,
+        path: &'a str,
+        context: &C,
+    ) -> (Option<&'a str>, Resolution<'a, T>) {
+        self.lookup_by(|router| router.lookup_with(path, context))
+    }
+
+    fn lookup_by<'a, F>(&'a self, lookup: F) -> (Option<&'a str>, Resolution<'a, T>)
+    where
+        F: Fn(&'a Router<T>) -> Resolution<'a, T>,
+    {
+        let mut fallback = None;
+        let mut rejected = None;
+        for (name, router) in &self.layers {
+            match lookup(router) {
+                found @ (Resolution::Found(..)
+                | Resolution::RedirectTo(_)
+                | Resolution::WrongScheme(..)) => return (Some(name), found),
+                resolution @ Resolution::Fallback(..) => {
+                    fallback.get_or_insert((Some(name.as_str()), resolution));
+                }
+                resolution @ Resolution::Rejected(_) => {
+                    rejected.get_or_insert((Some(name.as_str()), resolution));
+                }
+                Resolution::NotFound => {}
+            }
+        }
+        fallback
+            .or(rejected)
+            .unwrap_or((None, Resolution::NotFound))
+    }
+
+    /// Generate the path for a named route of the first layer that has a
+    /// route with the name, like `Router::path_for`.
+    pub fn path_for(
+        &self,
+        name: &str,
+        values: &HashMap<&str, &str>,
+    ) -> Result<String, ExpandError> {
+        for (_, router) in &self.layers {
+            match router.path_for(name, values) {
+                Err(ExpandError::UnknownRoute { .. }) => {}
+                result => return result,
+            }
+        }
+        Err(ExpandError::UnknownRoute {
+            name: name.to_string(),
+        })
+    }
+}
+
+impl<T> Default for RouterStack<T> {
+    fn default() -> Self {
+        RouterStack::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::InvalidPath;
+
+    fn stack() -> RouterStack<&'static str> {
+        let mut plugins = Router::new().with_character_check(true);
+        plugins.register("blog/{slug}", "plugin post").unwrap();
+        let mut app = Router::new();
+        app.register("blog/{slug}", "app post").unwrap();
+        app.register_named("about", "about", "about").unwrap();
+        let mut defaults = Router::new();
+        defaults.register("health", "health").unwrap();
+        defaults
+            .register_named("about", "info/about", "info")
+            .unwrap();
+        defaults.set_fallback("not found");
+        RouterStack::new()
+            .with_layer("plugins", plugins)
+            .with_layer("app", app)
+            .with_layer("defaults", defaults)
+    }
+
+    #[test]
+    fn test_resolve() {
+        let stack = stack();
+        let (layer, value, m) = stack.resolve("blog/hello").unwrap();
+        assert_eq!(
+            (layer, *value, m.get("slug")),
+            ("plugins", "plugin post", Some("hello"))
+        );
+        assert_eq!(stack.resolve("about").unwrap().0, "app");
+        assert_eq!(stack.resolve("health").unwrap().0, "defaults");
+        assert!(stack.resolve("missing").is_none());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let stack = stack();
+        assert!(matches!(
+            stack.lookup("missing"),
+            (Some("defaults"), Resolution::Fallback(&"not found", ..))
+        ));
+        // plugins rejects the path, but app doesn't check characters
+        assert_eq!(stack.lookup("blog/a%00b").0, Some("app"));
+        let mut plugins = Router::new().with_character_check(true);
+        plugins.register("blog/{slug}", "plugin post").unwrap();
+        let stack = RouterStack::new().with_layer("plugins", plugins);
+        assert!(matches!(
+            stack.lookup("blog/a%00b"),
+            (
+                Some("plugins"),
+                Resolution::Rejected(InvalidPath::ForbiddenCharacter { .. })
+            )
+        ));
+        assert!(matches!(
+            RouterStack::<i32>::new().lookup("a"),
+            (None, Resolution::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_path_for() {
+        let stack = stack();
+        let values = HashMap::new();
+        assert_eq!(stack.path_for("about", &values).unwrap(), "about");
+        assert_eq!(
+            stack.path_for("missing", &values),
+            Err(ExpandError::UnknownRoute {
+                name: "missing".to_string()
+            })
+        );
+    }
+}
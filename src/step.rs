@@ -0,0 +1,1457 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::converter::{ConverterRegistry, NamedConverter};
+use crate::encoding;
+use crate::error::{ConversionError, Direction, ExpandError, ParseError};
+#[cfg(feature = "regex")]
+use crate::lazy_regex::{self, LazyRegex};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Step {
+    pub(crate) s: String,
+    generalized: String,
+    pub(crate) parts: Vec<String>,
+    pub(crate) names: Vec<String>,
+    /// offsets of the variable names in `s`
+    pub(crate) offsets: Vec<usize>,
+    converters: Vec<Option<NamedConverter>>,
+    /// the default value of each variable, like `1` in `{page:int=1}`
+    defaults: Vec<Option<String>>,
+    /// a wildcard step like `{rest:*}` captures the rest of the path
+    pub(crate) wildcard: bool,
+    /// only steps with regex constraints are matched by regex, others by
+    /// looking for their literal parts
+    #[cfg(feature = "regex")]
+    variables_re: Option<LazyRegex>,
+    /// the capture group of each variable in `variables_re`
+    #[cfg(feature = "regex")]
+    groups: Vec<usize>,
+    /// literal text matches in any ASCII case
+    case_insensitive: bool,
+}
+
+impl Step {
+    /// Parse a step with the built-in converters.
+    #[cfg(test)]
+    pub(crate) fn new(s: &str) -> Result<Step, ParseError> {
+        Step::with_converters(s, crate::converter::builtin())
+    }
+
+    /// Parse a step. Offsets in errors are relative to the start of `s`.
+    pub(crate) fn with_converters(
+        s: &str,
+        converters: &ConverterRegistry,
+    ) -> Result<Step, ParseError> {
+        let (parts, variables) = scan(s)?;
+        let generalized = parts
+            .iter()
+            .map(|part| part.replace('{', "{{").replace('}', "}}"))
+            .collect::<Vec<_>>()
+            .join("{}");
+
+        let names = get_names(&variables)?;
+        let offsets = variables.iter().map(|v| v.offset).collect();
+        let converters = get_converters(&variables, converters)?;
+        check_consecutive(&parts, &variables, &converters)?;
+        let defaults = get_defaults(&variables, &converters)?;
+        let wildcard = is_wildcard(&parts, &variables)?;
+        #[cfg(feature = "regex")]
+        let constrained = variables
+            .iter()
+            .find_map(|variable| match variable.converter {
+                Some((offset, converter)) if converter.starts_with(&REGEX_PREFIX[1..]) => {
+                    Some((offset, converter))
+                }
+                _ => None,
+            });
+        #[cfg(feature = "regex")]
+        let variables_re = match constrained {
+            // regex constraints can still clash with each other, for
+            // instance by using the same group name
+            Some((offset, converter)) => Some(
+                get_variables_re(&parts, &converters, false)
+                    .ok_or_else(|| invalid_regex(offset, converter))?,
+            ),
+            None => None,
+        };
+        #[cfg(feature = "regex")]
+        let groups = if variables_re.is_some() {
+            capture_groups(&converters)
+        } else {
+            Vec::new()
+        };
+        Ok(Step {
+            s: s.to_owned(),
+            generalized,
+            parts,
+            names,
+            offsets,
+            converters,
+            defaults,
+            wildcard,
+            #[cfg(feature = "regex")]
+            variables_re,
+            #[cfg(feature = "regex")]
+            groups,
+            case_insensitive: false,
+        })
+    }
+
+    /// match literal text in any ASCII case, or only in the same case
+    pub(crate) fn set_case_insensitive(&mut self, enabled: bool) {
+        #[cfg(feature = "regex")]
+        if self.variables_re.is_some() {
+            self.variables_re = get_variables_re(&self.parts, &self.converters, enabled);
+        }
+        self.case_insensitive = enabled;
+    }
+
+    /// a step without variables only matches itself
+    pub(crate) fn is_literal(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// the text a literal step matches, with its braces unescaped, or the
+    /// step as written if it has variables
+    pub(crate) fn text(&self) -> &str {
+        if self.is_literal() {
+            &self.parts[0]
+        } else {
+            &self.s
+        }
+    }
+
+    /// a step with a single variable without converter matches any segment
+    pub(crate) fn is_catch_all(&self) -> bool {
+        self.names.len() == 1 && self.generalized == "{}" && self.converters[0].is_none()
+    }
+
+    /// how specific this step is, lower is more specific: literal steps
+    /// come first, then steps with literal text or converters, then steps
+    /// that match any segment, then wildcards
+    pub(crate) fn specificity(&self) -> u8 {
+        if self.is_literal() {
+            0
+        } else if self.wildcard {
+            3
+        } else if self.is_catch_all() {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// The step with its variables written as `{name}`, without their
+    /// converters. Braces in literal text are percent-encoded, as a
+    /// template has no other way to escape them.
+    #[cfg(feature = "openapi")]
+    pub(crate) fn template(&self) -> String {
+        let literal = |part: &str| part.replace('{', "%7B").replace('}', "%7D");
+        let mut template = literal(&self.parts[0]);
+        for (name, part) in self.names.iter().zip(&self.parts[1..]) {
+            template.push('{');
+            template.push_str(name);
+            template.push('}');
+            template.push_str(&literal(part));
+        }
+        template
+    }
+
+    /// The variable names with the names of their converters.
+    #[cfg(any(feature = "openapi", feature = "typescript"))]
+    pub(crate) fn variables(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.names
+            .iter()
+            .map(String::as_str)
+            .zip(self.converter_names())
+    }
+
+    /// Whether the step is nothing but a variable.
+    #[cfg(feature = "openapi")]
+    pub(crate) fn is_variable(&self) -> bool {
+        self.generalized == "{}"
+    }
+
+    /// The literal text and the variable names of the step, in order, with
+    /// the braces in literal text unescaped.
+    pub(crate) fn pieces(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.parts[0].as_str()).chain(
+            self.names
+                .iter()
+                .zip(&self.parts[1..])
+                .flat_map(|(name, part)| [name.as_str(), part.as_str()]),
+        )
+    }
+
+    /// The variables that have a default value, with that value.
+    pub(crate) fn defaults(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.names
+            .iter()
+            .zip(&self.defaults)
+            .filter_map(|(name, default)| Some((name.as_str(), default.as_deref()?)))
+    }
+
+    /// whether values has a value for one of the variables that isn't its
+    /// default
+    pub(crate) fn overridden(&self, values: &HashMap<&str, &str>) -> bool {
+        self.names
+            .iter()
+            .zip(&self.defaults)
+            .any(|(name, default)| match values.get(name.as_str()) {
+                Some(value) => default.as_deref() != Some(*value),
+                None => false,
+            })
+    }
+
+    /// The converter of a variable: `None` if the step doesn't have the
+    /// variable, `Some(None)` if it has no converter.
+    pub(crate) fn converter(&self, name: &str) -> Option<Option<&NamedConverter>> {
+        let index = self.names.iter().position(|n| n == name)?;
+        Some(self.converters[index].as_ref())
+    }
+
+    fn converter_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.converters
+            .iter()
+            .map(|converter| converter.as_ref().map(|c| c.name.as_str()))
+    }
+
+    /// whether this step has the same shape as other, apart from the names
+    /// of its variables
+    pub(crate) fn same_shape(&self, other: &Step) -> bool {
+        self.generalized == other.generalized
+            && self.wildcard == other.wildcard
+            && self.converter_names().eq(other.converter_names())
+    }
+
+    /// whether this step matches every segment that other matches
+    ///
+    /// This is conservative: it can say no for steps that do generalize,
+    /// but never says yes when they don't.
+    pub(crate) fn generalizes(&self, other: &Step) -> bool {
+        if other.is_literal() {
+            return self.match_segment(other.text()).is_some();
+        }
+        if self.is_catch_all() {
+            return true;
+        }
+        self.generalized == other.generalized
+            && self
+                .converter_names()
+                .zip(other.converter_names())
+                .all(|(mine, theirs)| mine.is_none() || mine == theirs)
+    }
+
+    /// match path segment, return (name, value) pairs
+    ///
+    /// A segment only matches if all converters accept their values.
+    pub(crate) fn match_segment<'a, 's>(&'a self, s: &'s str) -> Option<Vec<(&'a str, &'s str)>> {
+        let mut variables = Vec::new();
+        if self.match_segment_into(s, &mut variables) {
+            Some(variables)
+        } else {
+            None
+        }
+    }
+
+    /// match path segment, push (name, value) pairs onto variables
+    ///
+    /// Nothing is pushed if the segment doesn't match.
+    pub(crate) fn match_segment_into<'a, 's>(
+        &'a self,
+        s: &'s str,
+        variables: &mut Vec<(&'a str, &'s str)>,
+    ) -> bool {
+        if self.is_literal() {
+            return eq_text(self.text(), s, self.case_insensitive);
+        }
+        let start = variables.len();
+        if !self.capture_into(s, variables) {
+            return false;
+        }
+        let valid =
+            variables[start..]
+                .iter()
+                .zip(&self.converters)
+                .all(|((_, value), converter)| match converter {
+                    Some(converter) => converter.validate(value),
+                    None => true,
+                });
+        if !valid {
+            variables.truncate(start);
+        }
+        valid
+    }
+
+    /// the first variable whose converter rejects its value, if that is the
+    /// reason the segment doesn't match: (name, value, converter)
+    ///
+    /// A step with a regex constraint doesn't capture values its constraint
+    /// rejects, but a step of just one variable captures the whole segment.
+    pub(crate) fn rejection<'s>(&self, s: &'s str) -> Option<(&str, &'s str, &str)> {
+        if self.is_literal() || s.is_empty() {
+            return None;
+        }
+        let mut variables = Vec::new();
+        if !self.capture_into(s, &mut variables) {
+            if self.names.len() != 1 || self.parts.iter().any(|part| !part.is_empty()) {
+                return None;
+            }
+            variables.push((self.names[0].as_str(), s));
+        }
+        variables
+            .iter()
+            .zip(&self.converters)
+            .find_map(|(&(name, value), converter)| {
+                let converter = converter.as_ref()?;
+                (!converter.validate(value)).then_some((name, value, converter.name.as_str()))
+            })
+    }
+
+    /// capture the values of the variables in a segment of a step that has
+    /// variables, without checking converters
+    ///
+    /// This finds the same values as the regex `get_variables_re` makes for
+    /// the step: each variable captures at least one character, but no
+    /// newline, and as few characters as it can for the rest to match.
+    fn capture_into<'a, 's>(&'a self, s: &'s str, variables: &mut Vec<(&'a str, &'s str)>) -> bool {
+        #[cfg(feature = "regex")]
+        if let Some(variables_re) = &self.variables_re {
+            let c = match variables_re.get().and_then(|re| re.captures(s)) {
+                Some(c) => c,
+                None => return false,
+            };
+            variables.extend(self.names.iter().zip(&self.groups).map(|(name, &group)| {
+                (
+                    name.as_str(),
+                    c.get(group).expect("match not matched").as_str(),
+                )
+            }));
+            return true;
+        }
+        has_prefix(s, &self.parts[0], self.case_insensitive)
+            && self.capture_parts(0, s, self.parts[0].len(), variables)
+    }
+
+    /// capture variable i and those after it, starting at offset pos
+    fn capture_parts<'a, 's>(
+        &'a self,
+        i: usize,
+        s: &'s str,
+        pos: usize,
+        variables: &mut Vec<(&'a str, &'s str)>,
+    ) -> bool {
+        let name = self.names[i].as_str();
+        let part = self.parts[i + 1].as_str();
+        if i + 1 == self.names.len() {
+            // the last variable takes everything up to the last part
+            if !has_suffix(s, part, self.case_insensitive) || s.len() - part.len() <= pos {
+                return false;
+            }
+            let value = &s[pos..s.len() - part.len()];
+            if value.contains('\n') {
+                return false;
+            }
+            variables.push((name, value));
+            return true;
+        }
+        // the parts between variables are never empty, try each occurrence
+        // of the next part in turn
+        let mut from = match s[pos..].chars().next() {
+            Some(c) => pos + c.len_utf8(),
+            None => return false,
+        };
+        while let Some(j) = find_text(&s[from..], part, self.case_insensitive) {
+            let end = from + j;
+            let value = &s[pos..end];
+            if value.contains('\n') {
+                return false;
+            }
+            variables.push((name, value));
+            if self.capture_parts(i + 1, s, end + part.len(), variables) {
+                return true;
+            }
+            variables.pop();
+            from = end + s[end..].chars().next().map_or(1, char::len_utf8);
+        }
+        false
+    }
+
+    /// whether the segment fits this step, apart from its converters
+    fn fits(&self, s: &str) -> bool {
+        if self.is_literal() {
+            return eq_text(self.text(), s, self.case_insensitive);
+        }
+        self.capture_into(s, &mut Vec::new())
+    }
+
+    /// match a segment that may have been decoded, return (name, value)
+    /// pairs that borrow from it if it was borrowed
+    pub(crate) fn match_decoded<'a>(
+        &'a self,
+        segment: Cow<'a, str>,
+    ) -> Option<Vec<(&'a str, Cow<'a, str>)>> {
+        match segment {
+            Cow::Borrowed(segment) => Some(
+                self.match_segment(segment)?
+                    .into_iter()
+                    .map(|(name, value)| (name, Cow::Borrowed(value)))
+                    .collect(),
+            ),
+            Cow::Owned(segment) => Some(
+                self.match_segment(&segment)?
+                    .into_iter()
+                    .map(|(name, value)| (name, Cow::Owned(value.to_string())))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// expand step into segment, using variable values or their defaults
+    ///
+    /// With `encode` set to the separator of the pattern the segment is
+    /// percent-encoded, separator included; a wildcard keeps its separators.
+    pub(crate) fn expand(
+        &self,
+        values: &HashMap<&str, &str>,
+        encode: Option<char>,
+    ) -> Result<String, ExpandError> {
+        let escape = |s: &'_ str| -> String {
+            match (encode, self.wildcard) {
+                (None, _) => s.to_string(),
+                (Some(separator), false) => {
+                    encoding::percent_encode_segment(s, separator).into_owned()
+                }
+                (Some(separator), true) => encoding::percent_encode_rest(s, separator).into_owned(),
+            }
+        };
+        let mut result = escape(&self.parts[0]);
+        for (((name, converter), default), part) in self
+            .names
+            .iter()
+            .zip(&self.converters)
+            .zip(&self.defaults)
+            .zip(&self.parts[1..])
+        {
+            let value = values
+                .get(name.as_str())
+                .copied()
+                .or(default.as_deref())
+                .ok_or_else(|| ExpandError::MissingVariable { name: name.clone() })?;
+            if let Some(converter) = converter {
+                if !converter.validate(value) {
+                    return Err(ExpandError::Conversion(ConversionError {
+                        name: name.clone(),
+                        value: value.to_string(),
+                        converter: converter.name.clone(),
+                        direction: Direction::Encode,
+                    }));
+                }
+            }
+            result.push_str(&escape(value));
+            result.push_str(&escape(part));
+        }
+        Ok(result)
+    }
+}
+
+impl PartialEq for Step {
+    fn eq(&self, other: &Self) -> bool {
+        self.s == other.s
+    }
+}
+
+impl Eq for Step {}
+
+impl Ord for Step {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // if we have the same non-variable parts, we should be the same
+        // (otherwise it's a conflict)
+        if self.parts == other.parts {
+            return Ordering::Equal;
+        }
+        // if we can absorb the other's variables we sort after it,
+        // we'd have less hardcoded and more variables
+        if self.fits(other.text()) {
+            return Ordering::Greater;
+        }
+        // we sort before other if other's variables can absorb us,
+        // this means we have less variables and more hardcoded.
+        if other.fits(self.text()) {
+            return Ordering::Less;
+        }
+        // otherwise the more parts we are, the more specific we sort
+        if self.parts > other.parts {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    }
+}
+
+impl PartialOrd for Step {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Check whether a variable name is a proper identifier: a letter or `_`,
+/// followed by letters, digits and `_`.
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// A variable as written in a step, like `{id:int}`.
+pub(crate) struct Variable<'a> {
+    /// offset of the name, just after the `{`
+    pub(crate) offset: usize,
+    pub(crate) name: &'a str,
+    /// the converter name and its offset
+    pub(crate) converter: Option<(usize, &'a str)>,
+    /// the default value and its offset
+    pub(crate) default: Option<(usize, &'a str)>,
+}
+
+/// Split the variable between the braces at `open` and `close` into its
+/// name, converter and default.
+pub(crate) fn variable(s: &str, open: usize, close: usize) -> Variable<'_> {
+    let inner = &s[open + 1..close];
+    let (name, converter) = match inner.find(':') {
+        Some(k) => (&inner[..k], Some((open + 2 + k, &inner[k + 1..]))),
+        None => (inner, None),
+    };
+    // a regex constraint can contain `=`, so it can't have a default
+    let (name, converter, default) = match converter {
+        Some((offset, converter)) if !converter.starts_with(&REGEX_PREFIX[1..]) => {
+            match converter.find('=') {
+                Some(k) => (
+                    name,
+                    Some((offset, &converter[..k])),
+                    Some((offset + k + 1, &converter[k + 1..])),
+                ),
+                None => (name, Some((offset, converter)), None),
+            }
+        }
+        Some(_) => (name, converter, None),
+        None => match name.find('=') {
+            Some(k) => (&name[..k], None, Some((open + 2 + k, &name[k + 1..]))),
+            None => (name, None, None),
+        },
+    };
+    Variable {
+        offset: open + 1,
+        name,
+        converter,
+        default,
+    }
+}
+
+/// Split a step into its literal parts and the variables between them.
+///
+/// `{{` and `}}` in the literal parts are a literal `{` and `}`.
+fn scan(s: &str) -> Result<(Vec<String>, Vec<Variable<'_>>), ParseError> {
+    let mut parts = Vec::new();
+    let mut variables = Vec::new();
+    let mut part = String::new();
+    let mut part_start = 0;
+    while let Some(i) = s[part_start..].find(['{', '}']) {
+        let open = part_start + i;
+        part.push_str(&s[part_start..open]);
+        let brace = s.as_bytes()[open];
+        if s.as_bytes().get(open + 1) == Some(&brace) {
+            part.push(char::from(brace));
+            part_start = open + 2;
+            continue;
+        }
+        if brace == b'}' {
+            return Err(ParseError::UnbalancedBrace { offset: open });
+        }
+        let close = find_close(s, open)?;
+        parts.push(std::mem::take(&mut part));
+        variables.push(variable(s, open, close));
+        part_start = close + 1;
+    }
+    part.push_str(&s[part_start..]);
+    parts.push(part);
+    Ok((parts, variables))
+}
+
+/// The offset of the `}` that closes the variable opened at `open`.
+///
+/// In a regex constraint like `{sku:re:[A-Z]{3}}` braces nest, and can be
+/// escaped with a backslash.
+pub(crate) fn find_close(s: &str, open: usize) -> Result<usize, ParseError> {
+    let unbalanced = ParseError::UnbalancedBrace { offset: open };
+    let inner = &s[open + 1..];
+    let regex_start = inner
+        .find([':', '{', '}'])
+        .filter(|&k| inner[k..].starts_with(REGEX_PREFIX))
+        .map(|k| k + REGEX_PREFIX.len());
+    let regex_start = match regex_start {
+        Some(regex_start) => regex_start,
+        None => {
+            return match inner.find(['{', '}']) {
+                Some(j) if inner.as_bytes()[j] == b'}' => Ok(open + 1 + j),
+                _ => Err(unbalanced),
+            }
+        }
+    };
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in inner[regex_start..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' if depth == 0 => return Ok(open + 1 + regex_start + i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    Err(unbalanced)
+}
+
+fn get_names(variables: &[Variable]) -> Result<Vec<String>, ParseError> {
+    let mut name_set = HashSet::new();
+    for variable in variables {
+        if !is_identifier(variable.name) {
+            return Err(ParseError::InvalidIdentifier {
+                name: variable.name.to_string(),
+                offset: variable.offset,
+            });
+        }
+        if !name_set.insert(variable.name) {
+            return Err(ParseError::DuplicateVariable {
+                name: variable.name.to_string(),
+                offset: variable.offset,
+            });
+        }
+    }
+    Ok(variables.iter().map(|v| v.name.to_string()).collect())
+}
+
+/// The converter name of wildcard variables.
+const WILDCARD: &str = "*";
+
+/// What precedes the regex of a variable with a regex constraint, like
+/// `{sku:re:[A-Z]{3}-\d+}`.
+const REGEX_PREFIX: &str = ":re:";
+
+/// Check whether a step is a wildcard. A wildcard variable has to make up
+/// the whole step.
+fn is_wildcard(parts: &[String], variables: &[Variable]) -> Result<bool, ParseError> {
+    for variable in variables {
+        if let Some((offset, WILDCARD)) = variable.converter {
+            if variables.len() != 1 || parts.iter().any(|part| !part.is_empty()) {
+                return Err(ParseError::MisplacedWildcard { offset });
+            }
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Check that variables without literal text between them can only split
+/// a segment in one way: of each run of them, all but one need a regex
+/// constraint that always matches the same number of characters, like
+/// `{year:int}{month:re:\d{2}}`.
+fn check_consecutive(
+    parts: &[String],
+    variables: &[Variable],
+    converters: &[Option<NamedConverter>],
+) -> Result<(), ParseError> {
+    let mut flexible = false;
+    for (i, variable) in variables.iter().enumerate() {
+        if !parts[i].is_empty() || i == 0 {
+            flexible = false;
+        }
+        if fixed_len(&converters[i]).is_some() {
+            continue;
+        }
+        if flexible {
+            return Err(ParseError::ConsecutiveVariables {
+                offset: variable.offset - 1,
+            });
+        }
+        flexible = true;
+    }
+    Ok(())
+}
+
+/// The number of characters a variable always captures, if it has one.
+#[cfg(feature = "regex")]
+fn fixed_len(converter: &Option<NamedConverter>) -> Option<usize> {
+    converter
+        .as_ref()?
+        .name
+        .strip_prefix(&REGEX_PREFIX[1..])
+        .and_then(lazy_regex::fixed_len)
+}
+
+#[cfg(not(feature = "regex"))]
+fn fixed_len(_converter: &Option<NamedConverter>) -> Option<usize> {
+    None
+}
+
+/// The default values of the variables, which their converters have to
+/// accept.
+fn get_defaults(
+    variables: &[Variable],
+    converters: &[Option<NamedConverter>],
+) -> Result<Vec<Option<String>>, ParseError> {
+    variables
+        .iter()
+        .zip(converters)
+        .map(|(variable, converter)| match variable.default {
+            Some((offset, value))
+                if value.is_empty()
+                    || converter
+                        .as_ref()
+                        .is_some_and(|converter| !converter.validate(value)) =>
+            {
+                Err(ParseError::InvalidDefault {
+                    value: value.to_string(),
+                    offset,
+                })
+            }
+            Some((_, value)) => Ok(Some(value.to_string())),
+            None => Ok(None),
+        })
+        .collect()
+}
+
+fn get_converters(
+    variables: &[Variable],
+    converters: &ConverterRegistry,
+) -> Result<Vec<Option<NamedConverter>>, ParseError> {
+    variables
+        .iter()
+        .map(|variable| match variable.converter {
+            Some((_, WILDCARD)) => Ok(None),
+            #[cfg(feature = "regex")]
+            Some((offset, name)) if name.starts_with(&REGEX_PREFIX[1..]) => {
+                let regex = &name[REGEX_PREFIX.len() - 1..];
+                NamedConverter::regex(regex)
+                    .map(Some)
+                    .ok_or_else(|| invalid_regex(offset, name))
+            }
+            #[cfg(not(feature = "regex"))]
+            Some((offset, name)) if name.starts_with(&REGEX_PREFIX[1..]) => {
+                Err(ParseError::UnsupportedRegex { offset })
+            }
+            Some((offset, name)) => {
+                converters
+                    .get(name)
+                    .map(Some)
+                    .ok_or_else(|| ParseError::UnknownConverter {
+                        name: name.to_string(),
+                        offset,
+                    })
+            }
+            None => Ok(None),
+        })
+        .collect()
+}
+
+/// The regex matching a whole segment, with a capture group for each
+/// variable. Variables match non-greedily, so a variable stops at the first
+/// occurrence of the literal that follows it. A variable with a regex
+/// constraint matches its regex instead.
+///
+/// Steps of the same shape with the same constraints get the same regex,
+/// whatever their variables are called, so they share it once it is
+/// compiled.
+#[cfg(feature = "regex")]
+fn get_variables_re(
+    parts: &[String],
+    converters: &[Option<NamedConverter>],
+    case_insensitive: bool,
+) -> Option<LazyRegex> {
+    let literal = |part: &str| {
+        if case_insensitive && !part.is_empty() {
+            format!("(?i:{})", regex::escape(part))
+        } else {
+            regex::escape(part)
+        }
+    };
+    let mut variables_re = format!("^{}", literal(&parts[0]));
+    for (converter, part) in converters.iter().zip(&parts[1..]) {
+        variables_re.push_str(&format!("((?:{}))", constraint(converter)));
+        variables_re.push_str(&literal(part));
+    }
+    variables_re.push('$');
+    LazyRegex::new(variables_re)
+}
+
+/// The capture group of each variable in the regex of `get_variables_re`,
+/// after the groups in the constraints of the variables before it.
+#[cfg(feature = "regex")]
+fn capture_groups(converters: &[Option<NamedConverter>]) -> Vec<usize> {
+    let mut group = 1;
+    converters
+        .iter()
+        .map(|converter| {
+            let variable = group;
+            group += 1 + lazy_regex::captures_len(constraint(converter)).unwrap_or(0);
+            variable
+        })
+        .collect()
+}
+
+/// The regex a variable matches.
+#[cfg(feature = "regex")]
+fn constraint(converter: &Option<NamedConverter>) -> &str {
+    converter
+        .as_ref()
+        .and_then(|converter| converter.name.strip_prefix(&REGEX_PREFIX[1..]))
+        .unwrap_or(".+?")
+}
+
+fn eq_text(a: &str, b: &str, case_insensitive: bool) -> bool {
+    if case_insensitive {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+fn has_prefix(s: &str, prefix: &str, case_insensitive: bool) -> bool {
+    prefix.is_empty()
+        || s.get(..prefix.len())
+            .is_some_and(|start| eq_text(start, prefix, case_insensitive))
+}
+
+fn has_suffix(s: &str, suffix: &str, case_insensitive: bool) -> bool {
+    suffix.is_empty()
+        || s.len()
+            .checked_sub(suffix.len())
+            .and_then(|i| s.get(i..))
+            .is_some_and(|end| eq_text(end, suffix, case_insensitive))
+}
+
+/// the offset of the first occurrence of needle in s
+fn find_text(s: &str, needle: &str, case_insensitive: bool) -> Option<usize> {
+    if !case_insensitive {
+        return s.find(needle);
+    }
+    (0..=s.len().checked_sub(needle.len())?).find(|&i| {
+        s.get(i..i + needle.len())
+            .is_some_and(|candidate| candidate.eq_ignore_ascii_case(needle))
+    })
+}
+
+/// The error for the regex constraint in a converter name like `re:\d+`
+/// at offset.
+#[cfg(feature = "regex")]
+fn invalid_regex(offset: usize, converter: &str) -> ParseError {
+    let prefix = REGEX_PREFIX.len() - 1;
+    ParseError::InvalidRegex {
+        regex: converter[prefix..].to_string(),
+        offset: offset + prefix,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "regex")]
+    use proptest::prelude::*;
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn test_step_regex_constraint_unsupported() {
+        assert_eq!(
+            Step::new(r"x{a:re:\d+}").unwrap_err(),
+            ParseError::UnsupportedRegex { offset: 4 }
+        );
+    }
+
+    #[test]
+    fn test_is_identifier() {
+        assert!(is_identifier("foo"));
+        assert!(is_identifier("foo123"));
+        assert!(is_identifier("foo_bar"));
+        assert!(is_identifier("fooBar"));
+        assert!(!is_identifier("123"));
+        assert!(!is_identifier("$foo"));
+    }
+
+    #[test]
+    fn test_step_new_no_variables() {
+        let step = Step::new("foo").unwrap();
+        assert_eq!(step.s, "foo");
+        assert_eq!(step.generalized, "foo");
+        assert_eq!(step.parts, vec!["foo"]);
+        assert_eq!(step.names, vec![] as Vec<String>);
+    }
+
+    #[test]
+    fn test_step_new_one_variable_start() {
+        let step = Step::new("{bar}baz").unwrap();
+        assert_eq!(step.s, "{bar}baz");
+        assert_eq!(step.generalized, "{}baz");
+        assert_eq!(step.parts, vec!["", "baz"]);
+        assert_eq!(step.names, vec!["bar"]);
+    }
+
+    #[test]
+    fn test_step_new_one_variable_middle() {
+        let step = Step::new("foo{bar}baz").unwrap();
+        assert_eq!(step.s, "foo{bar}baz");
+        assert_eq!(step.generalized, "foo{}baz");
+        assert_eq!(step.parts, vec!["foo", "baz"]);
+        assert_eq!(step.names, vec!["bar"]);
+    }
+
+    #[test]
+    fn test_step_new_one_variable_end() {
+        let step = Step::new("foo{bar}").unwrap();
+        assert_eq!(step.s, "foo{bar}");
+        assert_eq!(step.generalized, "foo{}");
+        assert_eq!(step.parts, vec!["foo", ""]);
+        assert_eq!(step.names, vec!["bar"]);
+    }
+
+    #[test]
+    fn test_step_new_one_variable_only() {
+        let step = Step::new("{bar}").unwrap();
+        assert_eq!(step.s, "{bar}");
+        assert_eq!(step.generalized, "{}");
+        assert_eq!(step.parts, vec!["", ""]);
+        assert_eq!(step.names, vec!["bar"]);
+    }
+
+    #[test]
+    fn test_step_multiple_variables() {
+        let step = Step::new("foo{bar}baz{qux}frub").unwrap();
+        assert_eq!(step.s, "foo{bar}baz{qux}frub");
+        assert_eq!(step.generalized, "foo{}baz{}frub");
+        assert_eq!(step.parts, vec!["foo", "baz", "frub"]);
+        assert_eq!(step.names, vec!["bar", "qux"]);
+    }
+
+    #[test]
+    fn test_step_bad_variable() {
+        let step = Step::new("foo{%$}baz");
+        assert!(step.is_err());
+    }
+
+    #[test]
+    fn test_step_duplicate_variable() {
+        let step = Step::new("foo{bar}baz{bar}");
+        assert!(step.is_err());
+    }
+
+    #[test]
+    fn test_step_consecutive_variables() {
+        let step = Step::new("{bar}{baz}");
+        assert!(step.is_err());
+    }
+
+    #[test]
+    fn test_step_escaped_braces() {
+        let step = Step::new("{{draft}}").unwrap();
+        assert!(step.is_literal());
+        assert_eq!(step.parts, vec!["{draft}"]);
+        assert!(step.match_segment("{draft}").is_some());
+        assert!(step.match_segment("{{draft}}").is_none());
+        let step = Step::new("{{{a}}}-{b}").unwrap();
+        assert_eq!(step.parts, vec!["{", "}-", ""]);
+        assert_eq!(step.generalized, "{{{}}}-{}");
+        assert_eq!(
+            step.match_segment("{x}-y"),
+            Some(vec![("a", "x"), ("b", "y")])
+        );
+        assert_eq!(Step::new("{a}}}{{{b}").unwrap().parts, vec!["", "}{", ""]);
+        assert_eq!(
+            Step::new("a{{b}"),
+            Err(ParseError::UnbalancedBrace { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_step_only_open() {
+        let step = Step::new("{bar");
+        assert!(step.is_err());
+    }
+
+    #[test]
+    fn test_invalid_step_only_close() {
+        let step = Step::new("bar}");
+        assert!(step.is_err());
+    }
+
+    #[test]
+    fn test_step_errors() {
+        assert_eq!(
+            Step::new("foo{%$}baz"),
+            Err(ParseError::InvalidIdentifier {
+                name: "%$".to_string(),
+                offset: 4
+            })
+        );
+        assert_eq!(
+            Step::new("foo{bar}baz{bar}"),
+            Err(ParseError::DuplicateVariable {
+                name: "bar".to_string(),
+                offset: 12
+            })
+        );
+        assert_eq!(
+            Step::new("a{bar}{baz}"),
+            Err(ParseError::ConsecutiveVariables { offset: 6 })
+        );
+        assert_eq!(
+            Step::new("a{bar"),
+            Err(ParseError::UnbalancedBrace { offset: 1 })
+        );
+        assert_eq!(
+            Step::new("a{b{c}"),
+            Err(ParseError::UnbalancedBrace { offset: 1 })
+        );
+        assert_eq!(
+            Step::new("bar}"),
+            Err(ParseError::UnbalancedBrace { offset: 3 })
+        );
+        assert_eq!(
+            Step::new("{bar:unknown}"),
+            Err(ParseError::UnknownConverter {
+                name: "unknown".to_string(),
+                offset: 5
+            })
+        );
+    }
+
+    #[test]
+    fn test_match_segment_no_variables() {
+        let step = Step::new("foo").unwrap();
+        assert!(step.match_segment("foo").is_some());
+        assert!(step.match_segment("bar").is_none());
+    }
+
+    #[test]
+    fn test_match_segment_one_variable() {
+        let step = Step::new("{bar}").unwrap();
+        assert_eq!(step.match_segment("foo").unwrap(), vec![("bar", "foo")]);
+    }
+
+    #[test]
+    fn test_match_segment_two_variables() {
+        let step = Step::new("start{a}middle{b}end").unwrap();
+        assert_eq!(
+            step.match_segment("startAmiddleBend").unwrap(),
+            vec![("a", "A"), ("b", "B")]
+        );
+    }
+
+    #[test]
+    fn test_expand_no_variables() {
+        let step = Step::new("foo").unwrap();
+        assert_eq!(step.expand(&HashMap::new(), None).unwrap(), "foo");
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let step = Step::new("start{a}middle{b}end").unwrap();
+        let mut values = HashMap::new();
+        values.insert("a", "A");
+        values.insert("b", "B");
+        assert_eq!(step.expand(&values, None).unwrap(), "startAmiddleBend");
+    }
+
+    #[test]
+    fn test_expand_missing_variable() {
+        let step = Step::new("{a}-{b}").unwrap();
+        let mut values = HashMap::new();
+        values.insert("a", "A");
+        assert!(step.expand(&values, None).is_err());
+    }
+
+    #[test]
+    fn test_step_converter() {
+        let step = Step::new("foo{bar:int}").unwrap();
+        assert_eq!(step.s, "foo{bar:int}");
+        assert_eq!(step.generalized, "foo{}");
+        assert_eq!(step.parts, vec!["foo", ""]);
+        assert_eq!(step.names, vec!["bar"]);
+        assert_eq!(step.converters[0].as_ref().unwrap().name, "int");
+    }
+
+    #[test]
+    fn test_step_unknown_converter() {
+        assert!(Step::new("{bar:unknown}").is_err());
+    }
+
+    #[test]
+    fn test_step_converter_bad_variable() {
+        assert!(Step::new("{%$:int}").is_err());
+    }
+
+    #[test]
+    fn test_match_segment_converter() {
+        let step = Step::new("{a:int}-{b}").unwrap();
+        assert_eq!(
+            step.match_segment("10-x").unwrap(),
+            vec![("a", "10"), ("b", "x")]
+        );
+        assert!(step.match_segment("x-x").is_none());
+    }
+
+    #[test]
+    fn test_expand_converter() {
+        let step = Step::new("{a:int}").unwrap();
+        let mut values = HashMap::new();
+        values.insert("a", "10");
+        assert_eq!(step.expand(&values, None).unwrap(), "10");
+        values.insert("a", "x");
+        assert!(step.expand(&values, None).is_err());
+    }
+
+    #[test]
+    fn test_step_defaults() {
+        let step = Step::new("{page:int=1}-{title=none}").unwrap();
+        assert_eq!(step.names, vec!["page", "title"]);
+        assert_eq!(
+            step.defaults().collect::<Vec<_>>(),
+            vec![("page", "1"), ("title", "none")]
+        );
+        assert_eq!(step.expand(&HashMap::new(), None).unwrap(), "1-none");
+        assert_eq!(
+            step.match_segment("2-x"),
+            Some(vec![("page", "2"), ("title", "x")])
+        );
+        let mut values = HashMap::new();
+        values.insert("page", "1");
+        assert!(!step.overridden(&values));
+        values.insert("title", "x");
+        assert!(step.overridden(&values));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_step_regex_constraint_no_default() {
+        let step = Step::new("{a:re:x=y}").unwrap();
+        assert_eq!(step.defaults().count(), 0);
+        assert!(step.match_segment("x=y").is_some());
+    }
+
+    #[test]
+    fn test_match_segment_anchored() {
+        let step = Step::new("foo").unwrap();
+        assert!(step.match_segment("xfoo").is_none());
+        assert!(step.match_segment("foox").is_none());
+        let step = Step::new("a{x}b").unwrap();
+        assert!(step.match_segment("xaYb").is_none());
+        assert!(step.match_segment("aYbx").is_none());
+        assert_eq!(step.match_segment("aYb").unwrap(), vec![("x", "Y")]);
+    }
+
+    #[test]
+    fn test_match_segment_non_greedy() {
+        let step = Step::new("{a}x{b}").unwrap();
+        assert_eq!(
+            step.match_segment("fooxbarxbaz").unwrap(),
+            vec![("a", "foo"), ("b", "barxbaz")]
+        );
+    }
+
+    #[test]
+    fn test_match_segment_literal_is_not_regex() {
+        let step = Step::new("{name}.html").unwrap();
+        assert_eq!(
+            step.match_segment("index.html").unwrap(),
+            vec![("name", "index")]
+        );
+        assert!(step.match_segment("indexxhtml").is_none());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_step_regex_constraint() {
+        let step = Step::new(r"{sku:re:[A-Z]{3}-\d+}").unwrap();
+        assert_eq!(step.names, vec!["sku"]);
+        assert_eq!(step.match_segment("ABC-12"), Some(vec![("sku", "ABC-12")]));
+        assert_eq!(step.match_segment("AB-12"), None);
+        assert_eq!(step.match_segment("ABC-12x"), None);
+        assert_eq!(step.specificity(), 1);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_step_regex_constraint_with_literals() {
+        let step = Step::new(r"v{major:re:\d+}.{minor}").unwrap();
+        assert_eq!(
+            step.match_segment("v12.3.4"),
+            Some(vec![("major", "12"), ("minor", "3.4")])
+        );
+        let step = Step::new(r"{a:re:[a-z]+\.[a-z]+}.{b}").unwrap();
+        assert_eq!(
+            step.match_segment("foo.bar.baz"),
+            Some(vec![("a", "foo.bar"), ("b", "baz")])
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_step_regex_constraint_escaped_brace() {
+        let step = Step::new(r"{a:re:\}+}").unwrap();
+        assert_eq!(step.match_segment("}}"), Some(vec![("a", "}}")]));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_step_regex_constraint_errors() {
+        assert_eq!(
+            Step::new("x{a:re:[a-z}").unwrap_err(),
+            ParseError::InvalidRegex {
+                regex: "[a-z".to_string(),
+                offset: 7
+            }
+        );
+        assert_eq!(
+            Step::new("{a:re:(?P<g>x)}-{b:re:(?P<g>y)}").unwrap_err(),
+            ParseError::InvalidRegex {
+                regex: "(?P<g>x)".to_string(),
+                offset: 6
+            }
+        );
+        assert_eq!(
+            Step::new("{a:re:x{2}").unwrap_err(),
+            ParseError::UnbalancedBrace { offset: 0 }
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_step_consecutive_variables_fixed_len() {
+        let step = Step::new(r"{year:int}{month:re:\d{2}}").unwrap();
+        assert_eq!(
+            step.match_segment("202401"),
+            Some(vec![("year", "2024"), ("month", "01")])
+        );
+        assert_eq!(step.match_segment("1"), None);
+        let step = Step::new(r"{a:re:[a-z]}{b}{c:re:\d\d}.{ext}").unwrap();
+        assert_eq!(
+            step.match_segment("xyz12.tar.gz"),
+            Some(vec![
+                ("a", "x"),
+                ("b", "yz"),
+                ("c", "12"),
+                ("ext", "tar.gz")
+            ])
+        );
+        assert_eq!(
+            Step::new(r"{a:re:\d{2}}{b}{c:int}").unwrap_err(),
+            ParseError::ConsecutiveVariables { offset: 15 }
+        );
+        assert_eq!(
+            Step::new(r"{a:re:[a-z]+}{b}").unwrap_err(),
+            ParseError::ConsecutiveVariables { offset: 13 }
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_expand_regex_constraint() {
+        let step = Step::new(r"{a:re:\d+}").unwrap();
+        let values = [("a", "10")].iter().cloned().collect();
+        assert_eq!(step.expand(&values, None).unwrap(), "10");
+        let values = [("a", "x")].iter().cloned().collect();
+        assert_eq!(
+            step.expand(&values, None),
+            Err(ExpandError::Conversion(ConversionError {
+                name: "a".to_string(),
+                value: "x".to_string(),
+                converter: r"re:\d+".to_string(),
+                direction: Direction::Encode,
+            }))
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_rejection() {
+        let step = Step::new(r"{a:re:\d+}").unwrap();
+        assert_eq!(step.rejection("x"), Some(("a", "x", r"re:\d+")));
+        assert_eq!(step.rejection("10"), None);
+        assert_eq!(step.rejection(""), None);
+        let step = Step::new("v{a:re:[a-z]+}").unwrap();
+        assert_eq!(step.rejection("v1"), None);
+    }
+
+    #[test]
+    fn test_step_wildcard() {
+        let step = Step::new("{rest:*}").unwrap();
+        assert!(step.wildcard);
+        assert_eq!(step.names, vec!["rest"]);
+        assert!(!Step::new("{rest}").unwrap().wildcard);
+    }
+
+    #[test]
+    fn test_step_wildcard_not_whole_step() {
+        assert_eq!(
+            Step::new("a{rest:*}"),
+            Err(ParseError::MisplacedWildcard { offset: 7 })
+        );
+        assert_eq!(
+            Step::new("{a}-{rest:*}"),
+            Err(ParseError::MisplacedWildcard { offset: 10 })
+        );
+    }
+
+    #[test]
+    fn test_expand_encode() {
+        let step = Step::new("a b{x}").unwrap();
+        let mut values = HashMap::new();
+        values.insert("x", "c/d");
+        assert_eq!(step.expand(&values, None).unwrap(), "a bc/d");
+        assert_eq!(step.expand(&values, Some('/')).unwrap(), "a%20bc%2Fd");
+        let step = Step::new("{x:*}").unwrap();
+        assert_eq!(step.expand(&values, Some('/')).unwrap(), "c/d");
+    }
+
+    #[test]
+    fn test_same_shape() {
+        let step = Step::new("a{x:int}b{y}").unwrap();
+        assert!(step.same_shape(&Step::new("a{z:int}b{w}").unwrap()));
+        assert!(!step.same_shape(&Step::new("a{z}b{w}").unwrap()));
+        assert!(!step.same_shape(&Step::new("a{z:int}c{w}").unwrap()));
+    }
+
+    #[test]
+    fn test_generalizes() {
+        let any = Step::new("{x}").unwrap();
+        let int = Step::new("{x:int}").unwrap();
+        let prefixed = Step::new("a{x}").unwrap();
+        let literal = Step::new("a1").unwrap();
+        assert!(any.generalizes(&int));
+        assert!(any.generalizes(&prefixed));
+        assert!(any.generalizes(&literal));
+        assert!(prefixed.generalizes(&literal));
+        assert!(!int.generalizes(&literal));
+        assert!(int.generalizes(&Step::new("10").unwrap()));
+        assert!(!int.generalizes(&any));
+        assert!(!prefixed.generalizes(&any));
+        assert!(!literal.generalizes(&prefixed));
+    }
+
+    fn sorted_steps(l: Vec<&str>) -> Vec<String> {
+        let mut steps: Vec<Step> = l.iter().map(|s| Step::new(s).unwrap()).collect();
+        steps.sort();
+        steps.into_iter().map(|step| step.s).collect()
+    }
+
+    #[test]
+    fn test_order_prefix_earlier() {
+        assert_eq!(
+            sorted_steps(vec!["{foo}", "prefix{foo}"]),
+            vec!["prefix{foo}", "{foo}"]
+        )
+    }
+
+    #[test]
+    fn test_order_postfix_earlier() {
+        assert_eq!(
+            sorted_steps(vec!["{foo}", "{foo}postfix"]),
+            vec!["{foo}postfix", "{foo}"]
+        )
+    }
+
+    #[cfg(feature = "regex")]
+    fn regex_capture(step: &Step, s: &str) -> Option<Vec<String>> {
+        let re = get_variables_re(&step.parts, &step.converters, false).unwrap();
+        let c = re.get().unwrap().captures(s)?;
+        Some(
+            capture_groups(&step.converters)
+                .into_iter()
+                .map(|group| c[group].to_string())
+                .collect(),
+        )
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_groups() {
+        let step = Step::new(r"{a:re:(x)(?P<b>y)}-{b}-{c:re:\d}").unwrap();
+        assert_eq!(step.groups, vec![1, 4, 5]);
+        assert_eq!(
+            step.match_segment("xy-z-1"),
+            Some(vec![("a", "xy"), ("b", "z"), ("c", "1")])
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_shared_between_variable_names() {
+        let first = Step::new(r"a{x:re:\d+}").unwrap();
+        let second = Step::new(r"a{y:re:\d+}").unwrap();
+        assert_eq!(second.match_segment("a12"), Some(vec![("y", "12")]));
+        let source = |step: &Step| {
+            let regex = step.variables_re.as_ref().unwrap().get().unwrap();
+            regex.as_str().as_ptr()
+        };
+        assert_eq!(source(&first), source(&second));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let mut step = Step::new("Report-{id}.PDF").unwrap();
+        assert!(step.match_segment("report-1.pdf").is_none());
+        step.set_case_insensitive(true);
+        assert_eq!(
+            step.match_segment("REPORT-Ab.pdf"),
+            Some(vec![("id", "Ab")])
+        );
+        assert!(step.match_segment("report-1.txt").is_none());
+        let mut step = Step::new("INDEX").unwrap();
+        step.set_case_insensitive(true);
+        assert!(step.match_segment("index").is_some());
+        assert!(step.match_segment("indexes").is_none());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_case_insensitive_regex() {
+        let mut step = Step::new(r"V{v:re:[A-Z]\d}").unwrap();
+        step.set_case_insensitive(true);
+        assert_eq!(step.match_segment("vA1"), Some(vec![("v", "A1")]));
+        assert!(step.match_segment("va1").is_none());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_step_without_constraints_has_no_regex() {
+        assert!(Step::new("a{b}c{d:int}").unwrap().variables_re.is_none());
+        assert!(Step::new(r"a{b:re:\d}").unwrap().variables_re.is_some());
+    }
+
+    #[cfg(feature = "regex")]
+    proptest! {
+        #[test]
+        fn test_capture_agrees_with_regex(
+            step_s in "(a|ab|é)?\\{x\\}(a|ab|é)?(\\{y\\}(b|ba|aa))?",
+            s in "[abé\n]{0,10}",
+        ) {
+            if let Ok(step) = Step::new(&step_s) {
+                let mut variables = Vec::new();
+                let captured = if step.capture_into(&s, &mut variables) {
+                    Some(variables.iter().map(|(_, value)| value.to_string()).collect())
+                } else {
+                    None
+                };
+                prop_assert_eq!(captured, regex_capture(&step, &s));
+            }
+        }
+    }
+
+    // proptest! {
+    //     #[test]
+    //     fn doesnt_crash(s in "\\PC*") {
+    //         Step::new(&s).unwrap();
+    //     }
+    // }
+}
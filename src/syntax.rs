@@ -0,0 +1,344 @@
+use std::ops::Range;
+
+use crate::error::ParseError;
+use crate::pattern;
+use crate::step;
+
+/// Where something is written in a pattern, in bytes.
+pub type Span = Range<usize>;
+
+/// The syntax tree of a pattern, for tools that work with pattern text,
+/// like editors, formatters and linters.
+///
+/// Every node has the span it is written at, so a tool can point at any
+/// part of the pattern, and nothing of the source is lost. Parsing only
+/// checks the structure of a pattern: whether variable names are
+/// identifiers and their converters exist is up to `Pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Syntax<'a> {
+    pub source: &'a str,
+    pub separator: char,
+    /// the separator the pattern starts with, if any
+    pub leading: Option<Span>,
+    pub steps: Vec<StepSyntax>,
+    /// the `[` and `]` around the optional steps
+    pub optional: Option<(Span, Span)>,
+    /// the `?` before the query parameters
+    pub query: Option<Span>,
+    pub params: Vec<ParamSyntax>,
+}
+
+/// A step, or the value of a query parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepSyntax {
+    pub span: Span,
+    pub optional: bool,
+    pub pieces: Vec<Piece>,
+}
+
+/// Literal text, as written with doubled braces, or a variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece {
+    Literal(Span),
+    Variable(VariableSyntax),
+}
+
+/// A variable like `{id:int=1}`. The span includes the braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableSyntax {
+    pub span: Span,
+    pub name: Span,
+    /// the converter after the `:`, a regex constraint like `re:\d+` too
+    pub converter: Option<Span>,
+    /// the default value after the `=`
+    pub default: Option<Span>,
+}
+
+/// A query parameter like `version={v}`. The span of an optional parameter
+/// includes its brackets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamSyntax {
+    pub span: Span,
+    pub optional: bool,
+    pub key: Span,
+    pub value: StepSyntax,
+}
+
+impl<'a> Syntax<'a> {
+    pub fn parse(s: &'a str) -> Result<Syntax<'a>, ParseError> {
+        Syntax::with_separator(s, '/')
+    }
+
+    /// Parse a pattern with another separator than `/`.
+    ///
+    /// # Panics
+    ///
+    /// If the separator can't be used in patterns, as for
+    /// `Pattern::with_separator`.
+    pub fn with_separator(s: &'a str, separator: char) -> Result<Syntax<'a>, ParseError> {
+        pattern::assert_separator(separator);
+        let leading = if s.starts_with(separator) {
+            separator.len_utf8()
+        } else {
+            0
+        };
+        let rest = &s[leading..];
+        let (path_s, query) = match pattern::find_top_level(rest, '?').next() {
+            Some(i) => (&rest[..i], Some(leading + i)),
+            None => (rest, None),
+        };
+        let (step_strs, required) =
+            pattern::split_steps(path_s, separator).map_err(|e| e.shifted(leading))?;
+        let steps = step_strs
+            .iter()
+            .enumerate()
+            .map(|(i, &(offset, step_s))| step_syntax(step_s, leading + offset, i >= required))
+            .collect::<Result<Vec<_>, _>>()?;
+        let optional = steps.get(required).map(|first| {
+            let open = first.span.start - separator.len_utf8() - 1;
+            let close = leading + path_s.len() - 1;
+            (open..open + 1, close..close + 1)
+        });
+        let params = match query {
+            Some(i) => params_syntax(&s[i + 1..], i + 1)?,
+            None => Vec::new(),
+        };
+        Ok(Syntax {
+            source: s,
+            separator,
+            leading: (leading > 0).then_some(0..leading),
+            steps,
+            optional,
+            query: query.map(|i| i..i + 1),
+            params,
+        })
+    }
+
+    /// The text at a span of this pattern.
+    pub fn text(&self, span: &Span) -> &'a str {
+        &self.source[span.clone()]
+    }
+
+    /// The variables of the pattern, in the order they are written in.
+    pub fn variables(&self) -> impl Iterator<Item = &VariableSyntax> {
+        self.steps
+            .iter()
+            .chain(self.params.iter().map(|param| &param.value))
+            .flat_map(|step| &step.pieces)
+            .filter_map(|piece| match piece {
+                Piece::Variable(variable) => Some(variable),
+                Piece::Literal(_) => None,
+            })
+    }
+}
+
+/// The pieces of a step written at `offset`.
+fn step_syntax(s: &str, offset: usize, optional: bool) -> Result<StepSyntax, ParseError> {
+    let at = |(start, text): (usize, &str)| offset + start..offset + start + text.len();
+    let mut pieces = Vec::new();
+    let mut literal_start = 0;
+    let mut i = 0;
+    while let Some(k) = s[i..].find(['{', '}']) {
+        let open = i + k;
+        let brace = s.as_bytes()[open];
+        if s.as_bytes().get(open + 1) == Some(&brace) {
+            i = open + 2;
+            continue;
+        }
+        if brace == b'}' {
+            return Err(ParseError::UnbalancedBrace {
+                offset: offset + open,
+            });
+        }
+        let close = step::find_close(s, open).map_err(|e| e.shifted(offset))?;
+        if literal_start < open {
+            pieces.push(Piece::Literal(offset + literal_start..offset + open));
+        }
+        let variable = step::variable(s, open, close);
+        pieces.push(Piece::Variable(VariableSyntax {
+            span: offset + open..offset + close + 1,
+            name: at((variable.offset, variable.name)),
+            converter: variable.converter.map(at),
+            default: variable.default.map(at),
+        }));
+        i = close + 1;
+        literal_start = i;
+    }
+    if literal_start < s.len() {
+        pieces.push(Piece::Literal(offset + literal_start..offset + s.len()));
+    }
+    Ok(StepSyntax {
+        span: offset..offset + s.len(),
+        optional,
+        pieces,
+    })
+}
+
+/// The parameters of the query part `s` of a pattern, written at `offset`.
+fn params_syntax(s: &str, offset: usize) -> Result<Vec<ParamSyntax>, ParseError> {
+    let mut params = Vec::new();
+    for (start, param_s) in pattern::split_top_level(s, offset, '&') {
+        let (inner_start, inner, optional) =
+            match param_s.strip_prefix('[').and_then(|p| p.strip_suffix(']')) {
+                Some(inner) => (start + 1, inner, true),
+                None => (start, param_s, false),
+            };
+        let key = match inner.find('=') {
+            Some(i) if i > 0 && !inner[..i].contains(['{', '}', '[', ']']) => i,
+            _ => return Err(ParseError::InvalidQuery { offset: start }),
+        };
+        let value_start = inner_start + key + 1;
+        let value_s = &inner[key + 1..];
+        if let Some(i) = value_s.find(['[', ']']) {
+            return Err(ParseError::MisplacedOptional {
+                offset: value_start + i,
+            });
+        }
+        params.push(ParamSyntax {
+            span: start..start + param_s.len(),
+            optional,
+            key: inner_start..inner_start + key,
+            value: step_syntax(value_s, value_start, optional)?,
+        });
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pattern::Pattern;
+
+    fn texts<'a>(syntax: &Syntax<'a>, step: &StepSyntax) -> Vec<&'a str> {
+        step.pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Literal(span) => syntax.text(span),
+                Piece::Variable(variable) => syntax.text(&variable.span),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let syntax =
+            Syntax::parse("/users/{id:int}.{{x}}[/{tab=info}]?[lang={l}]&v=v{n:re:\\d{2}}")
+                .unwrap();
+        assert_eq!(syntax.leading, Some(0..1));
+        let steps: Vec<_> = syntax
+            .steps
+            .iter()
+            .map(|step| texts(&syntax, step))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                vec!["users"],
+                vec!["{id:int}", ".{{x}}"],
+                vec!["{tab=info}"]
+            ]
+        );
+        assert_eq!(
+            syntax
+                .steps
+                .iter()
+                .map(|step| step.optional)
+                .collect::<Vec<_>>(),
+            vec![false, false, true]
+        );
+        let (open, close) = syntax.optional.clone().unwrap();
+        assert_eq!((syntax.text(&open), syntax.text(&close)), ("[", "]"));
+        assert_eq!(syntax.text(&syntax.query.clone().unwrap()), "?");
+
+        let variables: Vec<_> = syntax
+            .variables()
+            .map(|variable| {
+                (
+                    syntax.text(&variable.name),
+                    variable.converter.as_ref().map(|span| syntax.text(span)),
+                    variable.default.as_ref().map(|span| syntax.text(span)),
+                )
+            })
+            .collect();
+        assert_eq!(
+            variables,
+            vec![
+                ("id", Some("int"), None),
+                ("tab", None, Some("info")),
+                ("l", None, None),
+                ("n", Some("re:\\d{2}"), None),
+            ]
+        );
+        let params: Vec<_> = syntax
+            .params
+            .iter()
+            .map(|param| {
+                (
+                    syntax.text(&param.span),
+                    syntax.text(&param.key),
+                    texts(&syntax, &param.value),
+                    param.optional,
+                )
+            })
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("[lang={l}]", "lang", vec!["{l}"], true),
+                ("v=v{n:re:\\d{2}}", "v", vec!["v", "{n:re:\\d{2}}"], false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_separator() {
+        let syntax = Syntax::with_separator("a.{b}[.c]", '.').unwrap();
+        assert_eq!(syntax.leading, None);
+        assert_eq!(syntax.steps.len(), 3);
+        assert_eq!(syntax.optional, Some((5..6, 8..9)));
+        assert_eq!(syntax.text(&syntax.steps[2].span), "c");
+    }
+
+    #[test]
+    fn test_parse_agrees_with_pattern() {
+        for s in [
+            "",
+            "/",
+            "a/{b}",
+            "{a:int}x{b}/{rest:*}",
+            "a[/{b}/{c}]",
+            "a?x={x}&[y={y:int=1}]",
+        ] {
+            let syntax = Syntax::parse(s).unwrap();
+            let pattern = Pattern::new(s).unwrap();
+            let names: Vec<_> = syntax
+                .variables()
+                .map(|variable| syntax.text(&variable.name))
+                .collect();
+            assert_eq!(names, pattern.names().collect::<Vec<_>>(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Syntax::parse("/a/{b").unwrap_err(),
+            ParseError::UnbalancedBrace { offset: 3 }
+        );
+        assert_eq!(
+            Syntax::parse("a}").unwrap_err(),
+            ParseError::UnbalancedBrace { offset: 1 }
+        );
+        assert_eq!(
+            Syntax::parse("/a[/b]/c").unwrap_err(),
+            ParseError::MisplacedOptional { offset: 5 }
+        );
+        assert_eq!(
+            Syntax::parse("a?x={x}&y").unwrap_err(),
+            ParseError::InvalidQuery { offset: 8 }
+        );
+        // only the structure is checked
+        assert!(Syntax::parse("{1:nothing}").is_ok());
+    }
+}
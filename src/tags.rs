@@ -0,0 +1,105 @@
+use crate::router::Router;
+
+/// The tags of a route, like `public` or `internal`, kept in its metadata.
+///
+/// ```
+/// use traject::{Metadata, Router, Tags};
+///
+/// let mut router = Router::new();
+/// let public = Metadata::new().with(Tags::new(["public"]));
+/// router.register_with_metadata("about", 1, public).unwrap();
+/// router.register("admin", 2).unwrap();
+/// let public = router.filter_by_tag("public");
+/// assert!(public.resolve("about").is_some());
+/// assert!(public.resolve("admin").is_none());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+    tags: Vec<String>,
+}
+
+impl Tags {
+    pub fn new<I, S>(tags: I) -> Tags
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Tags {
+            tags: tags.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+}
+
+impl<T: Clone> Router<T> {
+    /// A router with just the routes tagged with a tag, as by `filtered`.
+    pub fn filter_by_tag(&self, tag: &str) -> Router<T> {
+        self.filtered(|route| {
+            route
+                .metadata
+                .get::<Tags>()
+                .is_some_and(|tags| tags.contains(tag))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::error::ExpandError;
+    use crate::metadata::Metadata;
+    use crate::router::Resolution;
+
+    #[test]
+    fn test_tags() {
+        let tags = Tags::new(["public", "api"]);
+        assert!(tags.contains("api"));
+        assert!(!tags.contains("internal"));
+        assert_eq!(tags.iter().collect::<Vec<_>>(), vec!["public", "api"]);
+    }
+
+    #[test]
+    fn test_filter_by_tag() {
+        let mut router = Router::new();
+        let tagged = |tags: &[&str]| Metadata::new().with(Tags::new(tags.iter().copied()));
+        router
+            .register_with_metadata("users/{id}", 1, tagged(&["public", "api"]))
+            .unwrap();
+        router
+            .register_with_metadata("admin/{page}", 2, tagged(&["internal"]))
+            .unwrap();
+        router.register_named("status", "status", 3).unwrap();
+        router.set_fallback(0);
+
+        let public = router.filter_by_tag("public");
+        assert_eq!(public.iter().count(), 1);
+        assert_eq!(public.resolve("users/1").map(|(value, _)| *value), Some(1));
+        assert!(public.resolve("admin/1").is_none());
+        assert!(matches!(
+            public.lookup("admin/1"),
+            Resolution::Fallback(&0, ..)
+        ));
+        let internal = router.filter_by_tag("internal");
+        assert_eq!(
+            internal.resolve("admin/1").map(|(value, _)| *value),
+            Some(2)
+        );
+        assert!(router.filter_by_tag("missing").resolve("status").is_none());
+        assert_eq!(
+            public.path_for("status", &HashMap::new()),
+            Err(ExpandError::UnknownRoute {
+                name: "status".to_string()
+            })
+        );
+    }
+}
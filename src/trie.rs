@@ -0,0 +1,144 @@
+use std::borrow::Cow;
+
+use crate::hash::Map;
+use crate::pattern::Pattern;
+use crate::step::Step;
+
+/// A trie over the steps of registered patterns.
+///
+/// Literal steps are looked up by segment, ignoring ASCII case so that
+/// case-insensitive patterns are found too; all steps with variables share
+/// a single edge. Routes ending in a wildcard are kept at the node before the
+/// wildcard, as they match any non-empty rest of the path. Walking the trie
+/// for a path yields the routes that could match it, which the caller then
+/// matches for real. Candidates are only ever false positives, never missed.
+#[derive(Debug, Default)]
+pub(crate) struct Node {
+    literals: Map<String, Node>,
+    variable: Option<Box<Node>>,
+    routes: Vec<usize>,
+    wildcards: Vec<usize>,
+}
+
+impl Node {
+    /// Add route `index` with `pattern` to the trie.
+    pub(crate) fn insert(&mut self, pattern: &Pattern, index: usize) {
+        for steps in pattern.variants() {
+            self.insert_steps(steps, index);
+        }
+    }
+
+    fn insert_steps(&mut self, steps: &[Step], index: usize) {
+        let mut node = self;
+        for step in steps {
+            if step.wildcard {
+                node.wildcards.push(index);
+                return;
+            }
+            node = if step.is_literal() {
+                node.literals
+                    .entry(step.text().to_ascii_lowercase())
+                    .or_default()
+            } else {
+                node.variable.get_or_insert_with(Default::default)
+            };
+        }
+        node.routes.push(index);
+    }
+
+    /// The indexes of routes that could match `segments`, in ascending order.
+    pub(crate) fn candidates(&self, segments: &[&str]) -> Vec<usize> {
+        let lowercase: Vec<Cow<str>> = segments
+            .iter()
+            .map(|segment| {
+                if segment.bytes().any(|b| b.is_ascii_uppercase()) {
+                    Cow::Owned(segment.to_ascii_lowercase())
+                } else {
+                    Cow::Borrowed(*segment)
+                }
+            })
+            .collect();
+        let segments: Vec<&str> = lowercase.iter().map(|segment| segment.as_ref()).collect();
+        let mut result = Vec::new();
+        self.collect(&segments, &mut result);
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    fn collect(&self, segments: &[&str], result: &mut Vec<usize>) {
+        match segments.split_first() {
+            None => result.extend(&self.routes),
+            Some((segment, rest)) => {
+                result.extend(&self.wildcards);
+                if let Some(node) = self.literals.get(*segment) {
+                    node.collect(rest, result);
+                }
+                if let Some(node) = &self.variable {
+                    node.collect(rest, result);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trie(patterns: &[&str]) -> Node {
+        let mut node = Node::default();
+        for (i, pattern) in patterns.iter().enumerate() {
+            node.insert(&Pattern::new(pattern).unwrap(), i);
+        }
+        node
+    }
+
+    #[test]
+    fn test_candidates_literal() {
+        let node = trie(&["a/b", "a/c", "a/b/c"]);
+        assert_eq!(node.candidates(&["a", "b"]), vec![0]);
+        assert_eq!(node.candidates(&["a", "c"]), vec![1]);
+        assert_eq!(node.candidates(&["a", "b", "c"]), vec![2]);
+        assert_eq!(node.candidates(&["a", "d"]), vec![] as Vec<usize>);
+    }
+
+    #[test]
+    fn test_candidates_ignore_case() {
+        let node = trie(&["a/B", "A/b"]);
+        assert_eq!(node.candidates(&["a", "b"]), vec![0, 1]);
+        assert_eq!(node.candidates(&["A", "B"]), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_candidates_variable() {
+        let node = trie(&["a/{x}", "a/b", "{y}/b"]);
+        assert_eq!(node.candidates(&["a", "b"]), vec![0, 1, 2]);
+        assert_eq!(node.candidates(&["a", "z"]), vec![0]);
+        assert_eq!(node.candidates(&["z", "b"]), vec![2]);
+    }
+
+    #[test]
+    fn test_candidates_partial_literal_step() {
+        let node = trie(&["a/x{y}"]);
+        assert_eq!(node.candidates(&["a", "xz"]), vec![0]);
+        // false positive, filtered out by matching
+        assert_eq!(node.candidates(&["a", "z"]), vec![0]);
+    }
+
+    #[test]
+    fn test_candidates_wildcard() {
+        let node = trie(&["static/{path:*}", "static/a"]);
+        assert_eq!(node.candidates(&["static", "a"]), vec![0, 1]);
+        assert_eq!(node.candidates(&["static", "a", "b"]), vec![0]);
+        assert_eq!(node.candidates(&["static"]), vec![] as Vec<usize>);
+    }
+
+    #[test]
+    fn test_candidates_optional() {
+        let node = trie(&["a[/{b}]", "a[/c]"]);
+        assert_eq!(node.candidates(&["a"]), vec![0, 1]);
+        assert_eq!(node.candidates(&["a", "c"]), vec![0, 1]);
+        assert_eq!(node.candidates(&["a", "d"]), vec![0]);
+    }
+}
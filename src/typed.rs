@@ -0,0 +1,20 @@
+/// A route declared with the `routes!` macro of `traject-derive`, as a
+/// type.
+///
+/// `routes!` generates a zero-sized type for each route that implements
+/// this, like `route::Employee` for the `Employee` variant of `Route`. It
+/// is a key that the compiler checks: `Route::path_for::<route::Employee>`
+/// only takes the values of that route, and `ROUTE` gives the variant, to
+/// attach a handler to the route without spelling its name.
+pub trait TypedRoute {
+    /// The enum the route is a variant of.
+    type Route;
+    /// The typed values of the variables of the route.
+    type Params<'a>;
+
+    /// The variant of the route.
+    const ROUTE: Self::Route;
+
+    /// The path of the route with these values.
+    fn path(params: &Self::Params<'_>) -> String;
+}
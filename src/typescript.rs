@@ -0,0 +1,283 @@
+//! Exporting named routes as TypeScript definitions.
+
+use std::fmt::Write;
+
+use crate::encoding::json_quote;
+use crate::pattern::Pattern;
+use crate::router::Router;
+use crate::step::Step;
+
+/// What the generated `pathFor` needs besides the routes.
+const PRELUDE: &str = "\
+const encode = (value: string | number | boolean): string =>
+  encodeURIComponent(String(value));
+
+const query = (params: (string | undefined)[]): string => {
+  const given = params.filter((param) => param !== undefined);
+  return given.length > 0 ? \"?\" + given.join(\"&\") : \"\";
+};
+";
+
+impl<T> Router<T> {
+    /// TypeScript definitions for the named routes: an interface with the
+    /// variables of each route, and a `pathFor(name, params)` function that
+    /// builds their paths, so that frontend code is checked against the
+    /// routes of the backend.
+    ///
+    /// Variables of `int` and `float` converters are numbers, of `bool`
+    /// converters booleans, other variables strings. Variables with a
+    /// default, in optional steps or in optional query parameters are
+    /// optional. Paths are built like `path_for` builds them, with the
+    /// values percent-encoded, and without a leading separator. Optional
+    /// steps are in the path when one of their variables is given.
+    pub fn to_typescript(&self) -> String {
+        let routes: Vec<_> = self
+            .iter()
+            .filter_map(|route| Some((route.name?, route.pattern)))
+            .collect();
+        let mut ts = String::from("// Generated from the named routes of a traject router.\n");
+        for (name, pattern) in &routes {
+            write!(ts, "\nexport interface {} {{\n", interface_name(name)).unwrap();
+            for (variable, optional, ts_type) in variables(pattern) {
+                let optional = if optional { "?" } else { "" };
+                writeln!(ts, "  {}{}: {};", variable, optional, ts_type).unwrap();
+            }
+            ts.push_str("}\n");
+        }
+        ts.push_str("\nexport interface RouteParams {\n");
+        for (name, _) in &routes {
+            writeln!(ts, "  {}: {};", json_quote(name), interface_name(name)).unwrap();
+        }
+        ts.push_str("}\n\n");
+        ts.push_str(PRELUDE);
+        ts.push_str(
+            "\nconst routes: { [N in keyof RouteParams]: (params: RouteParams[N]) => string } = {\n",
+        );
+        for (name, pattern) in &routes {
+            writeln!(
+                ts,
+                "  {}: (params) =>\n    {},",
+                json_quote(name),
+                path(pattern)
+            )
+            .unwrap();
+        }
+        ts.push_str(
+            "};
+
+export function pathFor<N extends keyof RouteParams>(name: N, params: RouteParams[N]): string {
+  return routes[name](params);
+}
+",
+        );
+        ts
+    }
+}
+
+/// The name of the interface of a route: its name in upper camel case,
+/// with `Params` after it.
+fn interface_name(name: &str) -> String {
+    let mut interface = String::new();
+    for word in name.split(|c: char| !c.is_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            interface.extend(first.to_uppercase());
+            interface.push_str(chars.as_str());
+        }
+    }
+    if interface.starts_with(|c: char| c.is_ascii_digit()) {
+        interface.insert(0, '_');
+    }
+    interface.push_str("Params");
+    interface
+}
+
+/// The steps of a pattern, and whether each is optional.
+fn steps(pattern: &Pattern) -> impl Iterator<Item = (&Step, bool)> {
+    let required = pattern.variants().last().map_or(0, <[Step]>::len);
+    let steps = pattern.variants().next().unwrap_or(&[]);
+    steps
+        .iter()
+        .enumerate()
+        .map(move |(i, step)| (step, i >= required))
+}
+
+/// The variables of a pattern, whether each is optional, and its type.
+fn variables(pattern: &Pattern) -> Vec<(&str, bool, &'static str)> {
+    let query = pattern
+        .query()
+        .params()
+        .map(|(_, value, optional)| (value, optional));
+    steps(pattern)
+        .chain(query)
+        .flat_map(|(step, optional)| {
+            step.variables().map(move |(name, converter)| {
+                let optional = optional || step.defaults().any(|(n, _)| n == name);
+                let ts_type = match converter {
+                    Some("int") | Some("float") => "number",
+                    Some("bool") => "boolean",
+                    _ => "string",
+                };
+                (name, optional, ts_type)
+            })
+        })
+        .collect()
+}
+
+/// A TypeScript expression for the text of a step with the values in
+/// `params`.
+fn step_expression(step: &Step, separator: char) -> String {
+    let defaults: Vec<_> = step.defaults().collect();
+    let pieces: Vec<String> = step
+        .pieces()
+        .enumerate()
+        .filter(|(i, piece)| i % 2 == 1 || !piece.is_empty())
+        .map(|(i, piece)| {
+            if i % 2 == 0 {
+                return json_quote(piece);
+            }
+            if step.wildcard {
+                let separator = json_quote(separator.encode_utf8(&mut [0; 4]));
+                return format!(
+                    "params.{}.split({}).map(encode).join({})",
+                    piece, separator, separator
+                );
+            }
+            match defaults.iter().find(|(name, _)| *name == piece) {
+                Some((_, default)) => {
+                    format!("encode(params.{} ?? {})", piece, json_quote(default))
+                }
+                None => format!("encode(params.{})", piece),
+            }
+        })
+        .collect();
+    if pieces.is_empty() {
+        "\"\"".to_string()
+    } else {
+        pieces.join(" + ")
+    }
+}
+
+/// A TypeScript expression for the path of a pattern with the values in
+/// `params`.
+fn path(pattern: &Pattern) -> String {
+    let separator = pattern.separator();
+    let quoted_separator = json_quote(separator.encode_utf8(&mut [0; 4]));
+    let (required, optional): (Vec<_>, Vec<_>) =
+        steps(pattern).partition(|(_, optional)| !optional);
+    let mut expression = required
+        .iter()
+        .map(|(step, _)| step_expression(step, separator))
+        .collect::<Vec<_>>()
+        .join(&format!(" + {} + ", quoted_separator));
+    let given: Vec<String> = optional
+        .iter()
+        .flat_map(|(step, _)| &step.names)
+        .map(|name| format!("params.{} !== undefined", name))
+        .collect();
+    if !given.is_empty() {
+        let steps: Vec<String> = optional
+            .iter()
+            .map(|(step, _)| {
+                format!(
+                    "{} + {}",
+                    quoted_separator,
+                    step_expression(step, separator)
+                )
+            })
+            .collect();
+        write!(
+            expression,
+            " + ({} ? {} : \"\")",
+            given.join(" || "),
+            steps.join(" + ")
+        )
+        .unwrap();
+    }
+    let params: Vec<String> = pattern
+        .query()
+        .params()
+        .map(|(key, value, optional)| {
+            let param = format!(
+                "{} + {}",
+                json_quote(&format!("{}=", key)),
+                step_expression(value, separator)
+            );
+            if !optional {
+                return param;
+            }
+            let given: Vec<String> = value
+                .names
+                .iter()
+                .map(|name| format!("params.{} !== undefined", name))
+                .collect();
+            format!("{} ? {} : undefined", given.join(" || "), param)
+        })
+        .collect();
+    if !params.is_empty() {
+        write!(expression, " + query([{}])", params.join(", ")).unwrap();
+    }
+    expression
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_typescript() {
+        let mut router = Router::new();
+        router
+            .register_named("article.detail", "articles/{id:int}[/{tab=info}]", 1)
+            .unwrap();
+        router
+            .register_named(
+                "search",
+                "search/{{all}}?q={q}&[page={page:int}]&[exact={e:bool}]",
+                2,
+            )
+            .unwrap();
+        router.register_named("files", "files/{rest:*}", 3).unwrap();
+        router.register_named("root", "", 4).unwrap();
+        router.register("unnamed/{x}", 5).unwrap();
+        let ts = router.to_typescript();
+        assert!(ts.contains(
+            "export interface ArticleDetailParams {\n  id: number;\n  tab?: string;\n}\n"
+        ));
+        assert!(ts.contains(concat!(
+            "export interface SearchParams {\n",
+            "  q: string;\n  page?: number;\n  e?: boolean;\n}\n"
+        )));
+        assert!(ts.contains("export interface RootParams {\n}\n"));
+        assert!(ts.contains(concat!(
+            "export interface RouteParams {\n",
+            "  \"article.detail\": ArticleDetailParams;\n",
+            "  \"search\": SearchParams;\n",
+            "  \"files\": FilesParams;\n",
+            "  \"root\": RootParams;\n}\n"
+        )));
+        assert!(!ts.contains("unnamed"));
+        assert!(ts.contains(concat!(
+            "  \"article.detail\": (params) =>\n",
+            "    \"articles\" + \"/\" + encode(params.id)",
+            " + (params.tab !== undefined ? \"/\" + encode(params.tab ?? \"info\") : \"\"),\n"
+        )));
+        assert!(ts.contains(concat!(
+            "    \"search\" + \"/\" + \"{all}\" + query([\"q=\" + encode(params.q), ",
+            "params.page !== undefined ? \"page=\" + encode(params.page) : undefined, ",
+            "params.e !== undefined ? \"exact=\" + encode(params.e) : undefined]),\n"
+        )));
+        assert!(ts.contains(
+            "    \"files\" + \"/\" + params.rest.split(\"/\").map(encode).join(\"/\"),\n"
+        ));
+        assert!(ts.contains("  \"root\": (params) =>\n    \"\",\n"));
+        assert!(ts.contains("export function pathFor<N extends keyof RouteParams>"));
+    }
+
+    #[test]
+    fn test_interface_name() {
+        assert_eq!(interface_name("user_detail"), "UserDetailParams");
+        assert_eq!(interface_name("api.v2-users"), "ApiV2UsersParams");
+        assert_eq!(interface_name("2fa"), "_2faParams");
+    }
+}
@@ -0,0 +1,161 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use crate::error::ExpandError;
+use crate::matching::Match;
+use crate::metadata::Metadata;
+use crate::router::{self, Resolution, RouteInfo, Router};
+
+/// A context that decides which routes a `RouterView` shows, like the
+/// user of a request with their permissions and feature flags.
+///
+/// The context is also the context for the guards of routes, as with
+/// `Router::lookup_with`.
+pub trait ViewContext: Any {
+    /// Whether a route is shown, going by its metadata, like its
+    /// `Permission` or `Tags`.
+    fn shows(&self, metadata: &Metadata) -> bool;
+}
+
+/// A router as seen in a context: the routes the context doesn't show are
+/// left out of matching, iteration and reverse routing, as if they weren't
+/// registered. Made by `Router::view`, without copying the router.
+#[derive(Debug)]
+pub struct RouterView<'a, T, C> {
+    router: &'a Router<T>,
+    context: &'a C,
+}
+
+impl<T> Router<T> {
+    /// A view of this router in a context.
+    pub fn view<'a, C: ViewContext>(&'a self, context: &'a C) -> RouterView<'a, T, C> {
+        RouterView {
+            router: self,
+            context,
+        }
+    }
+}
+
+impl<'a, T, C: ViewContext> RouterView<'a, T, C> {
+    /// The router this is a view of.
+    pub fn router(&self) -> &'a Router<T> {
+        self.router
+    }
+
+    /// Resolve a path like `Router::resolve_with`.
+    pub fn resolve(&self, path: &'a str) -> Option<(&'a T, Match<'a>)> {
+        router::resolved(self.lookup(path))
+    }
+
+    /// Look up a path like `Router::lookup_with`.
+    pub fn lookup(&self, path: &'a str) -> Resolution<'a, T> {
+        let context = self.context;
+        self.router
+            .lookup_in(path, Some(context), &|metadata| context.shows(metadata))
+    }
+
+    /// Resolve a path to every route shown that matches it, like
+    /// `Router::match_all_with`.
+    pub fn match_all(&self, path: &'a str) -> Vec<(&'a T, Match<'a>)> {
+        let context = self.context;
+        self.router
+            .match_all_in(path, Some(context), &|metadata| context.shows(metadata))
+    }
+
+    /// Iterate over the routes shown in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = RouteInfo<'a, T>> {
+        let context = self.context;
+        self.router
+            .iter()
+            .filter(move |route| context.shows(route.metadata))
+    }
+
+    /// Generate the path for a named route like `Router::path_for`. A route
+    /// that isn't shown is unknown.
+    pub fn path_for(
+        &self,
+        name: &str,
+        values: &HashMap<&str, &str>,
+    ) -> Result<String, ExpandError> {
+        match self.router.metadata(name) {
+            Some(metadata) if !self.context.shows(metadata) => Err(ExpandError::UnknownRoute {
+                name: name.to_string(),
+            }),
+            _ => self.router.path_for(name, values),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::permission::Permission;
+
+    struct User {
+        permissions: Vec<&'static str>,
+        beta: bool,
+    }
+
+    struct Beta;
+
+    impl ViewContext for User {
+        fn shows(&self, metadata: &Metadata) -> bool {
+            let permitted = metadata
+                .get::<Permission>()
+                .is_none_or(|permission| self.permissions.contains(&permission.key()));
+            permitted && (self.beta || metadata.get::<Beta>().is_none())
+        }
+    }
+
+    fn router() -> Router<i32> {
+        let mut router = Router::new();
+        router.register_named("page", "{page}", 1).unwrap();
+        router.register_named("admin", "admin", 2).unwrap();
+        router
+            .metadata_mut("admin")
+            .unwrap()
+            .insert(Permission::new("admin"));
+        router
+            .register_with_metadata("new", 3, Metadata::new().with(Beta))
+            .unwrap();
+        router
+            .register_with_guard("owner", 4, |_, user: &User| user.beta)
+            .unwrap();
+        router
+    }
+
+    #[test]
+    fn test_view() {
+        let router = router();
+        let user = User {
+            permissions: vec![],
+            beta: false,
+        };
+        let view = router.view(&user);
+        // admin falls through to the route behind it
+        assert_eq!(view.resolve("admin").map(|(value, _)| *value), Some(1));
+        assert_eq!(view.resolve("new").map(|(value, _)| *value), Some(1));
+        assert_eq!(view.resolve("owner").map(|(value, _)| *value), Some(1));
+        assert_eq!(view.match_all("admin").len(), 1);
+        let values: Vec<_> = view.iter().map(|route| *route.value).collect();
+        assert_eq!(values, vec![1, 4]);
+        assert_eq!(
+            view.path_for("admin", &HashMap::new()),
+            Err(ExpandError::UnknownRoute {
+                name: "admin".to_string()
+            })
+        );
+
+        let admin = User {
+            permissions: vec!["admin"],
+            beta: true,
+        };
+        let view = router.view(&admin);
+        assert_eq!(view.resolve("admin").map(|(value, _)| *value), Some(2));
+        assert_eq!(view.resolve("new").map(|(value, _)| *value), Some(3));
+        assert_eq!(view.resolve("owner").map(|(value, _)| *value), Some(4));
+        assert_eq!(view.path_for("admin", &HashMap::new()).unwrap(), "admin");
+        assert_eq!(view.iter().count(), 4);
+    }
+}
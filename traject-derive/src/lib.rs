@@ -0,0 +1,709 @@
+//! `#[derive(RouteParams)]` for structs whose fields are the variables of a
+//! traject pattern.
+//!
+//! ```ignore
+//! use traject_derive::RouteParams;
+//!
+//! #[derive(RouteParams)]
+//! #[route("departments/{department_id}/employees/{employee_id}")]
+//! struct EmployeeParams {
+//!     department_id: u32,
+//!     employee_id: u32,
+//! }
+//!
+//! let params = EmployeeParams::match_path("departments/1/employees/2").unwrap();
+//! assert_eq!(params.path_for().unwrap(), "departments/1/employees/2");
+//! ```
+//!
+//! The pattern is checked when the struct is compiled: it has to parse, and
+//! its variables have to be exactly the fields of the struct. Fields are
+//! parsed with `FromStr` and serialized with `Display`.
+//!
+//! A variable that can be missing from a match, as it is in optional
+//! steps or an optional query parameter and has no default, has to be an
+//! `Option` field. A wildcard variable can be a `Vec` field, of the values
+//! of its segments:
+//!
+//! ```ignore
+//! #[derive(RouteParams)]
+//! #[route("files/{dir}/{path:*}[/{version}]")]
+//! struct FileParams {
+//!     dir: String,
+//!     path: Vec<String>,
+//!     version: Option<u32>,
+//! }
+//! ```
+//!
+//! Variables can be named differently from their fields. The route
+//! attribute can give a rule for all of them, one of `camelCase`,
+//! `PascalCase`, `snake_case` and `SCREAMING_SNAKE_CASE`, and a field can
+//! name its own:
+//!
+//! ```ignore
+//! #[derive(RouteParams)]
+//! #[route("users/{userId}/posts/{post}", rename_all = "camelCase")]
+//! struct PostParams {
+//!     user_id: u32,
+//!     #[route(rename = "post")]
+//!     post_id: u32,
+//! }
+//! ```
+//!
+//! The `pattern!` macro checks a pattern when it is compiled, and gives a
+//! `traject::StaticPattern` that can be a `static`. It is still parsed
+//! again when it is first used, as a `Pattern` can't be built at compile
+//! time:
+//!
+//! ```ignore
+//! use traject::StaticPattern;
+//! use traject_derive::pattern;
+//!
+//! static EMPLOYEE: StaticPattern = pattern!("employees/{id:int}");
+//! ```
+//!
+//! The `routes!` macro compiles a fixed set of routes into an enum, with
+//! a `resolve` function that is a `match` on the segments of the path. No
+//! patterns are parsed and no regexes are compiled when it runs:
+//!
+//! ```ignore
+//! traject_derive::routes! {
+//!     pub enum Route {
+//!         Home => "",
+//!         Employee => "departments/{d}/employees/{e:int}",
+//!     }
+//! }
+//!
+//! let (route, m) = Route::resolve("departments/a/employees/2").unwrap();
+//! assert_eq!(route, Route::Employee);
+//! ```
+//!
+//! Its steps have to be literal text or a single variable, with at most a
+//! built-in converter and no default, and it doesn't match queries.
+//!
+//! For each route it also generates a struct with the values of its
+//! variables, typed by their converters, in a module named after the enum
+//! in snake case. The `url!` macro builds paths with these, so a path to
+//! a route that doesn't exist, or without all of its variables, doesn't
+//! compile:
+//!
+//! ```ignore
+//! let path = traject_derive::url!(Route, Employee, d = "a", e = 2);
+//! assert_eq!(path, "departments/a/employees/2");
+//! assert_eq!(route::EmployeeParams { d: "a", e: 2 }.path(), path);
+//! ```
+//!
+//! Variables in optional steps are `Option`s.
+//!
+//! The module also has a type for each route, like `route::Employee`, that
+//! implements `traject::TypedRoute`:
+//!
+//! ```ignore
+//! let params = route::EmployeeParams { d: "a", e: 2 };
+//! assert_eq!(Route::path_for::<route::Employee>(&params), "departments/a/employees/2");
+//! ```
+//!
+//! And it has an enum `Params` with a variant for each route that holds its
+//! values, which `classify` resolves a path to. A `match` on it has to
+//! handle every route, so adding one to the table shows where it isn't
+//! handled yet:
+//!
+//! ```ignore
+//! match Route::classify("departments/a/employees/2") {
+//!     Some(route::Params::Employee(route::EmployeeParams { d, e })) => {}
+//!     Some(route::Params::Home(_)) | None => {}
+//! }
+//! ```
+//!
+//! The generated code refers to the `traject` crate, so it has to be a
+//! dependency as well.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Group, Spacing, TokenStream, TokenTree};
+use std::collections::{HashMap, HashSet};
+
+use traject::{Pattern, Piece, Syntax};
+
+mod routes;
+
+#[proc_macro_derive(RouteParams, attributes(route))]
+pub fn derive_route_params(input: TokenStream) -> TokenStream {
+    let generated = parse(input).and_then(|input| {
+        check(&input)?;
+        Ok(generate(&input))
+    });
+    match generated {
+        Ok(s) => s.parse().expect("generated code is valid"),
+        Err(message) => format!("::std::compile_error!({:?});", message)
+            .parse()
+            .expect("compile_error is valid"),
+    }
+}
+
+/// A `traject::StaticPattern` for a pattern, which has to parse with the
+/// built-in converters. The pattern is parsed again on first use.
+#[proc_macro]
+pub fn pattern(input: TokenStream) -> TokenStream {
+    let generated = pattern_literal(input).and_then(|s| {
+        Pattern::new(&s).map_err(|e| format!("invalid pattern `{}`: {}", s, e))?;
+        Ok(format!("::traject::StaticPattern::new({:?})", s))
+    });
+    match generated {
+        Ok(s) => s.parse().expect("generated code is valid"),
+        Err(message) => format!("::std::compile_error!({:?})", message)
+            .parse()
+            .expect("compile_error is valid"),
+    }
+}
+
+/// An enum of routes with a `resolve` function that dispatches on path
+/// segments.
+#[proc_macro]
+pub fn routes(input: TokenStream) -> TokenStream {
+    match routes::expand(input) {
+        Ok(s) => s.parse().expect("generated code is valid"),
+        Err(message) => format!("::std::compile_error!({:?});", message)
+            .parse()
+            .expect("compile_error is valid"),
+    }
+}
+
+/// The path of a route declared with `routes!`, checked when it is
+/// compiled: `url!(Route, Employee, d = "a", e = 2)`. The route has to
+/// exist, and exactly its variables have to be given, with values of the
+/// types of their converters.
+#[proc_macro]
+pub fn url(input: TokenStream) -> TokenStream {
+    match routes::url(input) {
+        Ok(s) => s.parse().expect("generated code is valid"),
+        Err(message) => format!("::std::compile_error!({:?})", message)
+            .parse()
+            .expect("compile_error is valid"),
+    }
+}
+
+/// The string literal that is the only argument of `pattern!`.
+fn pattern_literal(input: TokenStream) -> Result<String, String> {
+    let error = || "expected pattern!(\"...\") with a string literal".to_string();
+    let mut tokens = input.into_iter();
+    match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(literal)), None) => {
+            string_literal(&literal.to_string()).ok_or_else(error)
+        }
+        // a literal passed on by a `macro_rules!` macro
+        (Some(TokenTree::Group(group)), None) if group.delimiter() == Delimiter::None => {
+            pattern_literal(group.stream())
+        }
+        _ => Err(error()),
+    }
+}
+
+struct Input {
+    name: String,
+    route: String,
+    rename_all: Option<Rename>,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    name: String,
+    /// the variable name given with `#[route(rename = "...")]`
+    rename: Option<String>,
+    shape: Shape,
+}
+
+/// How many values a field holds, from its type.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    One,
+    /// `Option<T>`
+    Optional,
+    /// `Vec<T>`
+    Many,
+    /// `Option<Vec<T>>`
+    OptionalMany,
+}
+
+impl Shape {
+    fn new(ty: &[TokenTree]) -> Shape {
+        match outer_type(ty) {
+            Some(("Option", inner)) => match outer_type(inner) {
+                Some(("Vec", _)) => Shape::OptionalMany,
+                _ => Shape::Optional,
+            },
+            Some(("Vec", _)) => Shape::Many,
+            _ => Shape::One,
+        }
+    }
+
+    fn is_optional(self) -> bool {
+        matches!(self, Shape::Optional | Shape::OptionalMany)
+    }
+
+    fn is_many(self) -> bool {
+        matches!(self, Shape::Many | Shape::OptionalMany)
+    }
+}
+
+/// The name of a generic type like `Option` in `std::option::Option<u32>`,
+/// and the tokens of its argument.
+fn outer_type(ty: &[TokenTree]) -> Option<(&'static str, &[TokenTree])> {
+    let is_punct = |token: &TokenTree, c| matches!(token, TokenTree::Punct(p) if p.as_char() == c);
+    let open = ty.iter().position(|token| is_punct(token, '<'))?;
+    if !is_punct(ty.last()?, '>') {
+        return None;
+    }
+    let name = match &ty[open.checked_sub(1)?] {
+        TokenTree::Ident(ident) if ident.to_string() == "Option" => "Option",
+        TokenTree::Ident(ident) if ident.to_string() == "Vec" => "Vec",
+        _ => return None,
+    };
+    Some((name, &ty[open + 1..ty.len() - 1]))
+}
+
+/// How field names are turned into variable names.
+#[derive(Clone, Copy)]
+enum Rename {
+    Camel,
+    Pascal,
+    Snake,
+    ScreamingSnake,
+}
+
+impl Rename {
+    fn new(rule: &str) -> Result<Rename, String> {
+        match rule {
+            "camelCase" => Ok(Rename::Camel),
+            "PascalCase" => Ok(Rename::Pascal),
+            "snake_case" => Ok(Rename::Snake),
+            "SCREAMING_SNAKE_CASE" => Ok(Rename::ScreamingSnake),
+            _ => Err(format!(
+                "unknown rename_all rule `{}`, expected `camelCase`, `PascalCase`, \
+                 `snake_case` or `SCREAMING_SNAKE_CASE`",
+                rule
+            )),
+        }
+    }
+
+    /// The variable name for a field name in snake case.
+    fn apply(self, field: &str) -> String {
+        let capitalize = |word: &str| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        };
+        match self {
+            Rename::Snake => field.to_string(),
+            Rename::ScreamingSnake => field.to_uppercase(),
+            Rename::Pascal => field.split('_').map(capitalize).collect(),
+            Rename::Camel => {
+                let mut words = field.split('_');
+                let first = words.next().unwrap_or("").to_string();
+                first + &words.map(capitalize).collect::<String>()
+            }
+        }
+    }
+}
+
+impl Input {
+    /// The variable name for a field.
+    fn variable(&self, field: &Field) -> String {
+        // a raw identifier like `r#type` is the variable `type`
+        let name = field.name.trim_start_matches("r#");
+        match (&field.rename, self.rename_all) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(rule)) => rule.apply(name),
+            (None, None) => name.to_string(),
+        }
+    }
+}
+
+/// Find the name, `#[route]` attribute and field names of a struct.
+fn parse(input: TokenStream) -> Result<Input, String> {
+    let mut route = None;
+    let mut rename_all = None;
+    let mut tokens = input.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '#' => {
+                if let Some(TokenTree::Group(group)) = tokens.next() {
+                    if let Some(arguments) = route_attribute(&group)? {
+                        let (s, rule) = struct_arguments(arguments)?;
+                        route = Some(s);
+                        rename_all = rule.as_deref().map(Rename::new).transpose()?;
+                    }
+                }
+            }
+            TokenTree::Ident(ident) if ident.to_string() == "struct" => {
+                let name = match tokens.next() {
+                    Some(TokenTree::Ident(name)) => name.to_string(),
+                    _ => return Err("expected a struct name".to_string()),
+                };
+                let fields = match tokens.next() {
+                    Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+                        fields(group.stream())?
+                    }
+                    Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+                        return Err("RouteParams does not support generic structs".to_string())
+                    }
+                    _ => {
+                        return Err(
+                            "RouteParams can only be derived for structs with named fields"
+                                .to_string(),
+                        )
+                    }
+                };
+                let route = route.ok_or_else(|| {
+                    "RouteParams needs a #[route(\"...\")] attribute with the pattern".to_string()
+                })?;
+                return Ok(Input {
+                    name,
+                    route,
+                    rename_all,
+                    fields,
+                });
+            }
+            TokenTree::Ident(ident)
+                if ident.to_string() == "enum" || ident.to_string() == "union" =>
+            {
+                return Err("RouteParams can only be derived for structs".to_string())
+            }
+            _ => {}
+        }
+    }
+    Err("expected a struct".to_string())
+}
+
+/// The arguments of a `#[route(...)]` attribute, or `None` for another
+/// attribute.
+fn route_attribute(group: &Group) -> Result<Option<TokenStream>, String> {
+    let mut tokens = group.stream().into_iter();
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "route" => {}
+        _ => return Ok(None),
+    }
+    match tokens.next() {
+        Some(TokenTree::Group(arguments)) if arguments.delimiter() == Delimiter::Parenthesis => {
+            Ok(Some(arguments.stream()))
+        }
+        _ => Err("expected #[route(...)] with arguments".to_string()),
+    }
+}
+
+/// The pattern and the `rename_all` rule of `#[route("...", rename_all =
+/// "...")]` on the struct.
+fn struct_arguments(arguments: TokenStream) -> Result<(String, Option<String>), String> {
+    let error = || {
+        "expected #[route(\"...\")] with a string literal, and optionally \
+         rename_all = \"...\""
+            .to_string()
+    };
+    let mut tokens = arguments.into_iter();
+    let route = match tokens.next() {
+        Some(TokenTree::Literal(literal)) => {
+            string_literal(&literal.to_string()).ok_or_else(error)?
+        }
+        _ => return Err(error()),
+    };
+    match tokens.next() {
+        None => Ok((route, None)),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => {
+            let rule = key_value(tokens, "rename_all").ok_or_else(error)?;
+            Ok((route, Some(rule)))
+        }
+        _ => Err(error()),
+    }
+}
+
+/// The string of `key = "..."`, which has to be all of the tokens.
+fn key_value(tokens: impl Iterator<Item = TokenTree>, key: &str) -> Option<String> {
+    let tokens: Vec<TokenTree> = tokens.collect();
+    match tokens.as_slice() {
+        [TokenTree::Ident(ident), TokenTree::Punct(punct), TokenTree::Literal(literal)]
+            if ident.to_string() == key && punct.as_char() == '=' =>
+        {
+            string_literal(&literal.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The value of a string literal as written in the source.
+fn string_literal(s: &str) -> Option<String> {
+    if let Some(raw) = s.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let inner = raw[hashes..].strip_prefix('"')?;
+        return Some(inner[..inner.len().checked_sub(hashes + 1)?].to_string());
+    }
+    let inner = s.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            '\\' => value.push('\\'),
+            '"' => value.push('"'),
+            '\'' => value.push('\''),
+            // other escapes have no business in a route pattern
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+/// The fields in the body of a struct.
+fn fields(body: TokenStream) -> Result<Vec<Field>, String> {
+    let mut fields = Vec::new();
+    let mut tokens = body.into_iter().peekable();
+    while tokens.peek().is_some() {
+        let mut rename = None;
+        // attributes and visibility
+        loop {
+            match tokens.peek() {
+                Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => {
+                    tokens.next();
+                    if let Some(TokenTree::Group(group)) = tokens.next() {
+                        if let Some(arguments) = route_attribute(&group)? {
+                            rename = Some(key_value(arguments.into_iter(), "rename").ok_or_else(
+                                || "expected #[route(rename = \"...\")] on a field".to_string(),
+                            )?);
+                        }
+                    }
+                }
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {
+                    tokens.next();
+                    if let Some(TokenTree::Group(group)) = tokens.peek() {
+                        if group.delimiter() == Delimiter::Parenthesis {
+                            tokens.next();
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(ident)) => ident.to_string(),
+            _ => return Err("expected a field name".to_string()),
+        };
+        // the type, up to a comma that isn't within angle brackets
+        let mut ty = Vec::new();
+        let mut depth = 0;
+        let mut arrow = false;
+        for token in tokens.by_ref() {
+            if let TokenTree::Punct(punct) = &token {
+                match punct.as_char() {
+                    '<' => depth += 1,
+                    '>' if !arrow => depth -= 1,
+                    ',' if depth == 0 => break,
+                    _ => {}
+                }
+                arrow = punct.as_char() == '-' && punct.spacing() == Spacing::Joint;
+            } else {
+                arrow = false;
+            }
+            ty.push(token);
+        }
+        let ty = ty.get(1..).unwrap_or(&[]);
+        fields.push(Field {
+            name,
+            rename,
+            shape: Shape::new(ty),
+        });
+    }
+    Ok(fields)
+}
+
+/// Check that the pattern parses and that its variables are the fields.
+fn check(input: &Input) -> Result<(), String> {
+    let pattern = Pattern::new(&input.route)
+        .map_err(|e| format!("invalid route `{}`: {}", input.route, e))?;
+    let variables: Vec<String> = input
+        .fields
+        .iter()
+        .map(|field| input.variable(field))
+        .collect();
+    let names: HashSet<&str> = pattern.names().collect();
+    if let Some(name) = pattern
+        .names()
+        .find(|name| !variables.iter().any(|variable| variable == name))
+    {
+        return Err(format!(
+            "variable `{}` in route `{}` is not a field of `{}`",
+            name, input.route, input.name
+        ));
+    }
+    if let Some((field, variable)) = input
+        .fields
+        .iter()
+        .zip(&variables)
+        .find(|(_, variable)| !names.contains(variable.as_str()))
+    {
+        let field = field.name.trim_start_matches("r#");
+        let renamed = if field == variable {
+            String::new()
+        } else {
+            format!(" (variable `{}`)", variable)
+        };
+        return Err(format!(
+            "field `{}`{} of `{}` is not a variable in route `{}`",
+            field, renamed, input.name, input.route
+        ));
+    }
+    let kinds = variable_kinds(&input.route);
+    for (field, variable) in input.fields.iter().zip(&variables) {
+        let (optional, wildcard) = kinds[variable.as_str()];
+        let field_name = field.name.trim_start_matches("r#");
+        if optional && !field.shape.is_optional() {
+            return Err(format!(
+                "variable `{}` in route `{}` can be missing, so field `{}` of `{}` has to be \
+                 an `Option`",
+                variable, input.route, field_name, input.name
+            ));
+        }
+        if !optional && field.shape.is_optional() {
+            return Err(format!(
+                "field `{}` of `{}` is an `Option`, but variable `{}` is always in route `{}`",
+                field_name, input.name, variable, input.route
+            ));
+        }
+        if !wildcard && field.shape.is_many() {
+            return Err(format!(
+                "field `{}` of `{}` is a `Vec`, but variable `{}` in route `{}` is not a wildcard",
+                field_name, input.name, variable, input.route
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether each variable of a route can be missing from a match, and
+/// whether it is a wildcard.
+fn variable_kinds(route: &str) -> HashMap<&str, (bool, bool)> {
+    let syntax = Syntax::parse(route).expect("the route parses");
+    let steps = syntax.steps.iter().map(|step| (step, step.optional));
+    let params = syntax
+        .params
+        .iter()
+        .map(|param| (&param.value, param.optional));
+    let mut kinds = HashMap::new();
+    for (step, optional) in steps.chain(params) {
+        for piece in &step.pieces {
+            if let Piece::Variable(variable) = piece {
+                let converter = variable.converter.as_ref().map(|span| syntax.text(span));
+                kinds.insert(
+                    syntax.text(&variable.name),
+                    (
+                        optional && variable.default.is_none(),
+                        converter == Some("*"),
+                    ),
+                );
+            }
+        }
+    }
+    kinds
+}
+
+fn generate(input: &Input) -> String {
+    let collect = "::std::iter::Iterator::collect::<\
+        ::std::option::Option<::std::vec::Vec<_>>>";
+    let construct: Vec<String> = input
+        .fields
+        .iter()
+        .map(|field| {
+            let parse = |value: &str| match field.shape {
+                Shape::One | Shape::Optional => format!("{}.parse().ok()?", value),
+                Shape::Many | Shape::OptionalMany => {
+                    format!("{}({}.split('/').map(|s| s.parse().ok()))?", collect, value)
+                }
+            };
+            let value = if field.shape.is_optional() {
+                format!(
+                    "match m.get({:?}) {{ \
+                        ::std::option::Option::Some(s) => ::std::option::Option::Some({}), \
+                        ::std::option::Option::None => ::std::option::Option::None, \
+                    }}",
+                    input.variable(field),
+                    parse("s")
+                )
+            } else {
+                parse(&format!("m.get({:?})?", input.variable(field)))
+            };
+            format!("{}: {},", field.name, value)
+        })
+        .collect();
+    let serialize: Vec<String> = input
+        .fields
+        .iter()
+        .map(|field| {
+            let text = |value: &str| {
+                if field.shape.is_many() {
+                    format!(
+                        "::std::iter::Iterator::collect::<::std::vec::Vec<_>>(\
+                            ::std::iter::Iterator::map(({}).iter(), ::std::string::ToString::to_string)\
+                        ).join(\"/\")",
+                        value
+                    )
+                } else {
+                    format!("::std::string::ToString::to_string({})", value)
+                }
+            };
+            let push = |value: &str| format!("strings.push(({:?}, {}));", input.variable(field), text(value));
+            if field.shape.is_optional() {
+                format!(
+                    "if let ::std::option::Option::Some(value) = &self.{} {{ {} }}",
+                    field.name,
+                    push("value")
+                )
+            } else {
+                push(&format!("&self.{}", field.name))
+            }
+        })
+        .collect();
+    format!(
+        r#"
+        impl {name} {{
+            /// The route pattern.
+            pub const PATTERN: &'static str = {route:?};
+
+            /// The parsed route pattern.
+            pub fn route_pattern() -> &'static ::traject::Pattern {{
+                static PATTERN: ::std::sync::OnceLock<::traject::Pattern> =
+                    ::std::sync::OnceLock::new();
+                PATTERN.get_or_init(|| {{
+                    ::traject::Pattern::new({route:?}).expect("route was checked at compile time")
+                }})
+            }}
+
+            /// Match a path against the route, and parse the variables into
+            /// the fields.
+            pub fn match_path(path: &str) -> ::std::option::Option<Self> {{
+                let m = Self::route_pattern().match_path(path)?;
+                ::std::option::Option::Some({name} {{ {construct} }})
+            }}
+
+            /// Expand the route into a path, using the fields as variables.
+            pub fn path_for(&self) -> ::std::result::Result<::std::string::String, ::traject::ExpandError> {{
+                #[allow(unused_mut)]
+                let mut strings: ::std::vec::Vec<(&str, ::std::string::String)> =
+                    ::std::vec::Vec::new();
+                {serialize}
+                let values: ::std::collections::HashMap<&str, &str> = strings
+                    .iter()
+                    .map(|(name, value)| (*name, value.as_str()))
+                    .collect();
+                Self::route_pattern().expand(&values)
+            }}
+        }}
+        "#,
+        name = input.name,
+        route = input.route,
+        construct = construct.join(" "),
+        serialize = serialize.join(" "),
+    )
+}
@@ -0,0 +1,646 @@
+//! The `routes!` macro, which compiles a fixed set of routes into a `match`.
+
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+use traject::Pattern;
+
+use crate::string_literal;
+
+struct Table {
+    /// attributes and visibility of the enum
+    prefix: String,
+    /// visibility of the enum
+    visibility: String,
+    name: String,
+    routes: Vec<Route>,
+}
+
+struct Route {
+    attributes: String,
+    variant: String,
+    pattern: String,
+}
+
+/// A step of a pattern that `routes!` can compile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Literal(String),
+    Variable {
+        name: String,
+        converter: Option<String>,
+    },
+    Wildcard {
+        name: String,
+    },
+}
+
+impl Step {
+    /// The name and field type of the variable of the step, if it has one.
+    fn field(&self) -> Option<(&str, String)> {
+        match self {
+            Step::Literal(_) => None,
+            Step::Variable {
+                name,
+                converter: Some(converter),
+            } => Some((
+                name,
+                format!("<::traject::{} as ::traject::Converter>::Value", converter),
+            )),
+            Step::Variable { name, .. } | Step::Wildcard { name } => {
+                Some((name, "&'a str".to_string()))
+            }
+        }
+    }
+
+    /// An expression for the text of the step, given an expression for
+    /// a reference to the value of its variable.
+    fn text(&self, value: &str) -> String {
+        match self {
+            Step::Literal(s) => format!("{:?}.to_string()", s),
+            Step::Variable {
+                converter: Some(converter),
+                ..
+            } => format!(
+                "::traject::Converter::serialize(&::traject::{}, {})",
+                converter, value
+            ),
+            Step::Variable { .. } | Step::Wildcard { .. } => format!("({}).to_string()", value),
+        }
+    }
+
+    /// Like the specificity of steps in a router: lower is tried first.
+    fn specificity(&self) -> u8 {
+        match self {
+            Step::Literal(_) => 0,
+            Step::Variable {
+                converter: Some(_), ..
+            } => 1,
+            Step::Variable {
+                converter: None, ..
+            } => 2,
+            Step::Wildcard { .. } => 3,
+        }
+    }
+}
+
+pub(crate) fn expand(input: TokenStream) -> Result<String, String> {
+    let table = parse(input)?;
+    let mut arms = Vec::new();
+    let mut params = Vec::new();
+    let module = module_name(&table.name);
+    for (index, route) in table.routes.iter().enumerate() {
+        Pattern::new(&route.pattern)
+            .map_err(|e| format!("invalid pattern `{}`: {}", route.pattern, e))?;
+        let variants = variants(&route.pattern)?;
+        params.push(params_struct(&table.name, route, &variants));
+        let specificity: Vec<u8> = variants[0].iter().map(Step::specificity).collect();
+        let optional = &variants[0][variants.last().expect("one variant").len()..];
+        for steps in &variants {
+            arms.push((
+                specificity.clone(),
+                index,
+                resolve_arm(&table.name, route, steps),
+                classify_arm(&module, route, steps, optional),
+            ));
+        }
+    }
+    // the same order as a router with these routes at the same priority
+    arms.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+    let variants: Vec<String> = table
+        .routes
+        .iter()
+        .map(|route| format!("{} {},", route.attributes, route.variant))
+        .collect();
+    let all: Vec<String> = table
+        .routes
+        .iter()
+        .map(|route| format!("{}::{},", table.name, route.variant))
+        .collect();
+    let patterns: Vec<String> = table
+        .routes
+        .iter()
+        .map(|route| format!("{}::{} => {:?},", table.name, route.variant, route.pattern))
+        .collect();
+    let classify_arms: Vec<&str> = arms.iter().map(|(_, _, _, arm)| arm.as_str()).collect();
+    let resolve_arms: Vec<&str> = arms.iter().map(|(_, _, arm, _)| arm.as_str()).collect();
+    let borrows = params.iter().any(|(_, borrows)| *borrows);
+    let params_variants: Vec<String> = table
+        .routes
+        .iter()
+        .zip(&params)
+        .map(|(route, (_, borrows))| {
+            format!(
+                "{variant}({variant}Params{lifetime}),",
+                variant = route.variant,
+                lifetime = if *borrows { "<'a>" } else { "" }
+            )
+        })
+        .collect();
+    let params: Vec<&str> = params.iter().map(|(params, _)| params.as_str()).collect();
+    Ok(format!(
+        r#"
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        {prefix} enum {name} {{ {variants} }}
+
+        impl {name} {{
+            /// All routes, in the order they are declared.
+            pub const ALL: &'static [{name}] = &[{all}];
+
+            /// The pattern of the route.
+            pub fn pattern(self) -> &'static str {{
+                match self {{ {patterns} }}
+            }}
+
+            /// The path of the route that is `K`, with the values of its
+            /// variables.
+            pub fn path_for<K: ::traject::TypedRoute<Route = {name}>>(
+                params: &K::Params<'_>,
+            ) -> ::std::string::String {{
+                K::path(params)
+            }}
+
+            /// Resolve a path to a route and the captured variables, like a
+            /// router with these routes would. A query is ignored.
+            pub fn resolve(path: &str) -> ::std::option::Option<({name}, ::traject::Match<'_>)> {{
+                let path = match path.find('?') {{
+                    ::std::option::Option::Some(i) => &path[..i],
+                    ::std::option::Option::None => path,
+                }};
+                let path = path.strip_prefix('/').unwrap_or(path);
+                let segments: ::std::vec::Vec<&str> = path.split('/').collect();
+                #[allow(unreachable_patterns, unused_variables)]
+                match segments.as_slice() {{
+                    {resolve_arms}
+                    _ => ::std::option::Option::None,
+                }}
+            }}
+
+            /// Resolve a path like `resolve`, to the route with the values
+            /// of its variables, parsed by their converters.
+            pub fn classify(path: &str) -> ::std::option::Option<{module}::Params{params_lifetime}> {{
+                let path = match path.find('?') {{
+                    ::std::option::Option::Some(i) => &path[..i],
+                    ::std::option::Option::None => path,
+                }};
+                let path = path.strip_prefix('/').unwrap_or(path);
+                let segments: ::std::vec::Vec<&str> = path.split('/').collect();
+                #[allow(unreachable_patterns, unused_variables)]
+                match segments.as_slice() {{
+                    {classify_arms}
+                    _ => ::std::option::Option::None,
+                }}
+            }}
+        }}
+
+        #[doc = {doc:?}]
+        {visibility} mod {module} {{
+            /// A route with the values of its variables, as `classify`
+            /// gives it.
+            #[derive(Debug, Clone, PartialEq)]
+            pub enum Params{lifetime} {{ {params_variants} }}
+
+            {params}
+        }}
+        "#,
+        doc = format!(
+            "The typed values of the variables of each route of `{}`, for `url!` and `classify`.",
+            table.name
+        ),
+        visibility = table.visibility,
+        module = module,
+        lifetime = if borrows { "<'a>" } else { "" },
+        params_lifetime = if borrows { "<'_>" } else { "" },
+        params_variants = params_variants.join(" "),
+        params = params.join("\n"),
+        prefix = table.prefix,
+        name = table.name,
+        variants = variants.join(" "),
+        all = all.join(" "),
+        patterns = patterns.join(" "),
+        resolve_arms = resolve_arms.join("\n"),
+        classify_arms = classify_arms.join("\n"),
+    ))
+}
+
+/// The struct with the values of the variables of a route, and its `path`
+/// function, and the type of the route. Also whether the struct borrows
+/// from the path. Variables in optional steps are `Option`s; the steps are
+/// left out unless all of them are `Some`.
+fn params_struct(name: &str, route: &Route, variants: &[Vec<Step>]) -> (String, bool) {
+    let steps = &variants[0];
+    let required = variants.last().expect("one variant").len();
+    let mut fields = Vec::new();
+    let mut texts = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        let optional = variants.len() > 1 && i >= required;
+        if let Some((name, ty)) = step.field() {
+            if optional {
+                fields.push(format!("pub r#{}: ::std::option::Option<{}>,", name, ty));
+            } else {
+                fields.push(format!("pub r#{}: {},", name, ty));
+            }
+        }
+        let value = match step.field() {
+            Some((name, _)) if optional => format!("v_{}", name),
+            Some((name, _)) => format!("&self.r#{}", name),
+            None => String::new(),
+        };
+        texts.push(format!("segments.push({});", step.text(&value)));
+    }
+    let optional: Vec<(&str, String)> = steps[required..].iter().filter_map(Step::field).collect();
+    let body = if optional.is_empty() {
+        texts.join(" ")
+    } else {
+        format!(
+            "{required} if let ({bindings},) = ({values},) {{ {optional} }}",
+            required = texts[..required].join(" "),
+            bindings = optional
+                .iter()
+                .map(|(name, _)| format!("::std::option::Option::Some(v_{})", name))
+                .collect::<Vec<_>>()
+                .join(", "),
+            values = optional
+                .iter()
+                .map(|(name, _)| format!("&self.r#{}", name))
+                .collect::<Vec<_>>()
+                .join(", "),
+            optional = texts[required..].join(" "),
+        )
+    };
+    let borrows = fields.iter().any(|field| field.contains("'a"));
+    let lifetime = if borrows { "<'a>" } else { "" };
+    let params = format!(
+        r#"
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct {variant}Params{lifetime} {{ {fields} }}
+
+        #[doc = {doc:?}]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        pub struct {variant};
+
+        impl ::traject::TypedRoute for {variant} {{
+            type Route = super::{name};
+            type Params<'a> = {variant}Params{params_lifetime};
+
+            const ROUTE: super::{name} = super::{name}::{variant};
+
+            fn path(params: &Self::Params<'_>) -> ::std::string::String {{
+                params.path()
+            }}
+        }}
+
+        impl{lifetime} {variant}Params{lifetime} {{
+            /// The path of the route with these values.
+            pub fn path(&self) -> ::std::string::String {{
+                let mut segments: ::std::vec::Vec<::std::string::String> = ::std::vec::Vec::new();
+                {body}
+                segments.join("/")
+            }}
+        }}
+        "#,
+        variant = route.variant,
+        lifetime = lifetime,
+        params_lifetime = if lifetime.is_empty() { "" } else { "<'a>" },
+        doc = format!("The route `{}::{}`, as a type.", name, route.variant),
+        name = name,
+        fields = fields.join(" "),
+        body = body,
+    );
+    (params, borrows)
+}
+
+/// The name of the module with the params structs of an enum: the enum
+/// name in snake case, so `AppRoute` has `app_route`.
+pub(crate) fn module_name(name: &str) -> String {
+    let mut module = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                module.push('_');
+            }
+            module.extend(c.to_lowercase());
+        } else {
+            module.push(c);
+        }
+    }
+    module
+}
+
+/// The path of the params struct for `url!(ENUM, VARIANT, NAME = VALUE, ...)`,
+/// with the struct literal for the values.
+pub(crate) fn url(input: TokenStream) -> Result<String, String> {
+    let error = || "expected `url!(ENUM, VARIANT, NAME = VALUE, ...)`".to_string();
+    let mut arguments = split_commas(input).into_iter();
+    let mut path = arguments.next().ok_or_else(error)?;
+    let name = match path.pop() {
+        Some(TokenTree::Ident(name)) => name.to_string(),
+        _ => return Err(error()),
+    };
+    let variant = match arguments.next().as_deref() {
+        Some([TokenTree::Ident(variant)]) => variant.to_string(),
+        _ => return Err(error()),
+    };
+    let mut fields = Vec::new();
+    for argument in arguments {
+        match argument.as_slice() {
+            [TokenTree::Ident(field), TokenTree::Punct(eq), value @ ..]
+                if eq.as_char() == '=' && !value.is_empty() =>
+            {
+                let field = field.to_string();
+                let field = field.strip_prefix("r#").unwrap_or(&field);
+                let value: TokenStream = value.iter().cloned().collect();
+                fields.push(format!("r#{}: ({}),", field, value));
+            }
+            _ => return Err(error()),
+        }
+    }
+    let path: TokenStream = path.into_iter().collect();
+    Ok(format!(
+        "({path} {module}::{variant}Params {{ {fields} }}).path()",
+        path = path,
+        module = module_name(&name),
+        variant = variant,
+        fields = fields.join(" "),
+    ))
+}
+
+/// The tokens between the commas of macro arguments, leaving out a
+/// trailing comma.
+fn split_commas(input: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut arguments = vec![Vec::new()];
+    for token in input {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == ',' => arguments.push(Vec::new()),
+            token => arguments.last_mut().expect("an argument").push(token),
+        }
+    }
+    if arguments.last().is_some_and(Vec::is_empty) {
+        arguments.pop();
+    }
+    arguments
+}
+
+/// A variable that a match arm captures.
+struct Captured<'a> {
+    name: &'a str,
+    /// an expression for its text
+    value: String,
+    converter: Option<&'a str>,
+}
+
+/// The slice pattern with its guard that matches the segments of one
+/// variant of a route, and the variables it captures.
+fn arm(steps: &[Step]) -> (String, Vec<Captured<'_>>) {
+    let mut slice = Vec::new();
+    let mut guards = Vec::new();
+    let mut variables = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        let binding = format!("v{}", i);
+        match step {
+            Step::Literal(s) => slice.push(format!("{:?}", s)),
+            Step::Variable {
+                name: variable,
+                converter,
+            } => {
+                guards.push(format!("!{}.is_empty()", binding));
+                if let Some(converter) = converter {
+                    guards.push(format!(
+                        "::traject::Converter::parse(&::traject::{}, {}).is_some()",
+                        converter, binding
+                    ));
+                }
+                variables.push(Captured {
+                    name: variable,
+                    value: format!("*{}", binding),
+                    converter: converter.as_deref(),
+                });
+                slice.push(binding);
+            }
+            Step::Wildcard { name: variable } => {
+                // the rest of the path, which has to be non-empty
+                guards.push(format!("!::std::matches!({}, [] | [\"\"])", binding));
+                variables.push(Captured {
+                    name: variable,
+                    value: format!(
+                        "&path[{}[0].as_ptr() as usize - path.as_ptr() as usize..]",
+                        binding
+                    ),
+                    converter: None,
+                });
+                slice.push(format!("{} @ ..", binding));
+            }
+        }
+    }
+    let guard = if guards.is_empty() {
+        String::new()
+    } else {
+        format!("if {}", guards.join(" && "))
+    };
+    (format!("[{}] {}", slice.join(", "), guard), variables)
+}
+
+/// The match arm of `resolve` for one variant of a route.
+fn resolve_arm(name: &str, route: &Route, steps: &[Step]) -> String {
+    let (head, variables) = arm(steps);
+    let variables: Vec<String> = variables
+        .iter()
+        .map(|captured| format!("({:?}, {})", captured.name, captured.value))
+        .collect();
+    format!(
+        "{head} => ::std::option::Option::Some(({name}::{variant}, \
+         ::std::iter::IntoIterator::into_iter([{variables}]).collect())),",
+        head = head,
+        name = name,
+        variant = route.variant,
+        variables = variables.join(", "),
+    )
+}
+
+/// The match arm of `classify` for one variant of a route, which leaves out
+/// the variables of the optional steps in `omitted`.
+fn classify_arm(module: &str, route: &Route, steps: &[Step], omitted: &[Step]) -> String {
+    let (head, variables) = arm(steps);
+    let optional: Vec<&str> = omitted
+        .iter()
+        .filter_map(|step| Some(step.field()?.0))
+        .collect();
+    let mut fields: Vec<String> = variables
+        .iter()
+        .map(|captured| {
+            let value = match captured.converter {
+                Some(converter) => format!(
+                    "::traject::Converter::parse(&::traject::{}, {}).expect(\"checked by the guard\")",
+                    converter, captured.value
+                ),
+                None => captured.value.clone(),
+            };
+            if optional.contains(&captured.name) {
+                format!("r#{}: ::std::option::Option::Some({}),", captured.name, value)
+            } else {
+                format!("r#{}: {},", captured.name, value)
+            }
+        })
+        .collect();
+    fields.extend(
+        optional
+            .iter()
+            .filter(|variable| !variables.iter().any(|captured| captured.name == **variable))
+            .map(|variable| format!("r#{}: ::std::option::Option::None,", variable)),
+    );
+    format!(
+        "{head} => ::std::option::Option::Some({module}::Params::{variant}({module}::{variant}Params {{ {fields} }})),",
+        head = head,
+        module = module,
+        variant = route.variant,
+        fields = fields.join(" "),
+    )
+}
+
+/// The steps of a pattern with its optional steps, and without them if it
+/// has any.
+fn variants(pattern: &str) -> Result<Vec<Vec<Step>>, String> {
+    let unsupported = |what: &str| {
+        format!(
+            "routes! does not support {} in pattern `{}`; use a Router instead",
+            what, pattern
+        )
+    };
+    if pattern.contains('?') {
+        return Err(unsupported("queries"));
+    }
+    if pattern.contains(":re:") {
+        return Err(unsupported("regex constraints"));
+    }
+    if pattern.contains('=') {
+        return Err(unsupported("default values"));
+    }
+    let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+    let full = steps(&pattern.replace(['[', ']'], ""))
+        .ok_or_else(|| unsupported("steps that mix text and variables"))?;
+    match pattern.find('[') {
+        Some(i) => {
+            let required = steps(&pattern[..i]).expect("a prefix of supported steps");
+            Ok(vec![full, required])
+        }
+        None => Ok(vec![full]),
+    }
+}
+
+/// The steps of a pattern without optional steps, or `None` if a step mixes
+/// text and variables.
+fn steps(pattern: &str) -> Option<Vec<Step>> {
+    pattern
+        .split('/')
+        .map(|s| {
+            if !s.replace("{{", "").replace("}}", "").contains(['{', '}']) {
+                return Some(Step::Literal(s.replace("{{", "{").replace("}}", "}")));
+            }
+            let inner = s.strip_prefix('{')?.strip_suffix('}')?;
+            if inner.contains(['{', '}']) {
+                return None;
+            }
+            Some(match inner.split_once(':') {
+                Some((name, "*")) => Step::Wildcard {
+                    name: name.to_string(),
+                },
+                Some((name, converter)) => Step::Variable {
+                    name: name.to_string(),
+                    converter: Some(converter_type(converter)?.to_string()),
+                },
+                None => Step::Variable {
+                    name: inner.to_string(),
+                    converter: None,
+                },
+            })
+        })
+        .collect()
+}
+
+/// The type of a built-in converter.
+fn converter_type(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "int" => "IntConverter",
+        "float" => "FloatConverter",
+        "bool" => "BoolConverter",
+        "uuid" => "UuidConverter",
+        "slug" => "SlugConverter",
+        "date" => "DateConverter",
+        _ => return None,
+    })
+}
+
+/// Parse `ATTRIBUTES VISIBILITY enum NAME { VARIANT => "PATTERN", ... }`.
+fn parse(input: TokenStream) -> Result<Table, String> {
+    let mut prefix = Vec::new();
+    let mut visibility = Vec::new();
+    let mut tokens = input.into_iter();
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "enum" => break,
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => {
+                prefix.push(TokenTree::Punct(punct));
+                prefix.extend(tokens.next());
+            }
+            Some(token) => {
+                visibility.push(token.clone());
+                prefix.push(token);
+            }
+            None => return Err("expected `enum NAME { ... }`".to_string()),
+        }
+    }
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(name)) => name.to_string(),
+        _ => return Err("expected an enum name".to_string()),
+    };
+    let body = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Group(group)), None) if group.delimiter() == Delimiter::Brace => {
+            group.stream()
+        }
+        _ => return Err(format!("expected `{{ ... }}` after `enum {}`", name)),
+    };
+    let mut routes = Vec::new();
+    let mut tokens = body.into_iter().peekable();
+    while tokens.peek().is_some() {
+        let mut attributes = Vec::new();
+        while let Some(TokenTree::Punct(punct)) = tokens.peek() {
+            if punct.as_char() != '#' {
+                break;
+            }
+            attributes.push(tokens.next().expect("peeked"));
+            attributes.extend(tokens.next());
+        }
+        let error = || "expected `VARIANT => \"PATTERN\"`".to_string();
+        let variant = match tokens.next() {
+            Some(TokenTree::Ident(variant)) => variant.to_string(),
+            _ => return Err(error()),
+        };
+        match (tokens.next(), tokens.next()) {
+            (Some(TokenTree::Punct(eq)), Some(TokenTree::Punct(gt)))
+                if eq.as_char() == '=' && eq.spacing() == Spacing::Joint && gt.as_char() == '>' => {
+            }
+            _ => return Err(error()),
+        }
+        let pattern = match tokens.next() {
+            Some(TokenTree::Literal(literal)) => {
+                string_literal(&literal.to_string()).ok_or_else(error)?
+            }
+            _ => return Err(error()),
+        };
+        match tokens.next() {
+            None => {}
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => {}
+            _ => return Err("expected `,` after a route".to_string()),
+        }
+        routes.push(Route {
+            attributes: attributes.into_iter().collect::<TokenStream>().to_string(),
+            variant,
+            pattern,
+        });
+    }
+    Ok(Table {
+        prefix: prefix.into_iter().collect::<TokenStream>().to_string(),
+        visibility: visibility.into_iter().collect::<TokenStream>().to_string(),
+        name,
+        routes,
+    })
+}
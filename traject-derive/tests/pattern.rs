@@ -0,0 +1,33 @@
+use traject::StaticPattern;
+use traject_derive::pattern;
+
+static EMPLOYEE: StaticPattern = pattern!("departments/{d}/employees/{e:int}");
+
+macro_rules! wrapped {
+    ($s:literal) => {
+        pattern!($s)
+    };
+}
+
+#[test]
+fn test_pattern() {
+    assert_eq!(EMPLOYEE.as_str(), "departments/{d}/employees/{e:int}");
+    let m = EMPLOYEE.match_path("departments/a/employees/2").unwrap();
+    assert_eq!(m.get("e"), Some("2"));
+    assert!(EMPLOYEE.match_path("departments/a/employees/x").is_none());
+}
+
+#[test]
+fn test_pattern_raw_string() {
+    let pattern = pattern!(r"files/{path:*}");
+    assert_eq!(
+        pattern.match_path("files/a/b").unwrap().get("path"),
+        Some("a/b")
+    );
+}
+
+#[test]
+fn test_pattern_in_macro_rules() {
+    let pattern = wrapped!("a/{b}");
+    assert_eq!(pattern.as_str(), "a/{b}");
+}
@@ -0,0 +1,166 @@
+use traject_derive::RouteParams;
+
+#[derive(Debug, PartialEq, RouteParams)]
+#[route("departments/{department_id}/employees/{employee_id}")]
+struct EmployeeParams {
+    department_id: u32,
+    employee_id: u32,
+}
+
+#[derive(Debug, PartialEq, RouteParams)]
+#[route(r"files/{type}/{path:*}")]
+pub struct FileParams {
+    pub r#type: String,
+    pub(crate) path: String,
+}
+
+#[derive(Debug, PartialEq, RouteParams)]
+#[route("")]
+struct Root {}
+
+#[derive(Debug, PartialEq, RouteParams)]
+#[route("docs/{dir}/{path:*}")]
+struct DocParams {
+    dir: String,
+    path: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, RouteParams)]
+#[route("articles/{id}[/{page}/{size=10}]?[sort={sort}]")]
+struct ArticleParams {
+    id: u32,
+    page: Option<u32>,
+    size: u32,
+    sort: ::std::option::Option<String>,
+}
+
+#[derive(Debug, PartialEq, RouteParams)]
+#[route("archive[/{rest:*}]")]
+struct ArchiveParams {
+    rest: Option<Vec<u32>>,
+}
+
+#[derive(Debug, PartialEq, RouteParams)]
+#[route("users/{userId}/posts/{post}", rename_all = "camelCase")]
+struct PostParams {
+    user_id: u32,
+    #[route(rename = "post")]
+    post_id: u32,
+}
+
+#[derive(Debug, PartialEq, RouteParams)]
+#[route("{TenantName}/{API_VERSION}", rename_all = "PascalCase")]
+struct TenantParams {
+    tenant_name: String,
+    #[route(rename = "API_VERSION")]
+    api_version: String,
+}
+
+#[test]
+fn test_match_path() {
+    assert_eq!(
+        EmployeeParams::match_path("departments/1/employees/2"),
+        Some(EmployeeParams {
+            department_id: 1,
+            employee_id: 2
+        })
+    );
+    assert_eq!(
+        FileParams::match_path("files/css/a/b.css"),
+        Some(FileParams {
+            r#type: "css".to_string(),
+            path: "a/b.css".to_string()
+        })
+    );
+    assert_eq!(Root::match_path(""), Some(Root {}));
+}
+
+#[test]
+fn test_match_path_no_match() {
+    assert_eq!(EmployeeParams::match_path("departments/1"), None);
+}
+
+#[test]
+fn test_match_path_field_does_not_parse() {
+    assert_eq!(
+        EmployeeParams::match_path("departments/x/employees/2"),
+        None
+    );
+}
+
+#[test]
+fn test_path_for() {
+    let params = EmployeeParams {
+        department_id: 1,
+        employee_id: 2,
+    };
+    assert_eq!(params.path_for().unwrap(), "departments/1/employees/2");
+    assert_eq!(Root {}.path_for().unwrap(), "");
+}
+
+#[test]
+fn test_pattern() {
+    assert_eq!(
+        EmployeeParams::PATTERN,
+        "departments/{department_id}/employees/{employee_id}"
+    );
+    assert_eq!(
+        EmployeeParams::route_pattern().as_str(),
+        EmployeeParams::PATTERN
+    );
+}
+
+#[test]
+fn test_rename() {
+    let params = PostParams::match_path("users/1/posts/2").unwrap();
+    assert_eq!(
+        params,
+        PostParams {
+            user_id: 1,
+            post_id: 2
+        }
+    );
+    assert_eq!(params.path_for().unwrap(), "users/1/posts/2");
+    let params = TenantParams::match_path("acme/v2").unwrap();
+    assert_eq!(params.tenant_name, "acme");
+    assert_eq!(params.api_version, "v2");
+    assert_eq!(params.path_for().unwrap(), "acme/v2");
+}
+
+#[test]
+fn test_vec() {
+    let params = DocParams::match_path("docs/guide/a/b.md").unwrap();
+    assert_eq!(params.dir, "guide");
+    assert_eq!(params.path, vec!["a", "b.md"]);
+    assert_eq!(params.path_for().unwrap(), "docs/guide/a/b.md");
+}
+
+#[test]
+fn test_option() {
+    let params = ArticleParams::match_path("articles/1").unwrap();
+    assert_eq!(
+        params,
+        ArticleParams {
+            id: 1,
+            page: None,
+            size: 10,
+            sort: None
+        }
+    );
+    assert_eq!(params.path_for().unwrap(), "articles/1");
+    let params = ArticleParams::match_path("articles/1/2/20?sort=date").unwrap();
+    assert_eq!(params.page, Some(2));
+    assert_eq!(params.size, 20);
+    assert_eq!(params.sort.as_deref(), Some("date"));
+    assert_eq!(params.path_for().unwrap(), "articles/1/2/20?sort=date");
+    assert_eq!(ArticleParams::match_path("articles/1/x/20"), None);
+
+    assert_eq!(
+        ArchiveParams::match_path("archive"),
+        Some(ArchiveParams { rest: None })
+    );
+    let params = ArchiveParams::match_path("archive/2024/10").unwrap();
+    assert_eq!(params.rest, Some(vec![2024, 10]));
+    assert_eq!(params.path_for().unwrap(), "archive/2024/10");
+    assert_eq!(ArchiveParams::match_path("archive/2024/x"), None);
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use traject::{Router, TypedRoute};
+use traject_derive::url;
+
+traject_derive::routes! {
+    /// The routes of the application.
+    #[derive(Hash)]
+    pub enum Route {
+        Home => "",
+        Employee => "departments/{d}/employees/{e:int}",
+        /// Employees that aren't found by id.
+        EmployeeByName => "departments/{d}/employees/{e}",
+        Employees => "departments/{d}/employees",
+        Files => r"files/{type}/{path:*}",
+        Readme => "files/docs/README",
+        Articles => "articles[/{page:int}]",
+        Trailing => "trailing/",
+        Template => "templates/{{name}}",
+    }
+}
+
+#[test]
+fn test_resolve() {
+    let (route, m) = Route::resolve("departments/a/employees/2").unwrap();
+    assert_eq!(route, Route::Employee);
+    assert_eq!(m.get("d"), Some("a"));
+    assert_eq!(m.get("e"), Some("2"));
+    let (route, m) = Route::resolve("/files/css/a/b.css?v=1").unwrap();
+    assert_eq!(route, Route::Files);
+    assert_eq!(m.get("type"), Some("css"));
+    assert_eq!(m.get("path"), Some("a/b.css"));
+    assert_eq!(Route::resolve("departments/a").map(|(r, _)| r), None);
+}
+
+#[test]
+fn test_url() {
+    assert_eq!(url!(Route, Home), "");
+    assert_eq!(
+        url!(Route, Employee, d = "a", e = 2),
+        "departments/a/employees/2"
+    );
+    assert_eq!(
+        url!(crate::Route, Files, type = "css", path = "a/b.css"),
+        "files/css/a/b.css"
+    );
+    assert_eq!(url!(Route, Articles, page = Some(3)), "articles/3");
+    assert_eq!(url!(Route, Articles, page = None,), "articles");
+    assert_eq!(url!(Route, Trailing), "trailing/");
+    assert_eq!(url!(Route, Template), "templates/{name}");
+    let params = route::EmployeeParams { d: "a", e: -1 };
+    assert_eq!(params.path(), "departments/a/employees/-1");
+}
+
+/// Handlers for routes, attached by the types of the routes.
+#[derive(Default)]
+struct Handlers(HashMap<Route, &'static str>);
+
+impl Handlers {
+    fn attach<K: TypedRoute<Route = Route>>(&mut self, handler: &'static str) {
+        self.0.insert(K::ROUTE, handler);
+    }
+}
+
+#[test]
+fn test_typed_route() {
+    let params = route::EmployeeParams { d: "a", e: 2 };
+    assert_eq!(
+        Route::path_for::<route::Employee>(&params),
+        "departments/a/employees/2"
+    );
+    assert_eq!(Route::path_for::<route::Home>(&route::HomeParams {}), "");
+    assert_eq!(route::Files::ROUTE, Route::Files);
+    let mut handlers = Handlers::default();
+    handlers.attach::<route::Employee>("employee");
+    let (route, _) = Route::resolve("departments/a/employees/2").unwrap();
+    assert_eq!(handlers.0.get(&route), Some(&"employee"));
+}
+
+#[test]
+fn test_url_like_router() {
+    let mut router = Router::new();
+    for route in Route::ALL {
+        router
+            .register_named(&format!("{:?}", route), route.pattern(), *route)
+            .unwrap();
+    }
+    let values = HashMap::from([("d", "a"), ("e", "2")]);
+    assert_eq!(
+        url!(Route, Employee, d = "a", e = 2),
+        router.path_for("Employee", &values).unwrap()
+    );
+    let values = HashMap::from([("page", "3")]);
+    assert_eq!(
+        url!(Route, Articles, page = Some(3)),
+        router.path_for("Articles", &values).unwrap()
+    );
+    let (route, _) = Route::resolve(&url!(Route, EmployeeByName, d = "a", e = "x")).unwrap();
+    assert_eq!(route, Route::EmployeeByName);
+}
+
+#[test]
+fn test_classify() {
+    assert_eq!(
+        Route::classify("departments/a/employees/2"),
+        Some(route::Params::Employee(route::EmployeeParams {
+            d: "a",
+            e: 2
+        }))
+    );
+    assert_eq!(
+        Route::classify("/files/css/a/b.css?v=1"),
+        Some(route::Params::Files(route::FilesParams {
+            r#type: "css",
+            path: "a/b.css"
+        }))
+    );
+    assert_eq!(
+        Route::classify("articles"),
+        Some(route::Params::Articles(route::ArticlesParams {
+            page: None
+        }))
+    );
+    assert_eq!(
+        Route::classify("articles/3"),
+        Some(route::Params::Articles(route::ArticlesParams {
+            page: Some(3)
+        }))
+    );
+    assert_eq!(Route::classify("articles/x"), None);
+    let described = match Route::classify("templates/{name}").unwrap() {
+        route::Params::Home(route::HomeParams {}) => "home".to_string(),
+        route::Params::Employee(params) => format!("employee {}", params.e),
+        route::Params::EmployeeByName(params) => format!("employee {}", params.e),
+        route::Params::Employees(params) => format!("employees of {}", params.d),
+        route::Params::Files(params) => format!("file {}", params.path),
+        route::Params::Readme(_) => "readme".to_string(),
+        route::Params::Articles(params) => format!("articles {:?}", params.page),
+        route::Params::Trailing(_) => "trailing".to_string(),
+        route::Params::Template(_) => "template".to_string(),
+    };
+    assert_eq!(described, "template");
+}
+
+#[test]
+fn test_classify_like_resolve() {
+    let paths = [
+        "",
+        "departments/a/employees/2",
+        "departments/a/employees/x",
+        "files/docs/README",
+        "files/docs/README/more",
+        "articles/",
+        "elsewhere",
+    ];
+    for path in paths.iter() {
+        let classified = Route::classify(path).map(|params| match params {
+            route::Params::Home(_) => Route::Home,
+            route::Params::Employee(_) => Route::Employee,
+            route::Params::EmployeeByName(_) => Route::EmployeeByName,
+            route::Params::Employees(_) => Route::Employees,
+            route::Params::Files(_) => Route::Files,
+            route::Params::Readme(_) => Route::Readme,
+            route::Params::Articles(_) => Route::Articles,
+            route::Params::Trailing(_) => Route::Trailing,
+            route::Params::Template(_) => Route::Template,
+        });
+        assert_eq!(
+            classified,
+            Route::resolve(path).map(|(route, _)| route),
+            "{}",
+            path
+        );
+    }
+}
+
+#[test]
+fn test_pattern() {
+    assert_eq!(Route::ALL.len(), 9);
+    assert_eq!(Route::ALL[1], Route::Employee);
+    assert_eq!(
+        Route::Employee.pattern(),
+        "departments/{d}/employees/{e:int}"
+    );
+}
+
+#[test]
+fn test_resolve_like_router() {
+    let mut router = Router::new();
+    for route in Route::ALL {
+        router.register(route.pattern(), *route).unwrap();
+    }
+    let paths = [
+        "",
+        "/",
+        "departments/a/employees/2",
+        "departments/a/employees/x",
+        "departments/a/employees/",
+        "departments/a/employees",
+        "departments//employees/2",
+        "files/docs/README",
+        "files/docs/README/more",
+        "files/docs/",
+        "files/docs",
+        "files/css/a/",
+        "articles",
+        "articles/3",
+        "articles/x",
+        "articles/",
+        "trailing/",
+        "trailing",
+        "templates/{name}",
+        "templates/name",
+        "elsewhere",
+    ];
+    for path in paths.iter() {
+        let resolved = Route::resolve(path);
+        let expected = router.resolve(path);
+        assert_eq!(
+            resolved.as_ref().map(|(route, m)| (route, m)),
+            expected.as_ref().map(|(route, m)| (*route, m)),
+            "{}",
+            path
+        );
+    }
+}